@@ -0,0 +1,342 @@
+use std::{path::PathBuf, str::FromStr};
+
+use crate::{
+    lidar::{LidarSettings, Sensor},
+    loader::{
+        cache::CacheLocation,
+        convention::ConventionSettings,
+        kitti::KittiSettings,
+        rgbd::{is_rgbd, RgbdSettings},
+        LoadOptions,
+    },
+    mesh::object::MeshSettings,
+    normals::{Neighbourhood, NormalSettings, Orientation, Position},
+    octree::lod::LodSettings,
+    pass::{
+        edl::EdlSettings,
+        lighting::{Light, LightingSettings},
+        points_pass::PointSettings,
+        splat::SplatSettings,
+        ssao::SsaoSettings,
+    },
+};
+
+/// How the points are turned into a surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Points are drawn as sprites and the holes between them filled by a jump flood
+    JumpFlood,
+    /// Points are drawn as blended, oriented discs
+    Splat,
+}
+
+impl FromStr for RenderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jumpflood" => Ok(Self::JumpFlood),
+            "splat" => Ok(Self::Splat),
+            _ => Err(format!("Unknown render mode: {s}")),
+        }
+    }
+}
+
+/// Options passed on the command line
+pub struct Args {
+    /// Point cloud file, or octree directory, to display
+    pub input: PathBuf,
+    /// Further point cloud files shown together with the input, when it's read whole
+    pub extra_inputs: Vec<PathBuf>,
+    /// The input is first turned into an octree in this directory, which is then displayed
+    pub build_octree: Option<PathBuf>,
+    /// Where the loaded points are cached for the next launch, if anywhere. Octrees aren't
+    /// part of the cache: the one of `build_octree` is kept in its own directory and reused
+    /// while the input is unchanged.
+    pub cache: Option<CacheLocation>,
+    /// How arrays, tables and meshes are made into points. The convention of every input
+    /// is in `conventions` instead.
+    pub load: LoadOptions,
+    /// Axes of the input and then of every extra input, each given after it on the command
+    /// line, or before all inputs for every one of them
+    pub conventions: Vec<ConventionSettings>,
+    /// The shown points are written here when S is pressed, as LAS or PLY
+    pub export: Option<PathBuf>,
+    /// Loaded points beyond this many replace the oldest ones
+    pub max_points: Option<usize>,
+    /// Point cloud topic played back from an MCAP file, the first one if unset
+    pub topic: Option<String>,
+    /// How KITTI scans are colored and where their labels are
+    pub kitti: KittiSettings,
+    /// Only list the topics of the input MCAP file, without opening a window
+    pub list_topics: bool,
+    /// Only time decoding the input LAS file, without opening a window
+    pub benchmark_decode: bool,
+    pub lod: LodSettings,
+    pub render_mode: RenderMode,
+    pub points: PointSettings,
+    pub splat: SplatSettings,
+    /// Points are only shaded by their normals when this is set
+    pub lighting: Option<LightingSettings>,
+    /// Eye-Dome Lighting is only applied when this is set
+    pub edl: Option<EdlSettings>,
+    /// Ambient occlusion is only applied when this is set
+    pub ssao: Option<SsaoSettings>,
+    /// Normals are only estimated when this is set and the file has none
+    pub normals: Option<NormalSettings>,
+    /// The input is a depth image or an RGB-D dataset. Set for PNG files and dataset
+    /// directories even without any of its options.
+    pub rgbd: Option<RgbdSettings>,
+    /// The input is a capture of lidar packets, or packets arrive over the network
+    pub lidar: Option<LidarSettings>,
+    /// Triangle meshes drawn together with the points
+    pub meshes: Vec<PathBuf>,
+    /// Axes of every mesh, each given after it on the command line or before all inputs
+    pub mesh_conventions: Vec<ConventionSettings>,
+    pub mesh: MeshSettings,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut render_mode = RenderMode::JumpFlood;
+        let mut points = PointSettings::default();
+        let mut splat = SplatSettings::default();
+        let mut lighting: Option<LightingSettings> = None;
+        let mut edl: Option<EdlSettings> = None;
+        let mut ssao: Option<SsaoSettings> = None;
+        let mut normals: Option<NormalSettings> = None;
+        let mut lidar: Option<LidarSettings> = None;
+        let mut rgbd: Option<RgbdSettings> = None;
+        let mut inputs = Vec::new();
+        let mut conventions = Vec::new();
+        let mut convention = ConventionSettings::default();
+        let mut build_octree = None;
+        let mut benchmark_decode = false;
+        let mut cache = None;
+        let mut max_points = None;
+        let mut export = None;
+        let mut load = LoadOptions::default();
+        let mut topic = None;
+        let mut list_topics = false;
+        let mut kitti = KittiSettings::default();
+        let mut lod = LodSettings::default();
+        let mut meshes = Vec::new();
+        let mut mesh_conventions = Vec::new();
+        // Whether the last file given was a mesh, which the axes given next are for
+        let mut after_mesh = false;
+        let mut mesh = MeshSettings::default();
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--build-octree" => build_octree = Some(value(&arg, args.next())),
+                "--benchmark-decode" => benchmark_decode = true,
+                "--topic" => topic = Some(value(&arg, args.next())),
+                "--list-topics" => list_topics = true,
+                "--kitti-color" => kitti.coloring = Some(value(&arg, args.next())),
+                "--kitti-labels" => kitti.labels = Some(value(&arg, args.next())),
+                "--crs" => load.crs.target = Some(value(&arg, args.next())),
+                "--source-crs" => load.crs.source = Some(value(&arg, args.next())),
+                "--columns" => load.columns = value(&arg, args.next()),
+                "--up" | "--handedness" | "--flip" => {
+                    let last = match after_mesh {
+                        true => mesh_conventions.last_mut(),
+                        false => conventions.last_mut(),
+                    };
+                    let settings = last.unwrap_or(&mut convention);
+                    match arg.as_str() {
+                        "--up" => settings.up = Some(value(&arg, args.next())),
+                        "--handedness" => settings.handedness = Some(value(&arg, args.next())),
+                        _ => settings.flip = value(&arg, args.next()),
+                    }
+                }
+                "--sample-points" => load.sampling.points = Some(value(&arg, args.next())),
+                "--sample-density" => load.sampling.density = Some(value(&arg, args.next())),
+                "--sample-method" => load.sampling.method = value(&arg, args.next()),
+                "--mesh" => {
+                    meshes.push(value(&arg, args.next()));
+                    mesh_conventions.push(convention);
+                    after_mesh = true;
+                }
+                "--mesh-mode" => mesh.mode = value(&arg, args.next()),
+                "--mesh-opacity" => mesh.opacity = value(&arg, args.next()),
+                "--export" => export = Some(value(&arg, args.next())),
+                "--max-points" => max_points = Some(value(&arg, args.next())),
+                "--cache" => cache = Some(CacheLocation::NextToSource),
+                "--cache-dir" => cache = Some(CacheLocation::Directory(value(&arg, args.next()))),
+                "--point-budget" => lod.point_budget = value(&arg, args.next()),
+                "--lod-error" => lod.min_error = value(&arg, args.next()),
+                "--lod-cache" => lod.cache_points = value(&arg, args.next()),
+                "--render-mode" => render_mode = value(&arg, args.next()),
+                "--splat-radius" => splat.radius = value(&arg, args.next()),
+                "--splat-depth-offset" => splat.depth_offset = value(&arg, args.next()),
+                "--point-size" => points.size = value(&arg, args.next()),
+                "--point-shape" => points.shape = value(&arg, args.next()),
+                "--lighting" => lighting = Some(value(&arg, args.next())),
+                "--sun-azimuth" | "--sun-elevation" => {
+                    let settings = lighting.get_or_insert_with(LightingSettings::sun);
+                    if let Light::Headlight = settings.light {
+                        settings.light = LightingSettings::sun().light;
+                    }
+                    if let Light::Sun { azimuth, elevation } = &mut settings.light {
+                        match arg.as_str() {
+                            "--sun-azimuth" => *azimuth = value(&arg, args.next()),
+                            _ => *elevation = value(&arg, args.next()),
+                        }
+                    }
+                }
+                "--ambient" => {
+                    lighting.get_or_insert_with(Default::default).ambient = value(&arg, args.next())
+                }
+                "--specular" => {
+                    lighting.get_or_insert_with(Default::default).specular =
+                        value(&arg, args.next())
+                }
+                "--edl" => {
+                    edl.get_or_insert_with(Default::default);
+                }
+                "--edl-strength" => {
+                    edl.get_or_insert_with(Default::default).strength = value(&arg, args.next())
+                }
+                "--edl-radius" => {
+                    edl.get_or_insert_with(Default::default).radius = value(&arg, args.next())
+                }
+                "--ssao" => {
+                    ssao.get_or_insert_with(Default::default);
+                }
+                "--ssao-quality" => {
+                    ssao.get_or_insert_with(Default::default).quality = value(&arg, args.next())
+                }
+                "--ssao-radius" => {
+                    ssao.get_or_insert_with(Default::default).radius = value(&arg, args.next())
+                }
+                "--ssao-intensity" => {
+                    ssao.get_or_insert_with(Default::default).intensity = value(&arg, args.next())
+                }
+                "--ssao-blur" => {
+                    ssao.get_or_insert_with(Default::default).blur = value(&arg, args.next())
+                }
+                "--lidar" => {
+                    lidar.get_or_insert_with(Default::default).sensor = value(&arg, args.next())
+                }
+                "--ouster-metadata" => {
+                    let settings = lidar.get_or_insert_with(Default::default);
+                    settings.sensor = Sensor::Ouster;
+                    settings.metadata = Some(value(&arg, args.next()));
+                }
+                "--lidar-listen" => {
+                    lidar.get_or_insert_with(Default::default).listen =
+                        Some(value(&arg, args.next()))
+                }
+                "--lidar-port" => {
+                    lidar.get_or_insert_with(Default::default).port = Some(value(&arg, args.next()))
+                }
+                "--lidar-speed" => {
+                    lidar.get_or_insert_with(Default::default).speed = value(&arg, args.next())
+                }
+                "--lidar-accumulate" => {
+                    lidar.get_or_insert_with(Default::default).accumulate = true
+                }
+                "--intrinsics" => {
+                    rgbd.get_or_insert_with(Default::default).intrinsics = value(&arg, args.next())
+                }
+                "--depth-scale" => {
+                    rgbd.get_or_insert_with(Default::default).depth_scale =
+                        Some(value(&arg, args.next()))
+                }
+                "--max-depth" => {
+                    rgbd.get_or_insert_with(Default::default).max_depth =
+                        Some(value(&arg, args.next()))
+                }
+                "--color-image" => {
+                    rgbd.get_or_insert_with(Default::default).color = Some(value(&arg, args.next()))
+                }
+                "--trajectory" => {
+                    rgbd.get_or_insert_with(Default::default).trajectory =
+                        Some(value(&arg, args.next()))
+                }
+                "--depth-stride" => {
+                    rgbd.get_or_insert_with(Default::default).stride = value(&arg, args.next())
+                }
+                "--normals" => {
+                    normals.get_or_insert_with(Default::default);
+                }
+                "--normal-neighbours" => {
+                    normals.get_or_insert_with(Default::default).neighbourhood =
+                        Neighbourhood::Nearest(value(&arg, args.next()))
+                }
+                "--normal-radius" => {
+                    normals.get_or_insert_with(Default::default).neighbourhood =
+                        Neighbourhood::Radius(value(&arg, args.next()))
+                }
+                "--scanner" => {
+                    let Position(position) = value(&arg, args.next());
+                    normals.get_or_insert_with(Default::default).orientation =
+                        Orientation::Towards(position)
+                }
+                _ if !arg.starts_with("--") => {
+                    inputs.push(PathBuf::from(arg));
+                    conventions.push(convention);
+                    after_mesh = false;
+                }
+                _ => panic!("Unknown argument: {arg}"),
+            }
+        }
+
+        let mut inputs = inputs.into_iter();
+        let input = inputs
+            .next()
+            .unwrap_or_else(|| PathBuf::from("pointcloud.las"));
+        let extra_inputs = inputs.collect();
+        if conventions.is_empty() {
+            conventions.push(convention);
+        }
+
+        // Depth images are recognized without asking
+        if is_rgbd(&input) {
+            rgbd.get_or_insert_with(Default::default);
+        }
+
+        Self {
+            input,
+            extra_inputs,
+            build_octree,
+            cache,
+            load,
+            conventions,
+            export,
+            max_points,
+            topic,
+            kitti,
+            list_topics,
+            benchmark_decode,
+            lod,
+            render_mode,
+            points,
+            splat,
+            lighting,
+            edl,
+            ssao,
+            normals,
+            rgbd,
+            lidar,
+            meshes,
+            mesh_conventions,
+            mesh,
+        }
+    }
+
+    /// How the input at `index`, counting the extra inputs after the first, is loaded
+    pub fn load_options(&self, index: usize) -> LoadOptions {
+        LoadOptions {
+            convention: self.conventions[index],
+            ..self.load.clone()
+        }
+    }
+}
+
+fn value<T: FromStr>(name: &str, value: Option<String>) -> T {
+    value
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| panic!("Expected a valid value after {name}"))
+}
@@ -0,0 +1,81 @@
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+/// Vertical field of view, in radians
+pub const FOV_Y: f32 = 1.0;
+/// Distance of the near clipping plane
+pub const Z_NEAR: f32 = 0.1;
+/// Distance of the far clipping plane
+pub const Z_FAR: f32 = 100.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    pub position: Point3<f32>,
+    pub target: Point3<f32>,
+    pub up: Vector3<f32>,
+}
+
+impl Camera {
+    /// Circles around the origin, one radian per second. Its up is the viewer's y, which
+    /// every input's convention turns its own up into.
+    pub fn orbit(elapsed: f32) -> Self {
+        Self {
+            position: Point3::new(elapsed.cos(), 0.0, elapsed.sin()),
+            target: Point3::origin(),
+            up: Vector3::y(),
+        }
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        Matrix4::look_at_rh(&self.position, &self.target, &self.up)
+    }
+
+    /// Pixels covered by one world unit at a distance of one, for a viewport of the given height
+    pub fn projection_scale(viewport_height: f32) -> f32 {
+        viewport_height / 2.0 / (FOV_Y / 2.0).tan()
+    }
+
+    pub fn projection(aspect_ratio: f32) -> Matrix4<f32> {
+        Matrix4::new_perspective(aspect_ratio, FOV_Y, Z_NEAR, Z_FAR)
+    }
+
+    pub fn view_projection(&self, aspect_ratio: f32) -> Matrix4<f32> {
+        Self::projection(aspect_ratio) * self.view()
+    }
+
+    /// Unit vectors pointing right and up on the screen, in world space
+    pub fn screen_axes(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let forward = (self.target - self.position).normalize();
+        let right = forward.cross(&self.up).normalize();
+        (right, right.cross(&forward))
+    }
+}
+
+/// Planes bounding everything a view-projection matrix can see
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    /// Normals point inside, a point is inside when `dot(plane.xyz, p) + plane.w >= 0`
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    pub fn new(view_projection: &Matrix4<f32>) -> Self {
+        let row = |i: usize| view_projection.row(i).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        Self {
+            planes: [w + x, w - x, w + y, w - y, w + z, w - z],
+        }
+    }
+
+    /// Whether any part of the axis aligned box may be visible
+    pub fn intersects_box(&self, min: &Point3<f32>, max: &Point3<f32>) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the plane normal
+            let corner = Vector3::new(
+                if plane.x >= 0.0 { max.x } else { min.x },
+                if plane.y >= 0.0 { max.y } else { min.y },
+                if plane.z >= 0.0 { max.z } else { min.z },
+            );
+            plane.xyz().dot(&corner) + plane.w >= 0.0
+        })
+    }
+}
@@ -0,0 +1,81 @@
+use std::{fs::File, io::BufWriter, ops::Range, path::Path};
+
+use las::{point::Format, Builder, Color, Transform, Vector, Write};
+use nalgebra::Vector3;
+
+use crate::loader::{self, laz, LoadOptions, LoadResult, PointCloud};
+
+/// Step the coordinates of written points are rounded to
+const SCALE: f64 = 0.001;
+
+/// Copies ranges of point records to a new file with the header of the source, so the
+/// scale, offset, point format and every VLR stay the same. Counts and bounds are
+/// recomputed for the points written. Points of LAZ files are written uncompressed.
+pub fn copy(source: &Path, ranges: &[Range<u64>], destination: &Path) -> LoadResult<u64> {
+    let (_, header) = loader::las::read_header(source)?;
+    let layout = loader::las::Layout::read(source, &LoadOptions::default())?;
+
+    let mut builder = Builder::from(header);
+    builder.point_format.is_compressed = false;
+    builder
+        .vlrs
+        .retain(|v| !(v.user_id == laz::LASZIP_USER_ID && v.record_id == laz::LASZIP_RECORD_ID));
+    let mut writer = las::Writer::new(
+        BufWriter::new(File::create(destination)?),
+        builder.into_header()?,
+    )?;
+    let mut count = 0;
+    for range in ranges {
+        for point in layout.points(source, range.clone()) {
+            writer.write(point?)?;
+            count += 1;
+        }
+    }
+    writer.close()?;
+    Ok(count)
+}
+
+/// Writes points which didn't come from a LAS file, with their colors and a scale of a
+/// millimeter
+pub fn write(cloud: &PointCloud, destination: &Path) -> LoadResult<u64> {
+    let min = cloud
+        .vertices
+        .iter()
+        .fold(Vector3::repeat(f32::INFINITY), |min, v| {
+            min.inf(&v.position)
+        });
+    let transform = |offset: f32| Transform {
+        scale: SCALE,
+        offset: if offset.is_finite() {
+            offset as f64
+        } else {
+            0.0
+        },
+    };
+    let mut builder = Builder::from((1, 2));
+    builder.point_format = Format::new(2)?;
+    builder.generating_software = format!("pointclouds {}", env!("CARGO_PKG_VERSION"));
+    builder.transforms = Vector {
+        x: transform(min.x),
+        y: transform(min.y),
+        z: transform(min.z),
+    };
+
+    let mut writer = las::Writer::new(
+        BufWriter::new(File::create(destination)?),
+        builder.into_header()?,
+    )?;
+    for vertex in &cloud.vertices {
+        let [red, green, blue] = [vertex.color.x, vertex.color.y, vertex.color.z]
+            .map(|c| (c.clamp(0.0, 1.0) * 65535.0).round() as u16);
+        writer.write(las::Point {
+            x: vertex.position.x as f64,
+            y: vertex.position.y as f64,
+            z: vertex.position.z as f64,
+            color: Some(Color::new(red, green, blue)),
+            ..Default::default()
+        })?;
+    }
+    writer.close()?;
+    Ok(cloud.vertices.len() as u64)
+}
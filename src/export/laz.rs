@@ -0,0 +1,95 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use las::raw::{self, vlr::RecordLength};
+
+use crate::loader::{
+    laz::{self, LaszipVlr},
+    LoadResult,
+};
+
+/// Bytes of a VLR before its data
+const VLR_HEADER_SIZE: u32 = 54;
+
+/// Compresses a LAS file into a LAZ one with the same header, VLRs and extended VLRs, and
+/// a LASzip VLR describing the compression
+pub fn compress(source: &Path, destination: &Path) -> LoadResult<()> {
+    let mut input = BufReader::new(File::open(source)?);
+    let mut header = raw::Header::read_from(&mut input)?;
+    input.seek(SeekFrom::Start(header.header_size as u64))?;
+    let mut vlrs = Vec::with_capacity(header.number_of_variable_length_records as usize);
+    for _ in 0..header.number_of_variable_length_records {
+        vlrs.push(raw::Vlr::read_from(&mut input, false)?);
+    }
+    // Whatever is left between the VLRs and the points is kept as it is
+    let vlrs_end = input.stream_position()?;
+    let mut padding =
+        vec![0; (header.offset_to_point_data as u64).saturating_sub(vlrs_end) as usize];
+    input.read_exact(&mut padding)?;
+
+    let format = header.point_data_record_format & 0x3f;
+    let vlr = LaszipVlr::for_format(format, header.point_data_record_length)?;
+    let data = vlr.to_bytes();
+    let mut user_id = [0; 16];
+    user_id[..laz::LASZIP_USER_ID.len()].copy_from_slice(laz::LASZIP_USER_ID.as_bytes());
+    let laszip = raw::Vlr {
+        reserved: 0,
+        user_id,
+        record_id: laz::LASZIP_RECORD_ID,
+        record_length_after_header: RecordLength::Vlr(data.len() as u16),
+        description: [0; 32],
+        data,
+    };
+
+    let point_count = match header.large_file {
+        Some(large_file) if header.number_of_point_records == 0 => {
+            large_file.number_of_point_records
+        }
+        _ => header.number_of_point_records as u64,
+    };
+    let record_length = header.point_data_record_length as usize;
+    let evlrs = header.evlr.map(|evlr| evlr.start_of_first_evlr);
+    // Bit 7 of the point format tells the records are compressed
+    header.point_data_record_format = format | 0x80;
+    header.number_of_variable_length_records += 1;
+    header.offset_to_point_data += VLR_HEADER_SIZE + laszip.data.len() as u32;
+
+    let mut output = BufWriter::new(File::create(destination)?);
+    header.write_to(&mut output)?;
+    for vlr in vlrs.iter().chain([&laszip]) {
+        vlr.write_to(&mut output)?;
+    }
+    output.write_all(&padding)?;
+    // Offset of the chunk table, known once every chunk is written
+    output.write_all(&0i64.to_le_bytes())?;
+
+    let mut sizes = Vec::new();
+    let mut records = Vec::new();
+    let mut written = 0;
+    while written < point_count {
+        let count = (point_count - written).min(laz::CHUNK_SIZE as u64);
+        records.resize(count as usize * record_length, 0);
+        input.read_exact(&mut records)?;
+        let chunk = laz::compress_chunk(&vlr, &records);
+        output.write_all(&chunk)?;
+        sizes.push(chunk.len() as u64);
+        written += count;
+    }
+    let table_offset = output.stream_position()?;
+    output.write_all(&laz::write_chunk_table(&sizes))?;
+
+    if let (Some(start), Some(evlr)) = (evlrs, &mut header.evlr) {
+        evlr.start_of_first_evlr = output.stream_position()?;
+        input.seek(SeekFrom::Start(start))?;
+        io::copy(&mut input, &mut output)?;
+    }
+    output.seek(SeekFrom::Start(header.offset_to_point_data as u64))?;
+    output.write_all(&(table_offset as i64).to_le_bytes())?;
+    output.seek(SeekFrom::Start(0))?;
+    header.write_to(&mut output)?;
+    output.flush()?;
+    Ok(())
+}
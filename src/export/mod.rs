@@ -0,0 +1,165 @@
+use std::{
+    fs::OpenOptions,
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::loader::{
+    self,
+    convention::ConventionSettings,
+    crs::{Conversion, CrsSettings},
+    LoadOptions, LoadResult, PointCloud,
+};
+
+pub mod las;
+pub mod laz;
+pub mod ply;
+
+/// Writes the points of `source` in `range`, counted in the order they were loaded, to a
+/// file in the format its extension names. Points keep the coordinates and axes of the
+/// source. Points of LAS files are copied with all their attributes, those of other files
+/// with their position, color and normal. LAZ files are written as LAS first and then
+/// compressed.
+pub fn export(
+    source: &Path,
+    options: &LoadOptions,
+    range: Range<u64>,
+    destination: &Path,
+) -> LoadResult<u64> {
+    let extension = destination
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let from_las = loader::is_las(source);
+    match extension.as_deref() {
+        Some("las") => write_las(source, options, range, destination),
+        Some("laz") => {
+            let uncompressed = temporary_next_to(destination)?;
+            let result = write_las(source, options, range, &uncompressed).and_then(|count| {
+                laz::compress(&uncompressed, destination)?;
+                Ok(count)
+            });
+            std::fs::remove_file(&uncompressed).ok();
+            result
+        }
+        Some("ply") if from_las => {
+            ply::write_las(source, &las_ranges(source, options, range)?, destination)
+        }
+        Some("ply") => ply::write(&stored_points(source, options, range)?, destination),
+        _ => Err(format!("Unsupported export format: {}", destination.display()).into()),
+    }
+}
+
+fn write_las(
+    source: &Path,
+    options: &LoadOptions,
+    range: Range<u64>,
+    destination: &Path,
+) -> LoadResult<u64> {
+    if loader::is_las(source) {
+        las::copy(source, &las_ranges(source, options, range)?, destination)
+    } else {
+        las::write(&stored_points(source, options, range)?, destination)
+    }
+}
+
+/// New empty file in the directory of `destination`, with a name nothing else uses
+fn temporary_next_to(destination: &Path) -> LoadResult<PathBuf> {
+    let name = destination
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let mut attempt = 0;
+    loop {
+        let path =
+            destination.with_file_name(format!(".{name}.{}-{attempt}.las", std::process::id()));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Runs of records of a LAS file holding the points in `range`
+fn las_ranges(
+    source: &Path,
+    options: &LoadOptions,
+    range: Range<u64>,
+) -> LoadResult<Vec<Range<u64>>> {
+    let layout = loader::las::Layout::read(source, &LoadOptions::default())?;
+    let conversion = Conversion::for_file(source, &options.crs)?;
+    let positions = layout
+        .points(source, 0..layout.point_count)
+        .map(|point| point.map(|p| [p.x, p.y, p.z]));
+    source_ranges(&conversion, layout.point_count, positions, range)
+}
+
+/// Points of a file other than LAS in `range`, as they are stored in it
+fn stored_points(
+    source: &Path,
+    options: &LoadOptions,
+    range: Range<u64>,
+) -> LoadResult<PointCloud> {
+    let stored = LoadOptions {
+        crs: CrsSettings::default(),
+        convention: ConventionSettings::AS_STORED,
+        ..options.clone()
+    };
+    let cloud = loader::load(source, &stored)?;
+    let conversion = Conversion::for_file(source, &options.crs)?;
+    let positions = cloud.vertices.iter().map(|v| {
+        let p = v.position.map(|c| c as f64);
+        Ok([p.x, p.y, p.z])
+    });
+    let ranges = source_ranges(&conversion, cloud.vertices.len() as u64, positions, range)?;
+    let pick = |r: &Range<u64>| r.start as usize..r.end as usize;
+    Ok(PointCloud {
+        normals: cloud.normals.as_ref().map(|normals| {
+            ranges
+                .iter()
+                .flat_map(|r| normals.get(pick(r)).unwrap_or_default())
+                .copied()
+                .collect()
+        }),
+        vertices: ranges
+            .iter()
+            .flat_map(|r| &cloud.vertices[pick(r)])
+            .copied()
+            .collect(),
+    })
+}
+
+/// Runs of the `count` points of a source which make up the loaded points in `range`.
+/// Points which couldn't be reprojected were left out when loading, so they aren't
+/// counted.
+fn source_ranges(
+    conversion: &Conversion,
+    count: u64,
+    positions: impl Iterator<Item = LoadResult<[f64; 3]>>,
+    range: Range<u64>,
+) -> LoadResult<Vec<Range<u64>>> {
+    if !conversion.reprojects() {
+        let range = range.start.min(count)..range.end.min(count);
+        return Ok(std::iter::once(range).collect());
+    }
+    let mut ranges: Vec<Range<u64>> = Vec::new();
+    let mut loaded = 0;
+    for (index, position) in (0..).zip(positions) {
+        if loaded >= range.end {
+            break;
+        }
+        if !conversion.keeps(position?) {
+            continue;
+        }
+        if loaded >= range.start {
+            match ranges.last_mut() {
+                Some(last) if last.end == index => last.end += 1,
+                _ => ranges.push(index..index + 1),
+            }
+        }
+        loaded += 1;
+    }
+    Ok(ranges)
+}
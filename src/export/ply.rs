@@ -0,0 +1,104 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write as _},
+    ops::Range,
+    path::Path,
+};
+
+use crate::loader::{self, LoadOptions, LoadResult, PointCloud};
+
+/// Writes the header of a binary PLY file with one vertex element
+fn write_header(
+    out: &mut impl std::io::Write,
+    count: u64,
+    properties: &[(&str, &str)],
+) -> std::io::Result<()> {
+    writeln!(out, "ply")?;
+    writeln!(out, "format binary_little_endian 1.0")?;
+    writeln!(
+        out,
+        "comment written by pointclouds {}",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(out, "element vertex {count}")?;
+    for (kind, name) in properties {
+        writeln!(out, "property {kind} {name}")?;
+    }
+    writeln!(out, "end_header")
+}
+
+/// Writes positions, colors and, when known, normals
+pub fn write(cloud: &PointCloud, destination: &Path) -> LoadResult<u64> {
+    let mut out = BufWriter::new(File::create(destination)?);
+    let mut properties = vec![("float", "x"), ("float", "y"), ("float", "z")];
+    if cloud.normals.is_some() {
+        properties.extend([("float", "nx"), ("float", "ny"), ("float", "nz")]);
+    }
+    properties.extend([("uchar", "red"), ("uchar", "green"), ("uchar", "blue")]);
+    write_header(&mut out, cloud.vertices.len() as u64, &properties)?;
+
+    for (i, vertex) in cloud.vertices.iter().enumerate() {
+        for c in vertex.position.iter() {
+            out.write_all(&c.to_le_bytes())?;
+        }
+        if let Some(normals) = &cloud.normals {
+            for c in normals.get(i).copied().unwrap_or_default().iter() {
+                out.write_all(&c.to_le_bytes())?;
+            }
+        }
+        for c in vertex.color.iter() {
+            out.write_all(&[(c.clamp(0.0, 1.0) * 255.0).round() as u8])?;
+        }
+    }
+    out.flush()?;
+    Ok(cloud.vertices.len() as u64)
+}
+
+/// Writes ranges of the points of a LAS file, with full precision coordinates and the
+/// attributes other tools commonly read
+pub fn write_las(source: &Path, ranges: &[Range<u64>], destination: &Path) -> LoadResult<u64> {
+    let (_, header) = loader::las::read_header(source)?;
+    let format = *header.point_format();
+    let layout = loader::las::Layout::read(source, &LoadOptions::default())?;
+    let count = ranges.iter().map(|r| r.end - r.start).sum();
+
+    let mut out = BufWriter::new(File::create(destination)?);
+    let mut properties = vec![("double", "x"), ("double", "y"), ("double", "z")];
+    if format.has_color {
+        properties.extend([("ushort", "red"), ("ushort", "green"), ("ushort", "blue")]);
+    }
+    properties.extend([
+        ("ushort", "intensity"),
+        ("uchar", "classification"),
+        ("uchar", "return_number"),
+        ("uchar", "number_of_returns"),
+    ]);
+    if format.has_gps_time {
+        properties.push(("double", "gps_time"));
+    }
+    write_header(&mut out, count, &properties)?;
+
+    for point in ranges.iter().flat_map(|r| layout.points(source, r.clone())) {
+        let point = point?;
+        for c in [point.x, point.y, point.z] {
+            out.write_all(&c.to_le_bytes())?;
+        }
+        if format.has_color {
+            let color = point.color.unwrap_or_default();
+            for c in [color.red, color.green, color.blue] {
+                out.write_all(&c.to_le_bytes())?;
+            }
+        }
+        out.write_all(&point.intensity.to_le_bytes())?;
+        out.write_all(&[
+            u8::from(point.classification),
+            point.return_number,
+            point.number_of_returns,
+        ])?;
+        if format.has_gps_time {
+            out.write_all(&point.gps_time.unwrap_or_default().to_le_bytes())?;
+        }
+    }
+    out.flush()?;
+    Ok(count)
+}
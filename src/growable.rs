@@ -0,0 +1,198 @@
+use std::ops::Range;
+
+use bytemuck::Zeroable;
+use wgpu::{BindGroupLayout, Device, Queue, RenderPass, TextureFormat};
+
+use crate::{
+    camera::Frustum,
+    material::Material,
+    object::{chunk_capacity, BasicVertex, Object, Surfel, UpdateContext},
+    pass::points_pass::PointsPass,
+    point_buffers::PointBuffers,
+};
+
+/// Points still shown once `appended` points were added to an object keeping at most
+/// `max_points`, counted in the order they were added
+pub fn shown_range(appended: u64, max_points: Option<usize>) -> Range<u64> {
+    match max_points {
+        Some(max) => appended.saturating_sub(max.max(1) as u64)..appended,
+        None => 0..appended,
+    }
+}
+
+/// Points added while the object is displayed, such as a map being built. Points go into
+/// fixed size chunks, so only the new ones are uploaded.
+pub struct GrowableObject {
+    material: Material,
+    point_data_layout: BindGroupLayout,
+    /// Filled in order, only the last one may have room left
+    chunks: Vec<PointBuffers>,
+    /// Cleared chunks, the first one last, reused before new ones are created
+    spare: Vec<PointBuffers>,
+    chunk_size: u32,
+    /// Points kept before the oldest one is overwritten by a new one
+    max_points: Option<usize>,
+    /// Where the next point goes once every point is taken, counted over all chunks
+    next: usize,
+    /// Points appended since the object was created or cleared
+    appended: usize,
+    /// Chunks which passed the last frustum test
+    visible: Vec<usize>,
+}
+
+impl GrowableObject {
+    /// With `max_points` set, the oldest points are overwritten by new ones once there are
+    /// that many.
+    pub fn new(
+        device: &Device,
+        position_format: TextureFormat,
+        color_format: TextureFormat,
+        normal_format: TextureFormat,
+        bind_group_layout: &BindGroupLayout,
+        max_points: Option<usize>,
+    ) -> Self {
+        let mut chunk_size = chunk_capacity(device);
+        if let Some(max_points) = max_points {
+            chunk_size = chunk_size.min(max_points.max(1));
+        }
+        let point_data_layout = PointsPass::create_point_data_layout(device);
+        let material = PointsPass::create_point_material(
+            device,
+            position_format,
+            color_format,
+            normal_format,
+            bind_group_layout,
+            &point_data_layout,
+        );
+        Self {
+            material,
+            point_data_layout,
+            chunks: Vec::new(),
+            spare: Vec::new(),
+            chunk_size: chunk_size as u32,
+            max_points: max_points.map(|max| max.max(1)),
+            next: 0,
+            appended: 0,
+            visible: Vec::new(),
+        }
+    }
+
+    pub fn append(&mut self, device: &Device, queue: &Queue, mut vertices: &[BasicVertex]) {
+        self.appended += vertices.len();
+        while !vertices.is_empty() {
+            let len = self.len();
+            // The oldest points make room for the new ones, one by one
+            if let Some(max) = self.max_points.filter(|&max| len >= max) {
+                let chunk_size = self.chunk_size as usize;
+                let (index, first) = (self.next / chunk_size, self.next % chunk_size);
+                let chunk = &mut self.chunks[index];
+                let count = (chunk.point_count() as usize - first).min(vertices.len());
+                let (now, later) = vertices.split_at(count);
+                chunk.overwrite(queue, first as u32, now);
+                self.next = (self.next + count) % max;
+                vertices = later;
+                continue;
+            }
+
+            let full = self
+                .chunks
+                .last()
+                .is_none_or(|chunk| chunk.point_count() == chunk.capacity());
+            if full {
+                // The last chunk only has room for the points left under the cap
+                let capacity = match self.max_points {
+                    Some(max) => (max - len).min(self.chunk_size as usize) as u32,
+                    None => self.chunk_size,
+                };
+                let chunk = self
+                    .spare
+                    .pop_if(|chunk| chunk.capacity() == capacity)
+                    .unwrap_or_else(|| {
+                        PointBuffers::with_capacity(device, &self.point_data_layout, capacity)
+                    });
+                self.chunks.push(chunk);
+            }
+
+            let chunk = self.chunks.last_mut().unwrap();
+            let room = (chunk.capacity() - chunk.point_count()) as usize;
+            let (now, later) = vertices.split_at(room.min(vertices.len()));
+            chunk.write(queue, now);
+            vertices = later;
+        }
+        // Chunks may have changed, everything is drawn until the next update
+        self.visible = (0..self.chunks.len()).collect();
+    }
+
+    /// Points currently shown
+    fn len(&self) -> usize {
+        self.chunks.iter().map(|c| c.point_count() as usize).sum()
+    }
+
+    /// Removes every point, keeping the buffers for the next ones
+    pub fn clear(&mut self) {
+        for mut chunk in self.chunks.drain(..).rev() {
+            chunk.clear();
+            self.spare.push(chunk);
+        }
+        self.next = 0;
+        self.appended = 0;
+        self.visible.clear();
+    }
+}
+
+impl Object for GrowableObject {
+    fn update(&mut self, context: &UpdateContext) {
+        let frustum = Frustum::new(&context.camera.view_projection(context.aspect_ratio));
+        self.visible.clear();
+        self.visible.extend(
+            self.chunks
+                .iter()
+                .enumerate()
+                .filter(|(_, chunk)| {
+                    let (min, max) = chunk.bounds();
+                    chunk.point_count() > 0 && frustum.intersects_box(&min, &max)
+                })
+                .map(|(i, _)| i),
+        );
+    }
+
+    fn draw<'a>(&'a self, pass: &mut RenderPass<'a>) {
+        pass.set_pipeline(&self.material.render_pipeline);
+        self.draw_points(pass);
+    }
+
+    fn draw_points<'a>(&'a self, pass: &mut RenderPass<'a>) {
+        for &i in &self.visible {
+            self.chunks[i].draw(pass);
+        }
+    }
+
+    fn set_surfels(&mut self, device: &Device, surfels: &[Surfel]) {
+        if surfels.is_empty() {
+            return;
+        }
+        // Surfels come in the order the points were appended in, of which only the last
+        // `len` are left, the oldest one at `next`
+        let len = self.len();
+        let oldest = shown_range(self.appended as u64, self.max_points).start as usize;
+        let mut start = 0;
+        for chunk in &mut self.chunks {
+            let slots: Vec<Surfel> = (start..start + chunk.point_count() as usize)
+                .map(|slot| {
+                    let index = oldest + (slot + len - self.next) % len;
+                    surfels.get(index).copied().unwrap_or(Surfel::zeroed())
+                })
+                .collect();
+            chunk.set_surfels(device, &self.point_data_layout, &slots);
+            start += chunk.point_count() as usize;
+        }
+    }
+
+    fn append_points(&mut self, device: &Device, queue: &Queue, vertices: &[BasicVertex]) {
+        self.append(device, queue, vertices);
+    }
+
+    fn clear_points(&mut self) {
+        self.clear();
+    }
+}
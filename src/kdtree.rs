@@ -0,0 +1,114 @@
+use nalgebra::Vector3;
+
+/// Balanced k-d tree over borrowed points. The tree is implicit: every range of `indices`
+/// holds its splitting point in the middle, smaller coordinates before it and larger after.
+pub struct KdTree<'a> {
+    points: &'a [Vector3<f32>],
+    indices: Vec<u32>,
+}
+
+impl<'a> KdTree<'a> {
+    pub fn new(points: &'a [Vector3<f32>]) -> Self {
+        let mut indices: Vec<u32> = (0..points.len() as u32).collect();
+        Self::build(points, &mut indices, 0);
+        Self { points, indices }
+    }
+
+    fn build(points: &[Vector3<f32>], indices: &mut [u32], depth: usize) {
+        if indices.len() <= 1 {
+            return;
+        }
+        let axis = depth % 3;
+        let mid = indices.len() / 2;
+        indices.select_nth_unstable_by(mid, |&a, &b| {
+            points[a as usize][axis].total_cmp(&points[b as usize][axis])
+        });
+        let (left, right) = indices.split_at_mut(mid);
+        Self::build(points, left, depth + 1);
+        Self::build(points, &mut right[1..], depth + 1);
+    }
+
+    /// Fills `out` with the squared distances and indices of the `k` points closest to `query`,
+    /// nearest first
+    pub fn nearest(&self, query: &Vector3<f32>, k: usize, out: &mut Vec<(f32, u32)>) {
+        out.clear();
+        if k > 0 {
+            self.nearest_in(query, k, out, 0, self.indices.len(), 0);
+        }
+    }
+
+    fn nearest_in(
+        &self,
+        query: &Vector3<f32>,
+        k: usize,
+        out: &mut Vec<(f32, u32)>,
+        start: usize,
+        end: usize,
+        depth: usize,
+    ) {
+        if start >= end {
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let index = self.indices[mid];
+        let point = &self.points[index as usize];
+
+        let distance = (point - query).norm_squared();
+        if out.len() < k || distance < out[out.len() - 1].0 {
+            if out.len() == k {
+                out.pop();
+            }
+            let at = out.partition_point(|&(d, _)| d <= distance);
+            out.insert(at, (distance, index));
+        }
+
+        let axis = depth % 3;
+        let offset = query[axis] - point[axis];
+        let (near, far) = if offset < 0.0 {
+            ((start, mid), (mid + 1, end))
+        } else {
+            ((mid + 1, end), (start, mid))
+        };
+        self.nearest_in(query, k, out, near.0, near.1, depth + 1);
+        if out.len() < k || offset * offset < out[out.len() - 1].0 {
+            self.nearest_in(query, k, out, far.0, far.1, depth + 1);
+        }
+    }
+
+    /// Fills `out` with the squared distances and indices of every point within `radius` of `query`
+    pub fn within(&self, query: &Vector3<f32>, radius: f32, out: &mut Vec<(f32, u32)>) {
+        out.clear();
+        self.within_in(query, radius * radius, out, 0, self.indices.len(), 0);
+    }
+
+    fn within_in(
+        &self,
+        query: &Vector3<f32>,
+        radius_squared: f32,
+        out: &mut Vec<(f32, u32)>,
+        start: usize,
+        end: usize,
+        depth: usize,
+    ) {
+        if start >= end {
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let index = self.indices[mid];
+        let point = &self.points[index as usize];
+
+        let distance = (point - query).norm_squared();
+        if distance <= radius_squared {
+            out.push((distance, index));
+        }
+
+        let axis = depth % 3;
+        let offset = query[axis] - point[axis];
+        if offset < 0.0 || offset * offset <= radius_squared {
+            self.within_in(query, radius_squared, out, start, mid, depth + 1);
+        }
+        if offset >= 0.0 || offset * offset <= radius_squared {
+            self.within_in(query, radius_squared, out, mid + 1, end, depth + 1);
+        }
+    }
+}
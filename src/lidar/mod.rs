@@ -0,0 +1,162 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    path::PathBuf,
+    str::FromStr,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Instant,
+};
+
+use crate::{
+    loader::{convention::Convention, LoadResult},
+    object::BasicVertex,
+};
+
+use self::{ouster::OusterDecoder, pcap::PcapReader, velodyne::VelodyneDecoder};
+
+pub mod ouster;
+pub mod pcap;
+pub mod velodyne;
+
+/// Turns the UDP payloads of one sensor into points
+pub trait PacketDecoder: Send {
+    /// Decodes one packet. Once a packet of the next revolution arrives, the points of the
+    /// finished one are returned.
+    fn decode(&mut self, packet: &[u8]) -> Option<Vec<BasicVertex>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sensor {
+    Velodyne,
+    /// Needs the metadata the sensor reports for its calibration
+    Ouster,
+}
+
+impl FromStr for Sensor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "velodyne" => Ok(Self::Velodyne),
+            "ouster" => Ok(Self::Ouster),
+            _ => Err(format!("Unknown lidar: {s}")),
+        }
+    }
+}
+
+/// Where the packets come from and how they are shown
+#[derive(Debug, Clone)]
+pub struct LidarSettings {
+    pub sensor: Sensor,
+    /// Metadata JSON of an Ouster sensor
+    pub metadata: Option<PathBuf>,
+    /// Packets are received on this address instead of replayed from the input capture
+    pub listen: Option<SocketAddr>,
+    /// Only datagrams of the capture sent to this port are decoded
+    pub port: Option<u16>,
+    /// How many times faster than recorded a capture is replayed, zero for no waiting
+    pub speed: f64,
+    /// Revolutions are added to the previous ones instead of replacing them
+    pub accumulate: bool,
+}
+
+impl Default for LidarSettings {
+    fn default() -> Self {
+        Self {
+            sensor: Sensor::Velodyne,
+            metadata: None,
+            listen: None,
+            port: None,
+            speed: 1.0,
+            accumulate: false,
+        }
+    }
+}
+
+/// Decodes packets on their own thread, from the network or from the `capture` file, and
+/// hands over every completed revolution, turned from the sensor's axes by `convention`
+pub fn spawn(
+    capture: PathBuf,
+    settings: LidarSettings,
+    convention: Convention,
+) -> LoadResult<Receiver<Vec<BasicVertex>>> {
+    let decoder: Box<dyn PacketDecoder> = match settings.sensor {
+        Sensor::Velodyne => Box::<VelodyneDecoder>::default(),
+        Sensor::Ouster => Box::new(OusterDecoder::open(
+            settings
+                .metadata
+                .as_deref()
+                .ok_or("Ouster packets need the sensor metadata")?,
+        )?),
+    };
+    // Opened up front, so a bad input is reported right away
+    let source = match settings.listen {
+        Some(address) => Source::Udp(UdpSocket::bind(address)?),
+        None => Source::Capture(PcapReader::open(&capture)?),
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        if let Err(e) = run(source, decoder, &settings, convention, &sender) {
+            log::error!("Stopped reading lidar packets: {e}");
+        }
+    });
+    Ok(receiver)
+}
+
+enum Source {
+    Udp(UdpSocket),
+    Capture(PcapReader),
+}
+
+fn run(
+    source: Source,
+    mut decoder: Box<dyn PacketDecoder>,
+    settings: &LidarSettings,
+    convention: Convention,
+    sender: &Sender<Vec<BasicVertex>>,
+) -> LoadResult<()> {
+    // Stops once the window is gone and nobody receives the revolutions anymore
+    let mut send = |packet: &[u8]| match decoder.decode(packet) {
+        Some(mut revolution) => {
+            convention.apply_to(&mut revolution);
+            sender.send(revolution).is_ok()
+        }
+        None => true,
+    };
+
+    match source {
+        Source::Udp(socket) => {
+            let mut buffer = vec![0; 65536];
+            loop {
+                let length = socket.recv(&mut buffer)?;
+                if !send(&buffer[..length]) {
+                    return Ok(());
+                }
+            }
+        }
+        Source::Capture(mut capture) => {
+            let mut start = None;
+            let mut packets = 0;
+            while let Some(packet) = capture.next_packet()? {
+                if settings.port.is_some_and(|p| p != packet.port) {
+                    continue;
+                }
+                // Waits until the packet is due, relative to the first one
+                let (first, started) = *start.get_or_insert((packet.time, Instant::now()));
+                if settings.speed > 0.0 {
+                    let due = packet.time.saturating_sub(first).div_f64(settings.speed);
+                    if let Some(wait) = due.checked_sub(started.elapsed()) {
+                        thread::sleep(wait);
+                    }
+                }
+                packets += 1;
+                if !send(&packet.payload) {
+                    return Ok(());
+                }
+            }
+            log::info!("Replayed {packets} lidar packets");
+            Ok(())
+        }
+    }
+}
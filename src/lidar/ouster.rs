@@ -0,0 +1,185 @@
+use std::{f32::consts::TAU, path::Path};
+
+use nalgebra::{vector, Vector3};
+use serde::Deserialize;
+
+use crate::{loader::LoadResult, object::BasicVertex};
+
+use super::PacketDecoder;
+
+/// Bytes before the pixels of a column
+const COLUMN_HEADER: usize = 16;
+/// Bytes after the pixels of a column
+const COLUMN_STATUS: usize = 4;
+/// Bytes per pixel in the legacy packet profile
+const PIXEL_SIZE: usize = 12;
+
+/// The parts of the sensor metadata JSON the decoder needs. Older firmware writes the
+/// fields at the top level, newer firmware groups them.
+#[derive(Deserialize)]
+struct Metadata {
+    beam_altitude_angles: Option<Vec<f32>>,
+    beam_azimuth_angles: Option<Vec<f32>>,
+    lidar_origin_to_beam_origin_mm: Option<f32>,
+    lidar_mode: Option<String>,
+    data_format: Option<DataFormat>,
+    beam_intrinsics: Option<BeamIntrinsics>,
+    lidar_data_format: Option<DataFormat>,
+    config_params: Option<ConfigParams>,
+}
+
+#[derive(Deserialize)]
+struct BeamIntrinsics {
+    beam_altitude_angles: Vec<f32>,
+    beam_azimuth_angles: Vec<f32>,
+    lidar_origin_to_beam_origin_mm: f32,
+}
+
+#[derive(Deserialize)]
+struct DataFormat {
+    pixels_per_column: usize,
+    columns_per_packet: usize,
+    columns_per_frame: usize,
+    udp_profile_lidar: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ConfigParams {
+    lidar_mode: Option<String>,
+    udp_profile_lidar: Option<String>,
+}
+
+/// Decodes Ouster lidar packets in the legacy profile, calibrated by the metadata the
+/// sensor reports
+pub struct OusterDecoder {
+    /// Radians above the horizon of every beam
+    altitude: Vec<f32>,
+    /// Radians every beam points to the side of the encoder angle
+    azimuth: Vec<f32>,
+    /// Meters from the lidar origin to where the beams start
+    beam_origin: f32,
+    columns_per_packet: usize,
+    columns_per_frame: usize,
+    points: Vec<BasicVertex>,
+    frame: Option<u16>,
+}
+
+impl OusterDecoder {
+    pub fn open(metadata: &Path) -> LoadResult<Self> {
+        let metadata: Metadata = serde_json::from_reader(std::fs::File::open(metadata)?)?;
+        let (altitude, azimuth, beam_origin) = match metadata.beam_intrinsics {
+            Some(beams) => (
+                beams.beam_altitude_angles,
+                beams.beam_azimuth_angles,
+                beams.lidar_origin_to_beam_origin_mm,
+            ),
+            None => (
+                metadata
+                    .beam_altitude_angles
+                    .ok_or("The metadata has no beam altitude angles")?,
+                metadata
+                    .beam_azimuth_angles
+                    .ok_or("The metadata has no beam azimuth angles")?,
+                metadata.lidar_origin_to_beam_origin_mm.unwrap_or(0.0),
+            ),
+        };
+        if altitude.len() != azimuth.len() {
+            return Err("The metadata has different numbers of altitude and azimuth angles".into());
+        }
+
+        let config = metadata.config_params;
+        let profile = config
+            .as_ref()
+            .and_then(|c| c.udp_profile_lidar.clone())
+            .or_else(|| {
+                let format = metadata.lidar_data_format.as_ref();
+                format
+                    .or(metadata.data_format.as_ref())?
+                    .udp_profile_lidar
+                    .clone()
+            });
+        if profile.as_deref().is_some_and(|p| p != "LEGACY") {
+            return Err(format!("Unsupported Ouster packet profile {}", profile.unwrap()).into());
+        }
+        let (columns_per_packet, columns_per_frame) =
+            match metadata.lidar_data_format.or(metadata.data_format) {
+                Some(format) if format.pixels_per_column == altitude.len() => {
+                    (format.columns_per_packet, format.columns_per_frame)
+                }
+                Some(_) => return Err("The metadata doesn't match the number of beams".into()),
+                // Modes are named like "1024x10", columns per frame by rotations per second
+                None => {
+                    let mode = config
+                        .and_then(|c| c.lidar_mode)
+                        .or(metadata.lidar_mode)
+                        .ok_or("The metadata has no lidar mode")?;
+                    let columns = mode
+                        .split('x')
+                        .next()
+                        .and_then(|c| c.parse().ok())
+                        .ok_or_else(|| format!("Unknown lidar mode {mode}"))?;
+                    (16, columns)
+                }
+            };
+
+        Ok(Self {
+            altitude: altitude.iter().map(|a| a.to_radians()).collect(),
+            azimuth: azimuth.iter().map(|a| -a.to_radians()).collect(),
+            beam_origin: beam_origin / 1000.0,
+            columns_per_packet,
+            columns_per_frame,
+            points: Vec::new(),
+            frame: None,
+        })
+    }
+
+    fn column_size(&self) -> usize {
+        COLUMN_HEADER + self.altitude.len() * PIXEL_SIZE + COLUMN_STATUS
+    }
+}
+
+impl PacketDecoder for OusterDecoder {
+    fn decode(&mut self, packet: &[u8]) -> Option<Vec<BasicVertex>> {
+        let column_size = self.column_size();
+        if packet.len() != column_size * self.columns_per_packet {
+            return None;
+        }
+        let mut finished = None;
+        for column in packet.chunks_exact(column_size) {
+            let status = u32::from_le_bytes(column[column_size - 4..].try_into().unwrap());
+            if status & 1 == 0 {
+                continue;
+            }
+            let measurement = u16::from_le_bytes([column[8], column[9]]) as usize;
+            let frame = u16::from_le_bytes([column[10], column[11]]);
+            if self.frame.is_some_and(|last| last != frame) {
+                finished = Some(std::mem::take(&mut self.points));
+            }
+            self.frame = Some(frame);
+
+            let encoder = TAU * (1.0 - measurement as f32 / self.columns_per_frame as f32);
+            let pixels =
+                column[COLUMN_HEADER..column_size - COLUMN_STATUS].chunks_exact(PIXEL_SIZE);
+            for (beam, pixel) in pixels.enumerate() {
+                let range = u32::from_le_bytes(pixel[0..4].try_into().unwrap()) & 0xf_ffff;
+                if range == 0 {
+                    continue;
+                }
+                let reflectivity = u16::from_le_bytes([pixel[4], pixel[5]]);
+                // The beams start on a circle around the lidar origin
+                let range = range as f32 / 1000.0 - self.beam_origin;
+                let (altitude, azimuth) = (self.altitude[beam], encoder + self.azimuth[beam]);
+                let position = vector![
+                    range * azimuth.cos() * altitude.cos() + self.beam_origin * encoder.cos(),
+                    range * azimuth.sin() * altitude.cos() + self.beam_origin * encoder.sin(),
+                    range * altitude.sin()
+                ];
+                self.points.push(BasicVertex {
+                    position,
+                    color: Vector3::repeat((reflectivity as f32 / 255.0).min(1.0)),
+                });
+            }
+        }
+        finished
+    }
+}
@@ -0,0 +1,304 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, ErrorKind, Read},
+    path::Path,
+    time::Duration,
+};
+
+use crate::loader::LoadResult;
+
+/// Payload of a captured UDP datagram
+pub struct UdpPacket {
+    /// When it was captured
+    pub time: Duration,
+    pub port: u16,
+    pub payload: Vec<u8>,
+}
+
+const ETHERNET: u32 = 1;
+const RAW_IP: u32 = 101;
+const LINUX_SLL: u32 = 113;
+const IPV4: u32 = 228;
+const LINUX_SLL2: u32 = 276;
+
+#[derive(Clone, Copy)]
+enum Format {
+    /// Classic pcap, with timestamps of this many nanoseconds per fraction unit
+    Pcap { nanos_per_unit: u64, link: u32 },
+    /// pcapng, links and timestamp units are per interface
+    PcapNg,
+}
+
+/// Interface described by a pcapng block
+struct Interface {
+    link: u32,
+    /// Timestamp units per second
+    resolution: u64,
+}
+
+/// IPv4 datagram split into fragments
+#[derive(Default)]
+struct Fragments {
+    parts: Vec<(usize, Vec<u8>)>,
+    /// Known once the last fragment arrived
+    length: Option<usize>,
+}
+
+/// Reads the UDP datagrams of a pcap or pcapng capture, putting fragmented ones back
+/// together
+pub struct PcapReader {
+    input: BufReader<File>,
+    format: Format,
+    big_endian: bool,
+    interfaces: Vec<Interface>,
+    fragments: HashMap<(u16, [u8; 8]), Fragments>,
+}
+
+impl PcapReader {
+    pub fn open(path: &Path) -> LoadResult<Self> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        let mut reader = Self {
+            input,
+            format: Format::PcapNg,
+            big_endian: false,
+            interfaces: Vec::new(),
+            fragments: HashMap::new(),
+        };
+        match magic {
+            [0x0a, 0x0d, 0x0d, 0x0a] => {
+                // The section header comes first and sets the byte order
+                reader.read_block(u32::from_le_bytes(magic))?;
+            }
+            _ => {
+                let (big_endian, nanos_per_unit) = match u32::from_le_bytes(magic) {
+                    0xa1b2_c3d4 => (false, 1000),
+                    0xa1b2_3c4d => (false, 1),
+                    0xd4c3_b2a1 => (true, 1000),
+                    0x4d3c_b2a1 => (true, 1),
+                    _ => return Err(format!("{} isn't a pcap capture", path.display()).into()),
+                };
+                reader.big_endian = big_endian;
+                let header = reader.read_bytes(20)?;
+                let link = reader.u32_at(&header, 16) & 0xffff;
+                reader.format = Format::Pcap {
+                    nanos_per_unit,
+                    link,
+                };
+            }
+        }
+        Ok(reader)
+    }
+
+    /// Next UDP datagram, or `None` at the end of the capture
+    pub fn next_packet(&mut self) -> LoadResult<Option<UdpPacket>> {
+        loop {
+            let (time, link, frame) = match self.format {
+                Format::Pcap {
+                    nanos_per_unit,
+                    link,
+                } => {
+                    let Some(header) = self.read_or_end(16)? else {
+                        return Ok(None);
+                    };
+                    let seconds = self.u32_at(&header, 0) as u64;
+                    let fraction = self.u32_at(&header, 4) as u64 * nanos_per_unit;
+                    let frame = self.read_bytes(self.u32_at(&header, 8) as usize)?;
+                    (Duration::new(seconds, fraction as u32), link, frame)
+                }
+                Format::PcapNg => {
+                    let Some(kind) = self.read_or_end(4)? else {
+                        return Ok(None);
+                    };
+                    let kind = self.u32_at(&kind, 0);
+                    match self.read_block(kind)? {
+                        Some(packet) => packet,
+                        None => continue,
+                    }
+                }
+            };
+            if let Some(packet) = self.udp(time, link, &frame) {
+                return Ok(Some(packet));
+            }
+        }
+    }
+
+    /// Reads the rest of a pcapng block, returning the captured frame if it holds one
+    fn read_block(&mut self, kind: u32) -> LoadResult<Option<(Duration, u32, Vec<u8>)>> {
+        let length = self.read_bytes(4)?;
+        if kind == 0x0a0d_0d0a {
+            let magic = self.read_bytes(4)?;
+            self.big_endian = match u32::from_le_bytes(magic[..].try_into().unwrap()) {
+                0x1a2b_3c4d => false,
+                0x4d3c_2b1a => true,
+                _ => return Err("Corrupt pcapng section header".into()),
+            };
+            // Interface ids start over in every section
+            self.interfaces.clear();
+            let length = self.u32_at(&length, 0) as usize;
+            self.read_bytes(length.saturating_sub(12))?;
+            return Ok(None);
+        }
+        let length = self.u32_at(&length, 0) as usize;
+        let body = self.read_bytes(length.saturating_sub(8))?;
+        match kind {
+            // Interface description
+            1 => {
+                // Still counted, so the interfaces after it keep their ids, but its packets
+                // are on a link nothing is read from
+                if body.len() < 8 {
+                    log::warn!("Skipping a corrupt pcapng interface");
+                    self.interfaces.push(Interface {
+                        link: 0,
+                        resolution: 1_000_000,
+                    });
+                    return Ok(None);
+                }
+                let link = self.u16_at(&body, 0) as u32;
+                let mut resolution = 1_000_000;
+                let mut options = &body[8..body.len().saturating_sub(4).max(8)];
+                while options.len() >= 4 {
+                    let code = self.u16_at(options, 0);
+                    let size = self.u16_at(options, 2) as usize;
+                    let value = &options[4..(4 + size).min(options.len())];
+                    if code == 9 && !value.is_empty() {
+                        resolution = if value[0] & 0x80 == 0 {
+                            10u64.pow(value[0] as u32)
+                        } else {
+                            1 << (value[0] & 0x7f)
+                        };
+                    }
+                    if code == 0 {
+                        break;
+                    }
+                    options = &options[(4 + size.next_multiple_of(4)).min(options.len())..];
+                }
+                self.interfaces.push(Interface { link, resolution });
+                Ok(None)
+            }
+            // Enhanced packet
+            6 => {
+                if body.len() < 20 {
+                    log::warn!("Skipping a corrupt pcapng packet");
+                    return Ok(None);
+                }
+                let interface = self
+                    .interfaces
+                    .get(self.u32_at(&body, 0) as usize)
+                    .ok_or("pcapng packet on an undescribed interface")?;
+                let (link, resolution) = (interface.link, interface.resolution);
+                let units = (self.u32_at(&body, 4) as u64) << 32 | self.u32_at(&body, 8) as u64;
+                let time = Duration::from_secs(units / resolution)
+                    + Duration::from_nanos((units % resolution) * 1_000_000_000 / resolution);
+                let captured = self.u32_at(&body, 12) as usize;
+                let Some(frame) = body.get(20..20 + captured) else {
+                    log::warn!("Skipping a corrupt pcapng packet");
+                    return Ok(None);
+                };
+                Ok(Some((time, link, frame.to_vec())))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Finds the UDP payload in a captured frame
+    fn udp(&mut self, time: Duration, link: u32, frame: &[u8]) -> Option<UdpPacket> {
+        let ip = match link {
+            ETHERNET => {
+                let mut offset = 12;
+                // VLAN tags sit in front of the ethertype
+                while frame.get(offset..offset + 2)? == [0x81, 0x00] {
+                    offset += 4;
+                }
+                (frame.get(offset..offset + 2)? == [0x08, 0x00]).then_some(&frame[offset + 2..])?
+            }
+            RAW_IP | IPV4 => frame,
+            LINUX_SLL => (frame.get(14..16)? == [0x08, 0x00]).then_some(&frame[16..])?,
+            LINUX_SLL2 => (frame.get(0..2)? == [0x08, 0x00]).then_some(frame.get(20..)?)?,
+            _ => return None,
+        };
+
+        // IPv4 only, carrying UDP
+        if ip.len() < 20 || ip[0] >> 4 != 4 || ip[9] != 17 {
+            return None;
+        }
+        let header = (ip[0] & 0xf) as usize * 4;
+        let total = (u16::from_be_bytes([ip[2], ip[3]]) as usize).min(ip.len());
+        let id = u16::from_be_bytes([ip[4], ip[5]]);
+        let flags = u16::from_be_bytes([ip[6], ip[7]]);
+        let more = flags & 0x2000 != 0;
+        let offset = (flags & 0x1fff) as usize * 8;
+        let data = ip.get(header..total)?;
+
+        let datagram = if more || offset > 0 {
+            let key = (id, ip[12..20].try_into().unwrap());
+            let fragments = self.fragments.entry(key).or_default();
+            fragments.parts.push((offset, data.to_vec()));
+            if !more {
+                fragments.length = Some(offset + data.len());
+            }
+            let length = fragments.length?;
+            if fragments.parts.iter().map(|(_, d)| d.len()).sum::<usize>() < length {
+                return None;
+            }
+            let fragments = self.fragments.remove(&key)?;
+            let mut datagram = vec![0; length];
+            // Fragments reaching past the last one make the whole datagram unusable
+            for (offset, data) in fragments.parts {
+                datagram
+                    .get_mut(offset..offset + data.len())?
+                    .copy_from_slice(&data);
+            }
+            datagram
+        } else {
+            data.to_vec()
+        };
+
+        let port = u16::from_be_bytes([*datagram.get(2)?, *datagram.get(3)?]);
+        Some(UdpPacket {
+            time,
+            port,
+            payload: datagram.get(8..)?.to_vec(),
+        })
+    }
+
+    fn read_bytes(&mut self, count: usize) -> LoadResult<Vec<u8>> {
+        let mut bytes = vec![0; count];
+        self.input.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Like `read_bytes`, but `None` when the capture ends right here
+    fn read_or_end(&mut self, count: usize) -> LoadResult<Option<Vec<u8>>> {
+        match self.read_bytes(count) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e)
+                if e.downcast_ref::<std::io::Error>().map(|e| e.kind())
+                    == Some(ErrorKind::UnexpectedEof) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn u32_at(&self, bytes: &[u8], i: usize) -> u32 {
+        let value = bytes[i..i + 4].try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(value)
+        } else {
+            u32::from_le_bytes(value)
+        }
+    }
+
+    fn u16_at(&self, bytes: &[u8], i: usize) -> u16 {
+        let value = bytes[i..i + 2].try_into().unwrap();
+        if self.big_endian {
+            u16::from_be_bytes(value)
+        } else {
+            u16::from_le_bytes(value)
+        }
+    }
+}
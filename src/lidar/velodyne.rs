@@ -0,0 +1,154 @@
+use nalgebra::{vector, Vector3};
+
+use crate::object::BasicVertex;
+
+use super::PacketDecoder;
+
+/// Bytes of UDP payload in every data packet
+const PACKET_SIZE: usize = 1206;
+const BLOCKS: usize = 12;
+const BLOCK_SIZE: usize = 100;
+const CHANNELS: usize = 32;
+/// Meters per unit of the raw distance
+const DISTANCE_UNIT: f32 = 0.002;
+
+/// Angles of every laser of a model, as listed in its manual
+struct Calibration {
+    name: &'static str,
+    /// Degrees above the horizon
+    elevation: &'static [f32],
+    /// Degrees added to the azimuth of the block
+    azimuth_offset: &'static [f32],
+    /// Firing sequences in a block, the VLP-16 fires all its lasers twice per block
+    sequences: usize,
+}
+
+const VLP_16: Calibration = Calibration {
+    name: "VLP-16",
+    elevation: &[
+        -15.0, 1.0, -13.0, 3.0, -11.0, 5.0, -9.0, 7.0, -7.0, 9.0, -5.0, 11.0, -3.0, 13.0, -1.0,
+        15.0,
+    ],
+    azimuth_offset: &[0.0; 16],
+    sequences: 2,
+};
+
+const VLP_32C: Calibration = Calibration {
+    name: "VLP-32C",
+    elevation: &[
+        -25.0, -1.0, -1.667, -15.639, -11.31, 0.0, -0.667, -8.843, -7.254, 0.333, -0.333, -6.148,
+        -5.333, 1.333, 0.667, -4.0, -4.667, 1.667, 1.0, -3.667, -3.333, 3.333, 2.333, -2.667, -3.0,
+        7.0, 4.667, -2.333, -2.0, 15.0, 10.333, -1.333,
+    ],
+    azimuth_offset: &[
+        1.4, -4.2, 1.4, -1.4, 1.4, -1.4, 4.2, -1.4, 1.4, -4.2, 1.4, -1.4, 4.2, -1.4, 4.2, -1.4,
+        1.4, -4.2, 1.4, -4.2, 4.2, -1.4, 1.4, -1.4, 1.4, -1.4, 1.4, -4.2, 4.2, -1.4, 1.4, -1.4,
+    ],
+    sequences: 1,
+};
+
+impl Calibration {
+    /// Picks the table from the product id in the last byte of a packet
+    fn for_product(id: u8) -> Option<&'static Self> {
+        match id {
+            0x22 => Some(&VLP_16),
+            0x28 => Some(&VLP_32C),
+            _ => None,
+        }
+    }
+
+    /// When a channel fires, as a fraction of the time between two blocks
+    fn firing_time(&self, channel: usize) -> f32 {
+        // Lasers fire every 2.304 µs, one sequence takes 55.296 µs including the recharge
+        let lasers = self.elevation.len();
+        let (sequence, laser) = (channel / lasers, channel % lasers);
+        let firing = if self.sequences == 2 {
+            sequence as f32 * 55.296 + laser as f32 * 2.304
+        } else {
+            // The VLP-32C fires its lasers in pairs
+            (laser / 2) as f32 * 2.304
+        };
+        firing / (55.296 * self.sequences as f32)
+    }
+}
+
+/// Decodes VLP-16 and VLP-32C data packets. The model is recognized from the packets.
+#[derive(Default)]
+pub struct VelodyneDecoder {
+    points: Vec<BasicVertex>,
+    /// Azimuth of the last block, in degrees
+    last_azimuth: Option<f32>,
+    model: Option<&'static str>,
+}
+
+impl PacketDecoder for VelodyneDecoder {
+    fn decode(&mut self, packet: &[u8]) -> Option<Vec<BasicVertex>> {
+        if packet.len() != PACKET_SIZE {
+            return None;
+        }
+        let Some(calibration) = Calibration::for_product(packet[PACKET_SIZE - 1]) else {
+            if self.model.is_none() {
+                log::warn!("Unknown Velodyne product id {:#x}", packet[PACKET_SIZE - 1]);
+                self.model = Some("unknown");
+            }
+            return None;
+        };
+        if self.model != Some(calibration.name) {
+            log::info!("Decoding {} packets", calibration.name);
+            self.model = Some(calibration.name);
+        }
+
+        let block = |i: usize| &packet[i * BLOCK_SIZE..(i + 1) * BLOCK_SIZE];
+        let azimuth = |block: &[u8]| u16::from_le_bytes([block[2], block[3]]) as f32 / 100.0;
+        let mut finished = None;
+        let mut step = 0.0;
+        for i in 0..BLOCKS {
+            let data = block(i);
+            if data[0..2] != [0xff, 0xee] {
+                continue;
+            }
+            let start = azimuth(data);
+            // Dual return packets repeat every azimuth, the step to the next one is used
+            step = (i + 1..BLOCKS)
+                .map(|j| (azimuth(block(j)) - start).rem_euclid(360.0))
+                .find(|&step| step > 0.0)
+                .unwrap_or(step);
+            if self.last_azimuth.is_some_and(|last| start < last) {
+                finished = Some(std::mem::take(&mut self.points));
+            }
+            self.last_azimuth = Some(start);
+
+            for channel in 0..CHANNELS {
+                let raw = &data[4 + channel * 3..7 + channel * 3];
+                let distance = u16::from_le_bytes([raw[0], raw[1]]) as f32 * DISTANCE_UNIT;
+                if distance == 0.0 {
+                    continue;
+                }
+                let laser = channel % calibration.elevation.len();
+                let azimuth = start
+                    + step * calibration.firing_time(channel)
+                    + calibration.azimuth_offset[laser];
+                let position = spherical(
+                    distance,
+                    azimuth.to_radians(),
+                    calibration.elevation[laser].to_radians(),
+                );
+                self.points.push(BasicVertex {
+                    position,
+                    color: Vector3::repeat(raw[2] as f32 / 255.0),
+                });
+            }
+        }
+        finished
+    }
+}
+
+/// Azimuth is clockwise from the y axis, as Velodyne defines it
+fn spherical(distance: f32, azimuth: f32, elevation: f32) -> Vector3<f32> {
+    let horizontal = distance * elevation.cos();
+    vector![
+        horizontal * azimuth.sin(),
+        horizontal * azimuth.cos(),
+        distance * elevation.sin()
+    ]
+}
@@ -0,0 +1,255 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+use bytemuck::Zeroable;
+
+use crate::object::{BasicVertex, Surfel};
+
+use super::{
+    cache::{CacheWriter, Cached, SourceKey},
+    convention::Convention,
+    rgbd::{self, RgbdSettings},
+    LoadOptions, LoadResult,
+};
+
+/// Points sent to the window at once
+const BATCH_SIZE: usize = 1 << 19;
+
+pub enum LoadEvent {
+    /// More points, with how many were read so far and how many the file holds, if known
+    Points {
+        vertices: Vec<BasicVertex>,
+        loaded: u64,
+        total: Option<u64>,
+    },
+    /// Orientations stored in the file or its cache, one for every point sent before
+    Surfels(Vec<Surfel>),
+    Finished,
+    Failed(String),
+}
+
+/// Reads a file on its own thread, handing the points over in batches
+pub struct BackgroundLoad {
+    events: Receiver<LoadEvent>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl BackgroundLoad {
+    /// Reads the files one after the other, each from its cache instead when it has an up
+    /// to date one, and writes it otherwise
+    pub fn spawn(files: Vec<(PathBuf, Option<PathBuf>, LoadOptions)>) -> Self {
+        Self::run(files[0].0.clone(), move |sender, cancelled| {
+            read_all(&files, sender, cancelled)
+        })
+    }
+
+    /// Back-projects the depth images of the input, sending every frame once it's done
+    pub fn spawn_rgbd(path: PathBuf, settings: RgbdSettings, convention: Convention) -> Self {
+        Self::run(path.clone(), move |sender, cancelled| {
+            read_rgbd(&path, &settings, convention, sender, cancelled)
+        })
+    }
+
+    fn run(
+        path: PathBuf,
+        read: impl FnOnce(&Sender<LoadEvent>, &AtomicBool) -> LoadResult<()> + Send + 'static,
+    ) -> Self {
+        let (sender, events) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = cancelled.clone();
+        thread::spawn(move || {
+            let start = std::time::Instant::now();
+            match read(&sender, &flag) {
+                Ok(()) if flag.load(Ordering::Relaxed) => {
+                    log::info!("Stopped loading {}", path.display())
+                }
+                Ok(()) => {
+                    log::info!("Loaded {} in {:.2?}", path.display(), start.elapsed());
+                    let _ = sender.send(LoadEvent::Finished);
+                }
+                Err(error) => {
+                    let _ = sender.send(LoadEvent::Failed(error.to_string()));
+                }
+            }
+        });
+        Self { events, cancelled }
+    }
+
+    /// Events which arrived since the last call
+    pub fn poll(&self) -> impl Iterator<Item = LoadEvent> + '_ {
+        self.events.try_iter()
+    }
+
+    /// Stops reading after the current batch
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for BackgroundLoad {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+fn read_all(
+    files: &[(PathBuf, Option<PathBuf>, LoadOptions)],
+    sender: &Sender<LoadEvent>,
+    cancelled: &AtomicBool,
+) -> LoadResult<()> {
+    let mut loaded = 0;
+    let mut stopped = false;
+    let mut surfels: Option<Vec<Surfel>> = None;
+    for (path, cache, options) in files {
+        let before = loaded;
+        let mut send = |vertices: Vec<BasicVertex>, total: Option<u64>| {
+            loaded += vertices.len() as u64;
+            // The share of all the files isn't known before they are all read
+            let total = total.filter(|_| files.len() == 1);
+            let event = LoadEvent::Points {
+                vertices,
+                loaded,
+                total,
+            };
+            // The window is gone when nobody listens anymore
+            stopped = sender.send(event).is_err() || cancelled.load(Ordering::Relaxed);
+            !stopped
+        };
+        let normals = read(path, cache.as_deref(), options, &mut send)?;
+        if stopped {
+            return Ok(());
+        }
+        // Points of files without normals get unknown ones, so every point has one
+        if let Some(normals) = normals {
+            let all = surfels.get_or_insert_with(Vec::new);
+            all.resize(before as usize, Surfel::zeroed());
+            all.extend(normals);
+        }
+    }
+    if let Some(mut surfels) = surfels {
+        surfels.resize(loaded as usize, Surfel::zeroed());
+        let _ = sender.send(LoadEvent::Surfels(surfels));
+    }
+    Ok(())
+}
+
+/// Sends the points of one file, returning its normals if it has any. Stops once `send`
+/// returns false.
+fn read(
+    path: &Path,
+    cache: Option<&Path>,
+    options: &LoadOptions,
+    send: &mut impl FnMut(Vec<BasicVertex>, Option<u64>) -> bool,
+) -> LoadResult<Option<Vec<Surfel>>> {
+    let key = cache.map(|_| SourceKey::of(path)).transpose()?;
+    let mut writer = None;
+    if let (Some(cache), Some(key)) = (cache, &key) {
+        if let Some(cached) = Cached::open(cache, key)? {
+            let (min, max) = cached.bounds();
+            log::info!(
+                "Reading {} points within {:?} - {:?} from {}",
+                cached.vertices().len(),
+                min.as_slice(),
+                max.as_slice(),
+                cache.display()
+            );
+            let total = Some(cached.vertices().len() as u64);
+            for batch in cached.vertices().chunks(BATCH_SIZE) {
+                if !send(batch.to_vec(), total) {
+                    return Ok(None);
+                }
+            }
+            return Ok(cached.surfels().map(<[Surfel]>::to_vec));
+        }
+        // Loading still works without a cache
+        writer = CacheWriter::create(cache, key)
+            .inspect_err(|e| log::warn!("Can't write the cache {}: {e}", cache.display()))
+            .ok();
+    }
+    // Only LAS files can be read incrementally, the rest arrives in one go
+    let mut surfels = None;
+    if !super::is_las(path) {
+        let cloud = super::load(path, options)?;
+        let total = Some(cloud.vertices.len() as u64);
+        for batch in cloud.vertices.chunks(BATCH_SIZE) {
+            write_cache(&mut writer, batch);
+            if !send(batch.to_vec(), total) {
+                if let Some(writer) = writer {
+                    writer.discard();
+                }
+                return Ok(None);
+            }
+        }
+        surfels = cloud.normals.map(|normals| {
+            normals
+                .into_iter()
+                .map(|normal| Surfel {
+                    normal,
+                    radius: 0.0,
+                })
+                .collect::<Vec<_>>()
+        });
+    } else {
+        // Every batch is decoded on all cores
+        let layout = super::las::Layout::read(path, options)?;
+        let total = Some(layout.point_count);
+        for start in (0..layout.point_count).step_by(BATCH_SIZE) {
+            let end = (start + BATCH_SIZE as u64).min(layout.point_count);
+            let batch = super::las::read_parallel(path, &layout, start..end)?;
+            write_cache(&mut writer, &batch);
+            if !send(batch, total) {
+                if let Some(writer) = writer {
+                    writer.discard();
+                }
+                return Ok(None);
+            }
+        }
+    }
+
+    if let Some(writer) = writer {
+        if let Err(e) = writer.finish(surfels.as_deref()) {
+            log::warn!("Can't write the cache: {e}");
+        }
+    }
+    Ok(surfels)
+}
+
+fn read_rgbd(
+    path: &Path,
+    settings: &RgbdSettings,
+    convention: Convention,
+    sender: &Sender<LoadEvent>,
+    cancelled: &AtomicBool,
+) -> LoadResult<()> {
+    let (frames, depth_scale) = rgbd::frames(path, settings)?;
+    let mut loaded = 0;
+    for frame in &frames {
+        let mut vertices = rgbd::back_project(frame, depth_scale, settings)?;
+        convention.apply_to(&mut vertices);
+        loaded += vertices.len() as u64;
+        let event = LoadEvent::Points {
+            vertices,
+            loaded,
+            total: None,
+        };
+        if sender.send(event).is_err() || cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Stops writing the cache after the first error
+fn write_cache(writer: &mut Option<CacheWriter>, vertices: &[BasicVertex]) {
+    if let Some(Err(e)) = writer.as_mut().map(|out| out.write(vertices)) {
+        log::warn!("Can't write the cache: {e}");
+        writer.take().unwrap().discard();
+    }
+}
@@ -0,0 +1,301 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use memmap2::Mmap;
+use nalgebra::Vector3;
+
+use crate::object::{BasicVertex, Surfel};
+
+use super::LoadResult;
+
+const MAGIC: &[u8; 4] = b"PCCA";
+const VERSION: u32 = 2;
+const HEADER_SIZE: usize = 64;
+const EXTENSION: &str = "pccache";
+/// Set when the cache holds a surfel for every point
+const HAS_SURFELS: u32 = 1;
+
+/// Where the cache of a source file is kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheLocation {
+    /// `<source>.pccache` in the directory of the source
+    NextToSource,
+    /// A file named after the source path in a directory of its own
+    Directory(PathBuf),
+}
+
+impl CacheLocation {
+    /// Cache file of a source
+    pub fn path_for(&self, source: &Path) -> PathBuf {
+        match self {
+            Self::NextToSource => {
+                let mut name = source.as_os_str().to_owned();
+                name.push(".");
+                name.push(EXTENSION);
+                PathBuf::from(name)
+            }
+            Self::Directory(directory) => {
+                let source = fs::canonicalize(source).unwrap_or_else(|_| source.to_owned());
+                // FNV-1a, so the name stays the same between builds
+                let hash = source
+                    .to_string_lossy()
+                    .bytes()
+                    .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+                        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+                    });
+                directory.join(format!("{hash:016x}.{EXTENSION}"))
+            }
+        }
+    }
+}
+
+/// Identifies one version of a source file. A cache built from another version is
+/// ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceKey {
+    path: String,
+    size: u64,
+    /// Nanoseconds since the Unix epoch
+    modified: u64,
+}
+
+impl SourceKey {
+    pub fn of(source: &Path) -> LoadResult<Self> {
+        let metadata = fs::metadata(source)?;
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+        let path = fs::canonicalize(source)?.to_string_lossy().into_owned();
+        Ok(Self {
+            path,
+            size: metadata.len(),
+            modified,
+        })
+    }
+
+    /// Bytes taken by the path, padded so the arrays after it stay aligned
+    fn padded_path_len(&self) -> usize {
+        self.path.len().next_multiple_of(16)
+    }
+
+    /// Writes the key alone, for outputs which aren't caches themselves, along with the
+    /// version of their format and the settings they were made with
+    pub fn write(&self, path: &Path, version: u32, settings: &str) -> LoadResult<()> {
+        fs::write(path, self.key_file(version, settings))?;
+        Ok(())
+    }
+
+    /// Whether a key written by [`SourceKey::write`] matches this one, the version and the
+    /// settings
+    pub fn matches_file(&self, path: &Path, version: u32, settings: &str) -> bool {
+        fs::read(path).is_ok_and(|bytes| bytes == self.key_file(version, settings))
+    }
+
+    fn key_file(&self, version: u32, settings: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.extend_from_slice(&(settings.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.size.to_le_bytes());
+        bytes.extend_from_slice(&self.modified.to_le_bytes());
+        bytes.extend_from_slice(settings.as_bytes());
+        bytes.extend_from_slice(self.path.as_bytes());
+        bytes
+    }
+}
+
+/// Cache file mapped into memory
+pub struct Cached {
+    map: Mmap,
+    count: usize,
+    /// Where the vertices start
+    offset: usize,
+    has_surfels: bool,
+}
+
+impl Cached {
+    /// Maps the cache, or returns `None` when there's none or it belongs to another
+    /// version of the source
+    pub fn open(cache: &Path, key: &SourceKey) -> LoadResult<Option<Self>> {
+        let Ok(file) = File::open(cache) else {
+            return Ok(None);
+        };
+        // Only the viewer writes caches, always to a new file which then replaces the old
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < HEADER_SIZE || &map[0..4] != MAGIC || u32_at(&map, 4) != VERSION {
+            return Ok(None);
+        }
+        let path_len = u32_at(&map, 36) as usize;
+        let offset = HEADER_SIZE + path_len.next_multiple_of(16);
+        let stored = SourceKey {
+            path: String::from_utf8_lossy(
+                map.get(HEADER_SIZE..HEADER_SIZE + path_len).unwrap_or(&[]),
+            )
+            .into_owned(),
+            size: u64_at(&map, 8),
+            modified: u64_at(&map, 16),
+        };
+        if stored != *key {
+            return Ok(None);
+        }
+
+        let count = u64_at(&map, 24) as usize;
+        let has_surfels = u32_at(&map, 32) & HAS_SURFELS != 0;
+        let point_size = std::mem::size_of::<BasicVertex>()
+            + if has_surfels {
+                std::mem::size_of::<Surfel>()
+            } else {
+                0
+            };
+        // Left over from an interrupted write, the cache is built again
+        let expected = count
+            .checked_mul(point_size)
+            .and_then(|bytes| bytes.checked_add(offset));
+        if expected != Some(map.len()) {
+            log::warn!("Cache {} is truncated, rebuilding it", cache.display());
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            map,
+            count,
+            offset,
+            has_surfels,
+        }))
+    }
+
+    pub fn vertices(&self) -> &[BasicVertex] {
+        let end = self.offset + self.count * std::mem::size_of::<BasicVertex>();
+        bytemuck::cast_slice(&self.map[self.offset..end])
+    }
+
+    pub fn surfels(&self) -> Option<&[Surfel]> {
+        let start = self.offset + self.count * std::mem::size_of::<BasicVertex>();
+        self.has_surfels
+            .then(|| bytemuck::cast_slice(&self.map[start..]))
+    }
+
+    /// Corners of the box holding every point
+    pub fn bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let f32_at = |i: usize| f32::from_le_bytes(self.map[i..i + 4].try_into().unwrap());
+        (
+            Vector3::new(f32_at(40), f32_at(44), f32_at(48)),
+            Vector3::new(f32_at(52), f32_at(56), f32_at(60)),
+        )
+    }
+}
+
+/// Writes a cache as the points arrive. Nothing replaces the previous cache until
+/// [`CacheWriter::finish`].
+pub struct CacheWriter {
+    out: BufWriter<File>,
+    temporary: PathBuf,
+    cache: PathBuf,
+    count: u64,
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+}
+
+impl CacheWriter {
+    pub fn create(cache: &Path, key: &SourceKey) -> LoadResult<Self> {
+        if let Some(directory) = cache.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let temporary = cache.with_extension("tmp");
+        let mut out = BufWriter::new(File::create(&temporary)?);
+        // The counts and bounds are filled in at the end
+        let mut header = [0; HEADER_SIZE];
+        header[0..4].copy_from_slice(MAGIC);
+        header[4..8].copy_from_slice(&VERSION.to_le_bytes());
+        header[8..16].copy_from_slice(&key.size.to_le_bytes());
+        header[16..24].copy_from_slice(&key.modified.to_le_bytes());
+        header[36..40].copy_from_slice(&(key.path.len() as u32).to_le_bytes());
+        out.write_all(&header)?;
+        let mut path = key.path.clone().into_bytes();
+        path.resize(key.padded_path_len(), 0);
+        out.write_all(&path)?;
+        Ok(Self {
+            out,
+            temporary,
+            cache: cache.to_owned(),
+            count: 0,
+            min: Vector3::repeat(f32::INFINITY),
+            max: Vector3::repeat(f32::NEG_INFINITY),
+        })
+    }
+
+    pub fn write(&mut self, vertices: &[BasicVertex]) -> LoadResult<()> {
+        for vertex in vertices {
+            self.min = self.min.inf(&vertex.position);
+            self.max = self.max.sup(&vertex.position);
+        }
+        self.count += vertices.len() as u64;
+        self.out.write_all(bytemuck::cast_slice(vertices))?;
+        Ok(())
+    }
+
+    /// Removes the incomplete cache
+    pub fn discard(self) {
+        drop(self.out);
+        let _ = fs::remove_file(&self.temporary);
+    }
+
+    /// Completes the cache, with the surfels of every written point if there are any
+    pub fn finish(mut self, surfels: Option<&[Surfel]>) -> LoadResult<()> {
+        if let Some(surfels) = surfels {
+            self.out.write_all(bytemuck::cast_slice(surfels))?;
+        }
+        let mut file = self.out.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(24))?;
+        file.write_all(&self.count.to_le_bytes())?;
+        let flags = if surfels.is_some() { HAS_SURFELS } else { 0 };
+        file.write_all(&flags.to_le_bytes())?;
+        file.seek(SeekFrom::Start(40))?;
+        for value in self.min.iter().chain(self.max.iter()) {
+            file.write_all(&value.to_le_bytes())?;
+        }
+        drop(file);
+        fs::rename(&self.temporary, &self.cache)?;
+        Ok(())
+    }
+}
+
+/// Adds surfels computed after the cache was written, replacing any it had. The cache is
+/// copied to a new file which then replaces it, as the old one may still be mapped.
+pub fn store_surfels(cache: &Path, surfels: &[Surfel]) -> LoadResult<()> {
+    let mut input = BufReader::new(File::open(cache)?);
+    let mut header = [0; HEADER_SIZE];
+    input.read_exact(&mut header)?;
+    if &header[0..4] != MAGIC || u32_at(&header, 4) != VERSION {
+        return Err(format!("{} isn't a point cache", cache.display()).into());
+    }
+    let count = u64_at(&header, 24) as usize;
+    if surfels.len() != count {
+        return Err("The surfels don't match the cached points".into());
+    }
+    let flags = u32_at(&header, 32) | HAS_SURFELS;
+    header[32..36].copy_from_slice(&flags.to_le_bytes());
+    let path_len = u32_at(&header, 36) as usize;
+    let kept = (path_len.next_multiple_of(16) + count * std::mem::size_of::<BasicVertex>()) as u64;
+
+    let temporary = cache.with_extension("tmp");
+    let mut out = BufWriter::new(File::create(&temporary)?);
+    out.write_all(&header)?;
+    if io::copy(&mut input.take(kept), &mut out)? != kept {
+        drop(out);
+        let _ = fs::remove_file(&temporary);
+        return Err(format!("{} is truncated", cache.display()).into());
+    }
+    out.write_all(bytemuck::cast_slice(surfels))?;
+    drop(out.into_inner().map_err(|e| e.into_error())?);
+    fs::rename(&temporary, cache)?;
+    Ok(())
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], i: usize) -> u64 {
+    u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap())
+}
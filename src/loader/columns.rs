@@ -0,0 +1,247 @@
+use std::str::FromStr;
+
+use nalgebra::{vector, Vector3};
+
+use crate::object::BasicVertex;
+
+use super::LoadResult;
+
+/// Column names tried for every role when the mapping leaves it out
+const X: &[&str] = &["x"];
+const Y: &[&str] = &["y"];
+const Z: &[&str] = &["z"];
+const RED: &[&str] = &["r", "red"];
+const GREEN: &[&str] = &["g", "green"];
+const BLUE: &[&str] = &["b", "blue"];
+const SCALAR: &[&str] = &["intensity", "scalar", "value"];
+/// Arrays whose columns are positions or colors, like `points.0`, `points.1` and `points.2`
+const POSITION_ARRAYS: &[&str] = &["points", "xyz", "positions", "position", "pos"];
+const COLOR_ARRAYS: &[&str] = &["colors", "color", "rgb"];
+
+/// Which columns of a table or array hold what. Roles left out are guessed from the column
+/// names. Columns of unnamed arrays are named by their index, `0` to `n - 1`, and those of
+/// arrays in an archive by the array and the index, like `points.0`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnMapping {
+    pub x: Option<String>,
+    pub y: Option<String>,
+    pub z: Option<String>,
+    pub r: Option<String>,
+    pub g: Option<String>,
+    pub b: Option<String>,
+    /// Shown in grayscale when there's no color
+    pub scalar: Option<String>,
+}
+
+impl FromStr for ColumnMapping {
+    type Err = String;
+
+    /// Parses `role=column` pairs separated by commas, such as `x=lon,y=lat,z=height`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mapping = Self::default();
+        for pair in s.split(',') {
+            let (role, column) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Expected role=column but got {pair}"))?;
+            let column = Some(column.trim().to_string());
+            match role.trim() {
+                "x" => mapping.x = column,
+                "y" => mapping.y = column,
+                "z" => mapping.z = column,
+                "r" => mapping.r = column,
+                "g" => mapping.g = column,
+                "b" => mapping.b = column,
+                "scalar" => mapping.scalar = column,
+                other => return Err(format!("Unknown column role: {other}")),
+            }
+        }
+        Ok(mapping)
+    }
+}
+
+/// Indices of the columns the points are made of
+#[derive(Debug, Clone, Copy)]
+pub struct Columns {
+    pub position: [usize; 3],
+    pub color: Option<[usize; 3]>,
+    pub scalar: Option<usize>,
+}
+
+impl ColumnMapping {
+    /// Whether every role is guessed from the column names
+    pub fn is_auto(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Finds the columns among `names`
+    pub fn resolve(&self, names: &[String]) -> LoadResult<Columns> {
+        let find = |name: &str| {
+            names
+                .iter()
+                .position(|n| n == name)
+                .ok_or_else(|| format!("No column named {name}, there are {}", names.join(", ")))
+        };
+        let guess = |candidates: &[&str]| {
+            candidates
+                .iter()
+                .find_map(|c| names.iter().position(|n| n.eq_ignore_ascii_case(c)))
+        };
+        let pick = |chosen: &Option<String>, candidates: &[&str]| -> LoadResult<Option<usize>> {
+            match chosen {
+                Some(name) => Ok(Some(find(name)?)),
+                None => Ok(guess(candidates)),
+            }
+        };
+        // Columns of the array named `prefix`, in order
+        let array = |prefix: &str| -> Vec<usize> {
+            let name = |i: usize| match prefix {
+                "" => i.to_string(),
+                _ => format!("{prefix}.{i}"),
+            };
+            (0..)
+                .map_while(|i| names.iter().position(|n| *n == name(i)))
+                .collect()
+        };
+        // The arrays in the file, the unnamed one first
+        let prefixes: Vec<&str> = std::iter::once("")
+            .chain(names.iter().filter_map(|n| Some(n.rsplit_once('.')?.0)))
+            .collect();
+        let prefer = |preferred: &[&str]| {
+            preferred
+                .iter()
+                .find(|p| prefixes.contains(p))
+                .copied()
+                .into_iter()
+                .chain(prefixes.iter().copied())
+                .map(array)
+                .find(|columns| columns.len() >= 3)
+        };
+
+        let named = [pick(&self.x, X)?, pick(&self.y, Y)?, pick(&self.z, Z)?];
+        // An array of n points by 3 or more values holds positions, then a color or a scalar
+        let points = prefer(POSITION_ARRAYS).unwrap_or_default();
+        let position = match named {
+            [Some(x), Some(y), Some(z)] => [x, y, z],
+            _ if points.len() >= 3 => [points[0], points[1], points[2]],
+            _ => {
+                return Err(format!(
+                    "No x, y and z columns among {}, map them with --columns",
+                    names.join(", ")
+                )
+                .into())
+            }
+        };
+        let from_points = |range: std::ops::Range<usize>| {
+            (named.iter().all(Option::is_none) && points.len() >= range.end)
+                .then(|| points[range].to_vec())
+        };
+
+        // A scalar asked for is shown instead of the colors the file happens to have
+        let color = match [
+            pick(&self.r, RED)?,
+            pick(&self.g, GREEN)?,
+            pick(&self.b, BLUE)?,
+        ] {
+            [Some(r), Some(g), Some(b)] if self.scalar.is_none() || self.r.is_some() => {
+                Some([r, g, b])
+            }
+            _ if self.scalar.is_some() => None,
+            _ => from_points(3..6)
+                .or_else(|| {
+                    COLOR_ARRAYS
+                        .iter()
+                        .map(|p| array(p))
+                        .find(|columns| columns.len() >= 3)
+                })
+                .map(|c| [c[0], c[1], c[2]]),
+        };
+        let scalar = match pick(&self.scalar, SCALAR)? {
+            Some(scalar) => Some(scalar),
+            None if points.len() == 4 => from_points(3..4).map(|c| c[0]),
+            None => None,
+        };
+        Ok(Columns {
+            position,
+            color,
+            scalar,
+        })
+    }
+}
+
+impl Columns {
+    /// Every column needed, without duplicates
+    pub fn indices(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = self
+            .position
+            .iter()
+            .chain(self.color.iter().flatten())
+            .chain(&self.scalar)
+            .copied()
+            .collect();
+        indices.sort();
+        indices.dedup();
+        indices
+    }
+
+    /// Makes points of the values of the columns. Colors are scaled down from bytes or
+    /// 16-bit values when they go beyond 1, scalars are stretched from black to white over
+    /// their range. Points without a finite position are skipped.
+    pub fn vertices<'a>(
+        &self,
+        column: impl Fn(usize) -> &'a [f64],
+    ) -> LoadResult<Vec<BasicVertex>> {
+        let [x, y, z] = self.position.map(&column);
+        let count = x.len();
+        let color = self.color.map(|c| c.map(&column));
+        let scalar = self.scalar.map(&column);
+        let lengths = [y, z]
+            .into_iter()
+            .chain(color.iter().flatten().copied())
+            .chain(scalar);
+        if lengths.into_iter().any(|c| c.len() != count) {
+            return Err("The mapped columns have different lengths".into());
+        }
+
+        let color_scale = color.map(|channels| {
+            let max = channels
+                .iter()
+                .flat_map(|c| c.iter())
+                .copied()
+                .filter(|c| c.is_finite())
+                .fold(0.0, f64::max);
+            match max {
+                m if m <= 1.0 => 1.0,
+                m if m <= 255.0 => 255.0,
+                _ => 65535.0,
+            }
+        });
+        let scalar_range = scalar.map(|values| {
+            let finite = values.iter().copied().filter(|v| v.is_finite());
+            let min = finite.clone().fold(f64::INFINITY, f64::min);
+            let max = finite.fold(f64::NEG_INFINITY, f64::max);
+            (min, (max - min).max(f64::EPSILON))
+        });
+
+        let mut vertices = Vec::with_capacity(count);
+        for i in 0..count {
+            let position = vector![x[i], y[i], z[i]].cast::<f32>();
+            if !position.iter().all(|c| c.is_finite()) {
+                continue;
+            }
+            let color = match (color, color_scale, scalar, scalar_range) {
+                (Some([r, g, b]), Some(scale), _, _) => {
+                    // Missing channels are dark
+                    vector![r[i], g[i], b[i]]
+                        .map(|c| if c.is_finite() { c / scale } else { 0.0 })
+                        .cast::<f32>()
+                }
+                (_, _, Some(values), Some((min, range))) => {
+                    Vector3::repeat(((values[i] - min) / range).clamp(0.0, 1.0) as f32)
+                }
+                _ => Vector3::zeros(),
+            };
+            vertices.push(BasicVertex { position, color });
+        }
+        Ok(vertices)
+    }
+}
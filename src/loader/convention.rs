@@ -0,0 +1,149 @@
+use std::str::FromStr;
+
+use nalgebra::{vector, Point3, Vector3};
+
+use crate::object::BasicVertex;
+
+/// Axis of a file pointing up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpAxis {
+    Y,
+    Z,
+}
+
+impl FromStr for UpAxis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "y" => Ok(Self::Y),
+            "z" => Ok(Self::Z),
+            _ => Err(format!("Unknown up axis: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handedness {
+    Left,
+    Right,
+}
+
+impl FromStr for Handedness {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            _ => Err(format!("Unknown handedness: {s}")),
+        }
+    }
+}
+
+/// Axes of a file which point the opposite way, such as `x` or `yz`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Flip(pub [bool; 3]);
+
+impl FromStr for Flip {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut axes = [false; 3];
+        for axis in s.chars() {
+            match axis {
+                'x' => axes[0] = true,
+                'y' => axes[1] = true,
+                'z' => axes[2] = true,
+                _ => return Err(format!("Unknown axis: {axis}")),
+            }
+        }
+        Ok(Self(axes))
+    }
+}
+
+/// How the axes of a file are laid out. Points are turned into the axes of the viewer,
+/// which are right-handed with y up, so the camera's up is the file's up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Convention {
+    pub up: UpAxis,
+    pub handedness: Handedness,
+    pub flip: Flip,
+}
+
+impl Convention {
+    /// LAS files, their octrees and lidar sensors, with z up and y pointing north or ahead
+    pub const Z_UP: Self = Self {
+        up: UpAxis::Z,
+        handedness: Handedness::Right,
+        flip: Flip([false; 3]),
+    };
+    /// The viewer itself, and the files without a convention of their own
+    pub const Y_UP: Self = Self {
+        up: UpAxis::Y,
+        handedness: Handedness::Right,
+        flip: Flip([false; 3]),
+    };
+
+    /// Position or direction in the axes of the viewer
+    pub fn apply(&self, mut v: Vector3<f32>) -> Vector3<f32> {
+        for (c, flip) in v.iter_mut().zip(self.flip.0) {
+            if flip {
+                *c = -*c;
+            }
+        }
+        // Mirrors the axis which is neither x nor up
+        if self.handedness == Handedness::Left {
+            match self.up {
+                UpAxis::Y => v.z = -v.z,
+                UpAxis::Z => v.y = -v.y,
+            }
+        }
+        match self.up {
+            UpAxis::Y => v,
+            // Turned about x, so what was ahead goes into the screen
+            UpAxis::Z => vector![v.x, v.z, -v.y],
+        }
+    }
+
+    /// Turns the positions of the points, which is nothing for the viewer's own axes
+    pub fn apply_to(&self, vertices: &mut [BasicVertex]) {
+        if *self != Self::Y_UP {
+            for vertex in vertices {
+                vertex.position = self.apply(vertex.position);
+            }
+        }
+    }
+
+    /// Corner with the smallest coordinates, in the axes of the viewer, of a cube
+    pub fn cube_min(&self, min: Vector3<f64>, size: f64) -> Point3<f32> {
+        let a = self.apply(min.map(|c| c as f32));
+        let b = self.apply((min + Vector3::repeat(size)).map(|c| c as f32));
+        Point3::from(a.inf(&b))
+    }
+}
+
+/// Convention of an input, where anything left unset is the one of its format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConventionSettings {
+    pub up: Option<UpAxis>,
+    pub handedness: Option<Handedness>,
+    pub flip: Flip,
+}
+
+impl ConventionSettings {
+    /// Keeps the axes of any file as they are stored
+    pub const AS_STORED: Self = Self {
+        up: Some(UpAxis::Y),
+        handedness: Some(Handedness::Right),
+        flip: Flip([false; 3]),
+    };
+
+    pub fn resolve(&self, native: Convention) -> Convention {
+        Convention {
+            up: self.up.unwrap_or(native.up),
+            handedness: self.handedness.unwrap_or(native.handedness),
+            flip: self.flip,
+        }
+    }
+}
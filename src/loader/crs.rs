@@ -0,0 +1,651 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use nalgebra::Vector3;
+use proj4rs::proj::Proj;
+
+use crate::object::BasicVertex;
+
+use super::LoadResult;
+
+/// Meters in a US survey foot
+const US_FOOT: f64 = 1200.0 / 3937.0;
+/// Meters in an international foot
+const FOOT: f64 = 0.3048;
+/// GeoKey value of codes which aren't in the EPSG registry
+const USER_DEFINED: u16 = 32767;
+
+/// Coordinate reference system of a file, as far as its metadata tells
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crs {
+    pub name: String,
+    /// EPSG code of the horizontal system
+    pub epsg: Option<u32>,
+    /// Meters in a unit of the horizontal coordinates, unknown for angles
+    pub horizontal_unit: Option<f64>,
+    /// Meters in a unit of the heights
+    pub vertical_unit: Option<f64>,
+}
+
+impl fmt::Display for Crs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(epsg) = self.epsg {
+            write!(f, ", EPSG:{epsg}")?;
+        }
+        if let Some(unit) = self.vertical_unit.filter(|&u| u != 1.0) {
+            write!(f, ", heights in {}", unit_name(unit))?;
+        }
+        Ok(())
+    }
+}
+
+impl Crs {
+    /// Reads the WKT or GeoKey VLRs of a LAS file. The WKT is preferred when a file has
+    /// both, like LAS 1.4 asks.
+    pub fn read(path: &Path) -> LoadResult<Option<Self>> {
+        let (_, header) = super::las::read_header(path)?;
+        let vlrs: Vec<&las::Vlr> = header.vlrs().iter().chain(header.evlrs()).collect();
+        let record = |id: u16| {
+            vlrs.iter()
+                .find(|vlr| vlr.user_id == "LASF_Projection" && vlr.record_id == id)
+                .map(|vlr| &vlr.data[..])
+        };
+        if let Some(wkt) = record(2112) {
+            let wkt = String::from_utf8_lossy(wkt);
+            return Ok(Self::from_wkt(wkt.trim_end_matches('\0')));
+        }
+        Ok(record(34735).and_then(|keys| Self::from_geokeys(keys, record(34736), record(34737))))
+    }
+
+    /// Reads the horizontal system of OGC WKT, version 1 or 2, and the vertical one of
+    /// compound systems
+    pub fn from_wkt(wkt: &str) -> Option<Self> {
+        let root = Wkt::parse(wkt)?;
+        let (horizontal, vertical) = match root.keyword() {
+            "COMPD_CS" | "COMPOUNDCRS" => (
+                root.children().find(|c| is_horizontal(c.keyword()))?,
+                root.children().find(|c| is_vertical(c.keyword())),
+            ),
+            keyword if is_horizontal(keyword) => (&root, None),
+            _ => return None,
+        };
+        let projected = matches!(horizontal.keyword(), "PROJCS" | "PROJCRS" | "PROJECTEDCRS");
+        let horizontal_unit = projected.then(|| horizontal.unit().unwrap_or(1.0));
+        Some(Self {
+            name: root.name().unwrap_or("Unnamed").to_owned(),
+            epsg: horizontal.epsg(),
+            horizontal_unit,
+            vertical_unit: vertical
+                .map(|v| v.unit().unwrap_or(1.0))
+                .or(horizontal_unit)
+                .or(Some(1.0)),
+        })
+    }
+
+    /// Reads a GeoTIFF key directory, with the doubles and text it refers to
+    pub fn from_geokeys(keys: &[u8], doubles: Option<&[u8]>, ascii: Option<&[u8]>) -> Option<Self> {
+        let shorts: Vec<u16> = keys
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        let doubles: Vec<f64> = doubles
+            .unwrap_or_default()
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        let ascii = ascii.map(String::from_utf8_lossy).unwrap_or_default();
+
+        let count = *shorts.get(3)? as usize;
+        let entries = shorts.get(4..4 + count * 4)?;
+        let key = |id: u16| entries.chunks_exact(4).find(|entry| entry[0] == id);
+        let short = |id| key(id).filter(|entry| entry[1] == 0).map(|entry| entry[3]);
+        let double = |id| {
+            key(id)
+                .filter(|entry| entry[1] == 34736)
+                .and_then(|entry| doubles.get(entry[3] as usize).copied())
+        };
+        let text = |id| {
+            key(id).filter(|entry| entry[1] == 34737).and_then(|entry| {
+                let start = entry[3] as usize;
+                let text = ascii.get(start..start + entry[2] as usize)?;
+                Some(text.trim_end_matches(['|', '\0']).to_owned())
+            })
+        };
+        let code = |id| short(id).filter(|&c| c != 0 && c != USER_DEFINED);
+
+        let projected = code(3072).map(u32::from);
+        let geographic = code(2048).map(u32::from);
+        let known = projected.or(geographic).and_then(known);
+        let horizontal_unit = match projected.is_some() || short(1024) == Some(1) {
+            true => code(3076)
+                .and_then(linear_unit)
+                .or_else(|| double(3077))
+                .or(known.as_ref().map(|k| k.unit))
+                .or(Some(1.0)),
+            false => None,
+        };
+        let name = known
+            .as_ref()
+            .map(|k| k.name.clone())
+            .or_else(|| text(3073))
+            .or_else(|| text(1026))
+            .or_else(|| text(2049))
+            .or_else(|| projected.or(geographic).map(|code| format!("EPSG:{code}")))?;
+        Some(Self {
+            name,
+            epsg: projected.or(geographic),
+            horizontal_unit,
+            vertical_unit: code(4099)
+                .and_then(linear_unit)
+                .or_else(|| code(4096).and_then(vertical_unit))
+                .or(horizontal_unit)
+                .or(Some(1.0)),
+        })
+    }
+
+    /// Whether points of both systems line up without reprojecting them
+    pub fn matches(&self, other: &Crs) -> bool {
+        let horizontal = match (self.epsg, other.epsg) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.name == other.name,
+        };
+        horizontal && self.vertical_unit == other.vertical_unit
+    }
+
+    /// Projection string of the system, with the unit of its heights
+    fn proj_string(&self) -> LoadResult<String> {
+        let epsg = self
+            .epsg
+            .ok_or_else(|| format!("{} has no EPSG code to reproject it with", self.name))?;
+        let mut proj = CrsSpec::Epsg(epsg).proj_string()?;
+        if let Some(unit) = self.vertical_unit {
+            proj.push_str(&format!(" +vto_meter={unit}"));
+        }
+        Ok(proj)
+    }
+}
+
+/// Reads the systems of every LAS file, warns when they don't agree and returns the first
+pub fn check(paths: &[&Path]) -> Option<Crs> {
+    let mut first: Option<(&Path, Crs)> = None;
+    for &path in paths.iter().filter(|path| super::is_las(path)) {
+        let crs = match Crs::read(path) {
+            Ok(Some(crs)) => crs,
+            Ok(None) => {
+                log::info!("{} has no coordinate reference system", path.display());
+                continue;
+            }
+            Err(e) => {
+                log::warn!(
+                    "Can't read the coordinate system of {}: {e}",
+                    path.display()
+                );
+                continue;
+            }
+        };
+        log::info!("{} is in {crs}", path.display());
+        match &first {
+            Some((other, expected)) if !expected.matches(&crs) => log::warn!(
+                "{} is in {crs}, but {} is in {expected}",
+                path.display(),
+                other.display()
+            ),
+            Some(_) => {}
+            None => first = Some((path, crs)),
+        }
+    }
+    first.map(|(_, crs)| crs)
+}
+
+/// System given on the command line, by EPSG code or projection string
+#[derive(Debug, Clone, PartialEq)]
+pub enum CrsSpec {
+    Epsg(u32),
+    Proj(String),
+}
+
+impl FromStr for CrsSpec {
+    type Err = String;
+
+    /// Parses `EPSG:25832`, `25832` or `+proj=utm +zone=32 ...`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim_start().starts_with('+') {
+            return Ok(Self::Proj(s.to_owned()));
+        }
+        let code = s
+            .strip_prefix("EPSG:")
+            .or_else(|| s.strip_prefix("epsg:"))
+            .unwrap_or(s);
+        code.parse()
+            .map(Self::Epsg)
+            .map_err(|_| format!("Expected EPSG:<code> or a projection string: {s}"))
+    }
+}
+
+impl fmt::Display for CrsSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Epsg(code) => match known(*code) {
+                Some(known) => write!(f, "{}, EPSG:{code}", known.name),
+                None => write!(f, "EPSG:{code}"),
+            },
+            Self::Proj(proj) => write!(f, "{proj}"),
+        }
+    }
+}
+
+impl CrsSpec {
+    fn proj_string(&self) -> LoadResult<String> {
+        match self {
+            Self::Epsg(code) => known(*code)
+                .map(|known| known.proj)
+                .ok_or_else(|| format!("EPSG:{code} isn't one of the known systems").into()),
+            Self::Proj(proj) => Ok(proj.clone()),
+        }
+    }
+}
+
+/// Which systems points are shown in
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CrsSettings {
+    /// Points are reprojected into this system when set
+    pub target: Option<CrsSpec>,
+    /// Used instead of the system the file names, or for files without one
+    pub source: Option<CrsSpec>,
+}
+
+/// What is done to the coordinates of a file before they are shown
+pub enum Conversion {
+    /// Heights are multiplied to be in the unit of the other coordinates
+    ScaleHeights(f64),
+    Reproject(Box<Reprojection>),
+}
+
+impl Conversion {
+    /// Reprojects when a target is set, and otherwise only scales heights given in
+    /// another unit
+    pub fn new(crs: Option<&Crs>, settings: &CrsSettings) -> LoadResult<Self> {
+        if let Some(target) = &settings.target {
+            let source = match (&settings.source, crs) {
+                (Some(source), _) => source.proj_string()?,
+                (None, Some(crs)) => crs.proj_string()?,
+                (None, None) => return Err("The file has no coordinate system to reproject".into()),
+            };
+            return Ok(Self::Reproject(Box::new(Reprojection::new(
+                &source,
+                &target.proj_string()?,
+            )?)));
+        }
+        let scale = crs
+            .and_then(|crs| Some(crs.vertical_unit? / crs.horizontal_unit?))
+            .unwrap_or(1.0);
+        if scale != 1.0 {
+            log::info!("Heights are scaled by {scale} to the unit of the other coordinates");
+        }
+        Ok(Self::ScaleHeights(scale))
+    }
+
+    /// Reads the system of the file itself when a LAS file is given. Files of which the
+    /// system is unknown are shown as they are.
+    pub fn for_file(path: &Path, settings: &CrsSettings) -> LoadResult<Self> {
+        let crs = match super::is_las(path) && settings.source.is_none() {
+            true => Crs::read(path)?,
+            false => None,
+        };
+        if settings.target.is_some() && settings.source.is_none() && crs.is_none() {
+            log::warn!(
+                "{} has no coordinate system to reproject it from, it is shown as it is; \
+                 give one with --source-crs",
+                path.display()
+            );
+            return Self::new(None, &CrsSettings::default());
+        }
+        Self::new(crs.as_ref(), settings)
+    }
+
+    pub fn apply(&self, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        match self {
+            Self::ScaleHeights(scale) => [x, y, z * scale],
+            Self::Reproject(reprojection) => reprojection.apply([x, y, z]),
+        }
+    }
+
+    /// Whether points may fail to be reprojected, and be left out
+    pub fn reprojects(&self) -> bool {
+        matches!(self, Self::Reproject(_))
+    }
+
+    /// Whether the point at `position` is kept by [`Conversion::drop_failed`]
+    pub fn keeps(&self, position: [f64; 3]) -> bool {
+        match self {
+            Self::ScaleHeights(_) => true,
+            Self::Reproject(reprojection) => reprojection
+                .apply(position)
+                .iter()
+                .all(|&c| (c as f32).is_finite()),
+        }
+    }
+
+    /// Leaves out the points which couldn't be reprojected, along with their normals
+    pub fn drop_failed(
+        &self,
+        vertices: &mut Vec<BasicVertex>,
+        normals: Option<&mut Vec<Vector3<f32>>>,
+    ) {
+        if let Self::Reproject(_) = self {
+            let before = vertices.len();
+            let kept: Vec<bool> = vertices
+                .iter()
+                .map(|v| v.position.iter().all(|c| c.is_finite()))
+                .collect();
+            let mut keep = kept.iter();
+            vertices.retain(|_| *keep.next().unwrap());
+            if let Some(normals) = normals {
+                let mut keep = kept.iter();
+                normals.retain(|_| keep.next().is_none_or(|&k| k));
+            }
+            if vertices.len() != before {
+                log::warn!(
+                    "{} points can't be reprojected, they are left out",
+                    before - vertices.len()
+                );
+            }
+        }
+    }
+}
+
+/// Transformation between two systems, with coordinates in degrees for geographic ones
+pub struct Reprojection {
+    from: Proj,
+    to: Proj,
+}
+
+impl Reprojection {
+    pub fn new(from: &str, to: &str) -> LoadResult<Self> {
+        log::info!("Reprojecting from {from} to {to}");
+        Ok(Self {
+            from: Proj::from_proj_string(from)?,
+            to: Proj::from_proj_string(to)?,
+        })
+    }
+
+    /// Points which can't be reprojected become NaN
+    pub fn apply(&self, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        let mut point = match self.from.is_latlong() {
+            true => (x.to_radians(), y.to_radians(), z),
+            false => (x, y, z),
+        };
+        if proj4rs::transform::transform(&self.from, &self.to, &mut point).is_err() {
+            return [f64::NAN; 3];
+        }
+        match self.to.is_latlong() {
+            true => [point.0.to_degrees(), point.1.to_degrees(), point.2],
+            false => [point.0, point.1, point.2],
+        }
+    }
+}
+
+/// System of the embedded registry
+struct Known {
+    name: String,
+    proj: String,
+    /// Meters in a unit of the coordinates, for projected systems
+    unit: f64,
+}
+
+/// Common projected systems, UTM zones and national grids, along with the geographic systems
+/// they are based on
+fn known(code: u32) -> Option<Known> {
+    let entry = |name: String, proj: &str| Known {
+        name,
+        proj: format!("{proj} +no_defs"),
+        unit: 1.0,
+    };
+    let utm = |name: &str, zone: u32, south: bool, datum: &str| {
+        let hemisphere = if south { "S" } else { "N" };
+        let south = if south { " +south" } else { "" };
+        entry(
+            format!("{name} / UTM zone {zone}{hemisphere}"),
+            &format!("+proj=utm +zone={zone}{south} {datum} +units=m"),
+        )
+    };
+    const WGS84: &str = "+datum=WGS84";
+    const NAD83: &str = "+datum=NAD83";
+    const GRS80: &str = "+ellps=GRS80 +towgs84=0,0,0,0,0,0,0";
+    Some(match code {
+        4326 => entry("WGS 84".into(), "+proj=longlat +datum=WGS84"),
+        4269 => entry("NAD83".into(), "+proj=longlat +datum=NAD83"),
+        4258 => entry("ETRS89".into(), &format!("+proj=longlat {GRS80}")),
+        3857 => entry(
+            "WGS 84 / Pseudo-Mercator".into(),
+            "+proj=merc +a=6378137 +b=6378137 +lat_ts=0 +lon_0=0 +x_0=0 +y_0=0 +k=1 \
+             +units=m +nadgrids=@null",
+        ),
+        32601..=32660 => utm("WGS 84", code - 32600, false, WGS84),
+        32701..=32760 => utm("WGS 84", code - 32700, true, WGS84),
+        26901..=26923 => utm("NAD83", code - 26900, false, NAD83),
+        6330..=6348 => utm("NAD83(2011)", code - 6329, false, GRS80),
+        25828..=25838 => utm("ETRS89", code - 25800, false, GRS80),
+        28348..=28358 => {
+            let mut entry = utm("GDA94", code - 28300, true, GRS80);
+            entry.name = format!("GDA94 / MGA zone {}", code - 28300);
+            entry
+        }
+        7846..=7859 => {
+            let mut entry = utm("GDA2020", code - 7800, true, GRS80);
+            entry.name = format!("GDA2020 / MGA zone {}", code - 7800);
+            entry
+        }
+        27700 => entry(
+            "OSGB36 / British National Grid".into(),
+            "+proj=tmerc +lat_0=49 +lon_0=-2 +k=0.9996012717 +x_0=400000 +y_0=-100000 \
+             +ellps=airy +towgs84=446.448,-125.157,542.06,0.15,0.247,0.842,-20.489 +units=m",
+        ),
+        2154 => entry(
+            "RGF93 / Lambert-93".into(),
+            &format!(
+                "+proj=lcc +lat_0=46.5 +lon_0=3 +lat_1=49 +lat_2=44 +x_0=700000 \
+                 +y_0=6600000 {GRS80} +units=m"
+            ),
+        ),
+        28992 => entry(
+            "Amersfoort / RD New".into(),
+            "+proj=sterea +lat_0=52.1561605555556 +lon_0=5.38763888888889 +k=0.9999079 \
+             +x_0=155000 +y_0=463000 +ellps=bessel \
+             +towgs84=565.417,50.3319,465.552,-0.398957,0.343988,-1.8774,4.0725 +units=m",
+        ),
+        2056 => entry(
+            "CH1903+ / LV95".into(),
+            "+proj=somerc +lat_0=46.9524055555556 +lon_0=7.43958333333333 +k_0=1 \
+             +x_0=2600000 +y_0=1200000 +ellps=bessel +towgs84=674.374,15.056,405.346,0,0,0,0 \
+             +units=m",
+        ),
+        31466..=31469 => entry(
+            format!("DHDN / 3-degree Gauss-Kruger zone {}", code - 31464),
+            &format!(
+                "+proj=tmerc +lat_0=0 +lon_0={} +k=1 +x_0={}500000 +y_0=0 +ellps=bessel \
+                 +towgs84=598.1,73.7,418.2,0.202,0.045,-2.455,6.7 +units=m",
+                (code - 31464) * 3,
+                code - 31464
+            ),
+        ),
+        3006 => entry(
+            "SWEREF99 TM".into(),
+            &format!("+proj=utm +zone=33 {GRS80} +units=m"),
+        ),
+        2193 => entry(
+            "NZGD2000 / New Zealand Transverse Mercator 2000".into(),
+            &format!(
+                "+proj=tmerc +lat_0=0 +lon_0=173 +k=0.9996 +x_0=1600000 +y_0=10000000 \
+                 {GRS80} +units=m"
+            ),
+        ),
+        2263 => Known {
+            unit: US_FOOT,
+            ..entry(
+                "NAD83 / New York Long Island (ftUS)".into(),
+                "+proj=lcc +lat_0=40.1666666666667 +lon_0=-74 +lat_1=41.0333333333333 \
+                 +lat_2=40.6666666666667 +x_0=300000 +y_0=0 +datum=NAD83 +units=us-ft",
+            )
+        },
+        _ => return None,
+    })
+}
+
+/// Meters in the EPSG unit of measure with the code
+fn linear_unit(code: u16) -> Option<f64> {
+    match code {
+        9001 => Some(1.0),
+        9002 => Some(FOOT),
+        9003 => Some(US_FOOT),
+        9036 => Some(1000.0),
+        _ => None,
+    }
+}
+
+/// Meters in a unit of vertical systems which aren't in meters
+fn vertical_unit(code: u16) -> Option<f64> {
+    match code {
+        // NAVD88 height (ftUS) and NAVD88 height (ft)
+        6360 => Some(US_FOOT),
+        8228 => Some(FOOT),
+        _ => None,
+    }
+}
+
+fn unit_name(meters: f64) -> String {
+    if (meters - US_FOOT).abs() < 1e-9 {
+        "US survey feet".into()
+    } else if (meters - FOOT).abs() < 1e-9 {
+        "feet".into()
+    } else {
+        format!("units of {meters} m")
+    }
+}
+
+fn is_horizontal(keyword: &str) -> bool {
+    matches!(
+        keyword,
+        "PROJCS" | "PROJCRS" | "PROJECTEDCRS" | "GEOGCS" | "GEOGCRS" | "GEOGRAPHICCRS"
+    )
+}
+
+fn is_vertical(keyword: &str) -> bool {
+    matches!(keyword, "VERT_CS" | "VERTCRS" | "VERTICALCRS")
+}
+
+/// Element of WKT, a keyword with its values in brackets, or a single value
+enum Wkt {
+    Node(String, Vec<Wkt>),
+    Text(String),
+    Number(f64),
+}
+
+impl Wkt {
+    fn parse(text: &str) -> Option<Self> {
+        let mut chars = text.trim().chars().peekable();
+        Self::parse_value(&mut chars)
+    }
+
+    fn parse_value(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<Self> {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match *chars.peek()? {
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                // Quotes inside text are doubled
+                loop {
+                    match chars.next()? {
+                        '"' if chars.next_if_eq(&'"').is_some() => text.push('"'),
+                        '"' => return Some(Self::Text(text)),
+                        c => text.push(c),
+                    }
+                }
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                let mut number = String::new();
+                while let Some(c) =
+                    chars.next_if(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.'))
+                {
+                    number.push(c);
+                }
+                number.parse().ok().map(Self::Number)
+            }
+            _ => {
+                let mut keyword = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    keyword.push(c);
+                }
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                if chars.next_if(|c| matches!(c, '[' | '(')).is_none() {
+                    return (!keyword.is_empty()).then_some(Self::Text(keyword));
+                }
+                let mut children = Vec::new();
+                loop {
+                    children.push(Self::parse_value(chars)?);
+                    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                    match chars.next()? {
+                        ',' => continue,
+                        ']' | ')' => break,
+                        _ => return None,
+                    }
+                }
+                Some(Self::Node(keyword.to_ascii_uppercase(), children))
+            }
+        }
+    }
+
+    fn keyword(&self) -> &str {
+        match self {
+            Self::Node(keyword, _) => keyword,
+            _ => "",
+        }
+    }
+
+    fn children(&self) -> impl Iterator<Item = &Wkt> {
+        match self {
+            Self::Node(_, children) => children.iter(),
+            _ => [].iter(),
+        }
+    }
+
+    fn child(&self, keywords: &[&str]) -> Option<&Wkt> {
+        self.children().find(|c| keywords.contains(&c.keyword()))
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self.children().next()? {
+            Self::Text(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn number(&self, index: usize) -> Option<f64> {
+        match self.children().nth(index)? {
+            Self::Number(n) => Some(*n),
+            Self::Text(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// EPSG code of `AUTHORITY["EPSG","32632"]` or `ID["EPSG",32632]`
+    fn epsg(&self) -> Option<u32> {
+        let id = self.child(&["AUTHORITY", "ID"])?;
+        (id.name()? == "EPSG")
+            .then(|| id.number(1))?
+            .map(|code| code as u32)
+    }
+
+    /// Meters in the length unit of the system, which WKT 2 may also give on the axes
+    fn unit(&self) -> Option<f64> {
+        let units = ["UNIT", "LENGTHUNIT"];
+        self.child(&units)
+            .or_else(|| {
+                self.child(&["CS"])
+                    .into_iter()
+                    .chain(self.children().filter(|c| c.keyword() == "AXIS"))
+                    .find_map(|c| c.child(&units))
+            })?
+            .number(1)
+    }
+}
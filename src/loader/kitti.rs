@@ -0,0 +1,198 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use nalgebra::{vector, Vector3};
+
+use crate::object::BasicVertex;
+
+use super::{LoadResult, PointCloud};
+
+/// Bytes of every point in a scan: x, y, z and reflectance as float32
+const POINT_SIZE: usize = 16;
+
+/// SemanticKITTI `color_map`, copied as written in `semantic-kitti.yaml`, where the colors
+/// are in BGR order
+const PALETTE: &[(u16, [u8; 3])] = &[
+    (0, [0, 0, 0]),
+    (1, [0, 0, 255]),
+    (10, [245, 150, 100]),
+    (11, [245, 230, 100]),
+    (13, [250, 80, 100]),
+    (15, [150, 60, 30]),
+    (16, [255, 0, 0]),
+    (18, [180, 30, 80]),
+    (20, [255, 0, 0]),
+    (30, [30, 30, 255]),
+    (31, [200, 40, 255]),
+    (32, [90, 30, 150]),
+    (40, [255, 0, 255]),
+    (44, [255, 150, 255]),
+    (48, [75, 0, 75]),
+    (49, [75, 0, 175]),
+    (50, [0, 200, 255]),
+    (51, [50, 120, 255]),
+    (52, [0, 150, 255]),
+    (60, [170, 255, 150]),
+    (70, [0, 175, 0]),
+    (71, [0, 60, 135]),
+    (72, [80, 240, 150]),
+    (80, [150, 240, 255]),
+    (81, [0, 0, 255]),
+    (99, [255, 255, 50]),
+    (252, [245, 150, 100]),
+    (253, [200, 40, 255]),
+    (254, [30, 30, 255]),
+    (255, [90, 30, 150]),
+    (256, [255, 0, 0]),
+    (257, [250, 80, 100]),
+    (258, [180, 30, 80]),
+    (259, [255, 0, 0]),
+];
+
+/// What the points of a scan are colored by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coloring {
+    /// The reflectance the sensor measured
+    Intensity,
+    /// The semantic class of the label, in the SemanticKITTI palette
+    Semantic,
+    /// A color per object instance, points of no instance are gray
+    Instance,
+}
+
+impl FromStr for Coloring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "intensity" => Ok(Self::Intensity),
+            "semantic" => Ok(Self::Semantic),
+            "instance" => Ok(Self::Instance),
+            _ => Err(format!("Unknown KITTI coloring: {s}")),
+        }
+    }
+}
+
+/// How KITTI scans are shown
+#[derive(Debug, Clone, Default)]
+pub struct KittiSettings {
+    /// Semantic when the scans have labels and intensity otherwise, if unset
+    pub coloring: Option<Coloring>,
+    /// Labels are read from this directory instead of the `labels` one next to the scans,
+    /// such as the predictions of a network
+    pub labels: Option<PathBuf>,
+}
+
+pub fn is_scan(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("bin"))
+}
+
+/// The label file of a scan, if there is one. SemanticKITTI keeps them in a `labels`
+/// directory next to `velodyne`, with the same file names.
+pub fn label_path(scan: &Path, labels: Option<&Path>) -> Option<PathBuf> {
+    let name = Path::new(scan.file_stem()?).with_extension("label");
+    let candidates = match labels {
+        Some(labels) => vec![labels.join(&name)],
+        None => vec![
+            scan.parent()?.parent()?.join("labels").join(&name),
+            scan.with_extension("label"),
+        ],
+    };
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+/// Reads a scan with the labels found next to it
+pub fn load(path: &Path) -> LoadResult<PointCloud> {
+    Ok(PointCloud {
+        vertices: load_scan(path, &KittiSettings::default())?,
+        normals: None,
+    })
+}
+
+/// Reads a `velodyne/*.bin` scan and colors it by its labels, if it has any
+pub fn load_scan(path: &Path, settings: &KittiSettings) -> LoadResult<Vec<BasicVertex>> {
+    let data = std::fs::read(path)?;
+    if data.len() % POINT_SIZE != 0 {
+        return Err(format!("{} isn't a KITTI scan", path.display()).into());
+    }
+    let count = data.len() / POINT_SIZE;
+
+    let labels = match label_path(path, settings.labels.as_deref()) {
+        Some(labels) => {
+            let labels: Vec<u32> = std::fs::read(labels)?
+                .chunks_exact(4)
+                .map(|label| u32::from_le_bytes(label.try_into().unwrap()))
+                .collect();
+            if labels.len() != count {
+                return Err(format!(
+                    "{} has {count} points but {} labels",
+                    path.display(),
+                    labels.len()
+                )
+                .into());
+            }
+            Some(labels)
+        }
+        None => None,
+    };
+    let coloring = match (settings.coloring, &labels) {
+        (Some(coloring), _) => coloring,
+        (None, Some(_)) => Coloring::Semantic,
+        (None, None) => Coloring::Intensity,
+    };
+    if coloring != Coloring::Intensity && labels.is_none() {
+        return Err(format!("{} has no labels", path.display()).into());
+    }
+
+    Ok(data
+        .chunks_exact(POINT_SIZE)
+        .enumerate()
+        .map(|(i, point)| {
+            let value = |i: usize| f32::from_le_bytes(point[i * 4..i * 4 + 4].try_into().unwrap());
+            let label = labels.as_ref().map_or(0, |labels| labels[i]);
+            let color = match coloring {
+                Coloring::Intensity => Vector3::repeat(value(3).clamp(0.0, 1.0)),
+                Coloring::Semantic => semantic_color(label as u16),
+                Coloring::Instance => instance_color(label),
+            };
+            BasicVertex {
+                position: vector![value(0), value(1), value(2)],
+                color,
+            }
+        })
+        .collect())
+}
+
+/// Color of a semantic class, black for ones the palette doesn't know
+pub fn semantic_color(class: u16) -> Vector3<f32> {
+    let [b, g, r] = PALETTE
+        .iter()
+        .find(|(c, _)| *c == class)
+        .map_or([0; 3], |(_, color)| *color);
+    vector![r, g, b].map(|c| c as f32 / 255.0)
+}
+
+/// Color of the instance in the upper 16 bits of a label. Instance ids are only unique
+/// within a class, so the class is part of the color too.
+pub fn instance_color(label: u32) -> Vector3<f32> {
+    if label >> 16 == 0 {
+        return Vector3::repeat(0.3);
+    }
+    // Consecutive ids get far apart hues
+    let hash = label.wrapping_mul(0x9e37_79b1);
+    let hue = (hash >> 8) as f32 / (1 << 24) as f32 * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    let (r, g, b) = match hue as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    vector![r, g, b] * 0.8 + Vector3::repeat(0.2)
+}
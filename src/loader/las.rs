@@ -0,0 +1,355 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, Read as _, Seek, SeekFrom},
+    ops::Range,
+    path::Path,
+    thread,
+    time::Instant,
+};
+
+use bytemuck::Zeroable;
+use las::{point::Format, Read, Transform, Vector};
+use nalgebra::vector;
+
+use crate::object::BasicVertex;
+
+use super::{
+    convention::Convention,
+    crs::{Conversion, CrsSettings},
+    laz::{self, Chunk, LaszipVlr},
+    LoadOptions, LoadResult, PointCloud,
+};
+
+/// Points decoded together by [`stream`], split between the threads
+const STREAM_BATCH: u64 = 1 << 20;
+
+pub fn load(path: &Path, options: &LoadOptions) -> LoadResult<PointCloud> {
+    let layout = Layout::read(path, options)?;
+    Ok(PointCloud {
+        vertices: read_parallel(path, &layout, 0..layout.point_count)?,
+        normals: None,
+    })
+}
+
+/// Header of a LAS or LAZ file with its VLRs and extended VLRs. The las crate only reads
+/// the header of LAZ files when it's built with its own decompressor.
+pub fn read_header(path: &Path) -> LoadResult<(las::raw::Header, las::Header)> {
+    let mut file = BufReader::new(File::open(path)?);
+    let raw = las::raw::Header::read_from(&mut file)?;
+    let mut builder = las::Builder::new(raw.clone())?;
+    file.seek(SeekFrom::Start(raw.header_size as u64))?;
+    for _ in 0..raw.number_of_variable_length_records {
+        builder
+            .vlrs
+            .push(las::Vlr::new(las::raw::Vlr::read_from(&mut file, false)?));
+    }
+    if let Some(evlr) = raw.evlr {
+        file.seek(SeekFrom::Start(evlr.start_of_first_evlr))?;
+        for _ in 0..evlr.number_of_evlrs {
+            builder
+                .evlrs
+                .push(las::Vlr::new(las::raw::Vlr::read_from(&mut file, true)?));
+        }
+    }
+    Ok((raw, builder.into_header()?))
+}
+
+/// Reads `length` bytes at `offset`. Every call opens the file, so threads don't share
+/// a cursor.
+fn read_range(path: &Path, offset: u64, length: u64) -> LoadResult<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = vec![0; length as usize];
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// How the records of a LAZ file are compressed, and where its chunks are
+struct Compression {
+    vlr: LaszipVlr,
+    chunks: Vec<Chunk>,
+}
+
+impl Compression {
+    /// First point of the chunk holding `point`, where decompressing it has to start
+    fn chunk_start(&self, point: u64) -> u64 {
+        let i = self.chunks.partition_point(|c| c.points().end <= point);
+        self.chunks.get(i).map_or(point, |c| c.first_point)
+    }
+}
+
+/// Where the point records are and how to decode them
+pub struct Layout {
+    offset: u64,
+    record_length: u64,
+    pub point_count: u64,
+    format: Format,
+    transforms: Vector<Transform>,
+    /// Reprojection or unit change of the coordinates
+    conversion: Conversion,
+    convention: Convention,
+    /// Chunks of LAZ files, which are only decompressed whole
+    compression: Option<Compression>,
+}
+
+impl Layout {
+    pub fn read(path: &Path, options: &LoadOptions) -> LoadResult<Self> {
+        let (raw, header) = read_header(path)?;
+        let offset = raw.offset_to_point_data as u64;
+        let record_length = raw.point_data_record_length as u64;
+        let point_count = header.number_of_points();
+        let mut format = *header.point_format();
+        let compression = if format.is_compressed {
+            let vlr = header
+                .vlrs()
+                .iter()
+                .find(|v| v.user_id == laz::LASZIP_USER_ID && v.record_id == laz::LASZIP_RECORD_ID)
+                .ok_or("LAZ file has no LASzip VLR")?;
+            let vlr = LaszipVlr::read(&vlr.data)?;
+            if vlr.record_length() as u64 != record_length {
+                return Err("LASzip VLR doesn't match the point records".into());
+            }
+            let chunks = laz::read_chunk_table(
+                &vlr,
+                offset,
+                fs::metadata(path)?.len(),
+                point_count,
+                |offset, length| read_range(path, offset, length),
+            )?;
+            // Decompressed records are plain LAS ones
+            format.is_compressed = false;
+            Some(Compression { vlr, chunks })
+        } else {
+            None
+        };
+        Ok(Self {
+            offset,
+            record_length,
+            point_count,
+            format,
+            transforms: *header.transforms(),
+            conversion: Conversion::for_file(path, &options.crs)?,
+            convention: options.convention.resolve(Convention::Z_UP),
+            compression,
+        })
+    }
+
+    /// Raw records of the points in `range`. Compressed chunks are decompressed whole
+    /// and cut down to the range.
+    pub fn read_records(&self, path: &Path, range: Range<u64>) -> LoadResult<Vec<u8>> {
+        let length = self.record_length as usize;
+        let Some(compression) = &self.compression else {
+            let offset = self.offset + range.start * self.record_length;
+            return read_range(path, offset, (range.end - range.start) * self.record_length);
+        };
+        let mut records = Vec::with_capacity((range.end - range.start) as usize * length);
+        let first = compression
+            .chunks
+            .partition_point(|c| c.points().end <= range.start);
+        for chunk in compression.chunks[first..]
+            .iter()
+            .take_while(|c| c.first_point < range.end)
+        {
+            let bytes = read_range(path, chunk.offset, chunk.byte_size)?;
+            let decompressed =
+                laz::decompress_chunk(&compression.vlr, &bytes, chunk.point_count as usize)?;
+            let start = (range.start.max(chunk.first_point) - chunk.first_point) as usize;
+            let end = (range.end.min(chunk.points().end) - chunk.first_point) as usize;
+            records.extend_from_slice(&decompressed[start * length..end * length]);
+        }
+        if records.len() != records.capacity() {
+            return Err("LAZ chunk table has fewer points than the header".into());
+        }
+        Ok(records)
+    }
+
+    /// Point of a raw record, in the coordinates of the file
+    fn point(&self, record: &[u8]) -> LoadResult<las::Point> {
+        let raw = las::raw::Point::read_from(record, &self.format)?;
+        Ok(las::Point::new(raw, &self.transforms))
+    }
+
+    /// Points in `range` with all their attributes, read a batch at a time
+    pub fn points<'a>(
+        &'a self,
+        path: &'a Path,
+        range: Range<u64>,
+    ) -> impl Iterator<Item = LoadResult<las::Point>> + 'a {
+        (range.start..range.end)
+            .step_by(STREAM_BATCH as usize)
+            .flat_map(move |start| {
+                let end = (start + STREAM_BATCH).min(range.end);
+                match self.read_records(path, start..end) {
+                    Ok(records) => records
+                        .chunks_exact(self.record_length as usize)
+                        .map(|record| self.point(record))
+                        .collect(),
+                    Err(error) => vec![Err(error)],
+                }
+            })
+    }
+
+    /// Splits `range` into a piece per thread. Pieces of LAZ files start at a chunk, so
+    /// no chunk is decompressed twice.
+    fn pieces(&self, range: Range<u64>, threads: usize) -> Vec<Range<u64>> {
+        let per_thread = (range.end - range.start).div_ceil(threads as u64).max(1);
+        let mut starts = vec![range.start];
+        for i in 1..threads as u64 {
+            let mut start = range.start + i * per_thread;
+            if let Some(compression) = &self.compression {
+                start = compression.chunk_start(start);
+            }
+            if start > *starts.last().unwrap() && start < range.end {
+                starts.push(start);
+            }
+        }
+        let ends = starts.iter().skip(1).copied().chain([range.end]);
+        starts
+            .iter()
+            .zip(ends)
+            .map(|(&start, end)| start..end)
+            .collect()
+    }
+}
+
+/// Decodes a range of points, split evenly between all cores. Points which can't be
+/// reprojected are left out.
+pub fn read_parallel(
+    path: &Path,
+    layout: &Layout,
+    range: Range<u64>,
+) -> LoadResult<Vec<BasicVertex>> {
+    let count = (range.end - range.start) as usize;
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+    let mut vertices = vec![BasicVertex::zeroed(); count];
+    thread::scope(|scope| {
+        let mut rest = &mut vertices[..];
+        let workers: Vec<_> = layout
+            .pieces(range, threads)
+            .into_iter()
+            .map(|piece| {
+                let (out, others) =
+                    std::mem::take(&mut rest).split_at_mut((piece.end - piece.start) as usize);
+                rest = others;
+                scope.spawn(move || decode_range(path, layout, piece.start, out))
+            })
+            .collect();
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("LAS decoding thread panicked"))
+    })?;
+    layout.conversion.drop_failed(&mut vertices, None);
+    Ok(vertices)
+}
+
+/// Decodes the points starting at `start` straight into `out`
+fn decode_range(
+    path: &Path,
+    layout: &Layout,
+    start: u64,
+    out: &mut [BasicVertex],
+) -> LoadResult<()> {
+    let bytes = layout.read_records(path, start..start + out.len() as u64)?;
+    for (record, vertex) in bytes
+        .chunks_exact(layout.record_length as usize)
+        .zip(out.iter_mut())
+    {
+        *vertex = convert(
+            layout.point(record)?,
+            &layout.conversion,
+            &layout.convention,
+        );
+    }
+    Ok(())
+}
+
+/// Reads the points a batch at a time, without keeping the whole file in memory
+pub fn stream(
+    path: &Path,
+    options: &LoadOptions,
+) -> LoadResult<impl Iterator<Item = LoadResult<BasicVertex>>> {
+    let path = path.to_owned();
+    let layout = Layout::read(&path, options)?;
+    let mut next = 0;
+    let mut batch = Vec::new().into_iter();
+    Ok(std::iter::from_fn(move || {
+        if let Some(vertex) = batch.next() {
+            return Some(Ok(vertex));
+        }
+        if next >= layout.point_count {
+            return None;
+        }
+        let end = (next + STREAM_BATCH).min(layout.point_count);
+        match read_parallel(&path, &layout, next..end) {
+            Ok(vertices) => {
+                next = end;
+                batch = vertices.into_iter();
+                batch.next().map(Ok)
+            }
+            Err(error) => {
+                // Nothing more is read after an error
+                next = layout.point_count;
+                Some(Err(error))
+            }
+        }
+    }))
+}
+
+/// Times decoding the whole file one point at a time against decoding it on every core.
+/// LAZ files, which the las crate can't read, are decompressed a chunk at a time instead.
+pub fn benchmark(path: &Path) -> LoadResult<()> {
+    let conversion = Conversion::for_file(path, &CrsSettings::default())?;
+    let layout = Layout::read(path, &LoadOptions::default())?;
+    let start = Instant::now();
+    let mut sequential = Vec::with_capacity(layout.point_count as usize);
+    if layout.compression.is_some() {
+        sequential.resize(layout.point_count as usize, BasicVertex::zeroed());
+        decode_range(path, &layout, 0, &mut sequential)?;
+    } else {
+        let mut reader = las::Reader::from_path(path)?;
+        for point in reader.points() {
+            sequential.push(convert(point?, &conversion, &Convention::Z_UP));
+        }
+    }
+    let sequential_time = start.elapsed();
+
+    let start = Instant::now();
+    let parallel = read_parallel(path, &layout, 0..layout.point_count)?;
+    let parallel_time = start.elapsed();
+
+    let rate = |time: std::time::Duration| parallel.len() as f64 / time.as_secs_f64() / 1e6;
+    println!("{} points", parallel.len());
+    println!(
+        "sequential: {sequential_time:.2?} ({:.1} M points/s)",
+        rate(sequential_time)
+    );
+    println!(
+        "parallel on {} threads: {parallel_time:.2?} ({:.1} M points/s)",
+        thread::available_parallelism().map_or(1, |n| n.get()),
+        rate(parallel_time)
+    );
+    println!(
+        "speed-up: {:.2}x",
+        sequential_time.as_secs_f64() / parallel_time.as_secs_f64()
+    );
+    if bytemuck::cast_slice::<_, u8>(&sequential) != bytemuck::cast_slice::<_, u8>(&parallel) {
+        return Err("Sequential and parallel decoding disagree".into());
+    }
+    Ok(())
+}
+
+/// Points with and without a color are turned into the viewer's axes alike
+fn convert(point: las::Point, conversion: &Conversion, convention: &Convention) -> BasicVertex {
+    let [x, y, z] = conversion.apply([point.x, point.y, point.z]);
+    BasicVertex {
+        position: convention.apply(vector![x as f32, y as f32, z as f32]),
+        color: point.color.map_or(vector![0.0, 0.0, 0.0], |color| {
+            vector![
+                color.red as f32 / 65536.,
+                color.green as f32 / 65536.,
+                color.blue as f32 / 65536.
+            ]
+        }),
+    }
+}
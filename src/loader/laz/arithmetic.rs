@@ -0,0 +1,415 @@
+//! Adaptive arithmetic coder of LASzip, which every compressed layer is written with
+
+/// Below this the interval is renormalized a byte at a time
+const MIN_LENGTH: u32 = 0x0100_0000;
+const BIT_LENGTH_SHIFT: u32 = 13;
+const BIT_MAX_COUNT: u32 = 1 << BIT_LENGTH_SHIFT;
+const SYMBOL_LENGTH_SHIFT: u32 = 15;
+const SYMBOL_MAX_COUNT: u32 = 1 << SYMBOL_LENGTH_SHIFT;
+
+/// Probability of a single bit, adapted as bits are coded
+#[derive(Clone)]
+pub struct BitModel {
+    bit_0_count: u32,
+    bit_count: u32,
+    bit_0_prob: u32,
+    bits_until_update: u32,
+    update_cycle: u32,
+}
+
+impl BitModel {
+    pub fn new() -> Self {
+        Self {
+            bit_0_count: 1,
+            bit_count: 2,
+            bit_0_prob: 1 << (BIT_LENGTH_SHIFT - 1),
+            bits_until_update: 4,
+            update_cycle: 4,
+        }
+    }
+
+    fn update(&mut self) {
+        self.bit_count += self.update_cycle;
+        if self.bit_count > BIT_MAX_COUNT {
+            self.bit_count = (self.bit_count + 1) >> 1;
+            self.bit_0_count = (self.bit_0_count + 1) >> 1;
+            if self.bit_0_count == self.bit_count {
+                self.bit_count += 1;
+            }
+        }
+        let scale = 0x8000_0000 / self.bit_count;
+        self.bit_0_prob = (self.bit_0_count * scale) >> (31 - BIT_LENGTH_SHIFT);
+        self.update_cycle = ((5 * self.update_cycle) >> 2).min(64);
+        self.bits_until_update = self.update_cycle;
+    }
+}
+
+/// Probabilities of the symbols below a count, adapted as symbols are coded
+#[derive(Clone)]
+pub struct SymbolModel {
+    symbols: u32,
+    last_symbol: u32,
+    distribution: Vec<u32>,
+    symbol_count: Vec<u32>,
+    /// Speeds up finding the symbol of a value when there are many of them
+    decoder_table: Vec<u32>,
+    table_shift: u32,
+    total_count: u32,
+    update_cycle: u32,
+    symbols_until_update: u32,
+}
+
+impl SymbolModel {
+    pub fn new(symbols: u32) -> Self {
+        let (table_size, table_shift) = if symbols > 16 {
+            let mut table_bits = 3;
+            while symbols > 1 << (table_bits + 2) {
+                table_bits += 1;
+            }
+            (1 << table_bits, SYMBOL_LENGTH_SHIFT - table_bits)
+        } else {
+            (0, 0)
+        };
+        let mut model = Self {
+            symbols,
+            last_symbol: symbols - 1,
+            distribution: vec![0; symbols as usize],
+            symbol_count: vec![1; symbols as usize],
+            decoder_table: vec![0; if table_size > 0 { table_size + 2 } else { 0 }],
+            table_shift,
+            total_count: 0,
+            update_cycle: symbols,
+            symbols_until_update: 0,
+        };
+        model.update();
+        model.update_cycle = (symbols + 6) >> 1;
+        model.symbols_until_update = model.update_cycle;
+        model
+    }
+
+    fn update(&mut self) {
+        self.total_count += self.update_cycle;
+        if self.total_count > SYMBOL_MAX_COUNT {
+            self.total_count = 0;
+            for count in &mut self.symbol_count {
+                *count = (*count + 1) >> 1;
+                self.total_count += *count;
+            }
+        }
+        let scale = 0x8000_0000 / self.total_count;
+        let mut sum = 0;
+        let mut s = 0;
+        let table_size = self.decoder_table.len().saturating_sub(2);
+        for k in 0..self.symbols as usize {
+            self.distribution[k] = (scale * sum) >> (31 - SYMBOL_LENGTH_SHIFT);
+            sum += self.symbol_count[k];
+            if table_size > 0 {
+                let w = (self.distribution[k] >> self.table_shift) as usize;
+                while s < w {
+                    s += 1;
+                    self.decoder_table[s] = k as u32 - 1;
+                }
+            }
+        }
+        if table_size > 0 {
+            self.decoder_table[0] = 0;
+            while s <= table_size {
+                s += 1;
+                self.decoder_table[s] = self.symbols - 1;
+            }
+        }
+        self.update_cycle = ((5 * self.update_cycle) >> 2).min((self.symbols + 6) << 3);
+        self.symbols_until_update = self.update_cycle;
+    }
+
+    fn coded(&mut self, symbol: u32) {
+        self.symbol_count[symbol as usize] += 1;
+        self.symbols_until_update -= 1;
+        if self.symbols_until_update == 0 {
+            self.update();
+        }
+    }
+}
+
+/// Reads symbols from the bytes of one layer. Reading past the end gives zeros, like the
+/// padding the encoder finishes with.
+pub struct Decoder<'a> {
+    input: &'a [u8],
+    position: usize,
+    value: u32,
+    length: u32,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        let mut decoder = Self {
+            input,
+            position: 0,
+            value: 0,
+            length: u32::MAX,
+        };
+        for _ in 0..4 {
+            decoder.value = (decoder.value << 8) | decoder.byte();
+        }
+        decoder
+    }
+
+    fn byte(&mut self) -> u32 {
+        let byte = self.input.get(self.position).copied().unwrap_or(0);
+        self.position += 1;
+        byte as u32
+    }
+
+    fn renormalize(&mut self) {
+        loop {
+            self.value = (self.value << 8) | self.byte();
+            self.length <<= 8;
+            if self.length >= MIN_LENGTH {
+                break;
+            }
+        }
+    }
+
+    pub fn bit(&mut self, model: &mut BitModel) -> u32 {
+        let x = model.bit_0_prob * (self.length >> BIT_LENGTH_SHIFT);
+        let bit = if self.value >= x {
+            self.value -= x;
+            self.length -= x;
+            1
+        } else {
+            self.length = x;
+            model.bit_0_count += 1;
+            0
+        };
+        if self.length < MIN_LENGTH {
+            self.renormalize();
+        }
+        model.bits_until_update -= 1;
+        if model.bits_until_update == 0 {
+            model.update();
+        }
+        bit
+    }
+
+    pub fn symbol(&mut self, model: &mut SymbolModel) -> u32 {
+        let mut y = self.length;
+        let x;
+        let mut symbol;
+        self.length >>= SYMBOL_LENGTH_SHIFT;
+        if !model.decoder_table.is_empty() {
+            let dv = self.value / self.length;
+            let t = (dv >> model.table_shift) as usize;
+            symbol = model.decoder_table[t];
+            let mut n = model.decoder_table[t + 1] + 1;
+            while n > symbol + 1 {
+                let k = (symbol + n) >> 1;
+                if model.distribution[k as usize] > dv {
+                    n = k;
+                } else {
+                    symbol = k;
+                }
+            }
+            x = model.distribution[symbol as usize] * self.length;
+            if symbol != model.last_symbol {
+                y = model.distribution[symbol as usize + 1] * self.length;
+            }
+        } else {
+            let mut low = 0;
+            symbol = 0;
+            let mut n = model.symbols;
+            let mut k = n >> 1;
+            loop {
+                let z = self.length * model.distribution[k as usize];
+                if z > self.value {
+                    n = k;
+                    y = z;
+                } else {
+                    symbol = k;
+                    low = z;
+                }
+                k = (symbol + n) >> 1;
+                if k == symbol {
+                    break;
+                }
+            }
+            x = low;
+        }
+        self.value -= x;
+        self.length = y - x;
+        if self.length < MIN_LENGTH {
+            self.renormalize();
+        }
+        model.coded(symbol);
+        symbol
+    }
+
+    /// Up to 32 bits stored without a model
+    pub fn bits(&mut self, bits: u32) -> u32 {
+        if bits > 19 {
+            let lower = self.u16() as u32;
+            let upper = self.bits(bits - 16);
+            return (upper << 16) | lower;
+        }
+        self.length >>= bits;
+        let value = self.value / self.length;
+        self.value -= self.length * value;
+        if self.length < MIN_LENGTH {
+            self.renormalize();
+        }
+        value
+    }
+
+    pub fn u16(&mut self) -> u16 {
+        self.bits(16) as u16
+    }
+
+    pub fn u32(&mut self) -> u32 {
+        let lower = self.u16() as u32;
+        let upper = self.u16() as u32;
+        (upper << 16) | lower
+    }
+}
+
+/// Writes symbols to the bytes of one layer
+pub struct Encoder {
+    output: Vec<u8>,
+    base: u32,
+    length: u32,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            base: 0,
+            length: u32::MAX,
+        }
+    }
+
+    /// Carries an overflow of the base into the bytes already written
+    fn propagate_carry(&mut self) {
+        for byte in self.output.iter_mut().rev() {
+            if *byte == 0xff {
+                *byte = 0;
+            } else {
+                *byte += 1;
+                break;
+            }
+        }
+    }
+
+    fn renormalize(&mut self) {
+        loop {
+            self.output.push((self.base >> 24) as u8);
+            self.base <<= 8;
+            self.length <<= 8;
+            if self.length >= MIN_LENGTH {
+                break;
+            }
+        }
+    }
+
+    /// Moves the base forward, carrying when it wraps
+    fn advance(&mut self, x: u32) {
+        let (base, carry) = self.base.overflowing_add(x);
+        self.base = base;
+        if carry {
+            self.propagate_carry();
+        }
+    }
+
+    pub fn bit(&mut self, model: &mut BitModel, bit: u32) {
+        let x = model.bit_0_prob * (self.length >> BIT_LENGTH_SHIFT);
+        if bit == 0 {
+            self.length = x;
+            model.bit_0_count += 1;
+        } else {
+            self.advance(x);
+            self.length -= x;
+        }
+        if self.length < MIN_LENGTH {
+            self.renormalize();
+        }
+        model.bits_until_update -= 1;
+        if model.bits_until_update == 0 {
+            model.update();
+        }
+    }
+
+    pub fn symbol(&mut self, model: &mut SymbolModel, symbol: u32) {
+        if symbol == model.last_symbol {
+            let x = model.distribution[symbol as usize] * (self.length >> SYMBOL_LENGTH_SHIFT);
+            self.advance(x);
+            self.length -= x;
+        } else {
+            self.length >>= SYMBOL_LENGTH_SHIFT;
+            let x = model.distribution[symbol as usize] * self.length;
+            self.advance(x);
+            self.length = model.distribution[symbol as usize + 1] * self.length - x;
+        }
+        if self.length < MIN_LENGTH {
+            self.renormalize();
+        }
+        model.coded(symbol);
+    }
+
+    pub fn bits(&mut self, mut bits: u32, mut value: u32) {
+        if bits > 19 {
+            self.u16(value as u16);
+            value >>= 16;
+            bits -= 16;
+        }
+        self.length >>= bits;
+        self.advance(value * self.length);
+        if self.length < MIN_LENGTH {
+            self.renormalize();
+        }
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bits(16, value as u32);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.u16(value as u16);
+        self.u16((value >> 16) as u16);
+    }
+
+    /// Writes out the rest of the interval, with the zeros LASzip readers expect after it
+    pub fn finish(mut self) -> Vec<u8> {
+        let another_byte = self.length > 2 * MIN_LENGTH;
+        if another_byte {
+            self.advance(MIN_LENGTH);
+            self.length = MIN_LENGTH >> 1;
+        } else {
+            self.advance(MIN_LENGTH >> 1);
+            self.length = MIN_LENGTH >> 9;
+        }
+        self.renormalize();
+        self.output.extend_from_slice(&[0, 0]);
+        if another_byte {
+            self.output.push(0);
+        }
+        self.output
+    }
+}
+
+/// Symbol models picked by a previous value, each made the first time it's used
+pub struct LazyModels {
+    symbols: u32,
+    models: Vec<Option<SymbolModel>>,
+}
+
+impl LazyModels {
+    pub fn new(count: usize, symbols: u32) -> Self {
+        Self {
+            symbols,
+            models: vec![None; count],
+        }
+    }
+
+    pub fn get(&mut self, index: usize) -> &mut SymbolModel {
+        let symbols = self.symbols;
+        self.models[index].get_or_insert_with(|| SymbolModel::new(symbols))
+    }
+}
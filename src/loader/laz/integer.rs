@@ -0,0 +1,121 @@
+use super::arithmetic::{BitModel, Decoder, Encoder, SymbolModel};
+
+/// Bits of a corrector coded with a model, the rest are stored raw
+const BITS_HIGH: u32 = 8;
+
+/// Codes integers as the difference to a prediction: first how many bits the difference
+/// has, then the difference itself
+pub struct IntegerCompressor {
+    corr_range: u32,
+    corr_min: i32,
+    corr_max: i32,
+    /// Bit count of the last difference, which later predictions use as a context
+    k: u32,
+    bits: Vec<SymbolModel>,
+    corrector_0: BitModel,
+    /// Difference models by bit count, starting at one bit
+    correctors: Vec<SymbolModel>,
+}
+
+impl IntegerCompressor {
+    /// Compressor of `bits` wide values, with its own bit count model for every context
+    pub fn new(bits: u32, contexts: usize) -> Self {
+        let (corr_bits, corr_range, corr_min, corr_max) = if bits > 0 && bits < 32 {
+            let range = 1u32 << bits;
+            let min = -((range / 2) as i32);
+            (bits, range, min, min + range as i32 - 1)
+        } else {
+            (32, 0, i32::MIN, i32::MAX)
+        };
+        Self {
+            corr_range,
+            corr_min,
+            corr_max,
+            k: 0,
+            bits: vec![SymbolModel::new(corr_bits + 1); contexts],
+            corrector_0: BitModel::new(),
+            correctors: (1..=corr_bits)
+                .map(|i| SymbolModel::new(1 << i.min(BITS_HIGH)))
+                .collect(),
+        }
+    }
+
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
+    pub fn decompress(&mut self, decoder: &mut Decoder, prediction: i32, context: usize) -> i32 {
+        let mut real = prediction.wrapping_add(self.read_corrector(decoder, context));
+        if real < 0 {
+            real = real.wrapping_add(self.corr_range as i32);
+        } else if real as u32 >= self.corr_range {
+            real = real.wrapping_sub(self.corr_range as i32);
+        }
+        real
+    }
+
+    fn read_corrector(&mut self, decoder: &mut Decoder, context: usize) -> i32 {
+        self.k = decoder.symbol(&mut self.bits[context]);
+        let k = self.k;
+        if k == 0 {
+            return decoder.bit(&mut self.corrector_0) as i32;
+        }
+        if k >= 32 {
+            return self.corr_min;
+        }
+        let model = &mut self.correctors[k as usize - 1];
+        let c = if k <= BITS_HIGH {
+            decoder.symbol(model) as i32
+        } else {
+            let low_bits = k - BITS_HIGH;
+            let high = decoder.symbol(model);
+            ((high << low_bits) | decoder.bits(low_bits)) as i32
+        };
+        if c >= 1 << (k - 1) {
+            c.wrapping_add(1)
+        } else {
+            c.wrapping_sub(((1u32 << k) - 1) as i32)
+        }
+    }
+
+    pub fn compress(&mut self, encoder: &mut Encoder, prediction: i32, real: i32, context: usize) {
+        let mut corr = real.wrapping_sub(prediction);
+        if corr < self.corr_min {
+            corr = corr.wrapping_add(self.corr_range as i32);
+        } else if corr > self.corr_max {
+            corr = corr.wrapping_sub(self.corr_range as i32);
+        }
+        self.write_corrector(encoder, corr, context);
+    }
+
+    fn write_corrector(&mut self, encoder: &mut Encoder, c: i32, context: usize) {
+        let magnitude = if c <= 0 {
+            c.wrapping_neg() as u32
+        } else {
+            (c - 1) as u32
+        };
+        self.k = 32 - magnitude.leading_zeros();
+        let k = self.k;
+        encoder.symbol(&mut self.bits[context], k);
+        if k == 0 {
+            encoder.bit(&mut self.corrector_0, c as u32);
+            return;
+        }
+        if k >= 32 {
+            return;
+        }
+        let c = if c < 0 {
+            c.wrapping_add(((1u32 << k) - 1) as i32)
+        } else {
+            c - 1
+        } as u32;
+        let model = &mut self.correctors[k as usize - 1];
+        if k <= BITS_HIGH {
+            encoder.symbol(model, c);
+        } else {
+            let low_bits = k - BITS_HIGH;
+            encoder.symbol(model, c >> low_bits);
+            encoder.bits(low_bits, c & ((1 << low_bits) - 1));
+        }
+    }
+}
@@ -0,0 +1,791 @@
+//! LASzip compression of LAS point records. Points are compressed in chunks which don't
+//! depend on each other, so a file can be decompressed a chunk at a time on every core.
+
+use std::ops::Range;
+
+use super::LoadResult;
+
+mod arithmetic;
+mod integer;
+mod v2;
+mod v3;
+
+/// User id and record id of the VLR describing the compression
+pub const LASZIP_USER_ID: &str = "laszip encoded";
+pub const LASZIP_RECORD_ID: u16 = 22204;
+
+/// Points in the chunks of files written here, as LASzip writes them
+pub const CHUNK_SIZE: u32 = 50_000;
+
+/// Chunk size of files whose chunks each store their point count in the chunk table
+const VARIABLE_CHUNK_SIZE: u32 = u32::MAX;
+
+/// Context of the coordinate differences by number of returns and return number
+const NUMBER_RETURN_MAP: [[u8; 8]; 8] = [
+    [15, 14, 13, 12, 11, 10, 9, 8],
+    [14, 0, 1, 3, 6, 10, 10, 9],
+    [13, 1, 2, 4, 7, 11, 11, 10],
+    [12, 3, 4, 5, 8, 12, 12, 11],
+    [11, 6, 7, 8, 9, 13, 13, 12],
+    [10, 10, 11, 12, 13, 14, 14, 13],
+    [9, 10, 11, 12, 13, 14, 15, 14],
+    [8, 9, 10, 11, 12, 13, 14, 15],
+];
+
+/// Context of the heights: how far the return is from the last one
+const NUMBER_RETURN_LEVEL: [[u8; 8]; 8] = [
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    [1, 0, 1, 2, 3, 4, 5, 6],
+    [2, 1, 0, 1, 2, 3, 4, 5],
+    [3, 2, 1, 0, 1, 2, 3, 4],
+    [4, 3, 2, 1, 0, 1, 2, 3],
+    [5, 4, 3, 2, 1, 0, 1, 2],
+    [6, 5, 4, 3, 2, 1, 0, 1],
+    [7, 6, 5, 4, 3, 2, 1, 0],
+];
+
+/// Median of the last five values added, which coordinate differences are predicted with
+#[derive(Debug, Clone, Copy)]
+struct Median5 {
+    values: [i32; 5],
+    high: bool,
+}
+
+impl Default for Median5 {
+    fn default() -> Self {
+        Self {
+            values: [0; 5],
+            high: true,
+        }
+    }
+}
+
+impl Median5 {
+    fn get(&self) -> i32 {
+        self.values[2]
+    }
+
+    fn add(&mut self, v: i32) {
+        let values = &mut self.values;
+        if self.high {
+            if v < values[2] {
+                values[4] = values[3];
+                values[3] = values[2];
+                if v < values[0] {
+                    values[2] = values[1];
+                    values[1] = values[0];
+                    values[0] = v;
+                } else if v < values[1] {
+                    values[2] = values[1];
+                    values[1] = v;
+                } else {
+                    values[2] = v;
+                }
+            } else {
+                if v < values[3] {
+                    values[4] = values[3];
+                    values[3] = v;
+                } else {
+                    values[4] = v;
+                }
+                self.high = false;
+            }
+        } else if values[2] < v {
+            values[0] = values[1];
+            values[1] = values[2];
+            if values[4] < v {
+                values[2] = values[3];
+                values[3] = values[4];
+                values[4] = v;
+            } else if values[3] < v {
+                values[2] = values[3];
+                values[3] = v;
+            } else {
+                values[2] = v;
+            }
+        } else {
+            if values[1] < v {
+                values[0] = values[1];
+                values[1] = v;
+            } else {
+                values[0] = v;
+            }
+            self.high = true;
+        }
+    }
+}
+
+/// Part of a point record compressed on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Byte,
+    Point10,
+    GpsTime11,
+    Rgb12,
+    Point14,
+    Rgb14,
+    RgbNir14,
+    Byte14,
+}
+
+/// Item kinds with their code and version in the LASzip VLR
+const ITEM_CODES: [(ItemKind, u16, u16); 8] = [
+    (ItemKind::Byte, 0, 2),
+    (ItemKind::Point10, 6, 2),
+    (ItemKind::GpsTime11, 7, 2),
+    (ItemKind::Rgb12, 8, 2),
+    (ItemKind::Point14, 10, 3),
+    (ItemKind::Rgb14, 11, 3),
+    (ItemKind::RgbNir14, 12, 3),
+    (ItemKind::Byte14, 14, 3),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub size: u16,
+}
+
+impl Item {
+    /// Whether the item is one of LAS 1.4, which are only compressed in layers
+    fn is_layered(&self) -> bool {
+        matches!(
+            self.kind,
+            ItemKind::Point14 | ItemKind::Rgb14 | ItemKind::RgbNir14 | ItemKind::Byte14
+        )
+    }
+
+    /// Layers the item is split into in layered chunks
+    fn layer_count(&self) -> usize {
+        match self.kind {
+            ItemKind::Point14 => v3::POINT14_LAYERS,
+            ItemKind::RgbNir14 => 2,
+            ItemKind::Byte14 => self.size as usize,
+            _ => 1,
+        }
+    }
+}
+
+/// How the records of the file are compressed
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaszipVlr {
+    compressor: u16,
+    /// Points in every chunk but the last, or [`VARIABLE_CHUNK_SIZE`]
+    pub chunk_size: u32,
+    pub items: Vec<Item>,
+}
+
+/// Compressor of records which are all in a single chunk, without a chunk table
+const POINTWISE: u16 = 1;
+const POINTWISE_CHUNKED: u16 = 2;
+const LAYERED_CHUNKED: u16 = 3;
+
+impl LaszipVlr {
+    pub fn read(data: &[u8]) -> LoadResult<Self> {
+        let u16_at = |at: usize| {
+            data.get(at..at + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        };
+        let header = || -> Option<(u16, u16, u32, u16)> {
+            let chunk_size = u32::from_le_bytes(data.get(12..16)?.try_into().ok()?);
+            Some((u16_at(0)?, u16_at(2)?, chunk_size, u16_at(32)?))
+        };
+        let (compressor, coder, chunk_size, item_count) =
+            header().ok_or("LASzip VLR is too short")?;
+        if coder != 0 {
+            return Err(format!("Unknown LASzip coder {coder}").into());
+        }
+        if !matches!(compressor, POINTWISE | POINTWISE_CHUNKED | LAYERED_CHUNKED) {
+            return Err(format!("Unknown LASzip compressor {compressor}").into());
+        }
+        let items = (0..item_count as usize)
+            .map(|i| {
+                let at = 34 + 6 * i;
+                let (Some(code), Some(size), Some(version)) =
+                    (u16_at(at), u16_at(at + 2), u16_at(at + 4))
+                else {
+                    return Err("LASzip VLR is too short".into());
+                };
+                let Some(&(kind, ..)) = ITEM_CODES
+                    .iter()
+                    .find(|&&(_, c, v)| (c, v) == (code, version))
+                else {
+                    return Err(
+                        format!("LASzip item {code} version {version} is not supported").into(),
+                    );
+                };
+                let expected = match kind {
+                    ItemKind::Point10 => Some(v2::POINT10_SIZE),
+                    ItemKind::GpsTime11 => Some(8),
+                    ItemKind::Rgb12 | ItemKind::Rgb14 => Some(6),
+                    ItemKind::Point14 => Some(v3::POINT14_SIZE),
+                    ItemKind::RgbNir14 => Some(8),
+                    ItemKind::Byte | ItemKind::Byte14 => None,
+                };
+                if expected.is_some_and(|expected| expected != size as usize) {
+                    return Err(format!("LASzip item {code} can't be {size} bytes").into());
+                }
+                Ok(Item { kind, size })
+            })
+            .collect::<LoadResult<Vec<_>>>()?;
+        if items
+            .iter()
+            .any(|item| item.is_layered() != (compressor == LAYERED_CHUNKED))
+        {
+            return Err("LASzip items don't match the compressor".into());
+        }
+        Ok(Self {
+            compressor,
+            chunk_size,
+            items,
+        })
+    }
+
+    /// How records of a point format are compressed in chunks of [`CHUNK_SIZE`] points.
+    /// Bytes past the point format are compressed as extra bytes.
+    pub fn for_format(format: u8, record_length: u16) -> LoadResult<Self> {
+        let item = |kind, size| Item { kind, size };
+        let (compressor, mut items) = match format {
+            0..=3 => {
+                let mut items = vec![item(ItemKind::Point10, v2::POINT10_SIZE as u16)];
+                if format == 1 || format == 3 {
+                    items.push(item(ItemKind::GpsTime11, 8));
+                }
+                if format >= 2 {
+                    items.push(item(ItemKind::Rgb12, 6));
+                }
+                (POINTWISE_CHUNKED, items)
+            }
+            6..=8 => {
+                let mut items = vec![item(ItemKind::Point14, v3::POINT14_SIZE as u16)];
+                match format {
+                    7 => items.push(item(ItemKind::Rgb14, 6)),
+                    8 => items.push(item(ItemKind::RgbNir14, 8)),
+                    _ => {}
+                }
+                (LAYERED_CHUNKED, items)
+            }
+            _ => return Err(format!("Point format {format} can't be LAZ compressed").into()),
+        };
+        let point_size: u16 = items.iter().map(|item| item.size).sum();
+        let extra_bytes = record_length
+            .checked_sub(point_size)
+            .ok_or("Point records are shorter than their format")?;
+        if extra_bytes > 0 {
+            let kind = match compressor {
+                LAYERED_CHUNKED => ItemKind::Byte14,
+                _ => ItemKind::Byte,
+            };
+            items.push(item(kind, extra_bytes));
+        }
+        Ok(Self {
+            compressor,
+            chunk_size: CHUNK_SIZE,
+            items,
+        })
+    }
+
+    /// Contents of the VLR, written as LASzip 2.2 for point-wise items and 3.4 for layered ones
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (major, minor, revision): (u8, u8, u16) = if self.is_layered() {
+            (3, 4, 3)
+        } else {
+            (2, 2, 0)
+        };
+        let mut data = Vec::with_capacity(34 + 6 * self.items.len());
+        data.extend_from_slice(&self.compressor.to_le_bytes());
+        // Arithmetic coder
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&[major, minor]);
+        data.extend_from_slice(&revision.to_le_bytes());
+        // Options
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&self.chunk_size.to_le_bytes());
+        // No special EVLRs
+        data.extend_from_slice(&(-1i64).to_le_bytes());
+        data.extend_from_slice(&(-1i64).to_le_bytes());
+        data.extend_from_slice(&(self.items.len() as u16).to_le_bytes());
+        for item in &self.items {
+            let &(_, code, version) = ITEM_CODES
+                .iter()
+                .find(|(kind, ..)| *kind == item.kind)
+                .unwrap();
+            for value in [code, item.size, version] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        data
+    }
+
+    fn is_layered(&self) -> bool {
+        self.compressor == LAYERED_CHUNKED
+    }
+
+    /// Bytes of a whole record
+    pub fn record_length(&self) -> usize {
+        self.items.iter().map(|item| item.size as usize).sum()
+    }
+}
+
+/// Points and bytes of one chunk
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    /// Index of the first point of the chunk
+    pub first_point: u64,
+    pub point_count: u64,
+    /// Where the compressed chunk starts in the file
+    pub offset: u64,
+    pub byte_size: u64,
+}
+
+impl Chunk {
+    pub fn points(&self) -> Range<u64> {
+        self.first_point..self.first_point + self.point_count
+    }
+}
+
+/// Reads the chunk table of a file, given the start of its point data, its length and a way
+/// of reading ranges of it. Files compressed without chunks become a single one.
+pub fn read_chunk_table(
+    vlr: &LaszipVlr,
+    point_data: u64,
+    file_size: u64,
+    point_count: u64,
+    read_range: impl Fn(u64, u64) -> LoadResult<Vec<u8>>,
+) -> LoadResult<Vec<Chunk>> {
+    if vlr.compressor == POINTWISE {
+        return Ok(vec![Chunk {
+            first_point: 0,
+            point_count,
+            offset: point_data,
+            byte_size: file_size.saturating_sub(point_data),
+        }]);
+    }
+    let i64_at = |bytes: Vec<u8>| i64::from_le_bytes(bytes.try_into().unwrap());
+    let mut table_offset = i64_at(read_range(point_data, 8)?);
+    // Writers which couldn't seek back put the offset at the very end instead
+    if table_offset == -1 {
+        table_offset = i64_at(read_range(file_size.saturating_sub(8), 8)?);
+    }
+    let table_offset = table_offset as u64;
+    if table_offset <= point_data || table_offset + 8 > file_size {
+        return Err("LAZ chunk table is missing".into());
+    }
+    let table = read_range(table_offset, file_size - table_offset)?;
+    let chunk_count = u32::from_le_bytes(table[4..8].try_into().unwrap());
+    let variable = vlr.chunk_size == VARIABLE_CHUNK_SIZE;
+
+    let mut decoder = arithmetic::Decoder::new(&table[8..]);
+    let mut sizes = integer::IntegerCompressor::new(32, 2);
+    let (mut last_count, mut last_size) = (0, 0);
+    let mut chunks = Vec::with_capacity(chunk_count as usize);
+    let mut first_point = 0;
+    let mut offset = point_data + 8;
+    for _ in 0..chunk_count {
+        let count = if variable {
+            last_count = sizes.decompress(&mut decoder, last_count, 0);
+            last_count as u32 as u64
+        } else {
+            (vlr.chunk_size as u64).min(point_count.saturating_sub(first_point))
+        };
+        last_size = sizes.decompress(&mut decoder, last_size, 1);
+        let byte_size = last_size as u32 as u64;
+        chunks.push(Chunk {
+            first_point,
+            point_count: count,
+            offset,
+            byte_size,
+        });
+        first_point += count;
+        offset += byte_size;
+    }
+    if offset > table_offset {
+        return Err("LAZ chunk table points past its own start".into());
+    }
+    Ok(chunks)
+}
+
+/// Chunk table of chunks of [`CHUNK_SIZE`] points with the given byte sizes, written after
+/// the last chunk
+pub fn write_chunk_table(byte_sizes: &[u64]) -> Vec<u8> {
+    let mut table = Vec::with_capacity(8 + 4 * byte_sizes.len());
+    // Version
+    table.extend_from_slice(&0u32.to_le_bytes());
+    table.extend_from_slice(&(byte_sizes.len() as u32).to_le_bytes());
+    let mut encoder = arithmetic::Encoder::new();
+    let mut sizes = integer::IntegerCompressor::new(32, 2);
+    let mut last_size = 0;
+    for &size in byte_sizes {
+        sizes.compress(&mut encoder, last_size, size as i32, 1);
+        last_size = size as i32;
+    }
+    table.extend_from_slice(&encoder.finish());
+    table
+}
+
+/// Decompresses `point_count` records from the bytes of one chunk
+pub fn decompress_chunk(vlr: &LaszipVlr, chunk: &[u8], point_count: usize) -> LoadResult<Vec<u8>> {
+    let record_length = vlr.record_length();
+    let mut records = vec![0; point_count * record_length];
+    if point_count == 0 {
+        return Ok(records);
+    }
+    let first = chunk
+        .get(..record_length)
+        .ok_or("LAZ chunk is shorter than a point")?;
+    records[..record_length].copy_from_slice(first);
+    let (first, rest) = records.split_at_mut(record_length);
+    if vlr.is_layered() {
+        decompress_layered(vlr, first, &chunk[record_length..], rest)?;
+    } else {
+        decompress_pointwise(vlr, first, &chunk[record_length..], rest);
+    }
+    Ok(records)
+}
+
+/// Ranges of the items in a record
+fn item_ranges(vlr: &LaszipVlr) -> Vec<Range<usize>> {
+    let mut start = 0;
+    vlr.items
+        .iter()
+        .map(|item| {
+            start += item.size as usize;
+            start - item.size as usize..start
+        })
+        .collect()
+}
+
+/// Models of one item of point-wise chunks, which both decode and encode
+enum PointwiseItem {
+    Point10(Box<v2::Point10>),
+    GpsTime11(Box<v2::GpsTime11>),
+    Rgb12(Box<v2::Rgb12>),
+    Bytes(v2::Bytes),
+}
+
+/// Models of every item, predicting from the first record of a chunk
+fn pointwise_items(vlr: &LaszipVlr, first: &[u8]) -> Vec<PointwiseItem> {
+    vlr.items
+        .iter()
+        .zip(item_ranges(vlr))
+        .map(|(item, range)| {
+            let first = &first[range];
+            match item.kind {
+                ItemKind::Point10 => PointwiseItem::Point10(Box::new(v2::Point10::new(first))),
+                ItemKind::GpsTime11 => {
+                    PointwiseItem::GpsTime11(Box::new(v2::GpsTime11::new(first)))
+                }
+                ItemKind::Rgb12 => PointwiseItem::Rgb12(Box::new(v2::Rgb12::new(first))),
+                _ => PointwiseItem::Bytes(v2::Bytes::new(first)),
+            }
+        })
+        .collect()
+}
+
+fn decompress_pointwise(vlr: &LaszipVlr, first: &[u8], compressed: &[u8], rest: &mut [u8]) {
+    let ranges = item_ranges(vlr);
+    let mut items = pointwise_items(vlr, first);
+    let mut decoder = arithmetic::Decoder::new(compressed);
+    for record in rest.chunks_exact_mut(vlr.record_length()) {
+        for (item, range) in items.iter_mut().zip(&ranges) {
+            let bytes = &mut record[range.clone()];
+            match item {
+                PointwiseItem::Point10(item) => item.read(&mut decoder, bytes),
+                PointwiseItem::GpsTime11(item) => item.read(&mut decoder, bytes),
+                PointwiseItem::Rgb12(item) => item.read(&mut decoder, bytes),
+                PointwiseItem::Bytes(item) => item.read(&mut decoder, bytes),
+            }
+        }
+    }
+}
+
+/// Decoder of one item of layered chunks
+enum LayeredItem<'a> {
+    Point14(v3::Point14Decoder<'a>),
+    Rgb(v3::RgbDecoder<'a>),
+    Bytes(v3::BytesDecoder<'a>),
+}
+
+fn decompress_layered(
+    vlr: &LaszipVlr,
+    first: &[u8],
+    compressed: &[u8],
+    rest: &mut [u8],
+) -> LoadResult<()> {
+    if vlr.items.first().map(|item| item.kind) != Some(ItemKind::Point14) {
+        return Err("Layered LAZ chunks have to start with a LAS 1.4 point".into());
+    }
+    // The point count, then the sizes of every layer, then the layers
+    let layer_count: usize = vlr.items.iter().map(Item::layer_count).sum();
+    let sizes_end = 4 + 4 * layer_count;
+    let sizes = compressed
+        .get(4..sizes_end)
+        .ok_or("LAZ chunk is too short for its layer sizes")?;
+    let mut layers = Vec::with_capacity(layer_count);
+    let mut start = sizes_end;
+    for size in sizes.chunks_exact(4) {
+        let end = start + u32::from_le_bytes(size.try_into().unwrap()) as usize;
+        layers.push(
+            compressed
+                .get(start..end)
+                .ok_or("LAZ chunk is shorter than its layers")?,
+        );
+        start = end;
+    }
+
+    let ranges = item_ranges(vlr);
+    let mut context = 0;
+    let mut layers = &layers[..];
+    let mut items = Vec::with_capacity(vlr.items.len());
+    for (item, range) in vlr.items.iter().zip(&ranges) {
+        let (own, others) = layers.split_at(item.layer_count());
+        layers = others;
+        let first = &first[range.clone()];
+        items.push(match item.kind {
+            ItemKind::Point14 => {
+                let decoder = v3::Point14Decoder::new(first, own);
+                context = (first[15] >> 4 & 3) as usize;
+                LayeredItem::Point14(decoder)
+            }
+            ItemKind::Rgb14 | ItemKind::RgbNir14 => {
+                LayeredItem::Rgb(v3::RgbDecoder::new(first, context, own))
+            }
+            ItemKind::Byte14 => LayeredItem::Bytes(v3::BytesDecoder::new(first, context, own)),
+            _ => return Err("Point-wise LAZ items can't be in a layered chunk".into()),
+        });
+    }
+
+    for record in rest.chunks_exact_mut(vlr.record_length()) {
+        for (item, range) in items.iter_mut().zip(&ranges) {
+            let bytes = &mut record[range.clone()];
+            match item {
+                LayeredItem::Point14(item) => context = item.read(bytes),
+                LayeredItem::Rgb(item) => item.read(bytes, context),
+                LayeredItem::Bytes(item) => item.read(bytes, context),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compresses the records of one chunk, the first of which is stored as it is
+pub fn compress_chunk(vlr: &LaszipVlr, records: &[u8]) -> Vec<u8> {
+    let record_length = vlr.record_length();
+    let Some((first, rest)) = records.split_at_checked(record_length) else {
+        return Vec::new();
+    };
+    let mut chunk = first.to_vec();
+    if vlr.is_layered() {
+        // The point count, then the size of every layer, then the layers
+        let layers = compress_layered(vlr, first, rest);
+        chunk.extend_from_slice(&((records.len() / record_length) as u32).to_le_bytes());
+        for layer in &layers {
+            chunk.extend_from_slice(&(layer.len() as u32).to_le_bytes());
+        }
+        for layer in &layers {
+            chunk.extend_from_slice(layer);
+        }
+    } else {
+        chunk.extend_from_slice(&compress_pointwise(vlr, first, rest));
+    }
+    chunk
+}
+
+fn compress_pointwise(vlr: &LaszipVlr, first: &[u8], rest: &[u8]) -> Vec<u8> {
+    let ranges = item_ranges(vlr);
+    let mut items = pointwise_items(vlr, first);
+    let mut encoder = arithmetic::Encoder::new();
+    for record in rest.chunks_exact(vlr.record_length()) {
+        for (item, range) in items.iter_mut().zip(&ranges) {
+            let bytes = &record[range.clone()];
+            match item {
+                PointwiseItem::Point10(item) => item.write(&mut encoder, bytes),
+                PointwiseItem::GpsTime11(item) => item.write(&mut encoder, bytes),
+                PointwiseItem::Rgb12(item) => item.write(&mut encoder, bytes),
+                PointwiseItem::Bytes(item) => item.write(&mut encoder, bytes),
+            }
+        }
+    }
+    encoder.finish()
+}
+
+/// Encoder of one item of layered chunks
+enum LayeredEncoder {
+    Point14(v3::Point14Encoder),
+    Rgb(v3::RgbEncoder),
+    Bytes(v3::BytesEncoder),
+}
+
+/// Layers of every item, in the order of the items
+fn compress_layered(vlr: &LaszipVlr, first: &[u8], rest: &[u8]) -> Vec<Vec<u8>> {
+    let ranges = item_ranges(vlr);
+    // The point item comes first and picks the scanner channel of the others
+    let context = (first[15] >> 4 & 3) as usize;
+    let mut items: Vec<_> = vlr
+        .items
+        .iter()
+        .zip(&ranges)
+        .map(|(item, range)| {
+            let first = &first[range.clone()];
+            match item.kind {
+                ItemKind::Point14 => LayeredEncoder::Point14(v3::Point14Encoder::new(first)),
+                ItemKind::Rgb14 | ItemKind::RgbNir14 => {
+                    LayeredEncoder::Rgb(v3::RgbEncoder::new(first, context))
+                }
+                _ => LayeredEncoder::Bytes(v3::BytesEncoder::new(first, context)),
+            }
+        })
+        .collect();
+
+    let mut context = context;
+    for record in rest.chunks_exact(vlr.record_length()) {
+        for (item, range) in items.iter_mut().zip(&ranges) {
+            let bytes = &record[range.clone()];
+            match item {
+                LayeredEncoder::Point14(item) => context = item.write(bytes),
+                LayeredEncoder::Rgb(item) => item.write(bytes, context),
+                LayeredEncoder::Bytes(item) => item.write(bytes, context),
+            }
+        }
+    }
+    items
+        .into_iter()
+        .flat_map(|item| match item {
+            LayeredEncoder::Point14(item) => item.finish(),
+            LayeredEncoder::Rgb(item) => item.finish(),
+            LayeredEncoder::Bytes(item) => item.finish(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// The same points uncompressed as format 1 and compressed as format 3, from the test
+    /// data of the las crate
+    const AUTZEN_LAS: &[u8] = include_bytes!("../../../tests/data/autzen.las");
+    const AUTZEN_LAZ: &[u8] = include_bytes!("../../../tests/data/autzen.laz");
+
+    /// Header, VLRs and point data of a whole file
+    fn parse(file: &[u8]) -> (las::raw::Header, Vec<las::raw::Vlr>, &[u8]) {
+        let mut cursor = Cursor::new(file);
+        let header = las::raw::Header::read_from(&mut cursor).unwrap();
+        let vlrs = (0..header.number_of_variable_length_records)
+            .map(|_| las::raw::Vlr::read_from(&mut cursor, false).unwrap())
+            .collect();
+        let points = &file[header.offset_to_point_data as usize..];
+        (header, vlrs, points)
+    }
+
+    fn laszip_vlr(vlrs: &[las::raw::Vlr]) -> LaszipVlr {
+        let vlr = vlrs
+            .iter()
+            .find(|v| v.record_id == LASZIP_RECORD_ID)
+            .unwrap();
+        LaszipVlr::read(&vlr.data).unwrap()
+    }
+
+    fn chunk_table(header: &las::raw::Header, vlr: &LaszipVlr) -> Vec<Chunk> {
+        read_chunk_table(
+            vlr,
+            header.offset_to_point_data as u64,
+            AUTZEN_LAZ.len() as u64,
+            header.number_of_point_records as u64,
+            |offset, length| Ok(AUTZEN_LAZ[offset as usize..(offset + length) as usize].to_vec()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_autzen_chunk_table() {
+        let (header, vlrs, points) = parse(AUTZEN_LAZ);
+        let vlr = laszip_vlr(&vlrs);
+        let chunks = chunk_table(&header, &vlr);
+        // The chunk runs from the chunk table offset to the table itself
+        let table = i64::from_le_bytes(points[..8].try_into().unwrap()) as u64;
+        let start = header.offset_to_point_data as u64 + 8;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].points(), 0..106);
+        assert_eq!(chunks[0].offset, start);
+        assert_eq!(chunks[0].byte_size, table - start);
+    }
+
+    #[test]
+    fn decompresses_autzen() {
+        let (_, _, expected) = parse(AUTZEN_LAS);
+        let (header, vlrs, _) = parse(AUTZEN_LAZ);
+        let vlr = laszip_vlr(&vlrs);
+        assert_eq!(vlr.record_length(), 34);
+
+        let chunks = chunk_table(&header, &vlr);
+        let [chunk] = &chunks[..] else {
+            panic!("autzen.laz has a single chunk")
+        };
+        let bytes = &AUTZEN_LAZ[chunk.offset as usize..(chunk.offset + chunk.byte_size) as usize];
+        let records = decompress_chunk(&vlr, bytes, chunk.point_count as usize).unwrap();
+
+        // The LAS file has the same points and times, without the empty colors
+        for (record, expected) in records.chunks_exact(34).zip(expected.chunks_exact(28)) {
+            assert_eq!(&record[..28], expected);
+            assert_eq!(&record[28..], &[0; 6]);
+        }
+    }
+
+    #[test]
+    fn compresses_autzen() {
+        let (header, vlrs, points) = parse(AUTZEN_LAZ);
+        let vlr = laszip_vlr(&vlrs);
+        assert_eq!(LaszipVlr::for_format(3, 34).unwrap().items, vlr.items);
+
+        let chunks = chunk_table(&header, &vlr);
+        let chunk = &chunks[0];
+        let bytes = &AUTZEN_LAZ[chunk.offset as usize..(chunk.offset + chunk.byte_size) as usize];
+        let records = decompress_chunk(&vlr, bytes, chunk.point_count as usize).unwrap();
+        assert_eq!(compress_chunk(&vlr, &records), bytes);
+
+        let table = i64::from_le_bytes(points[..8].try_into().unwrap()) as usize;
+        let written = write_chunk_table(&[chunk.byte_size]);
+        assert_eq!(&AUTZEN_LAZ[table..table + written.len()], written);
+    }
+
+    #[test]
+    fn round_trips_layered_records() {
+        // Format 8 with 3 extra bytes, switching scanner channels now and then
+        let vlr = LaszipVlr::for_format(8, 41).unwrap();
+        let mut seed = 1u32;
+        let mut random = move || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            seed >> 8
+        };
+        let mut records = Vec::new();
+        let mut time = 1000.0f64;
+        for i in 0..500u32 {
+            let returns = (random() % 4 + 1) as u8;
+            let channel = (i / 40 % 4) as u8;
+            time += (random() % 3) as f64 * 0.25;
+            let mut record = Vec::with_capacity(41);
+            for axis in 0..3 {
+                record.extend_from_slice(&((i * 10 + random() % 50 + axis) as i32).to_le_bytes());
+            }
+            record.extend_from_slice(&((random() % 4000) as u16).to_le_bytes());
+            record.push(returns << 4 | (random() as u8 % returns + 1));
+            record.push(channel << 4 | (random() % 4) as u8);
+            record.push((random() % 11) as u8);
+            record.push((random() % 3) as u8);
+            record.extend_from_slice(&((random() % 200) as i16 - 100).to_le_bytes());
+            record.extend_from_slice(&((i / 100) as u16).to_le_bytes());
+            record.extend_from_slice(&time.to_le_bytes());
+            for _ in 0..4 {
+                record.extend_from_slice(&((random() % 65536) as u16).to_le_bytes());
+            }
+            record.extend_from_slice(&[i as u8, (random() % 7) as u8, 0]);
+            records.extend_from_slice(&record);
+        }
+
+        let chunk = compress_chunk(&vlr, &records);
+        assert_eq!(decompress_chunk(&vlr, &chunk, 500).unwrap(), records);
+    }
+}
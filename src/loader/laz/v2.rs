@@ -0,0 +1,624 @@
+//! Point-wise items of LAS 1.0 to 1.3 records, compressed one after another into a single
+//! stream per chunk
+
+use super::{
+    arithmetic::{Decoder, Encoder, LazyModels, SymbolModel},
+    integer::IntegerCompressor,
+    Median5, NUMBER_RETURN_LEVEL, NUMBER_RETURN_MAP,
+};
+
+/// Bytes of the point record shared by formats 0 to 5
+pub const POINT10_SIZE: usize = 20;
+
+fn i32_at(bytes: &[u8], at: usize) -> i32 {
+    i32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+/// Context of the coordinate differences, from the bit count of an earlier difference
+fn k_context(k: u32, max: u32) -> usize {
+    (if k < max { k & !1 } else { max }) as usize
+}
+
+/// Coordinates, intensity, returns, classification, scan angle, user data and point source
+pub struct Point10 {
+    last: [u8; POINT10_SIZE],
+    last_intensity: [u16; 16],
+    last_x_diff: [Median5; 16],
+    last_y_diff: [Median5; 16],
+    last_height: [i32; 8],
+    changed_values: SymbolModel,
+    scan_angle: [SymbolModel; 2],
+    bit_byte: LazyModels,
+    classification: LazyModels,
+    user_data: LazyModels,
+    intensity: IntegerCompressor,
+    point_source: IntegerCompressor,
+    dx: IntegerCompressor,
+    dy: IntegerCompressor,
+    z: IntegerCompressor,
+}
+
+impl Point10 {
+    pub fn new(first: &[u8]) -> Self {
+        let mut last: [u8; POINT10_SIZE] = first.try_into().unwrap();
+        // Intensities are predicted from the last one of the same return, none yet
+        last[12..14].fill(0);
+        Self {
+            last,
+            last_intensity: [0; 16],
+            last_x_diff: [Median5::default(); 16],
+            last_y_diff: [Median5::default(); 16],
+            last_height: [0; 8],
+            changed_values: SymbolModel::new(64),
+            scan_angle: [SymbolModel::new(256), SymbolModel::new(256)],
+            bit_byte: LazyModels::new(256, 256),
+            classification: LazyModels::new(256, 256),
+            user_data: LazyModels::new(256, 256),
+            intensity: IntegerCompressor::new(16, 4),
+            point_source: IntegerCompressor::new(16, 1),
+            dx: IntegerCompressor::new(32, 2),
+            dy: IntegerCompressor::new(32, 22),
+            z: IntegerCompressor::new(32, 20),
+        }
+    }
+
+    /// Return map and level of the returns in the last record
+    fn returns(&self) -> (usize, usize, usize) {
+        let r = (self.last[14] & 7) as usize;
+        let n = ((self.last[14] >> 3) & 7) as usize;
+        (
+            n,
+            NUMBER_RETURN_MAP[n][r] as usize,
+            NUMBER_RETURN_LEVEL[n][r] as usize,
+        )
+    }
+
+    pub fn read(&mut self, decoder: &mut Decoder, item: &mut [u8]) {
+        let changed = decoder.symbol(&mut self.changed_values);
+        if changed & 32 != 0 {
+            let model = self.bit_byte.get(self.last[14] as usize);
+            self.last[14] = decoder.symbol(model) as u8;
+        }
+        let (n, m, l) = self.returns();
+        if changed != 0 {
+            let intensity = if changed & 16 != 0 {
+                let prediction = self.last_intensity[m] as i32;
+                let intensity = self.intensity.decompress(decoder, prediction, m.min(3)) as u16;
+                self.last_intensity[m] = intensity;
+                intensity
+            } else {
+                self.last_intensity[m]
+            };
+            self.last[12..14].copy_from_slice(&intensity.to_le_bytes());
+            if changed & 8 != 0 {
+                let model = self.classification.get(self.last[15] as usize);
+                self.last[15] = decoder.symbol(model) as u8;
+            }
+            if changed & 4 != 0 {
+                let direction = ((self.last[14] >> 6) & 1) as usize;
+                let diff = decoder.symbol(&mut self.scan_angle[direction]) as u8;
+                self.last[16] = self.last[16].wrapping_add(diff);
+            }
+            if changed & 2 != 0 {
+                let model = self.user_data.get(self.last[17] as usize);
+                self.last[17] = decoder.symbol(model) as u8;
+            }
+            if changed & 1 != 0 {
+                let prediction = u16_at(&self.last, 18) as i32;
+                let source = self.point_source.decompress(decoder, prediction, 0) as u16;
+                self.last[18..20].copy_from_slice(&source.to_le_bytes());
+            }
+        }
+        let single = (n == 1) as usize;
+
+        let median = self.last_x_diff[m].get();
+        let diff = self.dx.decompress(decoder, median, single);
+        let x = i32_at(&self.last, 0).wrapping_add(diff);
+        self.last[0..4].copy_from_slice(&x.to_le_bytes());
+        self.last_x_diff[m].add(diff);
+
+        let median = self.last_y_diff[m].get();
+        let context = single + k_context(self.dx.k(), 20);
+        let diff = self.dy.decompress(decoder, median, context);
+        let y = i32_at(&self.last, 4).wrapping_add(diff);
+        self.last[4..8].copy_from_slice(&y.to_le_bytes());
+        self.last_y_diff[m].add(diff);
+
+        let context = single + k_context((self.dx.k() + self.dy.k()) / 2, 18);
+        let z = self.z.decompress(decoder, self.last_height[l], context);
+        self.last[8..12].copy_from_slice(&z.to_le_bytes());
+        self.last_height[l] = z;
+
+        item.copy_from_slice(&self.last);
+    }
+
+    pub fn write(&mut self, encoder: &mut Encoder, item: &[u8]) {
+        let r = (item[14] & 7) as usize;
+        let n = ((item[14] >> 3) & 7) as usize;
+        let m = NUMBER_RETURN_MAP[n][r] as usize;
+        let l = NUMBER_RETURN_LEVEL[n][r] as usize;
+        let intensity = u16_at(item, 12);
+        let changed = (((self.last[14] != item[14]) as u32) << 5)
+            | (((self.last_intensity[m] != intensity) as u32) << 4)
+            | (((self.last[15] != item[15]) as u32) << 3)
+            | (((self.last[16] != item[16]) as u32) << 2)
+            | (((self.last[17] != item[17]) as u32) << 1)
+            | (self.last[18..20] != item[18..20]) as u32;
+        encoder.symbol(&mut self.changed_values, changed);
+        if changed & 32 != 0 {
+            encoder.symbol(self.bit_byte.get(self.last[14] as usize), item[14] as u32);
+        }
+        if changed & 16 != 0 {
+            let prediction = self.last_intensity[m] as i32;
+            self.intensity
+                .compress(encoder, prediction, intensity as i32, m.min(3));
+            self.last_intensity[m] = intensity;
+        }
+        if changed & 8 != 0 {
+            encoder.symbol(
+                self.classification.get(self.last[15] as usize),
+                item[15] as u32,
+            );
+        }
+        if changed & 4 != 0 {
+            let direction = ((item[14] >> 6) & 1) as usize;
+            let diff = item[16].wrapping_sub(self.last[16]);
+            encoder.symbol(&mut self.scan_angle[direction], diff as u32);
+        }
+        if changed & 2 != 0 {
+            encoder.symbol(self.user_data.get(self.last[17] as usize), item[17] as u32);
+        }
+        if changed & 1 != 0 {
+            let prediction = u16_at(&self.last, 18) as i32;
+            self.point_source
+                .compress(encoder, prediction, u16_at(item, 18) as i32, 0);
+        }
+        let single = (n == 1) as usize;
+
+        let median = self.last_x_diff[m].get();
+        let diff = i32_at(item, 0).wrapping_sub(i32_at(&self.last, 0));
+        self.dx.compress(encoder, median, diff, single);
+        self.last_x_diff[m].add(diff);
+
+        let median = self.last_y_diff[m].get();
+        let diff = i32_at(item, 4).wrapping_sub(i32_at(&self.last, 4));
+        let context = single + k_context(self.dx.k(), 20);
+        self.dy.compress(encoder, median, diff, context);
+        self.last_y_diff[m].add(diff);
+
+        let z = i32_at(item, 8);
+        let context = single + k_context((self.dx.k() + self.dy.k()) / 2, 18);
+        self.z.compress(encoder, self.last_height[l], z, context);
+        self.last_height[l] = z;
+
+        self.last.copy_from_slice(item);
+    }
+}
+
+const GPS_MULTI: i32 = 500;
+const GPS_MULTI_MINUS: i32 = -10;
+const GPS_MULTI_UNCHANGED: u32 = (GPS_MULTI - GPS_MULTI_MINUS + 1) as u32;
+const GPS_MULTI_CODE_FULL: u32 = (GPS_MULTI - GPS_MULTI_MINUS + 2) as u32;
+const GPS_MULTI_TOTAL: u32 = (GPS_MULTI - GPS_MULTI_MINUS + 6) as u32;
+
+/// GPS times, predicted from up to four interleaved sequences of evenly spaced times
+pub struct GpsTime11 {
+    last: usize,
+    next: usize,
+    /// Bits of the last time of every sequence, as integers
+    last_time: [i64; 4],
+    last_diff: [i32; 4],
+    multi_extreme_counter: [i32; 4],
+    multi: SymbolModel,
+    zero_diff: SymbolModel,
+    time: IntegerCompressor,
+}
+
+impl GpsTime11 {
+    pub fn new(first: &[u8]) -> Self {
+        Self {
+            last: 0,
+            next: 0,
+            last_time: [i64::from_le_bytes(first.try_into().unwrap()), 0, 0, 0],
+            last_diff: [0; 4],
+            multi_extreme_counter: [0; 4],
+            multi: SymbolModel::new(GPS_MULTI_TOTAL),
+            zero_diff: SymbolModel::new(6),
+            time: IntegerCompressor::new(32, 9),
+        }
+    }
+
+    /// Counts a difference far off the multiple it was coded with, which becomes the
+    /// new difference after a few of them
+    fn extreme(&mut self, diff: i32) {
+        let last = self.last;
+        self.multi_extreme_counter[last] += 1;
+        if self.multi_extreme_counter[last] > 3 {
+            self.last_diff[last] = diff;
+            self.multi_extreme_counter[last] = 0;
+        }
+    }
+
+    /// Starts a new sequence at a time too far from the others
+    fn read_full(&mut self, decoder: &mut Decoder) {
+        self.next = (self.next + 1) & 3;
+        let prediction = (self.last_time[self.last] >> 32) as i32;
+        let high = self.time.decompress(decoder, prediction, 8) as u32 as u64;
+        self.last_time[self.next] = ((high << 32) | decoder.u32() as u64) as i64;
+        self.last = self.next;
+        self.last_diff[self.last] = 0;
+        self.multi_extreme_counter[self.last] = 0;
+    }
+
+    pub fn read(&mut self, decoder: &mut Decoder, item: &mut [u8]) {
+        loop {
+            let last = self.last;
+            if self.last_diff[last] == 0 {
+                let multi = decoder.symbol(&mut self.zero_diff);
+                match multi {
+                    0 => {}
+                    1 => {
+                        let diff = self.time.decompress(decoder, 0, 0);
+                        self.last_diff[last] = diff;
+                        self.last_time[last] = self.last_time[last].wrapping_add(diff as i64);
+                        self.multi_extreme_counter[last] = 0;
+                    }
+                    2 => self.read_full(decoder),
+                    _ => {
+                        self.last = (last + multi as usize - 2) & 3;
+                        continue;
+                    }
+                }
+            } else {
+                let multi = decoder.symbol(&mut self.multi);
+                if multi == 1 {
+                    let diff = self.time.decompress(decoder, self.last_diff[last], 1);
+                    self.last_time[last] = self.last_time[last].wrapping_add(diff as i64);
+                    self.multi_extreme_counter[last] = 0;
+                } else if multi < GPS_MULTI_UNCHANGED {
+                    let last_diff = self.last_diff[last];
+                    let multi = multi as i32;
+                    let diff = if multi == 0 {
+                        let diff = self.time.decompress(decoder, 0, 7);
+                        self.extreme(diff);
+                        diff
+                    } else if multi < GPS_MULTI {
+                        let context = if multi < 10 { 2 } else { 3 };
+                        self.time
+                            .decompress(decoder, multi.wrapping_mul(last_diff), context)
+                    } else if multi == GPS_MULTI {
+                        let prediction = GPS_MULTI.wrapping_mul(last_diff);
+                        let diff = self.time.decompress(decoder, prediction, 4);
+                        self.extreme(diff);
+                        diff
+                    } else {
+                        let multi = GPS_MULTI - multi;
+                        if multi > GPS_MULTI_MINUS {
+                            self.time
+                                .decompress(decoder, multi.wrapping_mul(last_diff), 5)
+                        } else {
+                            let prediction = GPS_MULTI_MINUS.wrapping_mul(last_diff);
+                            let diff = self.time.decompress(decoder, prediction, 6);
+                            self.extreme(diff);
+                            diff
+                        }
+                    };
+                    self.last_time[last] = self.last_time[last].wrapping_add(diff as i64);
+                } else if multi == GPS_MULTI_CODE_FULL {
+                    self.read_full(decoder);
+                } else if multi > GPS_MULTI_CODE_FULL {
+                    self.last = (last + (multi - GPS_MULTI_CODE_FULL) as usize) & 3;
+                    continue;
+                }
+            }
+            break;
+        }
+        item.copy_from_slice(&self.last_time[self.last].to_le_bytes());
+    }
+
+    /// Sequence other than the current one the time is close enough to
+    fn other_sequence(&self, time: i64) -> Option<usize> {
+        (1..4).find(|i| {
+            let diff = time.wrapping_sub(self.last_time[(self.last + i) & 3]);
+            diff == diff as i32 as i64
+        })
+    }
+
+    fn write_full(&mut self, encoder: &mut Encoder, time: i64) {
+        let prediction = (self.last_time[self.last] >> 32) as i32;
+        self.time
+            .compress(encoder, prediction, (time >> 32) as i32, 8);
+        encoder.u32(time as u32);
+        self.next = (self.next + 1) & 3;
+        self.last = self.next;
+        self.last_diff[self.last] = 0;
+        self.multi_extreme_counter[self.last] = 0;
+    }
+
+    pub fn write(&mut self, encoder: &mut Encoder, item: &[u8]) {
+        let time = i64::from_le_bytes(item.try_into().unwrap());
+        loop {
+            let last = self.last;
+            let unchanged = time == self.last_time[last];
+            let diff_64 = time.wrapping_sub(self.last_time[last]);
+            let diff = diff_64 as i32;
+            let small = diff_64 == diff as i64;
+            if self.last_diff[last] == 0 {
+                if unchanged {
+                    encoder.symbol(&mut self.zero_diff, 0);
+                    break;
+                }
+                if small {
+                    encoder.symbol(&mut self.zero_diff, 1);
+                    self.time.compress(encoder, 0, diff, 0);
+                    self.last_diff[last] = diff;
+                    self.multi_extreme_counter[last] = 0;
+                } else if let Some(i) = self.other_sequence(time) {
+                    encoder.symbol(&mut self.zero_diff, i as u32 + 2);
+                    self.last = (last + i) & 3;
+                    continue;
+                } else {
+                    encoder.symbol(&mut self.zero_diff, 2);
+                    self.write_full(encoder, time);
+                }
+            } else {
+                if unchanged {
+                    encoder.symbol(&mut self.multi, GPS_MULTI_UNCHANGED);
+                    break;
+                }
+                if small {
+                    let last_diff = self.last_diff[last];
+                    let multi = quantize(diff as f32 / last_diff as f32);
+                    if multi == 1 {
+                        encoder.symbol(&mut self.multi, 1);
+                        self.time.compress(encoder, last_diff, diff, 1);
+                        self.multi_extreme_counter[last] = 0;
+                    } else if multi > 0 && multi < GPS_MULTI {
+                        encoder.symbol(&mut self.multi, multi as u32);
+                        let context = if multi < 10 { 2 } else { 3 };
+                        self.time
+                            .compress(encoder, multi.wrapping_mul(last_diff), diff, context);
+                    } else if multi > 0 {
+                        encoder.symbol(&mut self.multi, GPS_MULTI as u32);
+                        let prediction = GPS_MULTI.wrapping_mul(last_diff);
+                        self.time.compress(encoder, prediction, diff, 4);
+                        self.extreme(diff);
+                    } else if multi < 0 && multi > GPS_MULTI_MINUS {
+                        encoder.symbol(&mut self.multi, (GPS_MULTI - multi) as u32);
+                        self.time
+                            .compress(encoder, multi.wrapping_mul(last_diff), diff, 5);
+                    } else if multi < 0 {
+                        encoder.symbol(&mut self.multi, (GPS_MULTI - GPS_MULTI_MINUS) as u32);
+                        let prediction = GPS_MULTI_MINUS.wrapping_mul(last_diff);
+                        self.time.compress(encoder, prediction, diff, 6);
+                        self.extreme(diff);
+                    } else {
+                        encoder.symbol(&mut self.multi, 0);
+                        self.time.compress(encoder, 0, diff, 7);
+                        self.extreme(diff);
+                    }
+                } else if let Some(i) = self.other_sequence(time) {
+                    encoder.symbol(&mut self.multi, GPS_MULTI_CODE_FULL + i as u32);
+                    self.last = (last + i) & 3;
+                    continue;
+                } else {
+                    encoder.symbol(&mut self.multi, GPS_MULTI_CODE_FULL);
+                    self.write_full(encoder, time);
+                }
+            }
+            self.last_time[self.last] = time;
+            break;
+        }
+    }
+}
+
+/// Rounds half away from zero, like LASzip does
+fn quantize(value: f32) -> i32 {
+    if value >= 0.0 {
+        (value + 0.5) as i32
+    } else {
+        (value - 0.5) as i32
+    }
+}
+
+/// Keeps a predicted byte in 0 to 255
+fn u8_clamp(value: i32) -> i32 {
+    value.clamp(0, 255)
+}
+
+/// Red, green and blue, each byte predicted from the change of the channel before it
+pub struct Rgb12 {
+    last: [u16; 3],
+    byte_used: SymbolModel,
+    diffs: [SymbolModel; 6],
+}
+
+impl Rgb12 {
+    pub fn new(first: &[u8]) -> Self {
+        Self {
+            last: read_rgb(first),
+            byte_used: SymbolModel::new(128),
+            diffs: std::array::from_fn(|_| SymbolModel::new(256)),
+        }
+    }
+
+    pub fn read(&mut self, decoder: &mut Decoder, item: &mut [u8]) {
+        let rgb = decode_rgb(decoder, &mut self.byte_used, &mut self.diffs, self.last);
+        write_rgb(item, rgb);
+        self.last = rgb;
+    }
+
+    pub fn write(&mut self, encoder: &mut Encoder, item: &[u8]) {
+        let rgb = read_rgb(item);
+        encode_rgb(
+            encoder,
+            &mut self.byte_used,
+            &mut self.diffs,
+            self.last,
+            rgb,
+        );
+        self.last = rgb;
+    }
+}
+
+pub fn read_rgb(bytes: &[u8]) -> [u16; 3] {
+    std::array::from_fn(|i| u16_at(bytes, 2 * i))
+}
+
+pub fn write_rgb(bytes: &mut [u8], rgb: [u16; 3]) {
+    for (i, c) in rgb.into_iter().enumerate() {
+        bytes[2 * i..2 * i + 2].copy_from_slice(&c.to_le_bytes());
+    }
+}
+
+/// Decodes one byte of a channel, predicted from the same byte of the last color
+fn decode_byte(decoder: &mut Decoder, model: &mut SymbolModel, prediction: i32) -> u16 {
+    (decoder.symbol(model) as u8).wrapping_add(prediction as u8) as u16
+}
+
+/// Colors as LASzip codes them, shared with the layered items of LAS 1.4
+pub fn decode_rgb(
+    decoder: &mut Decoder,
+    byte_used: &mut SymbolModel,
+    diffs: &mut [SymbolModel; 6],
+    last: [u16; 3],
+) -> [u16; 3] {
+    let sym = decoder.symbol(byte_used);
+    let low = |c: u16| (c & 0xff) as i32;
+    let high = |c: u16| (c >> 8) as i32;
+    let mut rgb = [0u16; 3];
+    rgb[0] = if sym & 1 != 0 {
+        decode_byte(decoder, &mut diffs[0], low(last[0]))
+    } else {
+        last[0] & 0xff
+    };
+    rgb[0] |= if sym & 2 != 0 {
+        decode_byte(decoder, &mut diffs[1], high(last[0])) << 8
+    } else {
+        last[0] & 0xff00
+    };
+    if sym & 64 == 0 {
+        return [rgb[0]; 3];
+    }
+    let mut diff = low(rgb[0]) - low(last[0]);
+    rgb[1] = if sym & 4 != 0 {
+        decode_byte(decoder, &mut diffs[2], u8_clamp(diff + low(last[1])))
+    } else {
+        last[1] & 0xff
+    };
+    rgb[2] = if sym & 16 != 0 {
+        diff = (diff + low(rgb[1]) - low(last[1])) / 2;
+        decode_byte(decoder, &mut diffs[4], u8_clamp(diff + low(last[2])))
+    } else {
+        last[2] & 0xff
+    };
+    let mut diff = high(rgb[0]) - high(last[0]);
+    rgb[1] |= if sym & 8 != 0 {
+        decode_byte(decoder, &mut diffs[3], u8_clamp(diff + high(last[1]))) << 8
+    } else {
+        last[1] & 0xff00
+    };
+    rgb[2] |= if sym & 32 != 0 {
+        diff = (diff + high(rgb[1]) - high(last[1])) / 2;
+        decode_byte(decoder, &mut diffs[5], u8_clamp(diff + high(last[2]))) << 8
+    } else {
+        last[2] & 0xff00
+    };
+    rgb
+}
+
+pub fn encode_rgb(
+    encoder: &mut Encoder,
+    byte_used: &mut SymbolModel,
+    diffs: &mut [SymbolModel; 6],
+    last: [u16; 3],
+    rgb: [u16; 3],
+) {
+    let low = |c: u16| (c & 0xff) as i32;
+    let high = |c: u16| (c >> 8) as i32;
+    let mut sym = 0;
+    for (i, (l, c)) in last.iter().zip(&rgb).enumerate() {
+        sym |= ((low(*l) != low(*c)) as u32) << (2 * i);
+        sym |= ((high(*l) != high(*c)) as u32) << (2 * i + 1);
+    }
+    let gray = rgb[1] == rgb[0] && rgb[2] == rgb[0];
+    sym |= (!gray as u32) << 6;
+    encoder.symbol(byte_used, sym);
+    let mut code = |model: &mut SymbolModel, value: i32, prediction: i32| {
+        encoder.symbol(model, (value - prediction) as u8 as u32);
+    };
+    let mut diff_low = low(rgb[0]) - low(last[0]);
+    let mut diff_high = high(rgb[0]) - high(last[0]);
+    if sym & 1 != 0 {
+        code(&mut diffs[0], low(rgb[0]), low(last[0]));
+    }
+    if sym & 2 != 0 {
+        code(&mut diffs[1], high(rgb[0]), high(last[0]));
+    }
+    if gray {
+        return;
+    }
+    if sym & 4 != 0 {
+        code(
+            &mut diffs[2],
+            low(rgb[1]),
+            u8_clamp(diff_low + low(last[1])),
+        );
+    }
+    if sym & 16 != 0 {
+        diff_low = (diff_low + low(rgb[1]) - low(last[1])) / 2;
+        code(
+            &mut diffs[4],
+            low(rgb[2]),
+            u8_clamp(diff_low + low(last[2])),
+        );
+    }
+    if sym & 8 != 0 {
+        code(
+            &mut diffs[3],
+            high(rgb[1]),
+            u8_clamp(diff_high + high(last[1])),
+        );
+    }
+    if sym & 32 != 0 {
+        diff_high = (diff_high + high(rgb[1]) - high(last[1])) / 2;
+        code(
+            &mut diffs[5],
+            high(rgb[2]),
+            u8_clamp(diff_high + high(last[2])),
+        );
+    }
+}
+
+/// Extra bytes, each predicted from the same byte of the last record
+pub struct Bytes {
+    last: Vec<u8>,
+    models: Vec<SymbolModel>,
+}
+
+impl Bytes {
+    pub fn new(first: &[u8]) -> Self {
+        Self {
+            last: first.to_vec(),
+            models: vec![SymbolModel::new(256); first.len()],
+        }
+    }
+
+    pub fn read(&mut self, decoder: &mut Decoder, item: &mut [u8]) {
+        for ((byte, last), model) in item.iter_mut().zip(&mut self.last).zip(&mut self.models) {
+            *last = last.wrapping_add(decoder.symbol(model) as u8);
+            *byte = *last;
+        }
+    }
+
+    pub fn write(&mut self, encoder: &mut Encoder, item: &[u8]) {
+        for ((byte, last), model) in item.iter().zip(&mut self.last).zip(&mut self.models) {
+            encoder.symbol(model, byte.wrapping_sub(*last) as u32);
+            *last = *byte;
+        }
+    }
+}
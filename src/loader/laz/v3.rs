@@ -0,0 +1,926 @@
+//! Layered items of LAS 1.4 records: every attribute is compressed into its own layer of
+//! the chunk, and points of the four scanner channels are predicted separately
+
+use super::{
+    arithmetic::{Decoder, Encoder, LazyModels, SymbolModel},
+    integer::IntegerCompressor,
+    v2::{decode_rgb, encode_rgb, read_rgb, write_rgb},
+    Median5,
+};
+
+/// Layers of the point item: coordinates and returns, then height, classification, flags,
+/// intensity, scan angle, user data, point source and GPS time
+pub const POINT14_LAYERS: usize = 9;
+
+/// Bytes of the point record shared by formats 6 to 10
+pub const POINT14_SIZE: usize = 30;
+
+/// Context of the coordinate differences by return number and number of returns
+const NUMBER_RETURN_MAP_6CTX: [[u8; 16]; 16] = [
+    [0, 1, 2, 3, 4, 5, 3, 4, 4, 5, 5, 5, 5, 5, 5, 5],
+    [1, 0, 1, 3, 4, 5, 3, 4, 4, 5, 5, 5, 5, 5, 5, 5],
+    [2, 1, 2, 4, 4, 5, 4, 4, 4, 5, 5, 5, 5, 5, 5, 5],
+    [3, 3, 4, 5, 4, 5, 4, 4, 4, 5, 5, 5, 5, 5, 5, 5],
+    [4, 4, 4, 4, 5, 5, 4, 4, 4, 5, 5, 5, 5, 5, 5, 5],
+    [5, 5, 5, 5, 5, 5, 5, 4, 4, 5, 5, 5, 5, 5, 5, 5],
+    [3, 3, 4, 4, 4, 4, 4, 4, 4, 4, 5, 5, 5, 5, 5, 5],
+    [4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 5, 5, 5, 5, 5],
+    [4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 5, 5, 5, 5],
+    [5, 5, 5, 5, 5, 5, 4, 4, 4, 4, 4, 4, 4, 5, 5, 5],
+    [5, 5, 5, 5, 5, 5, 5, 4, 4, 4, 4, 4, 4, 4, 5, 5],
+    [5, 5, 5, 5, 5, 5, 5, 5, 4, 4, 4, 4, 4, 4, 4, 5],
+    [5, 5, 5, 5, 5, 5, 5, 5, 5, 4, 4, 4, 4, 4, 4, 4],
+    [5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 4, 4, 4, 4, 4, 4],
+    [5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 4, 4, 4, 4, 4],
+    [5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 4, 4, 4, 4],
+];
+
+/// Context of the heights: how far the return is from the last one, up to 7
+fn number_return_level(n: u8, r: u8) -> usize {
+    (n.abs_diff(r) as usize).min(7)
+}
+
+fn k_context(k: u32, max: u32) -> usize {
+    (if k < max { k & !1 } else { max }) as usize
+}
+
+/// Fields of a LAS 1.4 point record the compressor predicts
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Point14 {
+    x: i32,
+    y: i32,
+    z: i32,
+    intensity: u16,
+    return_number: u8,
+    number_of_returns: u8,
+    classification_flags: u8,
+    scanner_channel: u8,
+    scan_direction: bool,
+    edge_of_flight_line: bool,
+    classification: u8,
+    user_data: u8,
+    scan_angle: i16,
+    point_source: u16,
+    /// Bits of the time, as an integer
+    gps_time: i64,
+    /// Whether the time changed from the point before, which later points use as a context
+    gps_time_change: bool,
+}
+
+impl Point14 {
+    fn read(bytes: &[u8]) -> Self {
+        let i32_at = |at: usize| i32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        Self {
+            x: i32_at(0),
+            y: i32_at(4),
+            z: i32_at(8),
+            intensity: u16_at(12),
+            return_number: bytes[14] & 0xf,
+            number_of_returns: bytes[14] >> 4,
+            classification_flags: bytes[15] & 0xf,
+            scanner_channel: (bytes[15] >> 4) & 3,
+            scan_direction: bytes[15] & 0x40 != 0,
+            edge_of_flight_line: bytes[15] & 0x80 != 0,
+            classification: bytes[16],
+            user_data: bytes[17],
+            scan_angle: u16_at(18) as i16,
+            point_source: u16_at(20),
+            gps_time: i64::from_le_bytes(bytes[22..30].try_into().unwrap()),
+            gps_time_change: false,
+        }
+    }
+
+    fn write(&self, bytes: &mut [u8]) {
+        bytes[0..4].copy_from_slice(&self.x.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.y.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.z.to_le_bytes());
+        bytes[12..14].copy_from_slice(&self.intensity.to_le_bytes());
+        bytes[14] = self.return_number | (self.number_of_returns << 4);
+        bytes[15] = self.classification_flags
+            | (self.scanner_channel << 4)
+            | ((self.scan_direction as u8) << 6)
+            | ((self.edge_of_flight_line as u8) << 7);
+        bytes[16] = self.classification;
+        bytes[17] = self.user_data;
+        bytes[18..20].copy_from_slice(&self.scan_angle.to_le_bytes());
+        bytes[20..22].copy_from_slice(&self.point_source.to_le_bytes());
+        bytes[22..30].copy_from_slice(&self.gps_time.to_le_bytes());
+    }
+
+    /// Classification flags, scan direction and edge of flight line as one symbol
+    fn flags(&self) -> u32 {
+        ((self.edge_of_flight_line as u32) << 5)
+            | ((self.scan_direction as u32) << 4)
+            | self.classification_flags as u32
+    }
+
+    /// Whether the point is the first, the last, both or neither of its pulse
+    fn last_point_return(&self) -> usize {
+        (self.return_number == 1) as usize
+            + 2 * (self.return_number >= self.number_of_returns) as usize
+    }
+}
+
+const GPS_MULTI: i32 = 500;
+const GPS_MULTI_MINUS: i32 = -10;
+const GPS_MULTI_CODE_FULL: u32 = (GPS_MULTI - GPS_MULTI_MINUS + 1) as u32;
+const GPS_MULTI_TOTAL: u32 = (GPS_MULTI - GPS_MULTI_MINUS + 5) as u32;
+
+/// GPS times of one scanner channel, predicted from up to four interleaved sequences.
+/// Unlike the point-wise item, unchanged times are told by the point item.
+struct GpsTime14 {
+    last: usize,
+    next: usize,
+    last_time: [i64; 4],
+    last_diff: [i32; 4],
+    multi_extreme_counter: [i32; 4],
+    multi: SymbolModel,
+    zero_diff: SymbolModel,
+    time: IntegerCompressor,
+}
+
+impl GpsTime14 {
+    fn new(first: i64) -> Self {
+        Self {
+            last: 0,
+            next: 0,
+            last_time: [first, 0, 0, 0],
+            last_diff: [0; 4],
+            multi_extreme_counter: [0; 4],
+            multi: SymbolModel::new(GPS_MULTI_TOTAL),
+            zero_diff: SymbolModel::new(5),
+            time: IntegerCompressor::new(32, 9),
+        }
+    }
+
+    fn time(&self) -> i64 {
+        self.last_time[self.last]
+    }
+
+    fn extreme(&mut self, diff: i32) {
+        let last = self.last;
+        self.multi_extreme_counter[last] += 1;
+        if self.multi_extreme_counter[last] > 3 {
+            self.last_diff[last] = diff;
+            self.multi_extreme_counter[last] = 0;
+        }
+    }
+
+    fn read_full(&mut self, decoder: &mut Decoder) {
+        self.next = (self.next + 1) & 3;
+        let prediction = (self.last_time[self.last] >> 32) as i32;
+        let high = self.time.decompress(decoder, prediction, 8) as u32 as u64;
+        self.last_time[self.next] = ((high << 32) | decoder.u32() as u64) as i64;
+        self.last = self.next;
+        self.last_diff[self.last] = 0;
+        self.multi_extreme_counter[self.last] = 0;
+    }
+
+    fn read(&mut self, decoder: &mut Decoder) {
+        loop {
+            let last = self.last;
+            if self.last_diff[last] == 0 {
+                match decoder.symbol(&mut self.zero_diff) {
+                    0 => {
+                        let diff = self.time.decompress(decoder, 0, 0);
+                        self.last_diff[last] = diff;
+                        self.last_time[last] = self.last_time[last].wrapping_add(diff as i64);
+                        self.multi_extreme_counter[last] = 0;
+                    }
+                    1 => self.read_full(decoder),
+                    multi => {
+                        self.last = (last + multi as usize - 1) & 3;
+                        continue;
+                    }
+                }
+            } else {
+                let multi = decoder.symbol(&mut self.multi);
+                if multi == 1 {
+                    let diff = self.time.decompress(decoder, self.last_diff[last], 1);
+                    self.last_time[last] = self.last_time[last].wrapping_add(diff as i64);
+                    self.multi_extreme_counter[last] = 0;
+                } else if multi < GPS_MULTI_CODE_FULL {
+                    let last_diff = self.last_diff[last];
+                    let multi = multi as i32;
+                    let diff = if multi == 0 {
+                        let diff = self.time.decompress(decoder, 0, 7);
+                        self.extreme(diff);
+                        diff
+                    } else if multi < GPS_MULTI {
+                        let context = if multi < 10 { 2 } else { 3 };
+                        self.time
+                            .decompress(decoder, multi.wrapping_mul(last_diff), context)
+                    } else if multi == GPS_MULTI {
+                        let prediction = GPS_MULTI.wrapping_mul(last_diff);
+                        let diff = self.time.decompress(decoder, prediction, 4);
+                        self.extreme(diff);
+                        diff
+                    } else {
+                        let multi = GPS_MULTI - multi;
+                        if multi > GPS_MULTI_MINUS {
+                            self.time
+                                .decompress(decoder, multi.wrapping_mul(last_diff), 5)
+                        } else {
+                            let prediction = GPS_MULTI_MINUS.wrapping_mul(last_diff);
+                            let diff = self.time.decompress(decoder, prediction, 6);
+                            self.extreme(diff);
+                            diff
+                        }
+                    };
+                    self.last_time[last] = self.last_time[last].wrapping_add(diff as i64);
+                } else if multi == GPS_MULTI_CODE_FULL {
+                    self.read_full(decoder);
+                } else {
+                    self.last = (last + (multi - GPS_MULTI_CODE_FULL) as usize) & 3;
+                    continue;
+                }
+            }
+            break;
+        }
+    }
+
+    fn write_full(&mut self, encoder: &mut Encoder, time: i64) {
+        self.next = (self.next + 1) & 3;
+        let prediction = (self.last_time[self.last] >> 32) as i32;
+        self.time
+            .compress(encoder, prediction, (time >> 32) as i32, 8);
+        encoder.u32(time as u32);
+        self.last = self.next;
+        self.last_time[self.last] = time;
+        self.last_diff[self.last] = 0;
+        self.multi_extreme_counter[self.last] = 0;
+    }
+
+    /// Codes a time that differs from the last one. Only the current sequence is
+    /// predicted from, times which don't follow it start a new one.
+    fn write(&mut self, encoder: &mut Encoder, time: i64) {
+        let last = self.last;
+        let Ok(diff) = i32::try_from(time.wrapping_sub(self.last_time[last])) else {
+            let code_full = if self.last_diff[last] == 0 {
+                (&mut self.zero_diff, 1)
+            } else {
+                (&mut self.multi, GPS_MULTI_CODE_FULL)
+            };
+            encoder.symbol(code_full.0, code_full.1);
+            self.write_full(encoder, time);
+            return;
+        };
+        if self.last_diff[last] == 0 {
+            encoder.symbol(&mut self.zero_diff, 0);
+            self.time.compress(encoder, 0, diff, 0);
+            self.last_diff[last] = diff;
+            self.multi_extreme_counter[last] = 0;
+        } else {
+            let last_diff = self.last_diff[last];
+            let multi = (diff as f32 / last_diff as f32).round() as i32;
+            if multi == 1 {
+                encoder.symbol(&mut self.multi, 1);
+                self.time.compress(encoder, last_diff, diff, 1);
+                self.multi_extreme_counter[last] = 0;
+            } else if multi == 0 {
+                encoder.symbol(&mut self.multi, 0);
+                self.time.compress(encoder, 0, diff, 7);
+                self.extreme(diff);
+            } else if multi > 0 && multi < GPS_MULTI {
+                encoder.symbol(&mut self.multi, multi as u32);
+                let context = if multi < 10 { 2 } else { 3 };
+                self.time
+                    .compress(encoder, multi.wrapping_mul(last_diff), diff, context);
+            } else if multi > 0 {
+                encoder.symbol(&mut self.multi, GPS_MULTI as u32);
+                let prediction = GPS_MULTI.wrapping_mul(last_diff);
+                self.time.compress(encoder, prediction, diff, 4);
+                self.extreme(diff);
+            } else if multi > GPS_MULTI_MINUS {
+                encoder.symbol(&mut self.multi, (GPS_MULTI - multi) as u32);
+                self.time
+                    .compress(encoder, multi.wrapping_mul(last_diff), diff, 5);
+            } else {
+                encoder.symbol(&mut self.multi, (GPS_MULTI - GPS_MULTI_MINUS) as u32);
+                let prediction = GPS_MULTI_MINUS.wrapping_mul(last_diff);
+                self.time.compress(encoder, prediction, diff, 6);
+                self.extreme(diff);
+            }
+        }
+        self.last_time[last] = time;
+    }
+}
+
+/// Models of the point item for one scanner channel
+struct PointContext {
+    last: Point14,
+    changed_values: Vec<SymbolModel>,
+    scanner_channel: SymbolModel,
+    number_of_returns: LazyModels,
+    return_number: LazyModels,
+    return_number_gps_same: SymbolModel,
+    dx: IntegerCompressor,
+    dy: IntegerCompressor,
+    z: IntegerCompressor,
+    classification: LazyModels,
+    flags: LazyModels,
+    user_data: LazyModels,
+    intensity: IntegerCompressor,
+    scan_angle: IntegerCompressor,
+    point_source: IntegerCompressor,
+    gps_time: GpsTime14,
+    last_x_diff: [Median5; 12],
+    last_y_diff: [Median5; 12],
+    last_z: [i32; 8],
+    last_intensity: [u16; 8],
+}
+
+impl PointContext {
+    /// Context of a channel first seen after `last`
+    fn new(last: &Point14) -> Box<Self> {
+        Box::new(Self {
+            last: Point14 {
+                gps_time_change: false,
+                ..*last
+            },
+            changed_values: vec![SymbolModel::new(128); 8],
+            scanner_channel: SymbolModel::new(3),
+            number_of_returns: LazyModels::new(16, 16),
+            return_number: LazyModels::new(16, 16),
+            return_number_gps_same: SymbolModel::new(13),
+            dx: IntegerCompressor::new(32, 2),
+            dy: IntegerCompressor::new(32, 22),
+            z: IntegerCompressor::new(32, 20),
+            classification: LazyModels::new(64, 256),
+            flags: LazyModels::new(64, 64),
+            user_data: LazyModels::new(64, 256),
+            intensity: IntegerCompressor::new(16, 4),
+            scan_angle: IntegerCompressor::new(16, 2),
+            point_source: IntegerCompressor::new(16, 1),
+            gps_time: GpsTime14::new(last.gps_time),
+            last_x_diff: [Median5::default(); 12],
+            last_y_diff: [Median5::default(); 12],
+            last_z: [last.z; 8],
+            last_intensity: [last.intensity; 8],
+        })
+    }
+}
+
+/// Decodes the point item of a chunk. Layers of attributes which never change in the
+/// chunk are empty.
+pub struct Point14Decoder<'a> {
+    layers: Vec<Option<Decoder<'a>>>,
+    contexts: [Option<Box<PointContext>>; 4],
+    current: usize,
+}
+
+impl<'a> Point14Decoder<'a> {
+    pub fn new(first: &[u8], layers: &[&'a [u8]]) -> Self {
+        let first = Point14::read(first);
+        let current = first.scanner_channel as usize;
+        let mut contexts: [Option<Box<PointContext>>; 4] = Default::default();
+        contexts[current] = Some(PointContext::new(&first));
+        Self {
+            layers: layers
+                .iter()
+                .enumerate()
+                .map(|(i, layer)| (i == 0 || !layer.is_empty()).then(|| Decoder::new(layer)))
+                .collect(),
+            contexts,
+            current,
+        }
+    }
+
+    /// Decodes the next record, returning the scanner channel the other items predict from
+    pub fn read(&mut self, item: &mut [u8]) -> usize {
+        let [xy, z_layer, classification, flags, intensity, scan_angle, user_data, point_source, gps_time] =
+            &mut self.layers[..]
+        else {
+            unreachable!("the point item has {POINT14_LAYERS} layers")
+        };
+        let xy = xy.as_mut().unwrap();
+
+        let mut context = self.contexts[self.current].as_mut().unwrap();
+        let lpr = context.last.last_point_return() + 4 * context.last.gps_time_change as usize;
+        let changed = xy.symbol(&mut context.changed_values[lpr]);
+        if changed & (1 << 6) != 0 {
+            let diff = xy.symbol(&mut context.scanner_channel) as usize;
+            let channel = (self.current + diff + 1) % 4;
+            let last = context.last;
+            self.current = channel;
+            context = self.contexts[channel].get_or_insert_with(|| PointContext::new(&last));
+            context.last.scanner_channel = channel as u8;
+        }
+        let point_source_change = changed & (1 << 5) != 0;
+        let gps_time_change = changed & (1 << 4) != 0;
+        let scan_angle_change = changed & (1 << 3) != 0;
+        let gps = gps_time_change as usize;
+
+        let last_n = context.last.number_of_returns;
+        let last_r = context.last.return_number;
+        let n = if changed & (1 << 2) != 0 {
+            xy.symbol(context.number_of_returns.get(last_n as usize)) as u8
+        } else {
+            last_n
+        };
+        let r = match changed & 3 {
+            0 => last_r,
+            1 => (last_r + 1) % 16,
+            2 => (last_r + 15) % 16,
+            _ if gps_time_change => xy.symbol(context.return_number.get(last_r as usize)) as u8,
+            _ => {
+                let sym = xy.symbol(&mut context.return_number_gps_same) as u8;
+                (last_r + sym + 2) % 16
+            }
+        };
+        context.last.number_of_returns = n;
+        context.last.return_number = r;
+
+        let m = NUMBER_RETURN_MAP_6CTX[n as usize][r as usize] as usize;
+        let l = number_return_level(n, r);
+        let cpr = if r == 1 { 2 } else { 0 } + (r >= n) as usize;
+        let single = (n == 1) as usize;
+
+        let median = context.last_x_diff[(m << 1) | gps].get();
+        let diff = context.dx.decompress(xy, median, single);
+        context.last.x = context.last.x.wrapping_add(diff);
+        context.last_x_diff[(m << 1) | gps].add(diff);
+
+        let median = context.last_y_diff[(m << 1) | gps].get();
+        let k = k_context(context.dx.k(), 20);
+        let diff = context.dy.decompress(xy, median, single + k);
+        context.last.y = context.last.y.wrapping_add(diff);
+        context.last_y_diff[(m << 1) | gps].add(diff);
+
+        if let Some(decoder) = z_layer {
+            let k = k_context((context.dx.k() + context.dy.k()) / 2, 18);
+            let z = context.z.decompress(decoder, context.last_z[l], single + k);
+            context.last.z = z;
+            context.last_z[l] = z;
+        }
+        if let Some(decoder) = classification {
+            let ccc = (((context.last.classification & 0x1f) as usize) << 1) + (cpr == 3) as usize;
+            context.last.classification = decoder.symbol(context.classification.get(ccc)) as u8;
+        }
+        if let Some(decoder) = flags {
+            let model = context.flags.get(context.last.flags() as usize);
+            let flags = decoder.symbol(model);
+            context.last.edge_of_flight_line = flags & (1 << 5) != 0;
+            context.last.scan_direction = flags & (1 << 4) != 0;
+            context.last.classification_flags = (flags & 0xf) as u8;
+        }
+        if let Some(decoder) = intensity {
+            let index = (cpr << 1) | gps;
+            let prediction = context.last_intensity[index] as i32;
+            let intensity = context.intensity.decompress(decoder, prediction, cpr) as u16;
+            context.last_intensity[index] = intensity;
+            context.last.intensity = intensity;
+        }
+        if let Some(decoder) = scan_angle.as_mut().filter(|_| scan_angle_change) {
+            let prediction = context.last.scan_angle as i32;
+            context.last.scan_angle =
+                context.scan_angle.decompress(decoder, prediction, gps) as i16;
+        }
+        if let Some(decoder) = user_data {
+            let model = context.user_data.get(context.last.user_data as usize / 4);
+            context.last.user_data = decoder.symbol(model) as u8;
+        }
+        if let Some(decoder) = point_source.as_mut().filter(|_| point_source_change) {
+            let prediction = context.last.point_source as i32;
+            context.last.point_source =
+                context.point_source.decompress(decoder, prediction, 0) as u16;
+        }
+        if let Some(decoder) = gps_time.as_mut().filter(|_| gps_time_change) {
+            context.gps_time.read(decoder);
+            context.last.gps_time = context.gps_time.time();
+        }
+
+        context.last.write(item);
+        context.last.gps_time_change = gps_time_change;
+        self.current
+    }
+}
+
+/// Encodes the point item of a chunk into its layers, the mirror of [`Point14Decoder`]
+pub struct Point14Encoder {
+    layers: Vec<Encoder>,
+    /// Whether the attribute of a layer changed anywhere in the chunk
+    changed: [bool; POINT14_LAYERS],
+    contexts: [Option<Box<PointContext>>; 4],
+    current: usize,
+}
+
+impl Point14Encoder {
+    pub fn new(first: &[u8]) -> Self {
+        let first = Point14::read(first);
+        let current = first.scanner_channel as usize;
+        let mut contexts: [Option<Box<PointContext>>; 4] = Default::default();
+        contexts[current] = Some(PointContext::new(&first));
+        Self {
+            layers: (0..POINT14_LAYERS).map(|_| Encoder::new()).collect(),
+            changed: [false; POINT14_LAYERS],
+            contexts,
+            current,
+        }
+    }
+
+    /// Encodes the next record, returning the scanner channel the other items predict from
+    pub fn write(&mut self, item: &[u8]) -> usize {
+        let point = Point14::read(item);
+        let [xy, z_layer, classification, flags, intensity, scan_angle, user_data, point_source, gps_time] =
+            &mut self.layers[..]
+        else {
+            unreachable!("the point item has {POINT14_LAYERS} layers")
+        };
+
+        let previous = self.current;
+        let last = self.contexts[previous].as_ref().unwrap().last;
+        let lpr = last.last_point_return() + 4 * last.gps_time_change as usize;
+        let channel = point.scanner_channel as usize;
+        if channel != previous {
+            let context = self.contexts[channel].get_or_insert_with(|| PointContext::new(&last));
+            context.last.scanner_channel = channel as u8;
+        }
+
+        let last = self.contexts[channel].as_ref().unwrap().last;
+        let point_source_change = point.point_source != last.point_source;
+        let gps_time_change = point.gps_time != last.gps_time;
+        let scan_angle_change = point.scan_angle != last.scan_angle;
+        let gps = gps_time_change as usize;
+        let (n, r) = (point.number_of_returns, point.return_number);
+        let (last_n, last_r) = (last.number_of_returns, last.return_number);
+        let return_change = if r == last_r {
+            0
+        } else if r == (last_r + 1) % 16 {
+            1
+        } else if r == (last_r + 15) % 16 {
+            2
+        } else {
+            3
+        };
+        let changed = (((channel != previous) as u32) << 6)
+            | ((point_source_change as u32) << 5)
+            | ((gps_time_change as u32) << 4)
+            | ((scan_angle_change as u32) << 3)
+            | (((n != last_n) as u32) << 2)
+            | return_change;
+        let previous_context = self.contexts[previous].as_mut().unwrap();
+        xy.symbol(&mut previous_context.changed_values[lpr], changed);
+        if channel != previous {
+            let diff = (channel + 3 - previous) % 4;
+            xy.symbol(&mut previous_context.scanner_channel, diff as u32);
+            self.current = channel;
+        }
+
+        let context = self.contexts[channel].as_mut().unwrap();
+        if n != last_n {
+            xy.symbol(context.number_of_returns.get(last_n as usize), n as u32);
+        }
+        if return_change == 3 {
+            if gps_time_change {
+                xy.symbol(context.return_number.get(last_r as usize), r as u32);
+            } else {
+                let sym = (r + 14 - last_r) % 16;
+                xy.symbol(&mut context.return_number_gps_same, sym as u32);
+            }
+        }
+
+        let m = NUMBER_RETURN_MAP_6CTX[n as usize][r as usize] as usize;
+        let l = number_return_level(n, r);
+        let cpr = if r == 1 { 2 } else { 0 } + (r >= n) as usize;
+        let single = (n == 1) as usize;
+
+        let median = context.last_x_diff[(m << 1) | gps].get();
+        let diff = point.x.wrapping_sub(last.x);
+        context.dx.compress(xy, median, diff, single);
+        context.last_x_diff[(m << 1) | gps].add(diff);
+
+        let median = context.last_y_diff[(m << 1) | gps].get();
+        let diff = point.y.wrapping_sub(last.y);
+        let k = k_context(context.dx.k(), 20);
+        context.dy.compress(xy, median, diff, single + k);
+        context.last_y_diff[(m << 1) | gps].add(diff);
+
+        let k = k_context((context.dx.k() + context.dy.k()) / 2, 18);
+        context
+            .z
+            .compress(z_layer, context.last_z[l], point.z, single + k);
+        context.last_z[l] = point.z;
+
+        let ccc = (((last.classification & 0x1f) as usize) << 1) + (cpr == 3) as usize;
+        let model = context.classification.get(ccc);
+        classification.symbol(model, point.classification as u32);
+
+        let model = context.flags.get(last.flags() as usize);
+        flags.symbol(model, point.flags());
+
+        let index = (cpr << 1) | gps;
+        let prediction = context.last_intensity[index] as i32;
+        context
+            .intensity
+            .compress(intensity, prediction, point.intensity as i32, cpr);
+        context.last_intensity[index] = point.intensity;
+
+        if scan_angle_change {
+            let (prediction, real) = (last.scan_angle as i32, point.scan_angle as i32);
+            context
+                .scan_angle
+                .compress(scan_angle, prediction, real, gps);
+        }
+        let model = context.user_data.get(last.user_data as usize / 4);
+        user_data.symbol(model, point.user_data as u32);
+        if point_source_change {
+            let (prediction, real) = (last.point_source as i32, point.point_source as i32);
+            context
+                .point_source
+                .compress(point_source, prediction, real, 0);
+        }
+        if gps_time_change {
+            context.gps_time.write(gps_time, point.gps_time);
+        }
+
+        let changed = [
+            true,
+            point.z != last.z,
+            point.classification != last.classification,
+            point.flags() != last.flags(),
+            point.intensity != last.intensity,
+            scan_angle_change,
+            point.user_data != last.user_data,
+            point_source_change,
+            gps_time_change,
+        ];
+        for (layer, changed) in self.changed.iter_mut().zip(changed) {
+            *layer |= changed;
+        }
+        context.last = Point14 {
+            gps_time_change,
+            ..point
+        };
+        channel
+    }
+
+    /// Bytes of every layer, empty for attributes which never changed
+    pub fn finish(self) -> Vec<Vec<u8>> {
+        finish_layers(self.layers, &self.changed)
+    }
+}
+
+fn finish_layers(layers: Vec<Encoder>, changed: &[bool]) -> Vec<Vec<u8>> {
+    layers
+        .into_iter()
+        .zip(changed)
+        .map(|(layer, &changed)| if changed { layer.finish() } else { Vec::new() })
+        .collect()
+}
+
+/// Models of the color item for one scanner channel
+struct RgbContext {
+    last: [u16; 4],
+    byte_used: SymbolModel,
+    diffs: [SymbolModel; 6],
+    nir_byte_used: SymbolModel,
+    nir_diffs: [SymbolModel; 2],
+}
+
+impl RgbContext {
+    fn new(last: [u16; 4]) -> Box<Self> {
+        Box::new(Self {
+            last,
+            byte_used: SymbolModel::new(128),
+            diffs: std::array::from_fn(|_| SymbolModel::new(256)),
+            nir_byte_used: SymbolModel::new(4),
+            nir_diffs: std::array::from_fn(|_| SymbolModel::new(256)),
+        })
+    }
+}
+
+fn read_rgbnir(bytes: &[u8]) -> [u16; 4] {
+    let [r, g, b] = read_rgb(bytes);
+    let nir = bytes
+        .get(6..8)
+        .map_or(0, |nir| u16::from_le_bytes([nir[0], nir[1]]));
+    [r, g, b, nir]
+}
+
+/// Decodes the color item, with or without near infrared, which has a layer of its own
+pub struct RgbDecoder<'a> {
+    rgb: Option<Decoder<'a>>,
+    nir: Option<Decoder<'a>>,
+    contexts: [Option<Box<RgbContext>>; 4],
+    current: usize,
+}
+
+impl<'a> RgbDecoder<'a> {
+    pub fn new(first: &[u8], context: usize, layers: &[&'a [u8]]) -> Self {
+        let mut contexts: [Option<Box<RgbContext>>; 4] = Default::default();
+        contexts[context] = Some(RgbContext::new(read_rgbnir(first)));
+        let layer = |i: usize| {
+            layers
+                .get(i)
+                .filter(|layer| !layer.is_empty())
+                .map(|layer| Decoder::new(layer))
+        };
+        Self {
+            rgb: layer(0),
+            nir: layer(1),
+            contexts,
+            current: context,
+        }
+    }
+
+    pub fn read(&mut self, item: &mut [u8], context: usize) {
+        if context != self.current {
+            let last = self.contexts[self.current].as_ref().unwrap().last;
+            self.contexts[context].get_or_insert_with(|| RgbContext::new(last));
+            self.current = context;
+        }
+        let context = self.contexts[self.current].as_mut().unwrap();
+        if let Some(decoder) = &mut self.rgb {
+            let [r, g, b, _] = context.last;
+            let [r, g, b] = decode_rgb(
+                decoder,
+                &mut context.byte_used,
+                &mut context.diffs,
+                [r, g, b],
+            );
+            context.last[..3].copy_from_slice(&[r, g, b]);
+        }
+        if let Some(decoder) = &mut self.nir {
+            let last = context.last[3];
+            let sym = decoder.symbol(&mut context.nir_byte_used);
+            let mut nir = if sym & 1 != 0 {
+                let diff = decoder.symbol(&mut context.nir_diffs[0]) as u8;
+                diff.wrapping_add(last as u8) as u16
+            } else {
+                last & 0xff
+            };
+            nir |= if sym & 2 != 0 {
+                let diff = decoder.symbol(&mut context.nir_diffs[1]) as u8;
+                (diff.wrapping_add((last >> 8) as u8) as u16) << 8
+            } else {
+                last & 0xff00
+            };
+            context.last[3] = nir;
+        }
+        let [r, g, b, nir] = context.last;
+        write_rgb(item, [r, g, b]);
+        if let Some(bytes) = item.get_mut(6..8) {
+            bytes.copy_from_slice(&nir.to_le_bytes());
+        }
+    }
+}
+
+/// Encodes the color item, the mirror of [`RgbDecoder`]
+pub struct RgbEncoder {
+    rgb: Encoder,
+    nir: Encoder,
+    changed: [bool; 2],
+    has_nir: bool,
+    contexts: [Option<Box<RgbContext>>; 4],
+    current: usize,
+}
+
+impl RgbEncoder {
+    pub fn new(first: &[u8], context: usize) -> Self {
+        let mut contexts: [Option<Box<RgbContext>>; 4] = Default::default();
+        contexts[context] = Some(RgbContext::new(read_rgbnir(first)));
+        Self {
+            rgb: Encoder::new(),
+            nir: Encoder::new(),
+            changed: [false; 2],
+            has_nir: first.len() >= 8,
+            contexts,
+            current: context,
+        }
+    }
+
+    pub fn write(&mut self, item: &[u8], context: usize) {
+        if context != self.current {
+            let last = self.contexts[self.current].as_ref().unwrap().last;
+            self.contexts[context].get_or_insert_with(|| RgbContext::new(last));
+            self.current = context;
+        }
+        let context = self.contexts[self.current].as_mut().unwrap();
+        let [r, g, b, nir] = read_rgbnir(item);
+        let [last_r, last_g, last_b, last_nir] = context.last;
+        encode_rgb(
+            &mut self.rgb,
+            &mut context.byte_used,
+            &mut context.diffs,
+            [last_r, last_g, last_b],
+            [r, g, b],
+        );
+        self.changed[0] |= [r, g, b] != [last_r, last_g, last_b];
+        if self.has_nir {
+            let low_changed = (nir & 0xff) != (last_nir & 0xff);
+            let high_changed = (nir >> 8) != (last_nir >> 8);
+            let sym = low_changed as u32 | ((high_changed as u32) << 1);
+            self.nir.symbol(&mut context.nir_byte_used, sym);
+            if low_changed {
+                let diff = (nir as u8).wrapping_sub(last_nir as u8);
+                self.nir.symbol(&mut context.nir_diffs[0], diff as u32);
+            }
+            if high_changed {
+                let diff = ((nir >> 8) as u8).wrapping_sub((last_nir >> 8) as u8);
+                self.nir.symbol(&mut context.nir_diffs[1], diff as u32);
+            }
+            self.changed[1] |= nir != last_nir;
+        }
+        context.last = [r, g, b, nir];
+    }
+
+    pub fn finish(self) -> Vec<Vec<u8>> {
+        let layers = if self.has_nir { 2 } else { 1 };
+        let mut encoders = vec![self.rgb, self.nir];
+        encoders.truncate(layers);
+        finish_layers(encoders, &self.changed[..layers])
+    }
+}
+
+/// Models of the extra bytes for one scanner channel
+struct BytesContext {
+    last: Vec<u8>,
+    models: Vec<SymbolModel>,
+}
+
+impl BytesContext {
+    fn new(last: Vec<u8>) -> Box<Self> {
+        let models = vec![SymbolModel::new(256); last.len()];
+        Box::new(Self { last, models })
+    }
+}
+
+/// Decodes the extra bytes, every one of which has a layer of its own
+pub struct BytesDecoder<'a> {
+    layers: Vec<Option<Decoder<'a>>>,
+    contexts: [Option<Box<BytesContext>>; 4],
+    current: usize,
+}
+
+impl<'a> BytesDecoder<'a> {
+    pub fn new(first: &[u8], context: usize, layers: &[&'a [u8]]) -> Self {
+        let mut contexts: [Option<Box<BytesContext>>; 4] = Default::default();
+        contexts[context] = Some(BytesContext::new(first.to_vec()));
+        Self {
+            layers: layers
+                .iter()
+                .map(|layer| (!layer.is_empty()).then(|| Decoder::new(layer)))
+                .collect(),
+            contexts,
+            current: context,
+        }
+    }
+
+    pub fn read(&mut self, item: &mut [u8], context: usize) {
+        if context != self.current {
+            let last = self.contexts[self.current].as_ref().unwrap().last.clone();
+            self.contexts[context].get_or_insert_with(|| BytesContext::new(last));
+            self.current = context;
+        }
+        let context = self.contexts[self.current].as_mut().unwrap();
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            if let Some(decoder) = layer {
+                let diff = decoder.symbol(&mut context.models[i]) as u8;
+                context.last[i] = context.last[i].wrapping_add(diff);
+            }
+            item[i] = context.last[i];
+        }
+    }
+}
+
+/// Encodes the extra bytes, the mirror of [`BytesDecoder`]
+pub struct BytesEncoder {
+    layers: Vec<Encoder>,
+    changed: Vec<bool>,
+    contexts: [Option<Box<BytesContext>>; 4],
+    current: usize,
+}
+
+impl BytesEncoder {
+    pub fn new(first: &[u8], context: usize) -> Self {
+        let mut contexts: [Option<Box<BytesContext>>; 4] = Default::default();
+        contexts[context] = Some(BytesContext::new(first.to_vec()));
+        Self {
+            layers: first.iter().map(|_| Encoder::new()).collect(),
+            changed: vec![false; first.len()],
+            contexts,
+            current: context,
+        }
+    }
+
+    pub fn write(&mut self, item: &[u8], context: usize) {
+        if context != self.current {
+            let last = self.contexts[self.current].as_ref().unwrap().last.clone();
+            self.contexts[context].get_or_insert_with(|| BytesContext::new(last));
+            self.current = context;
+        }
+        let context = self.contexts[self.current].as_mut().unwrap();
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            let diff = item[i].wrapping_sub(context.last[i]);
+            layer.symbol(&mut context.models[i], diff as u32);
+            self.changed[i] |= diff != 0;
+            context.last[i] = item[i];
+        }
+    }
+
+    pub fn finish(self) -> Vec<Vec<u8>> {
+        finish_layers(self.layers, &self.changed)
+    }
+}
@@ -0,0 +1,140 @@
+use std::{error::Error, path::Path};
+
+use nalgebra::{Point3, Vector3};
+
+use crate::{
+    mesh::{self, sample::SampleSettings},
+    object::BasicVertex,
+};
+
+use self::{
+    columns::ColumnMapping,
+    convention::{Convention, ConventionSettings},
+    crs::{Conversion, CrsSettings},
+};
+
+pub mod background;
+pub mod cache;
+pub mod columns;
+pub mod convention;
+pub mod crs;
+pub mod kitti;
+pub mod las;
+pub mod laz;
+pub mod numpy;
+pub mod pcd;
+pub mod ply;
+pub mod rgbd;
+pub mod table;
+
+pub type LoadResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Points read from a file, ready to be uploaded
+pub struct PointCloud {
+    pub vertices: Vec<BasicVertex>,
+    /// Normals stored in the file itself, if it has any
+    pub normals: Option<Vec<Vector3<f32>>>,
+}
+
+/// How files are turned into the points shown
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadOptions {
+    /// Which columns of arrays and tables the points are made of
+    pub columns: ColumnMapping,
+    /// How points are spread over meshes
+    pub sampling: SampleSettings,
+    /// Which coordinate system points are shown in
+    pub crs: CrsSettings,
+    /// Which way up the file is and how its axes are turned
+    pub convention: ConventionSettings,
+}
+
+/// Picks a reader based on the extension of the file
+pub fn load(path: &Path, options: &LoadOptions) -> LoadResult<PointCloud> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let tabular = matches!(extension.as_deref(), Some("npy" | "npz"))
+        || table::is_parquet(path)
+        || table::is_arrow(path);
+    if !options.columns.is_auto() && !tabular {
+        log::warn!(
+            "{} isn't an array or a table, its columns can't be mapped",
+            path.display()
+        );
+    }
+    let mut cloud = match extension.as_deref() {
+        Some("las" | "laz") => return las::load(path, options),
+        Some("ply") => ply::load(path),
+        Some("pcd") => pcd::load(path),
+        Some("bin") => kitti::load(path),
+        Some("npy" | "npz") => numpy::load(path, &options.columns),
+        _ if table::is_parquet(path) => table::load_parquet(path, &options.columns),
+        _ if table::is_arrow(path) => table::load_arrow(path, &options.columns),
+        _ if mesh::is_mesh(path) => mesh::sample::sample(&mesh::load(path)?, &options.sampling),
+        _ => Err(format!("Unsupported point cloud format: {}", path.display()).into()),
+    }?;
+    // Other files name no system, they are only reprojected from one given for them
+    if options.crs.target.is_some() {
+        let conversion = Conversion::for_file(path, &options.crs)?;
+        for vertex in &mut cloud.vertices {
+            let p = vertex.position.map(|c| c as f64);
+            vertex.position = Vector3::from(conversion.apply([p.x, p.y, p.z])).map(|c| c as f32);
+        }
+        conversion.drop_failed(&mut cloud.vertices, cloud.normals.as_mut());
+    }
+    let convention = options.convention.resolve(native_convention(path));
+    convention.apply_to(&mut cloud.vertices);
+    if let Some(normals) = &mut cloud.normals {
+        for normal in normals {
+            *normal = convention.apply(*normal);
+        }
+    }
+    Ok(cloud)
+}
+
+/// Axes of a file when no convention is given for it. Sensor scans are z-up like LAS, the
+/// rest is shown as stored.
+fn native_convention(path: &Path) -> Convention {
+    let extension = path.extension().and_then(|e| e.to_str());
+    match extension {
+        Some(e) if e.eq_ignore_ascii_case("bin") => Convention::Z_UP,
+        _ if is_las(path) => Convention::Z_UP,
+        _ => Convention::Y_UP,
+    }
+}
+
+/// A position in the coordinates of a file, such as where it was scanned from, moved like
+/// the points of the file are: reprojected, then turned into the axes of the viewer
+pub fn to_view(
+    path: &Path,
+    options: &LoadOptions,
+    position: Point3<f32>,
+) -> LoadResult<Point3<f32>> {
+    let p = position.map(|c| c as f64);
+    let p = Conversion::for_file(path, &options.crs)?.apply([p.x, p.y, p.z]);
+    let convention = options.convention.resolve(native_convention(path));
+    Ok(Point3::from(
+        convention.apply(Vector3::from(p).map(|c| c as f32)),
+    ))
+}
+
+/// Points of a file one at a time. Only LAS and LAZ are read incrementally, other formats
+/// are loaded whole first.
+pub fn stream(
+    path: &Path,
+    options: &LoadOptions,
+) -> LoadResult<Box<dyn Iterator<Item = LoadResult<BasicVertex>>>> {
+    if is_las(path) {
+        return Ok(Box::new(las::stream(path, options)?));
+    }
+    Ok(Box::new(load(path, options)?.vertices.into_iter().map(Ok)))
+}
+
+/// Whether the file is LAS, compressed or not
+pub fn is_las(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("las") || e.eq_ignore_ascii_case("laz"))
+}
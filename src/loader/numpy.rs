@@ -0,0 +1,308 @@
+use std::{fs::File, io::Read, path::Path};
+
+use super::{columns::ColumnMapping, LoadResult, PointCloud};
+
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// Values of a Python literal, as far as `.npy` headers use them
+#[derive(Debug)]
+enum Literal {
+    Str(String),
+    Int(usize),
+    Bool(bool),
+    /// Lists and tuples
+    List(Vec<Literal>),
+    Dict(Vec<(Literal, Literal)>),
+}
+
+impl Literal {
+    fn parse(text: &str) -> LoadResult<Self> {
+        Self::parse_next(&mut text.trim().chars().peekable())
+    }
+
+    fn parse_next(chars: &mut std::iter::Peekable<std::str::Chars>) -> LoadResult<Self> {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next().ok_or("Corrupt NumPy header")? {
+            quote @ ('\'' | '"') => Ok(Self::Str(chars.take_while(|&c| c != quote).collect())),
+            open @ ('(' | '[' | '{') => {
+                let close = match open {
+                    '(' => ')',
+                    '[' => ']',
+                    _ => '}',
+                };
+                let mut items = Vec::new();
+                let mut entries = Vec::new();
+                loop {
+                    while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+                    if chars.next_if_eq(&close).is_some() {
+                        break;
+                    }
+                    let item = Self::parse_next(chars)?;
+                    if open == '{' {
+                        while chars.next_if(|c| c.is_whitespace() || *c == ':').is_some() {}
+                        entries.push((item, Self::parse_next(chars)?));
+                    } else {
+                        items.push(item);
+                    }
+                }
+                Ok(match open {
+                    '{' => Self::Dict(entries),
+                    _ => Self::List(items),
+                })
+            }
+            first => {
+                let mut word = first.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "True" => Ok(Self::Bool(true)),
+                    "False" => Ok(Self::Bool(false)),
+                    // Dimensions of old files may be longs, like `100L`
+                    _ => Ok(Self::Int(word.trim_end_matches('L').parse()?)),
+                }
+            }
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Literal> {
+        match self {
+            Self::Dict(entries) => entries
+                .iter()
+                .find(|(k, _)| matches!(k, Self::Str(s) if s == key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// How a value is stored, like `<f4`
+#[derive(Clone, Copy)]
+struct Scalar {
+    kind: char,
+    size: usize,
+    big_endian: bool,
+}
+
+impl Scalar {
+    fn parse(descr: &str) -> LoadResult<Self> {
+        let mut chars = descr.chars();
+        let (order, kind) = match (chars.next(), chars.next()) {
+            (Some(order @ ('<' | '>' | '|' | '=')), Some(kind)) => (order, kind),
+            _ => return Err(format!("Unsupported NumPy type {descr}").into()),
+        };
+        let size = chars.as_str().parse()?;
+        if !matches!(
+            (kind, size),
+            ('f', 4 | 8) | ('i' | 'u', 1 | 2 | 4 | 8) | ('b', 1)
+        ) {
+            return Err(format!("Unsupported NumPy type {descr}").into());
+        }
+        Ok(Self {
+            kind,
+            size,
+            big_endian: order == '>',
+        })
+    }
+
+    fn read(&self, bytes: &[u8]) -> f64 {
+        macro_rules! number {
+            ($t:ty) => {{
+                let raw = bytes[..std::mem::size_of::<$t>()].try_into().unwrap();
+                if self.big_endian {
+                    <$t>::from_be_bytes(raw) as f64
+                } else {
+                    <$t>::from_le_bytes(raw) as f64
+                }
+            }};
+        }
+        match (self.kind, self.size) {
+            ('f', 4) => number!(f32),
+            ('f', 8) => number!(f64),
+            ('i', 1) => number!(i8),
+            ('i', 2) => number!(i16),
+            ('i', 4) => number!(i32),
+            ('i', 8) => number!(i64),
+            ('u' | 'b', 1) => number!(u8),
+            ('u', 2) => number!(u16),
+            ('u', 4) => number!(u32),
+            _ => number!(u64),
+        }
+    }
+}
+
+/// Columns of an array: one for a flat array, one for every value in a row of an n by m
+/// array, and one for every field of a structured array
+pub struct Array {
+    pub names: Vec<String>,
+    pub columns: Vec<Vec<f64>>,
+}
+
+impl Array {
+    /// Reads a `.npy` file. Columns of an n by m array are named `prefix.0` to `prefix.m-1`,
+    /// or just by their index without a prefix.
+    fn read(data: &[u8], prefix: &str) -> LoadResult<Self> {
+        if data.get(..6) != Some(MAGIC) {
+            return Err("Not a NumPy array".into());
+        }
+        let (header_length, start) = match data.get(6).ok_or("Truncated NumPy array")? {
+            1 => (
+                u16::from_le_bytes(data.get(8..10).ok_or("Truncated NumPy array")?.try_into()?)
+                    as usize,
+                10,
+            ),
+            _ => (
+                u32::from_le_bytes(data.get(8..12).ok_or("Truncated NumPy array")?.try_into()?)
+                    as usize,
+                12,
+            ),
+        };
+        let header = data
+            .get(start..start + header_length)
+            .ok_or("Truncated NumPy array")?;
+        let header = Literal::parse(std::str::from_utf8(header)?)?;
+        let body = &data[start + header_length..];
+
+        let shape: Vec<usize> = match header.get("shape") {
+            Some(Literal::List(dimensions)) => dimensions
+                .iter()
+                .map(|d| match d {
+                    Literal::Int(d) => Ok(*d),
+                    _ => Err("Corrupt NumPy shape"),
+                })
+                .collect::<Result<_, _>>()?,
+            _ => return Err("The NumPy array has no shape".into()),
+        };
+        let fortran_order = matches!(header.get("fortran_order"), Some(Literal::Bool(true)));
+        let name = |i: usize| match prefix {
+            "" => i.to_string(),
+            _ => format!("{prefix}.{i}"),
+        };
+
+        // Every column is where its values start in a row, and how they are stored
+        let mut fields = Vec::new();
+        let row_size = match header.get("descr") {
+            Some(Literal::Str(descr)) => {
+                let scalar = Scalar::parse(descr)?;
+                match shape.as_slice() {
+                    [_] => fields.push((prefix.to_string(), 0, scalar)),
+                    [_, width] => {
+                        for i in 0..*width {
+                            fields.push((name(i), i * scalar.size, scalar));
+                        }
+                    }
+                    _ => return Err("Only arrays of one or two dimensions are supported".into()),
+                }
+                fields.len() * scalar.size
+            }
+            // Structured arrays list their fields as (name, type) or (name, type, shape)
+            Some(Literal::List(descr)) => {
+                if shape.len() != 1 {
+                    return Err("Only flat structured arrays are supported".into());
+                }
+                let mut offset = 0;
+                for field in descr {
+                    let Literal::List(field) = field else {
+                        return Err("Corrupt NumPy type".into());
+                    };
+                    let (Some(Literal::Str(field_name)), Some(Literal::Str(kind))) =
+                        (field.first(), field.get(1))
+                    else {
+                        return Err("Corrupt NumPy type".into());
+                    };
+                    let count: usize = match field.get(2) {
+                        Some(Literal::List(shape)) => shape
+                            .iter()
+                            .map(|d| if let Literal::Int(d) = d { *d } else { 1 })
+                            .product(),
+                        _ => 1,
+                    };
+                    // Padding, bytes and text only take up space
+                    let width = match kind.get(1..2) {
+                        Some("V" | "S") => 1,
+                        Some("U") => 4,
+                        _ => {
+                            let scalar = Scalar::parse(kind)?;
+                            for i in 0..count {
+                                let column = match count {
+                                    1 => field_name.clone(),
+                                    _ => format!("{field_name}.{i}"),
+                                };
+                                fields.push((column, offset + i * scalar.size, scalar));
+                            }
+                            offset += scalar.size * count;
+                            continue;
+                        }
+                    };
+                    offset += kind[2..].parse::<usize>()? * width * count;
+                }
+                offset
+            }
+            _ => return Err("The NumPy array has no type".into()),
+        };
+
+        let rows = shape[0];
+        if body.len() < rows * row_size {
+            return Err("Truncated NumPy array".into());
+        }
+
+        let columns = fields
+            .iter()
+            .enumerate()
+            .map(|(index, (_, offset, scalar))| {
+                (0..rows)
+                    .map(|row| {
+                        // Fortran order stores the columns one after another
+                        let start = if fortran_order {
+                            (index * rows + row) * scalar.size
+                        } else {
+                            row * row_size + offset
+                        };
+                        scalar.read(&body[start..])
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            names: fields.into_iter().map(|(name, _, _)| name).collect(),
+            columns,
+        })
+    }
+}
+
+/// Reads a `.npy` array, or every array in a `.npz` archive, and maps the columns to points
+pub fn load(path: &Path, mapping: &ColumnMapping) -> LoadResult<PointCloud> {
+    let is_npz = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("npz"));
+
+    let mut names = Vec::new();
+    let mut columns = Vec::new();
+    if is_npz {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        let single = archive.len() == 1;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let Some(name) = entry.name().strip_suffix(".npy").map(str::to_string) else {
+                continue;
+            };
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            // The columns of a lone array are named like those of a `.npy` file
+            let array = Array::read(&data, if single { "" } else { &name })?;
+            names.extend(array.names);
+            columns.extend(array.columns);
+        }
+    } else {
+        let array = Array::read(&std::fs::read(path)?, "")?;
+        names = array.names;
+        columns = array.columns;
+    }
+
+    let mapped = mapping.resolve(&names)?;
+    Ok(PointCloud {
+        vertices: mapped.vertices(|i| &columns[i])?,
+        normals: None,
+    })
+}
@@ -0,0 +1,233 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use nalgebra::{vector, Vector3};
+
+use crate::object::BasicVertex;
+
+use super::{LoadResult, PointCloud};
+
+struct Field {
+    name: String,
+    size: usize,
+    kind: char,
+    count: usize,
+}
+
+impl Field {
+    fn decode(&self, bytes: &[u8]) -> f64 {
+        match (self.kind, self.size) {
+            ('F', 4) => f32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            ('F', 8) => f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            ('I', 1) => bytes[0] as i8 as f64,
+            ('I', 2) => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ('I', 4) => i32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            ('U', 1) => bytes[0] as f64,
+            ('U', 2) => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ('U', 4) => u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            _ => 0.0,
+        }
+    }
+
+    /// Colors are packed as 0x00RRGGBB, stored in either a float or an unsigned int
+    fn decode_color(&self, bytes: &[u8]) -> Vector3<f32> {
+        let packed = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+        vector![
+            ((packed >> 16) & 0xff) as f32 / 255.0,
+            ((packed >> 8) & 0xff) as f32 / 255.0,
+            (packed & 0xff) as f32 / 255.0
+        ]
+    }
+
+    fn parse_color(&self, token: &str) -> LoadResult<Vector3<f32>> {
+        let bits = match self.kind {
+            'F' => token.parse::<f32>()?.to_bits(),
+            _ => token.parse::<u32>()?,
+        };
+        Ok(self.decode_color(&bits.to_le_bytes()))
+    }
+}
+
+pub fn load(path: &Path) -> LoadResult<PointCloud> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut names = Vec::new();
+    let mut sizes = Vec::new();
+    let mut kinds = Vec::new();
+    let mut counts = Vec::new();
+    let mut points = 0;
+    let data;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err("PCD header has no DATA line".into());
+        }
+        let mut words = line.split_whitespace();
+        let Some(key) = words.next() else { continue };
+        let words: Vec<&str> = words.collect();
+        match key {
+            "FIELDS" => names = words.iter().map(|w| w.to_string()).collect(),
+            "SIZE" => sizes = words.iter().map(|w| w.parse()).collect::<Result<_, _>>()?,
+            "TYPE" => kinds = words.iter().filter_map(|w| w.chars().next()).collect(),
+            "COUNT" => counts = words.iter().map(|w| w.parse()).collect::<Result<_, _>>()?,
+            "POINTS" => points = words.first().ok_or("PCD POINTS is empty")?.parse()?,
+            "DATA" => {
+                data = words.first().ok_or("PCD DATA is empty")?.to_string();
+                break;
+            }
+            _ => {}
+        }
+    }
+    if counts.is_empty() {
+        counts = vec![1; names.len()];
+    }
+    if sizes.len() != names.len() || kinds.len() != names.len() || counts.len() != names.len() {
+        return Err("PCD header fields don't match".into());
+    }
+    let fields: Vec<Field> = (0..names.len())
+        .map(|i| Field {
+            name: names[i].clone(),
+            size: sizes[i],
+            kind: kinds[i],
+            count: counts[i],
+        })
+        .collect();
+
+    let find = |name: &str| fields.iter().position(|f| f.name == name);
+    let position = match (find("x"), find("y"), find("z")) {
+        (Some(x), Some(y), Some(z)) => [x, y, z],
+        _ => return Err("PCD points have no x, y and z".into()),
+    };
+    let normal = match (find("normal_x"), find("normal_y"), find("normal_z")) {
+        (Some(x), Some(y), Some(z)) => Some([x, y, z]),
+        _ => None,
+    };
+    let color = find("rgb").or_else(|| find("rgba"));
+
+    // Byte offset of every field within a point record
+    let mut offsets = Vec::with_capacity(fields.len());
+    let mut stride = 0;
+    for field in &fields {
+        offsets.push(stride);
+        stride += field.size * field.count;
+    }
+
+    let mut vertices = Vec::with_capacity(points);
+    let mut normals = Vec::new();
+    let mut push = |get: &dyn Fn(usize) -> f64, color: Vector3<f32>| {
+        let get3 = |i: [usize; 3]| vector![get(i[0]) as f32, get(i[1]) as f32, get(i[2]) as f32];
+        let position = get3(position);
+        // Organized clouds mark missing points with NaN
+        if !position.iter().all(|c| c.is_finite()) {
+            return;
+        }
+        vertices.push(BasicVertex { position, color });
+        if let Some(n) = normal {
+            normals.push(get3(n));
+        }
+    };
+
+    match data.as_str() {
+        "ascii" => {
+            // Index of the first token of every field
+            let mut columns = Vec::with_capacity(fields.len());
+            let mut column = 0;
+            for field in &fields {
+                columns.push(column);
+                column += field.count;
+            }
+            for line in reader.lines() {
+                let line = line?;
+                let tokens: Vec<&str> = line.split_whitespace().collect();
+                if tokens.len() < column {
+                    continue;
+                }
+                let values: Vec<f64> = columns
+                    .iter()
+                    .map(|&c| tokens[c].parse().unwrap_or(f64::NAN))
+                    .collect();
+                let color = match color {
+                    Some(c) => fields[c].parse_color(tokens[columns[c]])?,
+                    None => Vector3::zeros(),
+                };
+                push(&|i| values[i], color);
+            }
+        }
+        "binary" => {
+            let mut record = vec![0u8; stride];
+            for _ in 0..points {
+                reader.read_exact(&mut record)?;
+                let color = match color {
+                    Some(c) => fields[c].decode_color(&record[offsets[c]..]),
+                    None => Vector3::zeros(),
+                };
+                push(&|i| fields[i].decode(&record[offsets[i]..]), color);
+            }
+        }
+        "binary_compressed" => {
+            let mut sizes = [0u8; 8];
+            reader.read_exact(&mut sizes)?;
+            let compressed_size = u32::from_le_bytes(sizes[..4].try_into().unwrap()) as usize;
+            let size = u32::from_le_bytes(sizes[4..].try_into().unwrap()) as usize;
+            let mut compressed = vec![0u8; compressed_size];
+            reader.read_exact(&mut compressed)?;
+            let decompressed = lzf_decompress(&compressed, size)?;
+            if decompressed.len() < stride * points {
+                return Err("PCD compressed data is truncated".into());
+            }
+            // Compressed data stores every field of all points contiguously
+            let columns: Vec<usize> = offsets.iter().map(|o| o * points).collect();
+            for p in 0..points {
+                let at = |i: usize| columns[i] + p * fields[i].size * fields[i].count;
+                let color = match color {
+                    Some(c) => fields[c].decode_color(&decompressed[at(c)..]),
+                    None => Vector3::zeros(),
+                };
+                push(&|i| fields[i].decode(&decompressed[at(i)..]), color);
+            }
+        }
+        _ => return Err(format!("Unknown PCD data encoding: {data}").into()),
+    }
+
+    Ok(PointCloud {
+        vertices,
+        normals: (!normals.is_empty()).then_some(normals),
+    })
+}
+
+fn lzf_decompress(input: &[u8], size: usize) -> LoadResult<Vec<u8>> {
+    let mut output = Vec::with_capacity(size);
+    let mut i = 0;
+    while i < input.len() {
+        let control = input[i] as usize;
+        i += 1;
+        if control < 32 {
+            let end = i + control + 1;
+            output.extend_from_slice(input.get(i..end).ok_or("Corrupt LZF literal")?);
+            i = end;
+        } else {
+            let mut length = control >> 5;
+            if length == 7 {
+                length += *input.get(i).ok_or("Corrupt LZF length")? as usize;
+                i += 1;
+            }
+            let back = ((control & 0x1f) << 8)
+                + *input.get(i).ok_or("Corrupt LZF reference")? as usize
+                + 1;
+            i += 1;
+            let start = output
+                .len()
+                .checked_sub(back)
+                .ok_or("Corrupt LZF reference")?;
+            // Copies may overlap their own output
+            for j in 0..length + 2 {
+                output.push(output[start + j]);
+            }
+        }
+    }
+    Ok(output)
+}
@@ -0,0 +1,254 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use nalgebra::{vector, Vector3};
+
+use crate::object::BasicVertex;
+
+use super::{LoadResult, PointCloud};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> LoadResult<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return Err(format!("Unknown PLY type: {name}").into()),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// Largest value of integer types, used to normalize colors
+    fn range(self) -> f64 {
+        match self {
+            Self::U8 | Self::I8 => 255.0,
+            Self::U16 | Self::I16 => 65535.0,
+            Self::U32 | Self::I32 => u32::MAX as f64,
+            Self::F32 | Self::F64 => 1.0,
+        }
+    }
+}
+
+enum Property {
+    Scalar(String, Scalar),
+    List(Scalar, Scalar),
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Reads one value of every property, whether the file is text or binary
+struct ValueReader<R: BufRead> {
+    reader: R,
+    format: Format,
+    tokens: std::vec::IntoIter<String>,
+}
+
+impl<R: BufRead> ValueReader<R> {
+    fn read(&mut self, scalar: Scalar) -> LoadResult<f64> {
+        if self.format == Format::Ascii {
+            return Ok(self.token()?.parse()?);
+        }
+        let mut buf = [0u8; 8];
+        let bytes = &mut buf[..scalar.size()];
+        self.reader.read_exact(bytes)?;
+        if self.format == Format::BinaryBigEndian {
+            bytes.reverse();
+        }
+        Ok(match scalar {
+            Scalar::I8 => bytes[0] as i8 as f64,
+            Scalar::U8 => bytes[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+            Scalar::U32 => u32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+            Scalar::F32 => f32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+            Scalar::F64 => f64::from_le_bytes(buf),
+        })
+    }
+
+    fn token(&mut self) -> LoadResult<String> {
+        loop {
+            if let Some(token) = self.tokens.next() {
+                return Ok(token);
+            }
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err("Unexpected end of PLY file".into());
+            }
+            self.tokens = line
+                .split_whitespace()
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+                .into_iter();
+        }
+    }
+}
+
+pub fn load(path: &Path) -> LoadResult<PointCloud> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err("PLY header is not terminated".into());
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["ply"] | [] => {}
+            ["comment", ..] | ["obj_info", ..] => {}
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", "binary_big_endian", _] => format = Some(Format::BinaryBigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse()?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, _] => elements
+                .last_mut()
+                .ok_or("PLY property outside of an element")?
+                .properties
+                .push(Property::List(Scalar::parse(count)?, Scalar::parse(item)?)),
+            ["property", scalar, name] => elements
+                .last_mut()
+                .ok_or("PLY property outside of an element")?
+                .properties
+                .push(Property::Scalar(name.to_string(), Scalar::parse(scalar)?)),
+            ["end_header"] => break,
+            _ => return Err(format!("Unexpected PLY header line: {}", line.trim()).into()),
+        }
+    }
+    let format = format.ok_or("PLY file has no format")?;
+
+    let mut values = ValueReader {
+        reader,
+        format,
+        tokens: Vec::new().into_iter(),
+    };
+
+    let mut vertices = Vec::new();
+    let mut normals = Vec::new();
+    for element in &elements {
+        if element.name != "vertex" {
+            // Skip everything up to the vertices
+            for _ in 0..element.count {
+                for property in &element.properties {
+                    match property {
+                        Property::Scalar(_, scalar) => {
+                            values.read(*scalar)?;
+                        }
+                        Property::List(count, item) => {
+                            for _ in 0..values.read(*count)? as usize {
+                                values.read(*item)?;
+                            }
+                        }
+                    }
+                }
+            }
+            continue;
+        }
+
+        let column = |names: &[&str]| {
+            element.properties.iter().position(|p| match p {
+                Property::Scalar(name, _) => names.contains(&name.as_str()),
+                Property::List(..) => false,
+            })
+        };
+        let required = |name: &str| column(&[name]).ok_or(format!("PLY vertices have no {name}"));
+        let position = [required("x")?, required("y")?, required("z")?];
+        let normal = match (column(&["nx"]), column(&["ny"]), column(&["nz"])) {
+            (Some(x), Some(y), Some(z)) => Some([x, y, z]),
+            _ => None,
+        };
+        let color = match (
+            column(&["red", "r"]),
+            column(&["green", "g"]),
+            column(&["blue", "b"]),
+        ) {
+            (Some(r), Some(g), Some(b)) => Some([r, g, b]),
+            _ => None,
+        };
+
+        vertices.reserve(element.count);
+        let mut row = vec![0.0; element.properties.len()];
+        let mut range = vec![1.0; element.properties.len()];
+        for _ in 0..element.count {
+            for (i, property) in element.properties.iter().enumerate() {
+                match property {
+                    Property::Scalar(_, scalar) => {
+                        row[i] = values.read(*scalar)?;
+                        range[i] = scalar.range();
+                    }
+                    Property::List(count, item) => {
+                        for _ in 0..values.read(*count)? as usize {
+                            values.read(*item)?;
+                        }
+                    }
+                }
+            }
+            let get = |i: [usize; 3]| vector![row[i[0]] as f32, row[i[1]] as f32, row[i[2]] as f32];
+            vertices.push(BasicVertex {
+                position: get(position),
+                color: match color {
+                    Some(c) => vector![
+                        (row[c[0]] / range[c[0]]) as f32,
+                        (row[c[1]] / range[c[1]]) as f32,
+                        (row[c[2]] / range[c[2]]) as f32
+                    ],
+                    None => Vector3::zeros(),
+                },
+            });
+            if let Some(n) = normal {
+                normals.push(get(n));
+            }
+        }
+        break;
+    }
+
+    Ok(PointCloud {
+        vertices,
+        normals: (!normals.is_empty()).then_some(normals),
+    })
+}
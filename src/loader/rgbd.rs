@@ -0,0 +1,326 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use image::{DynamicImage, RgbImage};
+use nalgebra::{
+    vector, Isometry3, Matrix4, Point3, Quaternion, Rotation3, Translation3, UnitQuaternion,
+};
+
+use crate::object::BasicVertex;
+
+use super::LoadResult;
+
+/// Depth units per meter of TUM datasets, which list their images in `depth.txt`
+const TUM_DEPTH_SCALE: f32 = 5000.0;
+/// Depth units per meter of everything else, millimeters
+const DEPTH_SCALE: f32 = 1000.0;
+/// Seconds a color image or pose may be away from its depth image and still belong to it
+const MAX_TIME_DIFFERENCE: f64 = 0.05;
+
+/// Pinhole camera model of the depth image, in pixels
+#[derive(Debug, Clone, Copy)]
+pub struct Intrinsics {
+    pub fx: f32,
+    pub fy: f32,
+    pub cx: f32,
+    pub cy: f32,
+}
+
+impl Default for Intrinsics {
+    /// The factory calibration of 640x480 PrimeSense sensors, which both TUM and Redwood
+    /// assume
+    fn default() -> Self {
+        Self {
+            fx: 525.0,
+            fy: 525.0,
+            cx: 319.5,
+            cy: 239.5,
+        }
+    }
+}
+
+impl FromStr for Intrinsics {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|c| c.trim().parse::<f32>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match values.as_slice() {
+            &[fx, fy, cx, cy] => Ok(Self { fx, fy, cx, cy }),
+            _ => Err(format!("Expected fx,fy,cx,cy but got {s}")),
+        }
+    }
+}
+
+/// How depth images are turned into points
+#[derive(Debug, Clone)]
+pub struct RgbdSettings {
+    pub intrinsics: Intrinsics,
+    /// Depth units per meter, 5000 for TUM datasets and 1000 otherwise if unset
+    pub depth_scale: Option<f32>,
+    /// Meters beyond which depths are too noisy to keep
+    pub max_depth: Option<f32>,
+    /// Color image aligned with a single depth image
+    pub color: Option<PathBuf>,
+    /// Camera poses of a sequence, in the TUM format or a Redwood `.log`
+    pub trajectory: Option<PathBuf>,
+    /// Only every this many pixels in both directions become a point
+    pub stride: usize,
+}
+
+impl Default for RgbdSettings {
+    fn default() -> Self {
+        Self {
+            intrinsics: Intrinsics::default(),
+            depth_scale: None,
+            max_depth: None,
+            color: None,
+            trajectory: None,
+            stride: 1,
+        }
+    }
+}
+
+/// A depth image, with the color image taken with it and where the camera was
+pub struct Frame {
+    pub depth: PathBuf,
+    pub color: Option<PathBuf>,
+    pub pose: Isometry3<f32>,
+}
+
+/// Camera poses, either at points in time or one for every frame
+enum Trajectory {
+    Timed(Vec<(f64, Isometry3<f32>)>),
+    Indexed(Vec<Isometry3<f32>>),
+}
+
+/// Whether the input is a depth image, a TUM dataset or a Redwood one
+pub fn is_rgbd(path: &Path) -> bool {
+    let png = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("png"));
+    png || path.join("depth.txt").is_file() || path.join("depth").is_dir()
+}
+
+/// The frames of the input, with the depth scale they use. A single depth image is seen
+/// from the origin, the frames of a dataset directory from the poses of its trajectory.
+pub fn frames(input: &Path, settings: &RgbdSettings) -> LoadResult<(Vec<Frame>, f32)> {
+    if !input.is_dir() {
+        let frame = Frame {
+            depth: input.to_owned(),
+            color: settings.color.clone(),
+            pose: Isometry3::identity(),
+        };
+        return Ok((vec![frame], settings.depth_scale.unwrap_or(DEPTH_SCALE)));
+    }
+
+    // Depth images with their timestamps, if the dataset has any
+    let (depths, colors, default_scale) = if input.join("depth.txt").is_file() {
+        let depths = read_list(input, "depth.txt")?;
+        let colors = read_list(input, "rgb.txt").unwrap_or_default();
+        let depths: Vec<_> = depths
+            .into_iter()
+            .map(|(time, depth)| {
+                let color = nearest(&colors, time).cloned();
+                (Some(time), depth, color)
+            })
+            .collect();
+        (depths, Vec::new(), TUM_DEPTH_SCALE)
+    } else {
+        // Redwood keeps color next to depth, in a directory named after the dataset version
+        let depths = sorted_images(&input.join("depth"))?;
+        let colors = ["image", "rgb", "color"]
+            .iter()
+            .map(|name| input.join(name))
+            .find(|dir| dir.is_dir())
+            .map(|dir| sorted_images(&dir))
+            .transpose()?
+            .unwrap_or_default();
+        if !colors.is_empty() && colors.len() != depths.len() {
+            log::warn!(
+                "{} depth images but {} color images, colors are left out",
+                depths.len(),
+                colors.len()
+            );
+        }
+        let depths = depths
+            .into_iter()
+            .map(|depth| (None, depth, None))
+            .collect();
+        (depths, colors, DEPTH_SCALE)
+    };
+
+    let trajectory = match &settings.trajectory {
+        Some(path) => path.clone(),
+        None => find_trajectory(input).ok_or_else(|| {
+            format!(
+                "{} has no trajectory to put its frames together",
+                input.display()
+            )
+        })?,
+    };
+    let trajectory = read_trajectory(&trajectory)?;
+
+    let mut frames = Vec::new();
+    let paired = colors.len() == depths.len();
+    for (i, (time, depth, color)) in depths.into_iter().enumerate() {
+        let pose = match (&trajectory, time) {
+            (Trajectory::Timed(poses), Some(time)) => nearest(poses, time).copied(),
+            (Trajectory::Timed(poses), None) => poses.get(i).map(|(_, pose)| *pose),
+            (Trajectory::Indexed(poses), _) => poses.get(i).copied(),
+        };
+        // Frames the camera wasn't tracked for can't be placed
+        let Some(pose) = pose else { continue };
+        let color = color.or_else(|| paired.then(|| colors[i].clone()));
+        frames.push(Frame { depth, color, pose });
+    }
+    log::info!("Fusing {} frames of {}", frames.len(), input.display());
+    Ok((frames, settings.depth_scale.unwrap_or(default_scale)))
+}
+
+/// Back-projects the pixels of a depth image, colored by the aligned color image if there
+/// is one
+pub fn back_project(
+    frame: &Frame,
+    depth_scale: f32,
+    settings: &RgbdSettings,
+) -> LoadResult<Vec<BasicVertex>> {
+    let depth = match image::open(&frame.depth)? {
+        DynamicImage::ImageLuma16(depth) => depth,
+        _ => return Err(format!("{} isn't a 16-bit depth image", frame.depth.display()).into()),
+    };
+    let color: Option<RgbImage> = match &frame.color {
+        Some(color) => Some(image::open(color)?.into_rgb8()),
+        None => None,
+    };
+    let Intrinsics { fx, fy, cx, cy } = settings.intrinsics;
+
+    let mut vertices = Vec::new();
+    let stride = settings.stride.max(1);
+    for v in (0..depth.height()).step_by(stride) {
+        for u in (0..depth.width()).step_by(stride) {
+            let raw = depth.get_pixel(u, v).0[0];
+            if raw == 0 {
+                continue;
+            }
+            let z = raw as f32 / depth_scale;
+            if settings.max_depth.is_some_and(|max| z > max) {
+                continue;
+            }
+            let camera = vector![(u as f32 - cx) * z / fx, (v as f32 - cy) * z / fy, z];
+            let world = frame.pose * Point3::from(camera);
+            // A color image of another resolution still covers the same view
+            let color = color.as_ref().map_or(vector![1.0, 1.0, 1.0], |color| {
+                let x = u * color.width() / depth.width();
+                let y = v * color.height() / depth.height();
+                let [r, g, b] = color.get_pixel(x, y).0;
+                vector![r, g, b].map(|c| c as f32 / 255.0)
+            });
+            vertices.push(BasicVertex {
+                // Cameras look down z with y pointing down, the viewer has y up
+                position: vector![world.x, -world.y, -world.z],
+                color,
+            });
+        }
+    }
+    Ok(vertices)
+}
+
+/// Reads a TUM file list, made of `timestamp path` lines
+fn read_list(directory: &Path, name: &str) -> LoadResult<Vec<(f64, PathBuf)>> {
+    let text = std::fs::read_to_string(directory.join(name))?;
+    let mut entries = Vec::new();
+    for line in text.lines().filter(|l| !l.starts_with('#')) {
+        let mut words = line.split_whitespace();
+        if let (Some(time), Some(path)) = (words.next(), words.next()) {
+            entries.push((time.parse()?, directory.join(path)));
+        }
+    }
+    Ok(entries)
+}
+
+fn sorted_images(directory: &Path) -> LoadResult<Vec<PathBuf>> {
+    let mut images = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        let extension = path.extension().and_then(|e| e.to_str());
+        if extension
+            .is_some_and(|e| ["png", "jpg", "jpeg"].contains(&e.to_ascii_lowercase().as_str()))
+        {
+            images.push(path);
+        }
+    }
+    images.sort();
+    Ok(images)
+}
+
+/// The entry closest in time, if it's close enough. The entries are sorted by time.
+fn nearest<T>(entries: &[(f64, T)], time: f64) -> Option<&T> {
+    let i = entries.partition_point(|(t, _)| *t < time);
+    [i.checked_sub(1), Some(i)]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| entries.get(i))
+        .filter(|(t, _)| (t - time).abs() <= MAX_TIME_DIFFERENCE)
+        .min_by(|a, b| (a.0 - time).abs().total_cmp(&(b.0 - time).abs()))
+        .map(|(_, entry)| entry)
+}
+
+/// The ground truth of TUM datasets, or the one `.log` of a Redwood dataset
+fn find_trajectory(directory: &Path) -> Option<PathBuf> {
+    let groundtruth = directory.join("groundtruth.txt");
+    if groundtruth.is_file() {
+        return Some(groundtruth);
+    }
+    std::fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| path.extension().is_some_and(|e| e == "log"))
+}
+
+/// Reads TUM trajectories, `timestamp tx ty tz qx qy qz qw` lines, or Redwood `.log` ones,
+/// made of a line of frame numbers followed by the four rows of every pose
+fn read_trajectory(path: &Path) -> LoadResult<Trajectory> {
+    let text = std::fs::read_to_string(path)?;
+    let rows = text
+        .lines()
+        .filter(|l| !l.starts_with('#') && !l.trim().is_empty())
+        .map(|line| {
+            line.split_whitespace()
+                .map(|word| word.parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if path.extension().is_some_and(|e| e == "log") {
+        let mut poses = Vec::new();
+        for block in rows.chunks_exact(5) {
+            let values: Vec<f64> = block[1..].iter().flatten().copied().collect();
+            if values.len() != 16 {
+                return Err(format!("{} isn't a Redwood trajectory", path.display()).into());
+            }
+            let matrix = Matrix4::from_row_slice(&values).cast::<f32>();
+            let rotation = Rotation3::from_matrix(&matrix.fixed_view::<3, 3>(0, 0).into_owned());
+            let translation = Translation3::from(matrix.fixed_view::<3, 1>(0, 3).into_owned());
+            poses.push(Isometry3::from_parts(translation, rotation.into()));
+        }
+        return Ok(Trajectory::Indexed(poses));
+    }
+
+    let mut poses = Vec::new();
+    for row in rows {
+        let &[time, tx, ty, tz, qx, qy, qz, qw] = row.as_slice() else {
+            return Err(format!("{} isn't a TUM trajectory", path.display()).into());
+        };
+        let rotation = UnitQuaternion::from_quaternion(Quaternion::new(qw, qx, qy, qz));
+        let pose = Isometry3::from_parts(Translation3::new(tx, ty, tz), rotation);
+        poses.push((time, pose.cast::<f32>()));
+    }
+    poses.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(Trajectory::Timed(poses))
+}
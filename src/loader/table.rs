@@ -0,0 +1,186 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek},
+    path::Path,
+};
+
+use arrow_array::{
+    cast::AsArray,
+    types::{
+        Float16Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type,
+        UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+    },
+    Array, RecordBatch,
+};
+use arrow_schema::{ArrowError, DataType, Schema};
+use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ProjectionMask};
+
+use super::{
+    columns::{ColumnMapping, Columns},
+    LoadResult, PointCloud,
+};
+
+/// Arrow IPC files, Feather version 2 included, start with this
+const ARROW_MAGIC: &[u8; 6] = b"ARROW1";
+
+pub fn is_parquet(path: &Path) -> bool {
+    has_extension(path, &["parquet", "pq"])
+}
+
+pub fn is_arrow(path: &Path) -> bool {
+    has_extension(path, &["arrow", "arrows", "feather", "ipc"])
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.iter().any(|x| e.eq_ignore_ascii_case(x)))
+}
+
+/// Column of numbers in a table
+struct Column {
+    name: String,
+    /// Index of the field it's read from
+    field: usize,
+    /// Position in the list when the field is a fixed size list
+    item: Option<usize>,
+}
+
+/// Columns of a table. Fixed size lists of numbers, like a `position` field of three
+/// floats, become one column per value, named `position.0` and so on.
+fn columns(schema: &Schema) -> Vec<Column> {
+    let mut columns = Vec::new();
+    for (i, field) in schema.fields().iter().enumerate() {
+        match field.data_type() {
+            DataType::FixedSizeList(item, size) if item.data_type().is_numeric() => {
+                for j in 0..*size as usize {
+                    columns.push(Column {
+                        name: format!("{}.{j}", field.name()),
+                        field: i,
+                        item: Some(j),
+                    });
+                }
+            }
+            _ => columns.push(Column {
+                name: field.name().clone(),
+                field: i,
+                item: None,
+            }),
+        }
+    }
+    columns
+}
+
+fn resolve(mapping: &ColumnMapping, columns: &[Column]) -> LoadResult<Columns> {
+    let names: Vec<String> = columns.iter().map(|c| c.name.clone()).collect();
+    mapping.resolve(&names)
+}
+
+/// Reads a Parquet table, decoding only the mapped columns
+pub fn load_parquet(path: &Path, mapping: &ColumnMapping) -> LoadResult<PointCloud> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+    let schema = builder.schema().clone();
+    let columns = columns(&schema);
+    let mapped = resolve(mapping, &columns)?;
+    let fields: Vec<usize> = mapped.indices().iter().map(|&i| columns[i].field).collect();
+    let mask = ProjectionMask::roots(builder.parquet_schema(), fields);
+    let batches = builder.with_projection(mask).build()?;
+    read_batches(batches, &schema, &columns, mapped)
+}
+
+/// Reads an Arrow IPC file or stream, which Feather files are too
+pub fn load_arrow(path: &Path, mapping: &ColumnMapping) -> LoadResult<PointCloud> {
+    let mut input = BufReader::new(File::open(path)?);
+    let mut magic = [0; 6];
+    let is_file = input.read_exact(&mut magic).is_ok() && &magic == ARROW_MAGIC;
+    input.rewind()?;
+
+    if is_file {
+        let reader = arrow_ipc::reader::FileReader::try_new(input, None)?;
+        let schema = reader.schema();
+        let columns = columns(&schema);
+        let mapped = resolve(mapping, &columns)?;
+        read_batches(reader, &schema, &columns, mapped)
+    } else {
+        let reader = arrow_ipc::reader::StreamReader::try_new(input, None)?;
+        let schema = reader.schema();
+        let columns = columns(&schema);
+        let mapped = resolve(mapping, &columns)?;
+        read_batches(reader, &schema, &columns, mapped)
+    }
+}
+
+fn read_batches(
+    batches: impl Iterator<Item = Result<RecordBatch, ArrowError>>,
+    schema: &Schema,
+    columns: &[Column],
+    mapped: Columns,
+) -> LoadResult<PointCloud> {
+    let indices = mapped.indices();
+    let mut values = vec![Vec::new(); columns.len()];
+    for batch in batches {
+        let batch = batch?;
+        for &i in &indices {
+            let column = &columns[i];
+            // Projected batches only hold some of the fields, they're found by name
+            let name = schema.field(column.field).name();
+            let array = batch
+                .column_by_name(name)
+                .ok_or_else(|| format!("The table has no field {name}"))?;
+            append(&mut values[i], array.as_ref(), column.item)?;
+        }
+    }
+    Ok(PointCloud {
+        vertices: mapped.vertices(|i| &values[i])?,
+        normals: None,
+    })
+}
+
+/// Adds the numbers of an array to a column, or one item of every list of a list array.
+/// Missing values become NaN.
+fn append(column: &mut Vec<f64>, array: &dyn Array, item: Option<usize>) -> LoadResult<()> {
+    if let Some(item) = item {
+        let lists = array
+            .as_fixed_size_list_opt()
+            .ok_or("Expected a fixed size list")?;
+        let size = lists.value_length() as usize;
+        let mut items = Vec::new();
+        append(&mut items, lists.values().as_ref(), None)?;
+        column.extend((0..lists.len()).map(|row| match lists.is_null(row) {
+            true => f64::NAN,
+            false => items[row * size + item],
+        }));
+        return Ok(());
+    }
+
+    macro_rules! numbers {
+        ($t:ty) => {{
+            let numbers = array.as_primitive::<$t>();
+            column.extend((0..numbers.len()).map(|i| match numbers.is_null(i) {
+                true => f64::NAN,
+                false => numbers.value(i) as f64,
+            }))
+        }};
+    }
+    match array.data_type() {
+        DataType::Float16 => {
+            let numbers = array.as_primitive::<Float16Type>();
+            column.extend((0..numbers.len()).map(|i| match numbers.is_null(i) {
+                true => f64::NAN,
+                false => numbers.value(i).to_f64(),
+            }))
+        }
+        DataType::Float32 => numbers!(Float32Type),
+        DataType::Float64 => numbers!(Float64Type),
+        DataType::Int8 => numbers!(Int8Type),
+        DataType::Int16 => numbers!(Int16Type),
+        DataType::Int32 => numbers!(Int32Type),
+        DataType::Int64 => numbers!(Int64Type),
+        DataType::UInt8 => numbers!(UInt8Type),
+        DataType::UInt16 => numbers!(UInt16Type),
+        DataType::UInt32 => numbers!(UInt32Type),
+        DataType::UInt64 => numbers!(UInt64Type),
+        other => return Err(format!("Columns of {other} can't be mapped to points").into()),
+    }
+    Ok(())
+}
@@ -1,29 +1,82 @@
-use las::Read;
-use nalgebra::vector;
-use object::{BasicVertex, Object};
+use std::{
+    cell::{Cell, RefCell},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use args::{Args, RenderMode};
+use camera::Camera;
+use growable::GrowableObject;
+use loader::{
+    background::{BackgroundLoad, LoadEvent},
+    convention::Convention,
+    LoadOptions,
+};
+use mesh::object::{MeshMode, MeshObject};
+use object::{Object, SharedObjects, UpdateContext};
+use octree::lod::LodObject;
+use playback::{kitti::KittiSequence, mcap::McapFile, Playback};
 
 use pass::{
-    blit::BlitPass,
-    jumpflood::{self, JumpfloodPass},
+    edl::EdlPass,
+    jumpflood::JumpfloodPass,
+    lighting::LightingPass,
+    overlay::OverlayPass,
     points_pass::PointsPass,
     recolor::RecolorPass,
+    splat::{SplatNormalizePass, SplatPass, SplatStage},
+    ssao::{SsaoBlurPass, SsaoPass},
+    timeline::{TimelinePass, TIMELINE_HEIGHT},
     Pass,
 };
 use texture_store::{TextureHandle, TextureStore};
 use wgpu::{PresentMode, TextureDescriptor};
 use winit::{
     dpi::PhysicalSize,
-    event::{Event, WindowEvent},
+    event::{ElementState, Event, MouseButton, WindowEvent},
     event_loop::EventLoop,
+    keyboard::{Key, NamedKey},
     window::Window,
 };
 
+mod args;
+mod camera;
+mod export;
+mod growable;
+mod kdtree;
+mod lidar;
+mod loader;
 mod material;
+mod mesh;
+mod normals;
 mod object;
+mod octree;
 mod pass;
+mod playback;
+mod point_buffers;
 mod texture_store;
 
-async fn run(event_loop: EventLoop<()>, window: Window) {
+fn screen_texture(
+    size: PhysicalSize<u32>,
+    format: wgpu::TextureFormat,
+) -> TextureDescriptor<'static> {
+    TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    }
+}
+
+async fn run(event_loop: EventLoop<()>, window: Window, args: Args) {
     let mut size = window.inner_size();
     size.width = size.width.max(1);
     size.height = size.height.max(1);
@@ -46,9 +99,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
-                required_features: wgpu::Features::empty() | wgpu::Features::POLYGON_MODE_POINT,
+                required_features: wgpu::Features::empty(),
                 // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
-                required_limits: wgpu::Limits::default(),
+                required_limits: adapter.limits(),
             },
             None,
         )
@@ -62,149 +115,357 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     let mut texture_store = TextureStore::new();
     let depth_buffer = texture_store.reserve(
         &device,
-        &TextureDescriptor {
-            label: None,
-            size: wgpu::Extent3d {
-                width: size.width,
-                height: size.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        },
+        &screen_texture(size, wgpu::TextureFormat::Depth32Float),
     );
-
     let colorbuf = texture_store.reserve(
         &device,
-        &TextureDescriptor {
-            label: None,
-            size: wgpu::Extent3d {
-                width: size.width,
-                height: size.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        },
+        &screen_texture(size, wgpu::TextureFormat::Rgba8UnormSrgb),
+    );
+    let shadedbuf = texture_store.reserve(
+        &device,
+        &screen_texture(size, wgpu::TextureFormat::Rgba8UnormSrgb),
+    );
+    let normalbuf = texture_store.reserve(
+        &device,
+        &screen_texture(size, wgpu::TextureFormat::Rgba16Float),
     );
     let off1 = texture_store.reserve(
         &device,
-        &TextureDescriptor {
-            label: None,
-            size: wgpu::Extent3d {
-                width: size.width,
-                height: size.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba16Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        },
+        &screen_texture(size, wgpu::TextureFormat::Rgba16Float),
     );
     let off2 = texture_store.reserve(
         &device,
-        &TextureDescriptor {
-            label: None,
-            size: wgpu::Extent3d {
-                width: size.width,
-                height: size.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba16Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        },
+        &screen_texture(size, wgpu::TextureFormat::Rgba16Float),
     );
+    let aobuf1 =
+        texture_store.reserve(&device, &screen_texture(size, wgpu::TextureFormat::R8Unorm));
+    let aobuf2 =
+        texture_store.reserve(&device, &screen_texture(size, wgpu::TextureFormat::R8Unorm));
+    // Every texture which has to follow the size of the window
+    let screen_textures = [
+        (depth_buffer, wgpu::TextureFormat::Depth32Float),
+        (colorbuf, wgpu::TextureFormat::Rgba8UnormSrgb),
+        (shadedbuf, wgpu::TextureFormat::Rgba8UnormSrgb),
+        (normalbuf, wgpu::TextureFormat::Rgba16Float),
+        (off1, wgpu::TextureFormat::Rgba16Float),
+        (off2, wgpu::TextureFormat::Rgba16Float),
+        (aobuf1, wgpu::TextureFormat::R8Unorm),
+        (aobuf2, wgpu::TextureFormat::R8Unorm),
+    ];
 
     // Setup objects
-    let mut vertices = Vec::new();
-    let mut reader = las::Reader::from_path("pointcloud.las").unwrap();
-    for point in reader.points() {
-        let point = point.unwrap();
-        if let Some(color) = point.color {
-            vertices.push(BasicVertex {
-                position: vector![point.x as f32, point.z as f32, point.y as f32],
-                color: vector![
-                    color.red as f32 / 65536.,
-                    color.green as f32 / 65536.,
-                    color.blue as f32 / 65536.
-                ],
-            });
+    let bind_group_layout = PointsPass::create_bind_group_layout(&device);
+
+    let mut normal_receiver = None;
+    let mut loading = None;
+    let mut revolutions = None;
+    let mut playback = None;
+    // Kept only to estimate normals once everything is loaded
+    let mut positions = Vec::new();
+    let mut has_surfels = false;
+    // The cache doesn't know which columns, sampling, system or axes its points came from
+    let cache_for = |path: &Path, options: &LoadOptions| {
+        args.cache
+            .as_ref()
+            .filter(|_| *options == LoadOptions::default())
+            .map(|location| location.path_for(path))
+    };
+    // Estimated normals are only cached for a single file
+    let cache =
+        cache_for(&args.input, &args.load_options(0)).filter(|_| args.extra_inputs.is_empty());
+    // Lidar, recordings, sequences and depth images are read from the input alone
+    let convention = |native| args.conventions[0].resolve(native);
+    // The scanner is given in the coordinates of the first input, normals are estimated in
+    // those of the viewer
+    let normal_settings = args.normals.map(|mut settings| {
+        if let normals::Orientation::Towards(scanner) = &mut settings.orientation {
+            *scanner = loader::to_view(&args.input, &args.load_options(0), *scanner)
+                .unwrap_or_else(|e| panic!("Failed to place the scanner: {e}"));
+        }
+        settings
+    });
+    let inputs: Vec<&Path> = std::iter::once(&args.input)
+        .chain(&args.extra_inputs)
+        .map(PathBuf::as_path)
+        .collect();
+    let crs = loader::crs::check(&inputs);
+    let object: Box<dyn Object> = if let Some(settings) = args.lidar.clone() {
+        // Every revolution of the sensor is uploaded as it completes
+        revolutions = Some(
+            lidar::spawn(args.input.clone(), settings, convention(Convention::Z_UP))
+                .unwrap_or_else(|e| panic!("Failed to read lidar packets: {e}")),
+        );
+        Box::new(GrowableObject::new(
+            &device,
+            wgpu::TextureFormat::Rgba16Float,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::TextureFormat::Rgba16Float,
+            &bind_group_layout,
+            args.max_points,
+        ))
+    } else if playback::is_mcap(&args.input) {
+        // The recorded frames replace each other as they are played
+        let source = McapFile::open(&args.input)
+            .and_then(|file| file.into_source(args.topic.as_deref()))
+            .unwrap_or_else(|e| panic!("Failed to open {}: {e}", args.input.display()));
+        playback = Some(Playback::spawn(
+            Box::new(source),
+            convention(Convention::Z_UP),
+        ));
+        Box::new(GrowableObject::new(
+            &device,
+            wgpu::TextureFormat::Rgba16Float,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::TextureFormat::Rgba16Float,
+            &bind_group_layout,
+            None,
+        ))
+    } else if let Some(source) = octree::open(&args.input, &args.conventions[0]) {
+        let source =
+            source.unwrap_or_else(|e| panic!("Failed to open {}: {e}", args.input.display()));
+        Box::new(LodObject::new(
+            &device,
+            wgpu::TextureFormat::Rgba16Float,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::TextureFormat::Rgba16Float,
+            &bind_group_layout,
+            source,
+            args.lod,
+        ))
+    } else if playback::kitti::is_kitti(&args.input) {
+        // Scans of a sequence are stepped through like recorded frames
+        let source = KittiSequence::open(&args.input, args.kitti.clone())
+            .unwrap_or_else(|e| panic!("Failed to open {}: {e}", args.input.display()));
+        playback = Some(Playback::spawn(
+            Box::new(source),
+            convention(Convention::Z_UP),
+        ));
+        Box::new(GrowableObject::new(
+            &device,
+            wgpu::TextureFormat::Rgba16Float,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::TextureFormat::Rgba16Float,
+            &bind_group_layout,
+            None,
+        ))
+    } else {
+        // Points arrive from the loading thread while the window is already up
+        loading = Some(match args.rgbd.clone() {
+            Some(settings) => BackgroundLoad::spawn_rgbd(
+                args.input.clone(),
+                settings,
+                convention(Convention::Y_UP),
+            ),
+            None => BackgroundLoad::spawn(
+                inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &path)| {
+                        let options = args.load_options(i);
+                        (path.to_owned(), cache_for(path, &options), options)
+                    })
+                    .collect(),
+            ),
+        });
+        if args.max_points.is_some() {
+            // Only the most recent points are kept
+            Box::new(GrowableObject::new(
+                &device,
+                wgpu::TextureFormat::Rgba16Float,
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                wgpu::TextureFormat::Rgba16Float,
+                &bind_group_layout,
+                args.max_points,
+            ))
         } else {
-            vertices.push(BasicVertex {
-                position: vector![point.x as f32, point.y as f32, point.z as f32],
-                color: vector![0.0, 0.0, 0.0],
-            });
+            Box::new(object::BasicObject::new(
+                &device,
+                wgpu::TextureFormat::Rgba16Float,
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                wgpu::TextureFormat::Rgba16Float,
+                &bind_group_layout,
+                Vec::new(),
+            ))
+        }
+    };
+
+    let mut objects = vec![object];
+    // Meshes are drawn with the points, transparent ones over the finished image
+    if !args.meshes.is_empty() && matches!(args.render_mode, RenderMode::Splat) {
+        log::warn!("Meshes are only drawn in the jump flood render mode");
+    }
+    if matches!(args.render_mode, RenderMode::Splat) {
+        for (name, enabled) in [
+            ("Eye-dome lighting", args.edl.is_some()),
+            ("Ambient occlusion", args.ssao.is_some()),
+            ("Lighting", args.lighting.is_some()),
+        ] {
+            if enabled {
+                log::warn!("{name} is only applied in the jump flood render mode");
+            }
         }
     }
+    for (path, convention) in args.meshes.iter().zip(&args.mesh_conventions) {
+        let mut mesh =
+            mesh::load(path).unwrap_or_else(|e| panic!("Failed to open {}: {e}", path.display()));
+        mesh.convert(&convention.resolve(Convention::Y_UP));
+        objects.push(Box::new(MeshObject::new(
+            &device,
+            &queue,
+            wgpu::TextureFormat::Rgba16Float,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::TextureFormat::Rgba16Float,
+            surface_format,
+            &bind_group_layout,
+            &mesh,
+            args.mesh,
+        )));
+    }
+    let objects: SharedObjects = Rc::new(RefCell::new(objects));
+    // Points read from a file can be written out again, counted as they arrive
+    let exportable = loading.is_some() && args.rgbd.is_none() && args.extra_inputs.is_empty();
+    if !args.extra_inputs.is_empty() && (loading.is_none() || args.rgbd.is_some()) {
+        log::warn!("Only files which are read whole can be shown together, the rest is skipped");
+    }
+    let mut loaded_points = 0;
 
-    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: None,
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::VERTEX,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: wgpu::BufferSize::new(64),
-            },
-            count: None,
-        }],
-    });
+    // Create passes
+    let passes: Vec<Box<dyn Pass>> = match args.render_mode {
+        RenderMode::Splat => {
+            let splat = |stage| {
+                SplatPass::new(
+                    &device,
+                    stage,
+                    objects.clone(),
+                    off1,
+                    off2,
+                    depth_buffer,
+                    wgpu::TextureFormat::Rgba16Float,
+                    args.splat,
+                )
+            };
+            vec![
+                Box::new(splat(SplatStage::Visibility)),
+                Box::new(splat(SplatStage::Accumulation)),
+                Box::new(SplatNormalizePass::new(
+                    &device,
+                    off1,
+                    off2,
+                    TextureHandle::get_surface(),
+                    surface_format,
+                )),
+            ]
+        }
+        RenderMode::JumpFlood => {
+            let pointpass = PointsPass::new(
+                &device,
+                &bind_group_layout,
+                objects.clone(),
+                off1,
+                colorbuf,
+                normalbuf,
+                depth_buffer,
+                args.points,
+            );
 
-    let object1 = object::BasicObject::new(
-        &device,
-        wgpu::TextureFormat::Rgba16Float,
-        wgpu::TextureFormat::Rgba8UnormSrgb,
-        &bind_group_layout,
-        vertices,
-    );
+            let jumpfloodpre =
+                JumpfloodPass::new(&device, off1, off2, wgpu::TextureFormat::Rgba16Float, 1);
+            let jumpflood8 =
+                JumpfloodPass::new(&device, off2, off1, wgpu::TextureFormat::Rgba16Float, 8);
+            let jumpflood4 =
+                JumpfloodPass::new(&device, off1, off2, wgpu::TextureFormat::Rgba16Float, 4);
+            let jumpflood2 =
+                JumpfloodPass::new(&device, off2, off1, wgpu::TextureFormat::Rgba16Float, 2);
+            let jumpflood1 =
+                JumpfloodPass::new(&device, off1, off2, wgpu::TextureFormat::Rgba16Float, 1);
+            let jumpfloodpost =
+                JumpfloodPass::new(&device, off2, off1, wgpu::TextureFormat::Rgba16Float, 1);
 
-    let objects: Vec<Box<dyn Object>> = vec![Box::new(object1)];
+            let mut passes: Vec<Box<dyn Pass>> = vec![
+                Box::new(pointpass),
+                Box::new(jumpfloodpre),
+                Box::new(jumpflood8),
+                Box::new(jumpflood4),
+                Box::new(jumpflood2),
+                Box::new(jumpflood1),
+                Box::new(jumpfloodpost),
+            ];
 
-    // Create passes
-    let pointpass = PointsPass::new(
-        &device,
-        &bind_group_layout,
-        objects,
-        off1,
-        colorbuf,
-        depth_buffer,
-    );
+            // Shading happens on the points before they get spread over the holes,
+            // alternating between the color buffers
+            let mut shaded = colorbuf;
+            let mut spare = shadedbuf;
+            if let Some(settings) = args.lighting {
+                passes.push(Box::new(LightingPass::new(
+                    &device,
+                    shaded,
+                    normalbuf,
+                    spare,
+                    wgpu::TextureFormat::Rgba8UnormSrgb,
+                    settings,
+                )));
+                std::mem::swap(&mut shaded, &mut spare);
+            }
+            if let Some(settings) = args.edl {
+                passes.push(Box::new(EdlPass::new(
+                    &device,
+                    shaded,
+                    depth_buffer,
+                    spare,
+                    wgpu::TextureFormat::Rgba8UnormSrgb,
+                    settings,
+                )));
+                std::mem::swap(&mut shaded, &mut spare);
+            }
 
-    let jumpfloodpre = JumpfloodPass::new(&device, off1, off2, wgpu::TextureFormat::Rgba16Float, 1);
-    let jumpflood8 = JumpfloodPass::new(&device, off2, off1, wgpu::TextureFormat::Rgba16Float, 8);
-    let jumpflood4 = JumpfloodPass::new(&device, off1, off2, wgpu::TextureFormat::Rgba16Float, 4);
-    let jumpflood2 = JumpfloodPass::new(&device, off2, off1, wgpu::TextureFormat::Rgba16Float, 2);
-    let jumpflood1 = JumpfloodPass::new(&device, off1, off2, wgpu::TextureFormat::Rgba16Float, 1);
-    let jumpfloodpost = JumpfloodPass::new(&device, off2, off1, wgpu::TextureFormat::Rgba16Float, 1);
-
-    let recolor = RecolorPass::new(&device, colorbuf, off1, TextureHandle::get_surface(), surface_format);
-
-    let mut passes: Vec<Box<dyn Pass>> = vec![
-        Box::new(pointpass),
-        Box::new(jumpfloodpre),
-        Box::new(jumpflood8),
-        Box::new(jumpflood4),
-        Box::new(jumpflood2),
-        Box::new(jumpflood1),
-        Box::new(jumpfloodpost),
-        Box::new(recolor)
-    ];
+            // Ambient occlusion works on the hole-filled positions left in off1 by the jump flood
+            let occlusion = args.ssao.map(|settings| {
+                let format = wgpu::TextureFormat::R8Unorm;
+                passes.push(Box::new(SsaoPass::new(
+                    &device, off1, aobuf1, format, settings,
+                )));
+                passes.push(Box::new(SsaoBlurPass::new(
+                    &device,
+                    aobuf1,
+                    off1,
+                    aobuf2,
+                    format,
+                    settings.blur,
+                    false,
+                )));
+                passes.push(Box::new(SsaoBlurPass::new(
+                    &device,
+                    aobuf2,
+                    off1,
+                    aobuf1,
+                    format,
+                    settings.blur,
+                    true,
+                )));
+                aobuf1
+            });
+
+            let recolor = RecolorPass::new(
+                &device,
+                shaded,
+                off1,
+                occlusion,
+                TextureHandle::get_surface(),
+                surface_format,
+            );
+            passes.push(Box::new(recolor));
+            if args.mesh.mode == MeshMode::Transparent && !args.meshes.is_empty() {
+                passes.push(Box::new(OverlayPass::new(
+                    &device,
+                    &bind_group_layout,
+                    objects.clone(),
+                    TextureHandle::get_surface(),
+                    depth_buffer,
+                )));
+            }
+            passes
+        }
+    };
 
     let mut config = surface
         .get_default_config(&adapter, size.width, size.height)
@@ -212,12 +473,36 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     config.present_mode = PresentMode::Immediate;
     surface.configure(&device, &config);
 
+    let mut passes = passes;
+    // Played part of the recording, shown by the timeline
+    let progress = Rc::new(Cell::new(0.0));
+    if playback.is_some() {
+        passes.push(Box::new(TimelinePass::new(
+            &device,
+            TextureHandle::get_surface(),
+            surface_format,
+            progress.clone(),
+        )));
+    }
+    let mut cursor = winit::dpi::PhysicalPosition::new(0.0, 0.0);
+    let mut scrubbing = false;
     let window = &window;
 
     event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
 
     let start_time = std::time::Instant::now();
 
+    let title = args.input.file_name().map_or_else(
+        || args.input.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+    let title = match (&args.load.crs.target, &crs) {
+        (Some(target), _) => format!("{title} ({target})"),
+        (None, Some(crs)) => format!("{title} ({crs})"),
+        (None, None) => title,
+    };
+    window.set_title(&title);
+
     event_loop
         .run(move |event, target| {
             // Have the closure take ownership of the resources.
@@ -240,98 +525,160 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                         // On macos the window needs to be redrawn manually after resizing
                         window.request_redraw();
 
-                        // Update the depth buffer
-                        texture_store
-                            .recreate(
-                                &device,
-                                &TextureDescriptor {
-                                    label: None,
-                                    size: wgpu::Extent3d {
-                                        width: size.width,
-                                        height: size.height,
-                                        depth_or_array_layers: 1,
-                                    },
-                                    mip_level_count: 1,
-                                    sample_count: 1,
-                                    dimension: wgpu::TextureDimension::D2,
-                                    format: wgpu::TextureFormat::Depth32Float,
-                                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                                        | wgpu::TextureUsages::TEXTURE_BINDING,
-                                    view_formats: &[],
-                                },
-                                depth_buffer,
-                            )
-                            .unwrap();
-                        texture_store
-                            .recreate(
-                                &device,
-                                &TextureDescriptor {
-                                    label: None,
-                                    size: wgpu::Extent3d {
-                                        width: size.width,
-                                        height: size.height,
-                                        depth_or_array_layers: 1,
-                                    },
-                                    mip_level_count: 1,
-                                    sample_count: 1,
-                                    dimension: wgpu::TextureDimension::D2,
-                                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                                        | wgpu::TextureUsages::TEXTURE_BINDING,
-                                    view_formats: &[],
-                                },
-                                colorbuf,
-                            )
-                            .unwrap();
-                        texture_store
-                            .recreate(
-                                &device,
-                                &TextureDescriptor {
-                                    label: None,
-                                    size: wgpu::Extent3d {
-                                        width: size.width,
-                                        height: size.height,
-                                        depth_or_array_layers: 1,
-                                    },
-                                    mip_level_count: 1,
-                                    sample_count: 1,
-                                    dimension: wgpu::TextureDimension::D2,
-                                    format: wgpu::TextureFormat::Rgba16Float,
-                                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                                        | wgpu::TextureUsages::TEXTURE_BINDING,
-                                    view_formats: &[],
-                                },
-                                off1,
-                            )
-                            .unwrap();
-                        texture_store
-                            .recreate(
-                                &device,
-                                &TextureDescriptor {
-                                    label: None,
-                                    size: wgpu::Extent3d {
-                                        width: size.width,
-                                        height: size.height,
-                                        depth_or_array_layers: 1,
-                                    },
-                                    mip_level_count: 1,
-                                    sample_count: 1,
-                                    dimension: wgpu::TextureDimension::D2,
-                                    format: wgpu::TextureFormat::Rgba16Float,
-                                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                                        | wgpu::TextureUsages::TEXTURE_BINDING,
-                                    view_formats: &[],
-                                },
-                                off2,
-                            )
-                            .unwrap();
+                        // Update the screen sized textures
+                        for (handle, format) in screen_textures {
+                            texture_store
+                                .recreate(&device, &screen_texture(size, format), handle)
+                                .unwrap();
+                        }
+                    }
+                    // Dragging along the timeline seeks, space pauses and the arrows step
+                    WindowEvent::CursorMoved { position, .. } => {
+                        cursor = *position;
+                        if let (Some(playback), true) = (&mut playback, scrubbing) {
+                            playback.seek(cursor.x as f32 / size.width as f32);
+                        }
+                    }
+                    WindowEvent::MouseInput {
+                        state,
+                        button: MouseButton::Left,
+                        ..
+                    } => {
+                        let on_timeline =
+                            cursor.y as f32 >= size.height as f32 * (1.0 - TIMELINE_HEIGHT);
+                        scrubbing = *state == ElementState::Pressed && on_timeline;
+                        if let (Some(playback), true) = (&mut playback, scrubbing) {
+                            playback.seek(cursor.x as f32 / size.width as f32);
+                        }
+                    }
+                    WindowEvent::KeyboardInput { event, .. }
+                        if event.state == ElementState::Pressed =>
+                    {
+                        if let (Key::Character(c), Some(destination)) =
+                            (&event.logical_key, &args.export)
+                        {
+                            if c.eq_ignore_ascii_case("s") {
+                                export_shown(&args, destination, exportable, loaded_points);
+                            }
+                        }
+                        if let Some(playback) = &mut playback {
+                            match event.logical_key {
+                                Key::Named(NamedKey::Space) => playback.toggle(),
+                                Key::Named(NamedKey::ArrowLeft) => playback.step(-1),
+                                Key::Named(NamedKey::ArrowRight) => playback.step(1),
+                                _ => {}
+                            }
+                        }
+                    }
+                    WindowEvent::CloseRequested => {
+                        if let Some(loading) = &loading {
+                            loading.cancel();
+                        }
+                        target.exit()
                     }
-                    WindowEvent::CloseRequested => target.exit(),
                     _ => {}
                 };
             }
 
             if let Event::AboutToWait = &event {
+                let mut finished = false;
+                for event in loading.iter().flat_map(|l| l.poll()) {
+                    match event {
+                        LoadEvent::Points {
+                            vertices,
+                            loaded,
+                            total,
+                        } => {
+                            loaded_points = loaded;
+                            if normal_settings.is_some() {
+                                positions.extend(vertices.iter().map(|v| v.position));
+                            }
+                            for object in objects.borrow_mut().iter_mut() {
+                                object.append_points(&device, &queue, &vertices);
+                            }
+                            window.set_title(&match total {
+                                Some(total) => format!(
+                                    "{title} - loading {:.0}%",
+                                    loaded as f64 / total.max(1) as f64 * 100.0
+                                ),
+                                None => format!("{title} - loading {loaded} points"),
+                            });
+                        }
+                        // Normals stored in the file or cache are used as they are
+                        LoadEvent::Surfels(surfels) => {
+                            has_surfels = true;
+                            for object in objects.borrow_mut().iter_mut() {
+                                object.set_surfels(&device, &surfels);
+                            }
+                        }
+                        LoadEvent::Finished => {
+                            finished = true;
+                            window.set_title(&title);
+                            // Otherwise they may be estimated
+                            if let (Some(settings), false) = (normal_settings, has_surfels) {
+                                normal_receiver = Some(normals::spawn_estimation(
+                                    std::mem::take(&mut positions),
+                                    settings,
+                                ));
+                            }
+                        }
+                        LoadEvent::Failed(error) => {
+                            finished = true;
+                            log::error!("Failed to load {}: {error}", args.input.display());
+                            window.set_title(&format!("{title} - failed to load"));
+                        }
+                    }
+                }
+                if finished {
+                    loading = None;
+                }
+
+                if let (Some(receiver), Some(settings)) = (&revolutions, &args.lidar) {
+                    let mut arrived: Vec<_> = receiver.try_iter().collect();
+                    if !settings.accumulate && !arrived.is_empty() {
+                        // Only the latest revolution is shown
+                        arrived.drain(..arrived.len() - 1);
+                        for object in objects.borrow_mut().iter_mut() {
+                            object.clear_points();
+                        }
+                    }
+                    for revolution in arrived {
+                        for object in objects.borrow_mut().iter_mut() {
+                            object.append_points(&device, &queue, &revolution);
+                        }
+                    }
+                }
+
+                if let Some(playback) = &mut playback {
+                    if let Some((frame, points)) = playback.update() {
+                        for object in objects.borrow_mut().iter_mut() {
+                            object.clear_points();
+                            object.append_points(&device, &queue, &points);
+                        }
+                        window.set_title(&format!(
+                            "{title} - frame {} of {}, {:.1} s",
+                            frame + 1,
+                            playback.frame_count(),
+                            playback.time().as_secs_f32()
+                        ));
+                    }
+                    progress.set(playback.progress());
+                }
+
+                // Estimated normals replace the unknown ones as soon as they are ready
+                if let Some(surfels) = normal_receiver.as_ref().and_then(|r| r.try_recv().ok()) {
+                    for object in objects.borrow_mut().iter_mut() {
+                        object.set_surfels(&device, &surfels);
+                    }
+                    normal_receiver = None;
+                    // So they don't have to be estimated again
+                    if let Some(cache) = &cache {
+                        if let Err(e) = loader::cache::store_surfels(cache, &surfels) {
+                            log::warn!("Can't add the normals to {}: {e}", cache.display());
+                        }
+                    }
+                }
+
                 let frame = surface
                     .get_current_texture()
                     .expect("Failed to acquire next swap chain texture");
@@ -340,12 +687,25 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .create_view(&wgpu::TextureViewDescriptor::default());
                 let mut encoder =
                     device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                let elapsed = start_time.elapsed();
+                let aspect_ratio = size.width as f32 / size.height as f32;
+                {
+                    let camera = Camera::orbit(elapsed.as_secs_f32());
+                    let context = UpdateContext {
+                        device: &device,
+                        camera: &camera,
+                        aspect_ratio,
+                        viewport_height: size.height as f32,
+                    };
+                    for object in objects.borrow_mut().iter_mut() {
+                        object.update(&context);
+                    }
+                }
                 {
-                    let resolver = texture_store.get_resolver(&view);
-                    let elapsed = start_time.elapsed();
+                    let resolver = texture_store.get_resolver(&view, frame.texture.size());
                     for pass in &mut passes {
                         pass.render(
-                            size.width as f32 / size.height as f32,
+                            aspect_ratio,
                             &device,
                             &queue,
                             &mut encoder,
@@ -362,7 +722,73 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .unwrap();
 }
 
+/// Writes the points the window shows to `destination` on another thread
+fn export_shown(args: &Args, destination: &Path, exportable: bool, loaded: u64) {
+    if !exportable {
+        log::warn!("Only points loaded from a single file can be exported");
+        return;
+    }
+    // The oldest points are gone once more than the most allowed have been loaded
+    let shown = growable::shown_range(loaded, args.max_points);
+    let source = args.input.clone();
+    let options = args.load_options(0);
+    let destination = destination.to_owned();
+    std::thread::spawn(
+        move || match export::export(&source, &options, shown, &destination) {
+            Ok(count) => log::info!("Exported {count} points to {}", destination.display()),
+            Err(e) => log::error!("Failed to export to {}: {e}", destination.display()),
+        },
+    );
+}
+
 pub fn main() {
+    #[allow(unused_mut)]
+    let mut args = Args::parse();
+    #[cfg(not(target_arch = "wasm32"))]
+    env_logger::init();
+    #[cfg(not(target_arch = "wasm32"))]
+    if args.benchmark_decode {
+        loader::las::benchmark(&args.input)
+            .unwrap_or_else(|e| panic!("Failed to decode {}: {e}", args.input.display()));
+        return;
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if args.list_topics {
+        let file = McapFile::open(&args.input)
+            .unwrap_or_else(|e| panic!("Failed to open {}: {e}", args.input.display()));
+        for topic in file.topics() {
+            let marker = if topic.is_point_cloud() { "*" } else { " " };
+            println!(
+                "{marker} {} ({}, {} messages)",
+                topic.name, topic.schema, topic.messages
+            );
+        }
+        return;
+    }
+    // Building happens before the window opens, it may take a while
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(output) = args.build_octree.take() {
+        // An octree built from the same version of the input, read the same way into the
+        // same format, is reused
+        let options = args.load_options(0);
+        let settings = format!("{options:?}");
+        let version = octree::disk::VERSION;
+        let key = loader::cache::SourceKey::of(&args.input).ok();
+        let key_path = output.join(octree::disk::SOURCE_FILE);
+        if key
+            .as_ref()
+            .is_some_and(|key| key.matches_file(&key_path, version, &settings))
+        {
+            log::info!("Reusing the octree in {}", output.display());
+        } else {
+            octree::build::build(|| loader::stream(&args.input, &options), &output)
+                .unwrap_or_else(|e| panic!("Failed to build an octree: {e}"));
+            if let Some(Err(e)) = key.map(|key| key.write(&key_path, version, &settings)) {
+                log::warn!("Can't record the source of the octree: {e}");
+            }
+        }
+        args.input = output;
+    }
     let event_loop = EventLoop::new().unwrap();
     #[allow(unused_mut)]
     let mut builder = winit::window::WindowBuilder::new();
@@ -384,13 +810,12 @@ pub fn main() {
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        env_logger::init();
-        pollster::block_on(run(event_loop, window));
+        pollster::block_on(run(event_loop, window, args));
     }
     #[cfg(target_arch = "wasm32")]
     {
         std::panic::set_hook(Box::new(console_error_panic_hook::hook));
         console_log::init().expect("could not initialize logger");
-        wasm_bindgen_futures::spawn_local(run(event_loop, window));
+        wasm_bindgen_futures::spawn_local(run(event_loop, window, args));
     }
 }
@@ -0,0 +1,114 @@
+use std::{path::Path, sync::Arc};
+
+use ::gltf::{image::Format, mesh::Mode, Node};
+use image::RgbImage;
+use nalgebra::{Matrix3, Matrix4, Point3, Vector2, Vector3};
+
+use crate::loader::LoadResult;
+
+use super::{linear_to_srgb, Material, Mesh, Part};
+
+/// Reads the triangles of the default scene of a glTF or GLB file, placed by their nodes
+pub fn load(path: &Path) -> LoadResult<Mesh> {
+    let (document, buffers, images) = ::gltf::import(path)?;
+    // Images are only converted once, whichever parts use them
+    let textures: Vec<Option<Arc<RgbImage>>> = images
+        .into_iter()
+        .map(|image| {
+            let pixels = match image.format {
+                Format::R8G8B8 => image.pixels,
+                Format::R8G8B8A8 => image
+                    .pixels
+                    .chunks_exact(4)
+                    .flat_map(|p| [p[0], p[1], p[2]])
+                    .collect(),
+                Format::R8 => image.pixels.iter().flat_map(|&p| [p, p, p]).collect(),
+                other => {
+                    log::warn!("Textures in {other:?} aren't supported");
+                    return None;
+                }
+            };
+            RgbImage::from_raw(image.width, image.height, pixels).map(Arc::new)
+        })
+        .collect();
+
+    let mut parts = Vec::new();
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or("The file has no scenes")?;
+    for node in scene.nodes() {
+        add_node(&node, Matrix4::identity(), &buffers, &textures, &mut parts);
+    }
+    Ok(Mesh { parts })
+}
+
+fn add_node(
+    node: &Node,
+    parent: Matrix4<f32>,
+    buffers: &[::gltf::buffer::Data],
+    textures: &[Option<Arc<RgbImage>>],
+    parts: &mut Vec<Part>,
+) {
+    let transform = parent * Matrix4::from(node.transform().matrix());
+    // Normals are transformed so they stay perpendicular under non-uniform scaling
+    let normal_transform = transform
+        .fixed_view::<3, 3>(0, 0)
+        .try_inverse()
+        .map_or(Matrix3::identity(), |m| m.transpose());
+
+    for primitive in node.mesh().iter().flat_map(|mesh| mesh.primitives()) {
+        if primitive.mode() != Mode::Triangles {
+            continue;
+        }
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|b| &b.0[..]));
+        let Some(positions) = reader.read_positions() else {
+            continue;
+        };
+        let positions: Vec<Vector3<f32>> = positions
+            .map(|p| transform.transform_point(&Point3::from(p)).coords)
+            .collect();
+        let triangles = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect::<Vec<_>>(),
+            None => (0..positions.len() as u32).collect(),
+        };
+
+        let pbr = primitive.material().pbr_metallic_roughness();
+        let [r, g, b, _] = pbr.base_color_factor();
+        let texture = pbr.base_color_texture();
+        let material = Material {
+            color: Vector3::new(r, g, b).map(linear_to_srgb),
+            texture: texture
+                .as_ref()
+                .and_then(|info| textures.get(info.texture().source().index())?.clone()),
+        };
+        let uvs = texture
+            .and_then(|info| reader.read_tex_coords(info.tex_coord()))
+            .map(|uvs| uvs.into_f32().map(Vector2::from).collect());
+
+        parts.push(Part {
+            positions,
+            normals: reader.read_normals().map(|normals| {
+                normals
+                    .map(|n| (normal_transform * Vector3::from(n)).normalize())
+                    .collect()
+            }),
+            colors: reader.read_colors(0).map(|colors| {
+                colors
+                    .into_rgb_f32()
+                    .map(|c| Vector3::from(c).map(linear_to_srgb))
+                    .collect()
+            }),
+            uvs,
+            triangles: triangles
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect(),
+            material,
+        });
+    }
+
+    for child in node.children() {
+        add_node(&child, transform, buffers, textures, parts);
+    }
+}
@@ -0,0 +1,157 @@
+use std::{path::Path, sync::Arc};
+
+use image::RgbImage;
+use nalgebra::{Vector2, Vector3};
+
+use crate::loader::{convention::Convention, LoadResult};
+
+pub mod gltf;
+pub mod obj;
+pub mod object;
+pub mod sample;
+pub mod stl;
+
+/// Color of surfaces without any material
+const DEFAULT_COLOR: Vector3<f32> = Vector3::new(0.8, 0.8, 0.8);
+
+/// Triangles sharing one material
+pub struct Part {
+    pub positions: Vec<Vector3<f32>>,
+    pub normals: Option<Vec<Vector3<f32>>>,
+    /// Vertex colors, in sRGB
+    pub colors: Option<Vec<Vector3<f32>>>,
+    /// Texture coordinates with the origin at the top left of the texture
+    pub uvs: Option<Vec<Vector2<f32>>>,
+    pub triangles: Vec<[u32; 3]>,
+    pub material: Material,
+}
+
+impl Part {
+    /// Drops the attributes which don't have one value per vertex, and the triangles
+    /// pointing past the vertices, so every vertex can be looked up without checking
+    fn check(&mut self, path: &Path) {
+        let count = self.positions.len();
+        let warn = |attribute: &str, len: usize| {
+            log::warn!(
+                "{} has {len} {attribute} for {count} vertices, they are left out",
+                path.display()
+            );
+        };
+        if let Some(normals) = self.normals.take_if(|n| n.len() != count) {
+            warn("normals", normals.len());
+        }
+        if let Some(colors) = self.colors.take_if(|c| c.len() != count) {
+            warn("colors", colors.len());
+        }
+        if let Some(uvs) = self.uvs.take_if(|uv| uv.len() != count) {
+            warn("texture coordinates", uvs.len());
+        }
+        let before = self.triangles.len();
+        self.triangles
+            .retain(|t| t.iter().all(|&i| (i as usize) < count));
+        if self.triangles.len() != before {
+            log::warn!(
+                "{} has {} triangles with missing vertices, they are left out",
+                path.display(),
+                before - self.triangles.len()
+            );
+        }
+    }
+}
+
+/// How a surface is colored, in sRGB
+#[derive(Clone)]
+pub struct Material {
+    pub color: Vector3<f32>,
+    /// Multiplied by the color
+    pub texture: Option<Arc<RgbImage>>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            color: DEFAULT_COLOR,
+            texture: None,
+        }
+    }
+}
+
+impl Material {
+    /// Color at a point of the texture, which repeats outside of 0 to 1
+    pub fn color_at(&self, uv: Option<Vector2<f32>>) -> Vector3<f32> {
+        match (&self.texture, uv) {
+            (Some(texture), Some(uv)) => {
+                let x = (uv.x.rem_euclid(1.0) * texture.width() as f32) as u32;
+                let y = (uv.y.rem_euclid(1.0) * texture.height() as f32) as u32;
+                let [r, g, b] = texture
+                    .get_pixel(x.min(texture.width() - 1), y.min(texture.height() - 1))
+                    .0;
+                self.color
+                    .component_mul(&Vector3::new(r, g, b).map(|c| c as f32 / 255.0))
+            }
+            _ => self.color,
+        }
+    }
+}
+
+/// Surface made of triangles, read from a model file
+pub struct Mesh {
+    pub parts: Vec<Part>,
+}
+
+impl Mesh {
+    pub fn triangle_count(&self) -> usize {
+        self.parts.iter().map(|p| p.triangles.len()).sum()
+    }
+
+    /// Turns the positions and normals into the axes of the viewer
+    pub fn convert(&mut self, convention: &Convention) {
+        for part in &mut self.parts {
+            let normals = part.normals.iter_mut().flatten();
+            for v in part.positions.iter_mut().chain(normals) {
+                *v = convention.apply(*v);
+            }
+        }
+    }
+}
+
+pub fn is_mesh(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| {
+        ["obj", "stl", "gltf", "glb"]
+            .iter()
+            .any(|x| e.eq_ignore_ascii_case(x))
+    })
+}
+
+/// Picks a reader based on the extension of the file
+pub fn load(path: &Path) -> LoadResult<Mesh> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let mut mesh = match extension.as_deref() {
+        Some("obj") => obj::load(path)?,
+        Some("stl") => stl::load(path)?,
+        Some("gltf" | "glb") => gltf::load(path)?,
+        _ => return Err(format!("Unsupported mesh format: {}", path.display()).into()),
+    };
+    for part in &mut mesh.parts {
+        part.check(path);
+    }
+    log::info!(
+        "Read {} triangles in {} parts from {}",
+        mesh.triangle_count(),
+        mesh.parts.len(),
+        path.display()
+    );
+    Ok(mesh)
+}
+
+/// Converts a linear color component to sRGB, which glTF colors other than textures aren't
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
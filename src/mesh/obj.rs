@@ -0,0 +1,86 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::loader::LoadResult;
+
+use super::{Material, Mesh, Part};
+
+/// Reads a Wavefront OBJ file with the materials of its MTL library
+pub fn load(path: &Path) -> LoadResult<Mesh> {
+    let options = tobj::LoadOptions {
+        triangulate: true,
+        single_index: true,
+        ..Default::default()
+    };
+    let (models, materials) = tobj::load_obj(path, &options)?;
+    // A missing material library only loses the colors
+    let materials = materials.unwrap_or_else(|e| {
+        log::warn!("Can't read the materials of {}: {e}", path.display());
+        Vec::new()
+    });
+
+    // Textures are shared by the parts using them
+    let directory = path.parent().unwrap_or(Path::new("."));
+    let mut textures = HashMap::new();
+    let materials: Vec<Material> = materials
+        .iter()
+        .map(|material| {
+            let texture = material.diffuse_texture.as_ref().and_then(|name| {
+                textures
+                    .entry(name.clone())
+                    .or_insert_with(|| match image::open(directory.join(name)) {
+                        Ok(image) => Some(Arc::new(image.into_rgb8())),
+                        Err(e) => {
+                            log::warn!("Can't read the texture {name}: {e}");
+                            None
+                        }
+                    })
+                    .clone()
+            });
+            Material {
+                color: material
+                    .diffuse
+                    .map_or(Material::default().color, Vector3::from),
+                texture,
+            }
+        })
+        .collect();
+
+    let parts = models
+        .into_iter()
+        .map(|model| {
+            let mesh = model.mesh;
+            let vectors = |values: &[f32]| {
+                (!values.is_empty()).then(|| {
+                    values
+                        .chunks_exact(3)
+                        .map(|v| Vector3::new(v[0], v[1], v[2]))
+                        .collect::<Vec<_>>()
+                })
+            };
+            Part {
+                positions: vectors(&mesh.positions).unwrap_or_default(),
+                normals: vectors(&mesh.normals),
+                colors: vectors(&mesh.vertex_color),
+                // OBJ texture coordinates start at the bottom left
+                uvs: (!mesh.texcoords.is_empty()).then(|| {
+                    mesh.texcoords
+                        .chunks_exact(2)
+                        .map(|uv| Vector2::new(uv[0], 1.0 - uv[1]))
+                        .collect()
+                }),
+                triangles: mesh
+                    .indices
+                    .chunks_exact(3)
+                    .map(|t| [t[0], t[1], t[2]])
+                    .collect(),
+                material: mesh
+                    .material_id
+                    .and_then(|id| materials.get(id).cloned())
+                    .unwrap_or_default(),
+            }
+        })
+        .collect();
+    Ok(Mesh { parts })
+}
@@ -0,0 +1,457 @@
+use std::{borrow::Cow, collections::HashMap, str::FromStr, sync::Arc};
+
+use bytemuck::{Pod, Zeroable};
+use image::RgbImage;
+use nalgebra::{Vector2, Vector3};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupLayout, Buffer, Device, Queue, RenderPass, TextureFormat,
+};
+
+use crate::{
+    material::Material,
+    object::{Object, UpdateContext},
+};
+
+use super::{Mesh, Part};
+
+/// How the triangles of a mesh are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshMode {
+    /// Opaque and shaded like the points around it
+    Solid,
+    /// Only the edges of the triangles
+    Wireframe,
+    /// See-through, so the points behind the surface stay visible
+    Transparent,
+}
+
+impl FromStr for MeshMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "solid" => Ok(Self::Solid),
+            "wireframe" => Ok(Self::Wireframe),
+            "transparent" => Ok(Self::Transparent),
+            _ => Err(format!("Unknown mesh mode: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MeshSettings {
+    pub mode: MeshMode,
+    /// How much of the surface covers what's behind it, only used by transparent meshes
+    pub opacity: f32,
+}
+
+impl Default for MeshSettings {
+    fn default() -> Self {
+        Self {
+            mode: MeshMode::Solid,
+            opacity: 0.4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct MeshVertex {
+    position: Vector3<f32>,
+    normal: Vector3<f32>,
+    /// Color of the material and the vertex combined
+    color: Vector3<f32>,
+    tex_coord: Vector2<f32>,
+}
+
+/// Laid out like `Mesh` in mesh.wgsl
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct MeshUniform {
+    opacity: f32,
+    /// Whether normals are written, so the lighting pass shades the surface
+    lit: u32,
+    pad: [u32; 2],
+}
+
+/// Triangles of one part, uploaded with the texture of their material
+struct PartBuffers {
+    vertices: Buffer,
+    /// Triangles, or the edges of the triangles in wireframe mode
+    indices: Buffer,
+    index_count: u32,
+    bind_group: BindGroup,
+}
+
+/// Triangle mesh drawn by the points pass, hiding and hidden by the points through the
+/// same depth buffer. Transparent meshes are drawn by the overlay pass instead, blended
+/// over the finished image.
+pub struct MeshObject {
+    material: Material,
+    transparent: bool,
+    parts: Vec<PartBuffers>,
+    #[allow(dead_code)]
+    uniform_buf: Buffer,
+}
+
+impl MeshObject {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        position_format: TextureFormat,
+        color_format: TextureFormat,
+        normal_format: TextureFormat,
+        overlay_format: TextureFormat,
+        bind_group_layout: &BindGroupLayout,
+        mesh: &Mesh,
+        settings: MeshSettings,
+    ) -> Self {
+        let mesh_layout = Self::create_mesh_layout(device);
+        let material = Self::create_material(
+            device,
+            position_format,
+            color_format,
+            normal_format,
+            overlay_format,
+            bind_group_layout,
+            &mesh_layout,
+            settings.mode,
+        );
+
+        let uniform = MeshUniform {
+            opacity: match settings.mode {
+                MeshMode::Transparent => settings.opacity.clamp(0.0, 1.0),
+                _ => 1.0,
+            },
+            lit: (settings.mode == MeshMode::Solid) as u32,
+            pad: [0; 2],
+        };
+        let uniform_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("mesh uniform buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("mesh sampler"),
+            // Texture coordinates outside of 0 to 1 repeat the texture
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // Parts without a texture sample a white pixel, parts sharing one upload it once
+        let white = RgbImage::from_pixel(1, 1, image::Rgb([255, 255, 255]));
+        let blank = upload_texture(device, queue, &white);
+        let mut textures = HashMap::new();
+        let parts = mesh
+            .parts
+            .iter()
+            .filter(|part| !part.triangles.is_empty())
+            .map(|part| {
+                let view = match &part.material.texture {
+                    Some(texture) => textures
+                        .entry(Arc::as_ptr(texture))
+                        .or_insert_with(|| upload_texture(device, queue, texture)),
+                    None => &blank,
+                };
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("mesh bind group"),
+                    layout: &mesh_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: uniform_buf.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::Sampler(&sampler),
+                        },
+                    ],
+                });
+                let (vertices, indices) = part_geometry(part, settings.mode);
+                PartBuffers {
+                    vertices: device.create_buffer_init(&BufferInitDescriptor {
+                        label: Some("mesh vertex buffer"),
+                        contents: bytemuck::cast_slice(&vertices),
+                        usage: wgpu::BufferUsages::VERTEX,
+                    }),
+                    indices: device.create_buffer_init(&BufferInitDescriptor {
+                        label: Some("mesh index buffer"),
+                        contents: bytemuck::cast_slice(&indices),
+                        usage: wgpu::BufferUsages::INDEX,
+                    }),
+                    index_count: indices.len() as u32,
+                    bind_group,
+                }
+            })
+            .collect();
+
+        Self {
+            material,
+            transparent: settings.mode == MeshMode::Transparent,
+            parts,
+            uniform_buf,
+        }
+    }
+
+    /// Draws every part, with the camera already bound to group 0
+    fn draw_parts<'a>(&'a self, pass: &mut RenderPass<'a>) {
+        pass.set_pipeline(&self.material.render_pipeline);
+        for part in &self.parts {
+            pass.set_bind_group(1, &part.bind_group, &[]);
+            pass.set_vertex_buffer(0, part.vertices.slice(..));
+            pass.set_index_buffer(part.indices.slice(..), wgpu::IndexFormat::Uint32);
+            pass.draw_indexed(0..part.index_count, 0, 0..1);
+        }
+    }
+
+    /// Layout of the opacity, texture and sampler of every part
+    fn create_mesh_layout(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Mesh layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<MeshUniform>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_material(
+        device: &Device,
+        position_format: TextureFormat,
+        color_format: TextureFormat,
+        normal_format: TextureFormat,
+        overlay_format: TextureFormat,
+        bind_group_layout: &BindGroupLayout,
+        mesh_layout: &BindGroupLayout,
+        mode: MeshMode,
+    ) -> Material {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Mesh shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../shaders/mesh.wgsl"))),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Mesh pipeline layout"),
+            bind_group_layouts: &[bind_group_layout, mesh_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer = [wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<MeshVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![
+                0 => Float32x3,
+                1 => Float32x3,
+                2 => Float32x3,
+                3 => Float32x2,
+            ],
+        }];
+
+        // A transparent surface blends over the finished image, where the points are
+        // already spread over the holes, and leaves the depth to the points behind it
+        let transparent = mode == MeshMode::Transparent;
+        let target = |format| {
+            Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })
+        };
+        let (entry_point, targets) = match transparent {
+            true => (
+                "fs_overlay",
+                vec![Some(wgpu::ColorTargetState {
+                    format: overlay_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            ),
+            false => (
+                "fs_main",
+                vec![
+                    target(position_format),
+                    target(color_format),
+                    target(normal_format),
+                ],
+            ),
+        };
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Mesh pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &vertex_buffer,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point,
+                targets: &targets,
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: match mode {
+                    MeshMode::Wireframe => wgpu::PrimitiveTopology::LineList,
+                    _ => wgpu::PrimitiveTopology::TriangleList,
+                },
+                // Models seldom agree on which side is the front
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: !transparent,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Material {
+            pipeline_layout,
+            shader,
+            render_pipeline,
+        }
+    }
+}
+
+/// Uploads a texture with the colors as they are stored, like the colors of points
+fn upload_texture(device: &Device, queue: &Queue, image: &RgbImage) -> wgpu::TextureView {
+    let rgba: Vec<u8> = image
+        .pixels()
+        .flat_map(|p| [p.0[0], p.0[1], p.0[2], 255])
+        .collect();
+    let texture = device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("mesh texture"),
+            size: wgpu::Extent3d {
+                width: image.width(),
+                height: image.height(),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        &rgba,
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// Vertices and indices of a part. Triangles without normals get the normal of their face,
+/// so they don't share vertices. In wireframe mode every edge is listed once.
+fn part_geometry(part: &Part, mode: MeshMode) -> (Vec<MeshVertex>, Vec<u32>) {
+    let vertex = |i: usize, normal: Vector3<f32>| {
+        let mut color = part.material.color;
+        if let Some(colors) = &part.colors {
+            color.component_mul_assign(&colors[i]);
+        }
+        MeshVertex {
+            position: part.positions[i],
+            normal,
+            color,
+            tex_coord: part.uvs.as_ref().map_or(Vector2::zeros(), |uvs| uvs[i]),
+        }
+    };
+    let triangles = part
+        .triangles
+        .iter()
+        .filter(|t| t.iter().all(|&i| (i as usize) < part.positions.len()));
+
+    match (&part.normals, mode) {
+        (_, MeshMode::Wireframe) => {
+            let vertices = (0..part.positions.len())
+                .map(|i| vertex(i, Vector3::zeros()))
+                .collect();
+            let mut edges: Vec<[u32; 2]> = triangles
+                .flat_map(|&[a, b, c]| [[a, b], [b, c], [c, a]])
+                .map(|[a, b]| [a.min(b), a.max(b)])
+                .collect();
+            edges.sort_unstable();
+            edges.dedup();
+            (vertices, edges.into_iter().flatten().collect())
+        }
+        (Some(normals), _) => {
+            let vertices = (0..part.positions.len())
+                .map(|i| vertex(i, normals.get(i).copied().unwrap_or_default()))
+                .collect();
+            (vertices, triangles.flatten().copied().collect())
+        }
+        (None, _) => {
+            let vertices: Vec<MeshVertex> = triangles
+                .flat_map(|&[a, b, c]| {
+                    let [a, b, c] = [a, b, c].map(|i| i as usize);
+                    let p = &part.positions;
+                    let face = (p[b] - p[a])
+                        .cross(&(p[c] - p[a]))
+                        .try_normalize(f32::EPSILON)
+                        .unwrap_or_default();
+                    [vertex(a, face), vertex(b, face), vertex(c, face)]
+                })
+                .collect();
+            let indices = (0..vertices.len() as u32).collect();
+            (vertices, indices)
+        }
+    }
+}
+
+impl Object for MeshObject {
+    fn update(&mut self, _context: &UpdateContext) {}
+
+    fn draw<'a>(&'a self, pass: &mut RenderPass<'a>) {
+        if !self.transparent {
+            self.draw_parts(pass);
+        }
+    }
+
+    fn draw_overlay<'a>(&'a self, pass: &mut RenderPass<'a>) {
+        if self.transparent {
+            self.draw_parts(pass);
+        }
+    }
+}
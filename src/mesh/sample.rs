@@ -0,0 +1,206 @@
+use std::{collections::HashMap, str::FromStr};
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::{
+    loader::{LoadResult, PointCloud},
+    object::BasicVertex,
+};
+
+use super::{Mesh, Part};
+
+/// Points sampled when neither a count nor a density is given
+const DEFAULT_POINTS: usize = 1_000_000;
+/// Candidates drawn for every point Poisson-disk sampling aims for
+const POISSON_CANDIDATES: usize = 4;
+
+/// How points are spread over the triangles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// Independently, with as many on a triangle as its area calls for
+    Uniform,
+    /// Evenly, no two points closer than the spacing the density calls for
+    Poisson,
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Self::Uniform),
+            "poisson" => Ok(Self::Poisson),
+            _ => Err(format!("Unknown sampling method: {s}")),
+        }
+    }
+}
+
+/// How meshes are turned into points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleSettings {
+    pub method: Method,
+    /// Points for the whole mesh
+    pub points: Option<usize>,
+    /// Points per square unit of surface, instead of a count
+    pub density: Option<f32>,
+}
+
+impl Default for SampleSettings {
+    fn default() -> Self {
+        Self {
+            method: Method::Uniform,
+            points: None,
+            density: None,
+        }
+    }
+}
+
+/// Small generator, sampling only needs to look random and stay the same between runs
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> f32 {
+        // SplitMix64
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (z ^ (z >> 31)) as f32 / u64::MAX as f32
+    }
+}
+
+/// A triangle, found by its part and index
+struct Triangle {
+    part: usize,
+    index: usize,
+}
+
+/// Turns a mesh into points, colored like the surface and oriented by it
+pub fn sample(mesh: &Mesh, settings: &SampleSettings) -> LoadResult<PointCloud> {
+    // Triangles are picked in proportion to their area
+    let mut triangles = Vec::new();
+    let mut areas = Vec::new();
+    let mut total = 0.0;
+    for (p, part) in mesh.parts.iter().enumerate() {
+        for (index, triangle) in part.triangles.iter().enumerate() {
+            let [a, b, c] = corners(part, triangle)?;
+            let area = (b - a).cross(&(c - a)).norm() / 2.0;
+            if area > 0.0 {
+                total += area as f64;
+                triangles.push(Triangle { part: p, index });
+                areas.push(total);
+            }
+        }
+    }
+    if triangles.is_empty() {
+        return Err("The mesh has no surface to sample".into());
+    }
+
+    let target = match (settings.points, settings.density) {
+        (Some(points), _) => points,
+        (None, Some(density)) => (total * density as f64).round() as usize,
+        (None, None) => DEFAULT_POINTS,
+    };
+    let draws = match settings.method {
+        Method::Uniform => target,
+        Method::Poisson => target * POISSON_CANDIDATES,
+    };
+
+    let mut rng = Rng(0x5eed);
+    let mut points: Vec<(BasicVertex, Vector3<f32>)> = Vec::with_capacity(draws);
+    for _ in 0..draws {
+        let pick = rng.next() as f64 * total;
+        let i = areas
+            .partition_point(|&a| a < pick)
+            .min(triangles.len() - 1);
+        points.push(point_on(mesh, &triangles[i], &mut rng));
+    }
+    if settings.method == Method::Poisson {
+        // Spacing of points packed in a hexagonal grid at the target density, shrunk since
+        // dart throwing fills the surface less tightly than a grid
+        let spacing = (2.0 * total / (3f64.sqrt() * target.max(1) as f64)).sqrt() as f32;
+        points = thin_out(points, spacing * 0.65);
+    }
+    log::info!("Sampled {} points over an area of {total:.3}", points.len());
+
+    let (vertices, normals) = points.into_iter().unzip();
+    Ok(PointCloud {
+        vertices,
+        normals: Some(normals),
+    })
+}
+
+fn corners(part: &Part, triangle: &[u32; 3]) -> LoadResult<[Vector3<f32>; 3]> {
+    let corner = |i: u32| {
+        part.positions
+            .get(i as usize)
+            .copied()
+            .ok_or("A triangle refers to a missing vertex")
+    };
+    Ok([
+        corner(triangle[0])?,
+        corner(triangle[1])?,
+        corner(triangle[2])?,
+    ])
+}
+
+/// A random point on a triangle, with the color and normal of the surface there
+fn point_on(mesh: &Mesh, triangle: &Triangle, rng: &mut Rng) -> (BasicVertex, Vector3<f32>) {
+    let part = &mesh.parts[triangle.part];
+    let [a, b, c] = part.triangles[triangle.index].map(|i| i as usize);
+    // Barycentric coordinates spread evenly over the triangle
+    let (r1, r2) = (rng.next().sqrt(), rng.next());
+    let weights = [1.0 - r1, r1 * (1.0 - r2), r1 * r2];
+    // Parts were checked when read, so every attribute has a value per vertex
+    let mix3 = |values: &[Vector3<f32>]| {
+        values[a] * weights[0] + values[b] * weights[1] + values[c] * weights[2]
+    };
+    let mix2 = |values: &[Vector2<f32>]| {
+        values[a] * weights[0] + values[b] * weights[1] + values[c] * weights[2]
+    };
+
+    let position = mix3(&part.positions);
+    let face = (part.positions[b] - part.positions[a])
+        .cross(&(part.positions[c] - part.positions[a]))
+        .normalize();
+    let normal = match &part.normals {
+        Some(normals) => mix3(normals).try_normalize(f32::EPSILON).unwrap_or(face),
+        None => face,
+    };
+    let uv = part.uvs.as_deref().map(mix2);
+    let mut color = part.material.color_at(uv);
+    if let Some(colors) = &part.colors {
+        color.component_mul_assign(&mix3(colors));
+    }
+    (BasicVertex { position, color }, normal)
+}
+
+/// Keeps the points, in the order they were drawn, which are at least `radius` away from
+/// every point kept before
+fn thin_out(
+    points: Vec<(BasicVertex, Vector3<f32>)>,
+    radius: f32,
+) -> Vec<(BasicVertex, Vector3<f32>)> {
+    let cell = |p: Vector3<f32>| (p / radius).map(|c| c.floor() as i32);
+    let mut grid: HashMap<Vector3<i32>, Vec<Vector3<f32>>> = HashMap::new();
+    let mut kept = Vec::new();
+    for (vertex, normal) in points {
+        let center = cell(vertex.position);
+        let crowded = (-1..=1).any(|x| {
+            (-1..=1).any(|y| {
+                (-1..=1).any(|z| {
+                    grid.get(&(center + Vector3::new(x, y, z)))
+                        .is_some_and(|near| {
+                            near.iter()
+                                .any(|p| (p - vertex.position).norm_squared() < radius * radius)
+                        })
+                })
+            })
+        });
+        if !crowded {
+            grid.entry(center).or_default().push(vertex.position);
+            kept.push((vertex, normal));
+        }
+    }
+    kept
+}
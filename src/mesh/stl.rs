@@ -0,0 +1,28 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use nalgebra::Vector3;
+
+use crate::loader::LoadResult;
+
+use super::{Material, Mesh, Part};
+
+/// Reads an ASCII or binary STL file, which has no colors
+pub fn load(path: &Path) -> LoadResult<Mesh> {
+    let stl = stl_io::read_stl(&mut BufReader::new(File::open(path)?))?;
+    let positions = stl.vertices.iter().map(|v| Vector3::from(v.0)).collect();
+    let triangles = stl
+        .faces
+        .iter()
+        .map(|face| face.vertices.map(|i| i as u32))
+        .collect();
+    Ok(Mesh {
+        parts: vec![Part {
+            positions,
+            normals: None,
+            colors: None,
+            uvs: None,
+            triangles,
+            material: Material::default(),
+        }],
+    })
+}
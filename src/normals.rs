@@ -0,0 +1,158 @@
+use std::{
+    str::FromStr,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use nalgebra::{Matrix3, Point3, SymmetricEigen, Vector3};
+
+use crate::{kdtree::KdTree, object::Surfel};
+
+/// Which points a normal is fitted to
+#[derive(Debug, Clone, Copy)]
+pub enum Neighbourhood {
+    /// The given number of closest points
+    Nearest(usize),
+    /// Every point within the given distance
+    Radius(f32),
+}
+
+/// Which side of the surface the normals point to
+#[derive(Debug, Clone, Copy)]
+pub enum Orientation {
+    /// Towards the position the cloud was scanned from
+    Towards(Point3<f32>),
+    /// Left ambiguous, the shaders flip every normal towards the camera
+    Viewer,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NormalSettings {
+    pub neighbourhood: Neighbourhood,
+    pub orientation: Orientation,
+}
+
+impl Default for NormalSettings {
+    fn default() -> Self {
+        Self {
+            neighbourhood: Neighbourhood::Nearest(16),
+            orientation: Orientation::Viewer,
+        }
+    }
+}
+
+/// Parses a position written as `x,y,z`
+pub struct Position(pub Point3<f32>);
+
+impl FromStr for Position {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coordinates = s
+            .split(',')
+            .map(|c| c.trim().parse::<f32>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match coordinates.as_slice() {
+            [x, y, z] => Ok(Self(Point3::new(*x, *y, *z))),
+            _ => Err(format!("Expected x,y,z but got {s}")),
+        }
+    }
+}
+
+/// Estimates the normals on a separate thread, the receiver gets them once they are done
+pub fn spawn_estimation(
+    positions: Vec<Vector3<f32>>,
+    settings: NormalSettings,
+) -> Receiver<Vec<Surfel>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let start = std::time::Instant::now();
+        let surfels = estimate(&positions, settings);
+        log::info!(
+            "Estimated {} normals in {:.2?}",
+            surfels.len(),
+            start.elapsed()
+        );
+        // The window may have been closed in the meantime
+        let _ = sender.send(surfels);
+    });
+    receiver
+}
+
+/// Fits a plane through the neighbourhood of every point. The normal of the plane is the
+/// direction in which the neighbours vary the least.
+pub fn estimate(positions: &[Vector3<f32>], settings: NormalSettings) -> Vec<Surfel> {
+    let tree = KdTree::new(positions);
+    let mut surfels = vec![
+        Surfel {
+            normal: Vector3::zeros(),
+            radius: 0.0,
+        };
+        positions.len()
+    ];
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = positions.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        for (chunk, output) in surfels.chunks_mut(chunk_size).enumerate() {
+            let tree = &tree;
+            scope.spawn(move || {
+                let mut neighbours = Vec::new();
+                for (i, surfel) in output.iter_mut().enumerate() {
+                    let position = &positions[chunk * chunk_size + i];
+                    match settings.neighbourhood {
+                        Neighbourhood::Nearest(k) => tree.nearest(position, k, &mut neighbours),
+                        Neighbourhood::Radius(r) => tree.within(position, r, &mut neighbours),
+                    }
+                    *surfel = fit(positions, &neighbours, position, settings.orientation);
+                }
+            });
+        }
+    });
+    surfels
+}
+
+fn fit(
+    positions: &[Vector3<f32>],
+    neighbours: &[(f32, u32)],
+    position: &Vector3<f32>,
+    orientation: Orientation,
+) -> Surfel {
+    if neighbours.len() < 3 {
+        return Surfel {
+            normal: Vector3::zeros(),
+            radius: 0.0,
+        };
+    }
+
+    let count = neighbours.len() as f32;
+    let mean = neighbours
+        .iter()
+        .map(|&(_, i)| positions[i as usize])
+        .sum::<Vector3<f32>>()
+        / count;
+    let covariance = neighbours
+        .iter()
+        .map(|&(_, i)| {
+            let d = positions[i as usize] - mean;
+            d * d.transpose()
+        })
+        .sum::<Matrix3<f32>>()
+        / count;
+
+    let eigen = SymmetricEigen::new(covariance);
+    let smallest = eigen.eigenvalues.imin();
+    let mut normal: Vector3<f32> = eigen.eigenvectors.column(smallest).into();
+    if let Orientation::Towards(origin) = orientation {
+        if normal.dot(&(origin.coords - position)) < 0.0 {
+            normal = -normal;
+        }
+    }
+
+    // Half the distance to the farthest neighbour closes the gaps without blurring much
+    let farthest = neighbours.iter().map(|&(d, _)| d).fold(0.0, f32::max);
+    Surfel {
+        normal,
+        radius: farthest.sqrt() * 0.5,
+    }
+}
@@ -1,12 +1,43 @@
+use std::{cell::RefCell, ops::Range, rc::Rc};
+
 use bytemuck::{Pod, Zeroable};
 use nalgebra::Vector3;
-use wgpu::{util::DeviceExt, BindGroupLayout, Buffer, Device, RenderPass, TextureFormat};
+use wgpu::{BindGroupLayout, Device, Queue, RenderPass, TextureFormat};
+
+use crate::{
+    camera::{Camera, Frustum},
+    material::Material,
+    pass::points_pass::PointsPass,
+    point_buffers::{PointBuffers, MAX_NARROW_EXTENT},
+};
 
-use crate::{material::Material, pass::points_pass::PointsPass};
+/// Objects drawn by several passes of the same frame
+pub type SharedObjects = Rc<RefCell<Vec<Box<dyn Object>>>>;
+
+/// State of the frame about to be drawn
+pub struct UpdateContext<'a> {
+    pub device: &'a Device,
+    pub camera: &'a Camera,
+    pub aspect_ratio: f32,
+    pub viewport_height: f32,
+}
 
 pub trait Object {
-    fn update(&mut self);
+    fn update(&mut self, context: &UpdateContext);
     fn draw<'a>(&'a self, pass: &mut RenderPass<'a>);
+    /// Binds the point data to group 1 and draws one six vertex instance per point,
+    /// using the pipeline already set by the pass. Objects without points draw nothing.
+    fn draw_points<'a>(&'a self, _pass: &mut RenderPass<'a>) {}
+    /// Draws what blends over the finished image with its own pipeline, after the points
+    /// are shaded and spread. The camera is bound to group 0. Most objects draw nothing.
+    fn draw_overlay<'a>(&'a self, _pass: &mut RenderPass<'a>) {}
+    /// Replaces the orientation of every point, in the same order as the points
+    fn set_surfels(&mut self, _device: &Device, _surfels: &[Surfel]) {}
+    /// Adds points after the ones the object already has. Objects with fixed contents
+    /// ignore them.
+    fn append_points(&mut self, _device: &Device, _queue: &Queue, _vertices: &[BasicVertex]) {}
+    /// Removes every point, objects with fixed contents keep theirs
+    fn clear_points(&mut self) {}
 }
 
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
@@ -16,10 +47,41 @@ pub struct BasicVertex {
     pub color: Vector3<f32>,
 }
 
+/// Orientation and extent of the surface around a point, used by splatting
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct Surfel {
+    /// Zero when the orientation is unknown
+    pub normal: Vector3<f32>,
+    /// Zero when the default splat radius should be used
+    pub radius: f32,
+}
+
+/// Most points in one buffer, smaller chunks are culled more precisely
+const MAX_CHUNK_POINTS: usize = 1 << 20;
+/// Fewest points a chunk is cut down to so its positions fit in 16 bits, fewer would
+/// cost more in draw calls than they save
+const MIN_NARROW_CHUNK_POINTS: usize = 1 << 14;
+
+/// Points sharing a buffer, drawn only when their bounds are in view
+struct PointChunk {
+    buffers: PointBuffers,
+    /// Positions of the points in the sorted order
+    range: Range<usize>,
+}
+
 pub struct BasicObject {
     material: Material,
-    buffer: Buffer,
-    vertex_count: u32,
+    point_data_layout: BindGroupLayout,
+    chunks: Vec<PointChunk>,
+    /// Most points put in a single chunk
+    chunk_size: usize,
+    /// Original index of every point, the points are sorted so chunks stay compact
+    order: Vec<u32>,
+    /// Chunks which passed the last frustum test
+    visible: Vec<usize>,
+    /// Bytes taken by the uploaded points
+    size: u64,
 }
 
 impl BasicObject {
@@ -27,39 +89,184 @@ impl BasicObject {
         device: &Device,
         position_format: TextureFormat,
         color_format: TextureFormat,
+        normal_format: TextureFormat,
         bind_group_layout: &BindGroupLayout,
         vertices: Vec<BasicVertex>,
     ) -> Self {
-        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-
+        let point_data_layout = PointsPass::create_point_data_layout(device);
         let material = PointsPass::create_point_material(
             device,
             position_format,
             color_format,
+            normal_format,
             bind_group_layout,
+            &point_data_layout,
         );
 
-        Self {
+        let mut object = Self {
             material,
-            buffer,
-            vertex_count: vertices.len() as u32,
+            point_data_layout,
+            chunks: Vec::new(),
+            chunk_size: chunk_capacity(device),
+            order: Vec::new(),
+            visible: Vec::new(),
+            size: 0,
+        };
+        object.add_points(device, vertices);
+        object
+    }
+
+    fn add_points(&mut self, device: &Device, vertices: Vec<BasicVertex>) {
+        // Only the new points are sorted, so every batch gets chunks of its own
+        let first = self.order.len();
+        let (vertices, order) = sort_spatially(vertices);
+        self.order
+            .extend(order.into_iter().map(|i| i + first as u32));
+
+        for range in split_chunks(&vertices, self.chunk_size) {
+            let chunk = &vertices[range.clone()];
+            let start = first + range.start;
+            let buffers = PointBuffers::new(device, &self.point_data_layout, chunk);
+            self.size += buffers.size();
+            // Drawn until the next update decides otherwise
+            self.visible.push(self.chunks.len());
+            self.chunks.push(PointChunk {
+                buffers,
+                range: start..start + chunk.len(),
+            });
+        }
+
+        let uncompressed = self.order.len() * std::mem::size_of::<BasicVertex>();
+        log::info!(
+            "{} points take {:.1} MiB instead of {:.1} MiB as plain vertices, {:.2}x fewer",
+            self.order.len(),
+            self.size as f64 / (1 << 20) as f64,
+            uncompressed as f64 / (1 << 20) as f64,
+            uncompressed as f64 / self.size.max(1) as f64,
+        );
+    }
+}
+
+/// Most points a chunk may hold, so it fits both in a buffer and in a single storage binding
+pub fn chunk_capacity(device: &Device) -> usize {
+    let limits = device.limits();
+    let max_bytes = (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size);
+    ((max_bytes / PointBuffers::MAX_BYTES_PER_POINT) as usize).min(MAX_CHUNK_POINTS)
+}
+
+/// Cuts points sorted along the Morton curve into chunks of at most `chunk_size`. A chunk
+/// ends early where its next point would no longer let it store 16 bit positions, as long
+/// as it has enough points by then.
+fn split_chunks(vertices: &[BasicVertex], chunk_size: usize) -> Vec<Range<usize>> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let (mut min, mut max) = (
+        Vector3::repeat(f32::INFINITY),
+        Vector3::repeat(f32::NEG_INFINITY),
+    );
+    for (i, vertex) in vertices.iter().enumerate() {
+        let (next_min, next_max) = (min.inf(&vertex.position), max.sup(&vertex.position));
+        let len = i - start;
+        let too_wide =
+            (next_max - next_min).max() > MAX_NARROW_EXTENT && len >= MIN_NARROW_CHUNK_POINTS;
+        if len == chunk_size || too_wide {
+            chunks.push(start..i);
+            start = i;
+            (min, max) = (vertex.position, vertex.position);
+        } else {
+            (min, max) = (next_min, next_max);
         }
     }
+    if start < vertices.len() {
+        chunks.push(start..vertices.len());
+    }
+    chunks
+}
+
+/// Orders the points along a Morton curve, so consecutive points lie close together.
+/// Also returns the original index of every point.
+fn sort_spatially(vertices: Vec<BasicVertex>) -> (Vec<BasicVertex>, Vec<u32>) {
+    if vertices.is_empty() {
+        return (vertices, Vec::new());
+    }
+    let (min, max) = vertices.iter().fold(
+        (
+            Vector3::repeat(f32::INFINITY),
+            Vector3::repeat(f32::NEG_INFINITY),
+        ),
+        |(min, max), v| (min.inf(&v.position), max.sup(&v.position)),
+    );
+    let extent = (max - min).max().max(f32::EPSILON);
+    let key = |v: &BasicVertex| {
+        let cell = (v.position - min) / extent * ((1 << 21) - 1) as f32;
+        spread_bits(cell.x as u64) << 2
+            | spread_bits(cell.y as u64) << 1
+            | spread_bits(cell.z as u64)
+    };
+
+    let mut order: Vec<u32> = (0..vertices.len() as u32).collect();
+    order.sort_by_cached_key(|&i| key(&vertices[i as usize]));
+    let sorted = order.iter().map(|&i| vertices[i as usize]).collect();
+    (sorted, order)
+}
+
+/// Moves the low 21 bits two places apart
+fn spread_bits(mut x: u64) -> u64 {
+    x &= 0x1f_ffff;
+    x = (x | x << 32) & 0x001f_0000_0000_ffff;
+    x = (x | x << 16) & 0x001f_0000_ff00_00ff;
+    x = (x | x << 8) & 0x100f_00f0_0f00_f00f;
+    x = (x | x << 4) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x << 2) & 0x1249_2492_4924_9249;
+    x
 }
 
 impl Object for BasicObject {
-    fn update(&mut self) {
-        // Update the object
+    fn update(&mut self, context: &UpdateContext) {
+        let frustum = Frustum::new(&context.camera.view_projection(context.aspect_ratio));
+        self.visible.clear();
+        self.visible.extend(
+            self.chunks
+                .iter()
+                .enumerate()
+                .filter(|(_, chunk)| {
+                    let (min, max) = chunk.buffers.bounds();
+                    frustum.intersects_box(&min, &max)
+                })
+                .map(|(i, _)| i),
+        );
     }
 
     fn draw<'a>(&'a self, pass: &mut RenderPass<'a>) {
         // Draw the object
         pass.set_pipeline(&self.material.render_pipeline);
-        pass.set_vertex_buffer(0, self.buffer.slice(..));
-        pass.draw(0..self.vertex_count, 0..1);
+        self.draw_points(pass);
+    }
+
+    fn draw_points<'a>(&'a self, pass: &mut RenderPass<'a>) {
+        for &i in &self.visible {
+            // Every point is an instance of a six vertex quad
+            self.chunks[i].buffers.draw(pass);
+        }
+    }
+
+    fn set_surfels(&mut self, device: &Device, surfels: &[Surfel]) {
+        if surfels.is_empty() {
+            return;
+        }
+        for chunk in &mut self.chunks {
+            // Surfels come in the order the points were loaded in
+            let sorted: Vec<Surfel> = self.order[chunk.range.clone()]
+                .iter()
+                .map(|&i| surfels.get(i as usize).copied().unwrap_or(Surfel::zeroed()))
+                .collect();
+            chunk
+                .buffers
+                .set_surfels(device, &self.point_data_layout, &sorted);
+        }
+    }
+
+    fn append_points(&mut self, device: &Device, _queue: &Queue, vertices: &[BasicVertex]) {
+        self.add_points(device, vertices.to_vec());
     }
 }
@@ -0,0 +1,157 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use nalgebra::{Point3, Vector3};
+
+use crate::{loader::LoadResult, object::BasicVertex};
+
+use super::disk::{write_hierarchy, HierarchyEntry, HIERARCHY_FILE, POINTS_FILE};
+
+/// Every node keeps at most one point per cell of a grid this many cells wide
+pub const GRID_SIZE: u32 = 64;
+/// Nodes with fewer points keep all of them and aren't split further
+const MAX_LEAF_POINTS: u64 = 50_000;
+/// Stops splitting clouds with many identical points
+const MAX_DEPTH: u32 = 20;
+
+/// Points waiting to be sorted into a node, spilled to disk
+struct Pending {
+    min: Point3<f32>,
+    size: f32,
+    parent: Option<u32>,
+    depth: u32,
+    path: PathBuf,
+    count: u64,
+}
+
+/// Builds an octree in `output` without holding the whole cloud in memory. `open` has to
+/// return the points from the start every time it's called, it's called twice.
+pub fn build<F, I>(mut open: F, output: &Path) -> LoadResult<()>
+where
+    F: FnMut() -> LoadResult<I>,
+    I: Iterator<Item = LoadResult<BasicVertex>>,
+{
+    fs::create_dir_all(output)?;
+    let scratch = output.join("pending");
+    fs::create_dir_all(&scratch)?;
+
+    // The first pass finds the bounds and spills the points for the root
+    let root_path = scratch.join("0.bin");
+    let mut min = Point3::from(Vector3::repeat(f32::INFINITY));
+    let mut max = Point3::from(Vector3::repeat(f32::NEG_INFINITY));
+    let mut count = 0;
+    {
+        let mut writer = BufWriter::new(File::create(&root_path)?);
+        for vertex in open()? {
+            let vertex = vertex?;
+            min = min.inf(&vertex.position.into());
+            max = max.sup(&vertex.position.into());
+            writer.write_all(bytemuck::bytes_of(&vertex))?;
+            count += 1;
+        }
+        writer.flush()?;
+    }
+    if count == 0 {
+        return Err("Can't build an octree without points".into());
+    }
+    // Grown a little so the points on the far faces still fall inside
+    let size = (max - min).max().max(f32::EPSILON) * 1.001;
+
+    let mut points = BufWriter::new(File::create(output.join(POINTS_FILE))?);
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    let mut next_file = 1;
+    let mut stack = vec![Pending {
+        min,
+        size,
+        parent: None,
+        depth: 0,
+        path: root_path,
+        count,
+    }];
+    while let Some(node) = stack.pop() {
+        let index = entries.len() as u32;
+        let leaf = node.count <= MAX_LEAF_POINTS || node.depth >= MAX_DEPTH;
+
+        let mut kept = 0;
+        let mut occupied = HashSet::new();
+        let mut children: [Option<(PathBuf, BufWriter<File>, u64)>; 8] = Default::default();
+        let mut reader = BufReader::new(File::open(&node.path)?);
+        let mut bytes = [0u8; std::mem::size_of::<BasicVertex>()];
+        for _ in 0..node.count {
+            reader.read_exact(&mut bytes)?;
+            let vertex: BasicVertex = bytemuck::pod_read_unaligned(&bytes);
+            let local = (vertex.position - node.min.coords) / node.size;
+
+            let cell = local.map(|c| ((c * GRID_SIZE as f32) as u32).min(GRID_SIZE - 1));
+            if leaf || occupied.insert((cell.x, cell.y, cell.z)) {
+                points.write_all(&bytes)?;
+                kept += 1;
+                continue;
+            }
+
+            let child = ((local.x >= 0.5) as usize) << 2
+                | ((local.y >= 0.5) as usize) << 1
+                | (local.z >= 0.5) as usize;
+            if children[child].is_none() {
+                let path = scratch.join(format!("{next_file}.bin"));
+                next_file += 1;
+                let writer = BufWriter::new(File::create(&path)?);
+                children[child] = Some((path, writer, 0));
+            }
+            let (_, writer, count) = children[child].as_mut().unwrap();
+            writer.write_all(&bytes)?;
+            *count += 1;
+        }
+        drop(reader);
+        fs::remove_file(&node.path)?;
+
+        entries.push(HierarchyEntry {
+            min: node.min,
+            size: node.size,
+            spacing: if leaf {
+                node.size / GRID_SIZE as f32 / 2.0
+            } else {
+                node.size / GRID_SIZE as f32
+            },
+            offset,
+            count: kept,
+            parent: node.parent,
+        });
+        offset += kept as u64;
+
+        let half = node.size / 2.0;
+        for (child, pending) in children.into_iter().enumerate() {
+            let Some((path, mut writer, count)) = pending else {
+                continue;
+            };
+            writer.flush()?;
+            let corner = Vector3::new(
+                (child >> 2 & 1) as f32,
+                (child >> 1 & 1) as f32,
+                (child & 1) as f32,
+            );
+            stack.push(Pending {
+                min: node.min + corner * half,
+                size: half,
+                parent: Some(index),
+                depth: node.depth + 1,
+                path,
+                count,
+            });
+        }
+    }
+    points.flush()?;
+    fs::remove_dir_all(&scratch)?;
+
+    write_hierarchy(&output.join(HIERARCHY_FILE), &entries)?;
+    log::info!(
+        "Built an octree of {} nodes from {count} points",
+        entries.len()
+    );
+    Ok(())
+}
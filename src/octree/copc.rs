@@ -0,0 +1,276 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use nalgebra::{vector, Vector3};
+
+use crate::{
+    loader::{
+        convention::Convention,
+        laz::{self, LaszipVlr},
+        LoadResult,
+    },
+    object::BasicVertex,
+};
+
+use super::{Node, NodeSource};
+
+/// Size of the LAS 1.4 header every COPC file starts with
+const HEADER_SIZE: u64 = 375;
+/// Size of an entry in a hierarchy page
+const ENTRY_SIZE: usize = 32;
+/// Where the color is in records of point formats 7 and 8
+const COLOR_OFFSET: usize = 30;
+
+/// Reads byte ranges of a file, wherever it's stored. Called from the loading threads.
+pub trait RangeReader: Send + Sync {
+    fn read_range(&self, offset: u64, length: u64) -> LoadResult<Vec<u8>>;
+}
+
+/// Ranges of a local file
+pub struct FileRanges {
+    path: PathBuf,
+}
+
+impl FileRanges {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_owned(),
+        }
+    }
+}
+
+impl RangeReader for FileRanges {
+    fn read_range(&self, offset: u64, length: u64) -> LoadResult<Vec<u8>> {
+        // Every call opens the file, so reads on several threads don't share a cursor
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut bytes = vec![0; length as usize];
+        file.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Whether the file is named like a Cloud Optimized Point Cloud
+pub fn is_copc(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.to_ascii_lowercase().ends_with(".copc.laz"))
+}
+
+/// Octant of the cube at a given depth, as stored in the hierarchy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct VoxelKey {
+    depth: i32,
+    x: i32,
+    y: i32,
+    z: i32,
+}
+
+impl VoxelKey {
+    fn parent(&self) -> Option<Self> {
+        (self.depth > 0).then(|| Self {
+            depth: self.depth - 1,
+            x: self.x >> 1,
+            y: self.y >> 1,
+            z: self.z >> 1,
+        })
+    }
+}
+
+/// Where the compressed points of a node are
+#[derive(Debug, Clone, Copy)]
+struct Chunk {
+    offset: u64,
+    byte_size: u64,
+}
+
+/// Cloud Optimized Point Cloud: a LAZ file whose chunks are the nodes of an octree,
+/// described by a hierarchy stored in an extended VLR
+pub struct CopcDataset {
+    reader: Box<dyn RangeReader>,
+    nodes: Vec<Node>,
+    chunks: Vec<Chunk>,
+    /// How the chunks are compressed
+    laszip: LaszipVlr,
+    record_length: usize,
+    has_color: bool,
+    scale: Vector3<f64>,
+    offset: Vector3<f64>,
+    convention: Convention,
+}
+
+impl CopcDataset {
+    pub fn open(reader: Box<dyn RangeReader>, convention: Convention) -> LoadResult<Self> {
+        let header = las::raw::Header::read_from(Cursor::new(reader.read_range(0, HEADER_SIZE)?))?;
+        if header.version.minor < 4 || header.header_size as u64 != HEADER_SIZE {
+            return Err("COPC files have to be LAS 1.4".into());
+        }
+        if header.point_data_record_format & 0x3f < 6 {
+            return Err("COPC files have to use point format 6, 7 or 8".into());
+        }
+
+        let vlr_size = (header.offset_to_point_data as u64)
+            .checked_sub(HEADER_SIZE)
+            .ok_or("COPC point data starts inside the header")?;
+        let vlr_bytes = reader.read_range(HEADER_SIZE, vlr_size)?;
+        let mut cursor = Cursor::new(vlr_bytes);
+        let mut info = None;
+        let mut laszip = None;
+        for _ in 0..header.number_of_variable_length_records {
+            let vlr = las::raw::Vlr::read_from(&mut cursor, false)?;
+            let user_id = String::from_utf8_lossy(&vlr.user_id);
+            let user_id = user_id.trim_end_matches('\0');
+            match (user_id, vlr.record_id) {
+                ("copc", 1) => info = Some(CopcInfo::read(&vlr.data)?),
+                (laz::LASZIP_USER_ID, laz::LASZIP_RECORD_ID) => {
+                    laszip = Some(LaszipVlr::read(&vlr.data)?)
+                }
+                _ => {}
+            }
+        }
+        let info = info.ok_or("File has no COPC info VLR")?;
+        let laszip = laszip.ok_or("File has no LASzip VLR")?;
+        let record_length = header.point_data_record_length as usize;
+        if laszip.record_length() != record_length {
+            return Err("LASzip VLR doesn't match the point records".into());
+        }
+
+        // Pages of the hierarchy may point to further pages
+        let mut entries = HashMap::new();
+        let mut pages = vec![(info.root_hierarchy_offset, info.root_hierarchy_size)];
+        while let Some((offset, size)) = pages.pop() {
+            for entry in reader.read_range(offset, size)?.chunks_exact(ENTRY_SIZE) {
+                let i32_at = |at: usize| i32::from_le_bytes(entry[at..at + 4].try_into().unwrap());
+                let key = VoxelKey {
+                    depth: i32_at(0),
+                    x: i32_at(4),
+                    y: i32_at(8),
+                    z: i32_at(12),
+                };
+                let offset = u64::from_le_bytes(entry[16..24].try_into().unwrap());
+                let byte_size = i32_at(24);
+                let point_count = i32_at(28);
+                if point_count == -1 {
+                    pages.push((offset, byte_size as u64));
+                } else {
+                    let chunk = Chunk {
+                        offset,
+                        byte_size: byte_size.max(0) as u64,
+                    };
+                    entries.insert(key, (chunk, point_count.max(0) as u32));
+                }
+            }
+        }
+
+        // Sorted by depth, so parents come before their children
+        let mut keys: Vec<VoxelKey> = entries.keys().copied().collect();
+        keys.sort_unstable();
+        let indices: HashMap<VoxelKey, usize> =
+            keys.iter().enumerate().map(|(i, &k)| (k, i)).collect();
+
+        let cube_min = info.center - Vector3::repeat(info.halfsize);
+        let mut nodes = Vec::with_capacity(keys.len());
+        let mut chunks = Vec::with_capacity(keys.len());
+        for key in &keys {
+            let (chunk, point_count) = entries[key];
+            let scale = 0.5f64.powi(key.depth);
+            let size = info.halfsize * 2.0 * scale;
+            let min = cube_min + vector![key.x as f64, key.y as f64, key.z as f64] * size;
+            nodes.push(Node {
+                min: convention.cube_min(min, size),
+                size: size as f32,
+                parent: key.parent().and_then(|p| indices.get(&p).copied()),
+                point_count,
+                spacing: (info.spacing * scale) as f32,
+            });
+            chunks.push(chunk);
+        }
+        log::info!("Opened a COPC file with {} nodes", nodes.len());
+
+        Ok(Self {
+            reader,
+            nodes,
+            chunks,
+            laszip,
+            record_length,
+            has_color: matches!(header.point_data_record_format & 0x3f, 7 | 8),
+            scale: vector![
+                header.x_scale_factor,
+                header.y_scale_factor,
+                header.z_scale_factor
+            ],
+            offset: vector![header.x_offset, header.y_offset, header.z_offset],
+            convention,
+        })
+    }
+}
+
+/// Contents of the COPC info VLR
+struct CopcInfo {
+    center: Vector3<f64>,
+    halfsize: f64,
+    spacing: f64,
+    root_hierarchy_offset: u64,
+    root_hierarchy_size: u64,
+}
+
+impl CopcInfo {
+    fn read(data: &[u8]) -> LoadResult<Self> {
+        if data.len() < 56 {
+            return Err("COPC info VLR is too short".into());
+        }
+        let f64_at = |at: usize| f64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+        Ok(Self {
+            center: vector![f64_at(0), f64_at(8), f64_at(16)],
+            halfsize: f64_at(24),
+            spacing: f64_at(32),
+            root_hierarchy_offset: u64_at(40),
+            root_hierarchy_size: u64_at(48),
+        })
+    }
+}
+
+impl NodeSource for CopcDataset {
+    fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    fn load(&self, node: usize) -> LoadResult<Vec<BasicVertex>> {
+        let chunk = self.chunks[node];
+        if self.nodes[node].point_count == 0 || chunk.byte_size == 0 {
+            return Ok(Vec::new());
+        }
+        // Only the chunk of this node is fetched
+        let compressed = self.reader.read_range(chunk.offset, chunk.byte_size)?;
+        let count = self.nodes[node].point_count as usize;
+        let records = laz::decompress_chunk(&self.laszip, &compressed, count)?;
+
+        let i32_at = |at: usize| i32::from_le_bytes(records[at..at + 4].try_into().unwrap());
+        let u16_at = |at: usize| u16::from_le_bytes([records[at], records[at + 1]]);
+        Ok((0..count)
+            .map(|i| {
+                let base = i * self.record_length;
+                let stored = vector![i32_at(base), i32_at(base + 4), i32_at(base + 8)];
+                let color = if self.has_color {
+                    let c = base + COLOR_OFFSET;
+                    vector![u16_at(c), u16_at(c + 2), u16_at(c + 4)].cast::<f32>() / 65536.
+                } else {
+                    Vector3::zeros()
+                };
+                BasicVertex {
+                    position: self.convention.apply(
+                        (stored.cast::<f64>().component_mul(&self.scale) + self.offset).cast(),
+                    ),
+                    color,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use bytemuck::Zeroable;
+use nalgebra::Point3;
+
+use crate::{loader::LoadResult, object::BasicVertex};
+
+use super::{Node, NodeSource};
+
+pub const HIERARCHY_FILE: &str = "hierarchy.bin";
+pub const POINTS_FILE: &str = "points.bin";
+/// Identifies the file the octree was built from, written by the viewer
+pub const SOURCE_FILE: &str = "source.key";
+const MAGIC: &[u8; 4] = b"PCOT";
+pub const VERSION: u32 = 1;
+
+/// How a node is stored in the hierarchy file
+pub struct HierarchyEntry {
+    pub min: Point3<f32>,
+    pub size: f32,
+    pub spacing: f32,
+    /// Index of the first point in the points file
+    pub offset: u64,
+    pub count: u32,
+    pub parent: Option<u32>,
+}
+
+impl HierarchyEntry {
+    const SIZE: usize = 36;
+
+    fn write(&self, out: &mut impl Write) -> std::io::Result<()> {
+        for value in [self.min.x, self.min.y, self.min.z, self.size, self.spacing] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        out.write_all(&self.offset.to_le_bytes())?;
+        out.write_all(&self.count.to_le_bytes())?;
+        out.write_all(&self.parent.unwrap_or(u32::MAX).to_le_bytes())
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        let f32_at = |i: usize| f32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let parent = u32_at(32);
+        Self {
+            min: Point3::new(f32_at(0), f32_at(4), f32_at(8)),
+            size: f32_at(12),
+            spacing: f32_at(16),
+            offset: u64::from_le_bytes(bytes[20..28].try_into().unwrap()),
+            count: u32_at(28),
+            parent: (parent != u32::MAX).then_some(parent),
+        }
+    }
+}
+
+pub fn write_hierarchy(path: &Path, entries: &[HierarchyEntry]) -> LoadResult<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(MAGIC)?;
+    out.write_all(&VERSION.to_le_bytes())?;
+    out.write_all(&(entries.len() as u32).to_le_bytes())?;
+    for entry in entries {
+        entry.write(&mut out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Whether the directory holds an octree written by `build`
+pub fn is_octree(path: &Path) -> bool {
+    path.join(HIERARCHY_FILE).is_file()
+}
+
+/// Octree written by `build`, nodes are read straight from the points file
+pub struct OctreeFiles {
+    nodes: Vec<Node>,
+    offsets: Vec<u64>,
+    points: PathBuf,
+}
+
+impl OctreeFiles {
+    pub fn open(dir: &Path) -> LoadResult<Self> {
+        let mut reader = BufReader::new(File::open(dir.join(HIERARCHY_FILE))?);
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err("Not an octree hierarchy file".into());
+        }
+        let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        if version != VERSION {
+            return Err(format!("Unsupported octree version {version}").into());
+        }
+        let count = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+
+        let mut nodes = Vec::with_capacity(count);
+        let mut offsets = Vec::with_capacity(count);
+        let mut bytes = [0u8; HierarchyEntry::SIZE];
+        for _ in 0..count {
+            reader.read_exact(&mut bytes)?;
+            let entry = HierarchyEntry::read(&bytes);
+            nodes.push(Node {
+                min: entry.min,
+                size: entry.size,
+                parent: entry.parent.map(|p| p as usize),
+                point_count: entry.count,
+                spacing: entry.spacing,
+            });
+            offsets.push(entry.offset);
+        }
+
+        Ok(Self {
+            nodes,
+            offsets,
+            points: dir.join(POINTS_FILE),
+        })
+    }
+}
+
+impl NodeSource for OctreeFiles {
+    fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    fn load(&self, node: usize) -> LoadResult<Vec<BasicVertex>> {
+        let stride = std::mem::size_of::<BasicVertex>() as u64;
+        // Every call opens the file, so loads on several threads don't share a cursor
+        let mut file = File::open(&self.points)?;
+        file.seek(SeekFrom::Start(self.offsets[node] * stride))?;
+        let mut vertices = vec![BasicVertex::zeroed(); self.nodes[node].point_count as usize];
+        file.read_exact(bytemuck::cast_slice_mut(&mut vertices))?;
+        Ok(vertices)
+    }
+}
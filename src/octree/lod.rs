@@ -0,0 +1,319 @@
+use std::{
+    collections::{BinaryHeap, HashMap, HashSet},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+};
+
+use wgpu::{BindGroupLayout, Device, RenderPass, TextureFormat};
+
+use crate::{
+    camera::{Camera, Frustum},
+    loader::LoadResult,
+    material::Material,
+    object::{BasicVertex, Object, UpdateContext},
+    pass::points_pass::PointsPass,
+    point_buffers::PointBuffers,
+};
+
+use super::NodeSource;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LodSettings {
+    /// Most points drawn in a single frame
+    pub point_budget: u64,
+    /// Nodes are refined while their point spacing covers more pixels than this
+    pub min_error: f32,
+    /// Most points kept in GPU memory, including nodes which aren't drawn anymore
+    pub cache_points: u64,
+    /// Number of threads reading nodes
+    pub threads: usize,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            point_budget: 5_000_000,
+            min_error: 1.5,
+            cache_points: 15_000_000,
+            threads: 2,
+        }
+    }
+}
+
+/// Nodes which should be loaded, the most important one last
+struct LoadQueue {
+    wanted: Vec<usize>,
+    closed: bool,
+}
+
+type LoadRequests = Arc<(Mutex<LoadQueue>, Condvar)>;
+
+struct GpuNode {
+    buffers: PointBuffers,
+    last_used: u64,
+}
+
+/// Draws an octree too big for memory, keeping only the nodes needed for the current view
+/// on the GPU
+pub struct LodObject {
+    source: Arc<dyn NodeSource>,
+    children: Vec<Vec<usize>>,
+    roots: Vec<usize>,
+    material: Material,
+    point_data_layout: BindGroupLayout,
+    resident: HashMap<usize, GpuNode>,
+    resident_points: u64,
+    visible: Vec<usize>,
+    requests: LoadRequests,
+    in_flight: HashSet<usize>,
+    failed: HashSet<usize>,
+    loaded: Receiver<(usize, LoadResult<Vec<BasicVertex>>)>,
+    frame: u64,
+    settings: LodSettings,
+}
+
+impl LodObject {
+    pub fn new(
+        device: &Device,
+        position_format: TextureFormat,
+        color_format: TextureFormat,
+        normal_format: TextureFormat,
+        bind_group_layout: &BindGroupLayout,
+        source: Arc<dyn NodeSource>,
+        settings: LodSettings,
+    ) -> Self {
+        let nodes = source.nodes();
+        let mut children = vec![Vec::new(); nodes.len()];
+        let mut roots = Vec::new();
+        for (index, node) in nodes.iter().enumerate() {
+            match node.parent {
+                Some(parent) => children[parent].push(index),
+                None => roots.push(index),
+            }
+        }
+
+        let point_data_layout = PointsPass::create_point_data_layout(device);
+        let material = PointsPass::create_point_material(
+            device,
+            position_format,
+            color_format,
+            normal_format,
+            bind_group_layout,
+            &point_data_layout,
+        );
+
+        let requests: LoadRequests = Arc::new((
+            Mutex::new(LoadQueue {
+                wanted: Vec::new(),
+                closed: false,
+            }),
+            Condvar::new(),
+        ));
+        let (sender, loaded) = mpsc::channel();
+        for _ in 0..settings.threads.max(1) {
+            let source = source.clone();
+            let requests = requests.clone();
+            let sender = sender.clone();
+            thread::spawn(move || load_nodes(source.as_ref(), &requests, sender));
+        }
+
+        Self {
+            source,
+            children,
+            roots,
+            material,
+            point_data_layout,
+            resident: HashMap::new(),
+            resident_points: 0,
+            visible: Vec::new(),
+            requests,
+            in_flight: HashSet::new(),
+            failed: HashSet::new(),
+            loaded,
+            frame: 0,
+            settings,
+        }
+    }
+
+    fn upload(&mut self, device: &Device, node: usize, vertices: &[BasicVertex]) {
+        let buffers = PointBuffers::new(device, &self.point_data_layout, vertices);
+        self.resident_points += buffers.point_count() as u64;
+        self.resident.insert(
+            node,
+            GpuNode {
+                buffers,
+                last_used: self.frame,
+            },
+        );
+    }
+
+    /// Picks the nodes worth drawing, most important first, until the point budget runs out
+    fn select(&self, camera: &Camera, frustum: &Frustum, projection_scale: f32) -> Vec<usize> {
+        let nodes = self.source.nodes();
+        // Projected size in pixels, and how many pixels lie between its points
+        let project = |index: usize| {
+            let node = &nodes[index];
+            let radius = node.size * 3f32.sqrt() / 2.0;
+            let distance = ((node.center() - camera.position).norm() - radius).max(Z_EPSILON);
+            (
+                node.size * projection_scale / distance,
+                node.spacing * projection_scale / distance,
+            )
+        };
+        // Sizes are never negative, so their bits sort like the floats
+        let mut queue: BinaryHeap<(u32, usize)> = self
+            .roots
+            .iter()
+            .map(|&root| (project(root).0.to_bits(), root))
+            .collect();
+
+        let mut selected = Vec::new();
+        let mut points = 0;
+        while let Some((_, index)) = queue.pop() {
+            let node = &nodes[index];
+            if !frustum.intersects_box(&node.min, &node.max()) {
+                continue;
+            }
+            if points + node.point_count as u64 > self.settings.point_budget {
+                break;
+            }
+            points += node.point_count as u64;
+            selected.push(index);
+
+            if project(index).1 > self.settings.min_error {
+                for &child in &self.children[index] {
+                    queue.push((project(child).0.to_bits(), child));
+                }
+            }
+        }
+        selected
+    }
+
+    /// Frees the nodes unused for the longest until the cache fits its budget
+    fn evict(&mut self) {
+        if self.resident_points <= self.settings.cache_points {
+            return;
+        }
+        let mut unused: Vec<(u64, usize)> = self
+            .resident
+            .iter()
+            .filter(|(_, node)| node.last_used < self.frame)
+            .map(|(&index, node)| (node.last_used, index))
+            .collect();
+        unused.sort_unstable();
+        for (_, index) in unused {
+            if self.resident_points <= self.settings.cache_points {
+                break;
+            }
+            if let Some(node) = self.resident.remove(&index) {
+                self.resident_points -= node.buffers.point_count() as u64;
+            }
+        }
+    }
+}
+
+/// Keeps nodes from being treated as infinitely close when the camera is inside them
+const Z_EPSILON: f32 = 1e-3;
+
+fn load_nodes(
+    source: &dyn NodeSource,
+    requests: &LoadRequests,
+    sender: Sender<(usize, LoadResult<Vec<BasicVertex>>)>,
+) {
+    let (queue, condvar) = &**requests;
+    loop {
+        let node = {
+            let mut queue = queue.lock().unwrap();
+            loop {
+                if queue.closed {
+                    return;
+                }
+                if let Some(node) = queue.wanted.pop() {
+                    break node;
+                }
+                queue = condvar.wait(queue).unwrap();
+            }
+        };
+        if sender.send((node, source.load(node))).is_err() {
+            return;
+        }
+    }
+}
+
+impl Object for LodObject {
+    fn update(&mut self, context: &UpdateContext) {
+        self.frame += 1;
+
+        while let Ok((node, result)) = self.loaded.try_recv() {
+            self.in_flight.remove(&node);
+            match result {
+                Ok(vertices) if !vertices.is_empty() => {
+                    self.upload(context.device, node, &vertices)
+                }
+                // Nothing to draw, don't ask for it again
+                Ok(_) => {
+                    self.failed.insert(node);
+                }
+                Err(error) => {
+                    log::warn!("Failed to load octree node {node}: {error}");
+                    self.failed.insert(node);
+                }
+            }
+        }
+
+        let view_projection = context.camera.view_projection(context.aspect_ratio);
+        let selected = self.select(
+            context.camera,
+            &Frustum::new(&view_projection),
+            Camera::projection_scale(context.viewport_height),
+        );
+
+        self.visible.clear();
+        let mut wanted = Vec::new();
+        for &index in &selected {
+            if let Some(node) = self.resident.get_mut(&index) {
+                node.last_used = self.frame;
+                self.visible.push(index);
+            } else if !self.failed.contains(&index) {
+                wanted.push(index);
+            }
+        }
+
+        // Replace the queue, so nodes which went out of view aren't loaded anymore
+        {
+            let (queue, condvar) = &*self.requests;
+            let mut queue = queue.lock().unwrap();
+            for index in queue.wanted.drain(..) {
+                self.in_flight.remove(&index);
+            }
+            wanted.retain(|index| !self.in_flight.contains(index));
+            self.in_flight.extend(wanted.iter().copied());
+            queue.wanted = wanted.into_iter().rev().collect();
+            condvar.notify_all();
+        }
+
+        self.evict();
+    }
+
+    fn draw<'a>(&'a self, pass: &mut RenderPass<'a>) {
+        pass.set_pipeline(&self.material.render_pipeline);
+        self.draw_points(pass);
+    }
+
+    fn draw_points<'a>(&'a self, pass: &mut RenderPass<'a>) {
+        for index in &self.visible {
+            self.resident[index].buffers.draw(pass);
+        }
+    }
+}
+
+impl Drop for LodObject {
+    fn drop(&mut self) {
+        let (queue, condvar) = &*self.requests;
+        queue.lock().unwrap().closed = true;
+        condvar.notify_all();
+    }
+}
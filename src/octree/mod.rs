@@ -0,0 +1,73 @@
+use std::{path::Path, sync::Arc};
+
+use nalgebra::Point3;
+
+use crate::{
+    loader::{
+        convention::{Convention, ConventionSettings},
+        LoadResult,
+    },
+    object::BasicVertex,
+};
+
+pub mod build;
+pub mod copc;
+pub mod disk;
+pub mod lod;
+pub mod potree;
+
+/// One cube of an octree. Every node holds a subsample of the points inside it and its
+/// children hold the rest, so a node together with its ancestors is a complete level of detail.
+#[derive(Debug, Clone)]
+pub struct Node {
+    /// Corner with the smallest coordinates
+    pub min: Point3<f32>,
+    /// Length of every edge
+    pub size: f32,
+    pub parent: Option<usize>,
+    pub point_count: u32,
+    /// Typical distance between the points of this node
+    pub spacing: f32,
+}
+
+impl Node {
+    pub fn max(&self) -> Point3<f32> {
+        self.min + nalgebra::Vector3::repeat(self.size)
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min + nalgebra::Vector3::repeat(self.size * 0.5)
+    }
+}
+
+/// Somewhere the nodes of an octree can be read from. Loading is called from background
+/// threads, so it may block.
+pub trait NodeSource: Send + Sync {
+    /// Every node, parents before their children
+    fn nodes(&self) -> &[Node];
+    fn load(&self, node: usize) -> LoadResult<Vec<BasicVertex>>;
+}
+
+/// Opens the octree stored at `path`, or returns `None` when it isn't one. Octrees built
+/// here are already in the viewer's axes, COPC and Potree ones are z-up like LAS.
+pub fn open(
+    path: &Path,
+    convention: &ConventionSettings,
+) -> Option<LoadResult<Arc<dyn NodeSource>>> {
+    let convention = convention.resolve(Convention::Z_UP);
+    if disk::is_octree(path) {
+        Some(disk::OctreeFiles::open(path).map(|s| Arc::new(s) as Arc<dyn NodeSource>))
+    } else if copc::is_copc(path) {
+        let reader = Box::new(copc::FileRanges::new(path));
+        Some(
+            copc::CopcDataset::open(reader, convention).map(|s| Arc::new(s) as Arc<dyn NodeSource>),
+        )
+    } else if potree::is_potree(path) {
+        Some(
+            potree::PotreeDataset::open(path, convention)
+                .map(|s| Arc::new(s) as Arc<dyn NodeSource>),
+        )
+    } else {
+        None
+    }
+}
@@ -0,0 +1,362 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use nalgebra::{vector, Vector3};
+use serde::Deserialize;
+
+use crate::{
+    loader::{convention::Convention, LoadResult},
+    object::BasicVertex,
+};
+
+use super::{Node, NodeSource};
+
+const METADATA_FILE: &str = "metadata.json";
+const HIERARCHY_FILE: &str = "hierarchy.bin";
+const OCTREE_FILE: &str = "octree.bin";
+/// Size of a node in the hierarchy file
+const ENTRY_SIZE: usize = 22;
+/// The node's hierarchy continues in another chunk of the hierarchy file
+const PROXY: u8 = 2;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Metadata {
+    version: String,
+    hierarchy: HierarchyInfo,
+    offset: [f64; 3],
+    scale: [f64; 3],
+    spacing: f32,
+    bounding_box: BoundingBox,
+    encoding: String,
+    attributes: Vec<Attribute>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HierarchyInfo {
+    first_chunk_size: u64,
+}
+
+#[derive(Deserialize)]
+struct BoundingBox {
+    min: [f64; 3],
+    max: [f64; 3],
+}
+
+#[derive(Deserialize)]
+struct Attribute {
+    name: String,
+    size: usize,
+    #[serde(default)]
+    max: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    /// Attributes of every point are interleaved
+    Default,
+    /// Brotli compressed, every attribute stored separately and positions and colors as Morton codes
+    Brotli,
+}
+
+/// Where the data of a node lies in the octree file
+#[derive(Debug, Clone, Copy, Default)]
+struct NodeData {
+    byte_offset: u64,
+    byte_size: u64,
+}
+
+/// Directory written by PotreeConverter 2
+pub struct PotreeDataset {
+    nodes: Vec<Node>,
+    data: Vec<NodeData>,
+    octree: PathBuf,
+    encoding: Encoding,
+    offset: Vector3<f64>,
+    scale: Vector3<f64>,
+    /// Offsets of the position and color in a point, or in the decompressed node for Brotli
+    position: usize,
+    color: Option<usize>,
+    attributes: Vec<(String, usize)>,
+    bytes_per_point: usize,
+    /// Divides the stored colors into the 0 to 1 range
+    color_range: f32,
+    convention: Convention,
+}
+
+/// Whether the directory was written by PotreeConverter 2
+pub fn is_potree(path: &Path) -> bool {
+    path.join(METADATA_FILE).is_file() && path.join(OCTREE_FILE).is_file()
+}
+
+impl PotreeDataset {
+    pub fn open(dir: &Path, convention: Convention) -> LoadResult<Self> {
+        let metadata: Metadata = serde_json::from_reader(std::io::BufReader::new(File::open(
+            dir.join(METADATA_FILE),
+        )?))?;
+        if !metadata.version.starts_with('2') {
+            return Err(format!("Unsupported Potree version {}", metadata.version).into());
+        }
+        let encoding = match metadata.encoding.as_str() {
+            "DEFAULT" => Encoding::Default,
+            "BROTLI" => Encoding::Brotli,
+            other => return Err(format!("Unsupported Potree encoding {other}").into()),
+        };
+
+        let mut attributes = Vec::new();
+        let mut position = None;
+        let mut color = None;
+        let mut color_range = 65536.0;
+        let mut bytes_per_point = 0;
+        for attribute in &metadata.attributes {
+            match attribute.name.as_str() {
+                "position" => position = Some(bytes_per_point),
+                "rgb" => {
+                    color = Some(bytes_per_point);
+                    // Converted 8 bit colors keep their range
+                    if attribute.max.iter().all(|&m| m <= 255.0) && !attribute.max.is_empty() {
+                        color_range = 256.0;
+                    }
+                }
+                _ => {}
+            }
+            attributes.push((attribute.name.clone(), attribute.size));
+            bytes_per_point += attribute.size;
+        }
+        let position = position.ok_or("Potree points have no position")?;
+
+        let (nodes, data) = read_hierarchy(
+            &dir.join(HIERARCHY_FILE),
+            metadata.hierarchy.first_chunk_size,
+            &metadata,
+            &convention,
+        )?;
+        log::info!("Opened a Potree dataset of {} nodes", nodes.len());
+
+        Ok(Self {
+            nodes,
+            data,
+            convention,
+            octree: dir.join(OCTREE_FILE),
+            encoding,
+            offset: Vector3::from(metadata.offset),
+            scale: Vector3::from(metadata.scale),
+            position,
+            color,
+            attributes,
+            bytes_per_point,
+            color_range,
+        })
+    }
+
+    fn decode_default(&self, bytes: &[u8], count: usize) -> Vec<BasicVertex> {
+        let i32_at = |at: usize| i32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        (0..count)
+            .map(|i| {
+                let base = i * self.bytes_per_point;
+                let p = base + self.position;
+                let stored = vector![i32_at(p), i32_at(p + 4), i32_at(p + 8)].cast::<f64>();
+                let color = match self.color {
+                    Some(c) => {
+                        let c = base + c;
+                        vector![u16_at(c), u16_at(c + 2), u16_at(c + 4)].cast::<f32>()
+                            / self.color_range
+                    }
+                    None => Vector3::zeros(),
+                };
+                BasicVertex {
+                    position: self
+                        .convention
+                        .apply((stored.component_mul(&self.scale) + self.offset).cast()),
+                    color,
+                }
+            })
+            .collect()
+    }
+
+    fn decode_brotli(&self, bytes: &[u8], count: usize) -> LoadResult<Vec<BasicVertex>> {
+        let mut decompressed = Vec::new();
+        brotli_decompressor::BrotliDecompress(&mut &bytes[..], &mut decompressed)?;
+
+        // Attributes follow each other, positions and colors are stored as Morton codes
+        let mut positions = None;
+        let mut colors = None;
+        let mut at = 0;
+        for (name, size) in &self.attributes {
+            let stored = match name.as_str() {
+                "position" => 16,
+                "rgb" => 8,
+                _ => *size,
+            };
+            let column = decompressed
+                .get(at..at + stored * count)
+                .ok_or("Potree node is truncated")?;
+            match name.as_str() {
+                "position" => positions = Some(column),
+                "rgb" => colors = Some(column),
+                _ => {}
+            }
+            at += stored * count;
+        }
+        let positions = positions.ok_or("Potree node has no positions")?;
+
+        let u64_at =
+            |column: &[u8], at: usize| u64::from_le_bytes(column[at..at + 8].try_into().unwrap());
+        Ok((0..count)
+            .map(|i| {
+                // The low 48 bits hold the lower 16 bits of every axis
+                let low = u64_at(positions, i * 16 + 8);
+                let high = u64_at(positions, i * 16);
+                let axis = |a: u32| (deinterleave(low, a) | deinterleave(high, a) << 16) as i32;
+                let stored = vector![axis(0), axis(1), axis(2)].cast::<f64>();
+                let color = match colors {
+                    Some(colors) => {
+                        let code = u64_at(colors, i * 8);
+                        vector![
+                            deinterleave(code, 0),
+                            deinterleave(code, 1),
+                            deinterleave(code, 2)
+                        ]
+                        .cast::<f32>()
+                            / self.color_range
+                    }
+                    None => Vector3::zeros(),
+                };
+                BasicVertex {
+                    position: self
+                        .convention
+                        .apply((stored.component_mul(&self.scale) + self.offset).cast()),
+                    color,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Takes every third bit of the low 48 bits, starting at `axis`
+fn deinterleave(code: u64, axis: u32) -> u32 {
+    (0..16).fold(0, |value, bit| {
+        value | (((code >> (bit * 3 + axis)) & 1) as u32) << bit
+    })
+}
+
+/// Reads the whole hierarchy up front, following every proxy into its own chunk
+fn read_hierarchy(
+    path: &Path,
+    first_chunk_size: u64,
+    metadata: &Metadata,
+    convention: &Convention,
+) -> LoadResult<(Vec<Node>, Vec<NodeData>)> {
+    let mut file = File::open(path)?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let min = Vector3::from(metadata.bounding_box.min);
+    let max = Vector3::from(metadata.bounding_box.max);
+    // Bounds and spacing in file coordinates, converted once the node is known
+    let mut bounds: Vec<(Vector3<f64>, f64)> = vec![(min, (max - min).max())];
+    let mut spacing = vec![metadata.spacing];
+    let mut parents = vec![None];
+    let mut counts = vec![0];
+    let mut data = vec![NodeData::default()];
+
+    // Chunks still to read, with the node their first entry describes
+    let mut chunks = vec![(0, 0, first_chunk_size)];
+    while let Some((root, chunk_offset, chunk_size)) = chunks.pop() {
+        let chunk = bytes
+            .get(chunk_offset as usize..(chunk_offset + chunk_size) as usize)
+            .ok_or("Potree hierarchy is truncated")?;
+        // Nodes of a chunk are stored breadth first
+        let mut order = vec![root];
+        for (i, entry) in chunk.chunks_exact(ENTRY_SIZE).enumerate() {
+            let Some(&node) = order.get(i) else {
+                break;
+            };
+            let kind = entry[0];
+            let child_mask = entry[1];
+            let count = u32::from_le_bytes(entry[2..6].try_into().unwrap());
+            let byte_offset = u64::from_le_bytes(entry[6..14].try_into().unwrap());
+            let byte_size = u64::from_le_bytes(entry[14..22].try_into().unwrap());
+
+            counts[node] = count;
+            if kind == PROXY {
+                if node != root {
+                    chunks.push((node, byte_offset, byte_size));
+                }
+                continue;
+            }
+            data[node] = NodeData {
+                byte_offset,
+                byte_size,
+            };
+
+            let (min, size) = bounds[node];
+            for child in 0..8 {
+                if child_mask & (1 << child) == 0 {
+                    continue;
+                }
+                // Bit 2 is x, bit 1 is y and bit 0 is z
+                let corner = vector![
+                    (child >> 2 & 1) as f64,
+                    (child >> 1 & 1) as f64,
+                    (child & 1) as f64
+                ];
+                order.push(bounds.len());
+                bounds.push((min + corner * (size / 2.0), size / 2.0));
+                spacing.push(spacing[node] / 2.0);
+                parents.push(Some(node));
+                counts.push(0);
+                data.push(NodeData::default());
+            }
+        }
+    }
+
+    let nodes = bounds
+        .iter()
+        .enumerate()
+        .map(|(i, &(min, size))| Node {
+            min: convention.cube_min(min, size),
+            size: size as f32,
+            parent: parents[i],
+            point_count: counts[i],
+            spacing: spacing[i],
+        })
+        .collect();
+    Ok((nodes, data))
+}
+
+impl NodeSource for PotreeDataset {
+    fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    fn load(&self, node: usize) -> LoadResult<Vec<BasicVertex>> {
+        let NodeData {
+            byte_offset,
+            byte_size,
+        } = self.data[node];
+        let count = self.nodes[node].point_count as usize;
+        if count == 0 || byte_size == 0 {
+            return Ok(Vec::new());
+        }
+        let mut file = File::open(&self.octree)?;
+        file.seek(SeekFrom::Start(byte_offset))?;
+        let mut bytes = vec![0; byte_size as usize];
+        file.read_exact(&mut bytes)?;
+
+        match self.encoding {
+            Encoding::Default => {
+                if bytes.len() < count * self.bytes_per_point {
+                    return Err("Potree node is truncated".into());
+                }
+                Ok(self.decode_default(&bytes, count))
+            }
+            Encoding::Brotli => self.decode_brotli(&bytes, count),
+        }
+    }
+}
@@ -9,6 +9,7 @@ use crate::{material::Material, texture_store::TextureHandle};
 
 use super::Pass;
 
+#[allow(dead_code)]
 pub struct BlitPass {
     input_texture: TextureHandle,
     output_texture: TextureHandle,
@@ -26,6 +27,7 @@ struct BlitVertex {
     tex_coords: nalgebra::Vector2<f32>,
 }
 
+#[allow(dead_code)]
 impl BlitPass {
     pub fn new(
         device: &Device,
@@ -227,7 +229,7 @@ impl Pass for BlitPass {
 
         self.bind_group = Some(bind_group);
 
-        rpass.set_bind_group(0, (&self.bind_group).as_ref().unwrap(), &[]);
+        rpass.set_bind_group(0, self.bind_group.as_ref().unwrap(), &[]);
         rpass.set_pipeline(&self.material.render_pipeline);
         rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         rpass.draw(0..6, 0..1);
@@ -0,0 +1,201 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupLayout, Buffer, Device, TextureFormat,
+};
+
+use crate::{
+    camera::{Z_FAR, Z_NEAR},
+    material::Material,
+    texture_store::TextureHandle,
+};
+
+use super::{create_quad_material, Pass, FULLSCREEN_QUAD};
+
+/// Parameters of the Eye-Dome Lighting shading.
+#[derive(Debug, Clone, Copy)]
+pub struct EdlSettings {
+    /// How strongly depth discontinuities darken the image.
+    pub strength: f32,
+    /// Distance, in pixels, at which neighbouring depths are compared.
+    pub radius: f32,
+}
+
+impl Default for EdlSettings {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            radius: 1.4,
+        }
+    }
+}
+
+/// Shades the color buffer using only the depth buffer, so clouds without
+/// any color information still show their shape.
+pub struct EdlPass {
+    color_texture: TextureHandle,
+    depth_texture: TextureHandle,
+    output_texture: TextureHandle,
+    bind_group_layout: BindGroupLayout,
+    material: Material,
+    bind_group: Option<wgpu::BindGroup>,
+    vertex_buffer: Buffer,
+    uniform_buf: Buffer,
+    settings: EdlSettings,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct EdlUniform {
+    strength: f32,
+    radius: f32,
+    z_near: f32,
+    z_far: f32,
+}
+
+impl EdlPass {
+    pub fn new(
+        device: &Device,
+        color_buffer: TextureHandle,
+        depth_buffer: TextureHandle,
+        output_texture: TextureHandle,
+        output_format: TextureFormat,
+        settings: EdlSettings,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<EdlUniform>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let material = create_quad_material(
+            device,
+            "edl",
+            Cow::Borrowed(include_str!("../shaders/edl.wgsl")),
+            output_format,
+            &bind_group_layout,
+        );
+
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("edl vertex buffer"),
+            contents: bytemuck::cast_slice(FULLSCREEN_QUAD),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("edl uniform buffer"),
+            size: std::mem::size_of::<EdlUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            color_texture: color_buffer,
+            depth_texture: depth_buffer,
+            output_texture,
+            material,
+            bind_group_layout,
+            vertex_buffer,
+            uniform_buf,
+            settings,
+            bind_group: None,
+        }
+    }
+}
+
+impl Pass for EdlPass {
+    fn render(
+        &mut self,
+        _: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        textures: &crate::texture_store::TextureResolver,
+        _: std::time::Duration,
+    ) {
+        let uniform = EdlUniform {
+            strength: self.settings.strength,
+            radius: self.settings.radius,
+            z_near: Z_NEAR,
+            z_far: Z_FAR,
+        };
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniform));
+
+        let color_view = textures.resolve(self.color_texture);
+        let depth_view = textures.resolve(self.depth_texture);
+        let output_view = textures.resolve(self.output_texture);
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("edl pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("edl bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(color_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        self.bind_group = Some(bind_group);
+
+        rpass.set_bind_group(0, self.bind_group.as_ref().unwrap(), &[]);
+        rpass.set_pipeline(&self.material.render_pipeline);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.draw(0..6, 0..1);
+    }
+}
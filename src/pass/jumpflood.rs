@@ -7,7 +7,7 @@ use wgpu::{
 
 use crate::{material::Material, texture_store::TextureHandle};
 
-use super::Pass;
+use super::{create_quad_material, Pass, FULLSCREEN_QUAD};
 
 pub struct JumpfloodPass {
     input_texture: TextureHandle,
@@ -19,13 +19,6 @@ pub struct JumpfloodPass {
     vertex_buffer: Buffer,
 }
 
-#[repr(C)]
-#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
-struct JumpfloodVertex {
-    position: nalgebra::Vector2<f32>,
-    tex_coords: nalgebra::Vector2<f32>,
-}
-
 impl JumpfloodPass {
     pub fn new(
         device: &Device,
@@ -56,7 +49,15 @@ impl JumpfloodPass {
             ],
         });
 
-        let material = Self::create_material(device, output_format, &bind_group_layout, jump);
+        let shadersource =
+            include_str!("../shaders/jumpflood.wgsl").replace("{JUMP}", &jump.to_string());
+        let material = create_quad_material(
+            device,
+            "jumpflood",
+            Cow::Owned(shadersource),
+            output_format,
+            &bind_group_layout,
+        );
 
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("jumpflood sampler"),
@@ -73,36 +74,9 @@ impl JumpfloodPass {
             border_color: None,
         });
 
-        let vertices: &[JumpfloodVertex] = &[
-            JumpfloodVertex {
-                position: nalgebra::Vector2::new(-1.0, 1.0),
-                tex_coords: nalgebra::Vector2::new(0.0, 0.0),
-            },
-            JumpfloodVertex {
-                position: nalgebra::Vector2::new(-1.0, -1.0),
-                tex_coords: nalgebra::Vector2::new(0.0, 1.0),
-            },
-            JumpfloodVertex {
-                position: nalgebra::Vector2::new(1.0, 1.0),
-                tex_coords: nalgebra::Vector2::new(1.0, 0.0),
-            },
-            JumpfloodVertex {
-                position: nalgebra::Vector2::new(1.0, 1.0),
-                tex_coords: nalgebra::Vector2::new(1.0, 0.0),
-            },
-            JumpfloodVertex {
-                position: nalgebra::Vector2::new(-1.0, -1.0),
-                tex_coords: nalgebra::Vector2::new(0.0, 1.0),
-            },
-            JumpfloodVertex {
-                position: nalgebra::Vector2::new(1.0, -1.0),
-                tex_coords: nalgebra::Vector2::new(1.0, 1.0),
-            },
-        ];
-
         let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("jumpflood vertex buffer"),
-            contents: bytemuck::cast_slice(vertices),
+            contents: bytemuck::cast_slice(FULLSCREEN_QUAD),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
@@ -116,77 +90,6 @@ impl JumpfloodPass {
             bind_group: None,
         }
     }
-
-    fn create_material(
-        device: &Device,
-        format: TextureFormat,
-        bind_group_layout: &BindGroupLayout,
-        jump: u32,
-    ) -> Material {
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("jumpflood pipeline layout"),
-            bind_group_layouts: &[bind_group_layout],
-            push_constant_ranges: &[],
-        });
-
-        let shadersource = include_str!("../shaders/jumpflood.wgsl");
-
-        let shadersource = shadersource.replace("{JUMP}", &jump.to_string());
-
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("jumpflood shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shadersource)),
-        });
-
-        let vertex_buffer = [wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<JumpfloodVertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x2,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x2,
-                    offset: 8,
-                    shader_location: 1,
-                },
-            ],
-        }];
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("jumpflood pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &vertex_buffer,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(format.into())],
-            }),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                ..Default::default()
-            },
-            multiview: None,
-        });
-
-        Material {
-            shader,
-            pipeline_layout,
-            render_pipeline,
-        }
-    }
 }
 
 impl Pass for JumpfloodPass {
@@ -233,7 +136,7 @@ impl Pass for JumpfloodPass {
 
         self.bind_group = Some(bind_group);
 
-        rpass.set_bind_group(0, (&self.bind_group).as_ref().unwrap(), &[]);
+        rpass.set_bind_group(0, self.bind_group.as_ref().unwrap(), &[]);
         rpass.set_pipeline(&self.material.render_pipeline);
         rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         rpass.draw(0..6, 0..1);
@@ -0,0 +1,283 @@
+use std::{borrow::Cow, str::FromStr};
+
+use nalgebra::Vector3;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupLayout, Buffer, Device, TextureFormat,
+};
+
+use crate::{camera::Camera, material::Material, texture_store::TextureHandle};
+
+use super::{create_quad_material, Pass, FULLSCREEN_QUAD};
+
+/// Where the light comes from
+#[derive(Debug, Clone, Copy)]
+pub enum Light {
+    /// Shines from the camera, so every visible surface is lit
+    Headlight,
+    /// Fixed direction, angles in degrees. Azimuth is measured clockwise from north (-z),
+    /// elevation up from the horizon.
+    Sun { azimuth: f32, elevation: f32 },
+}
+
+impl Light {
+    /// Unit vector pointing towards the light
+    fn direction(&self, camera: &Camera) -> Vector3<f32> {
+        match *self {
+            Self::Headlight => (camera.position - camera.target).normalize(),
+            Self::Sun { azimuth, elevation } => {
+                let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+                Vector3::new(
+                    azimuth.sin() * elevation.cos(),
+                    elevation.sin(),
+                    -azimuth.cos() * elevation.cos(),
+                )
+            }
+        }
+    }
+}
+
+/// Parameters of the normal based shading.
+#[derive(Debug, Clone, Copy)]
+pub struct LightingSettings {
+    pub light: Light,
+    /// Light reaching surfaces facing away from the light.
+    pub ambient: f32,
+    /// Strength of the Lambertian term.
+    pub diffuse: f32,
+    /// Strength of the Blinn-Phong highlight.
+    pub specular: f32,
+    /// Exponent of the highlight, higher values give smaller highlights.
+    pub shininess: f32,
+}
+
+impl Default for LightingSettings {
+    fn default() -> Self {
+        Self::headlight()
+    }
+}
+
+impl LightingSettings {
+    pub fn headlight() -> Self {
+        Self {
+            light: Light::Headlight,
+            ambient: 0.2,
+            diffuse: 0.8,
+            specular: 0.2,
+            shininess: 32.0,
+        }
+    }
+
+    pub fn sun() -> Self {
+        Self {
+            light: Light::Sun {
+                azimuth: 135.0,
+                elevation: 40.0,
+            },
+            ..Self::headlight()
+        }
+    }
+
+    /// The usual cartographic hillshade: matte terrain lit from the north-west.
+    pub fn hillshade() -> Self {
+        Self {
+            light: Light::Sun {
+                azimuth: 315.0,
+                elevation: 45.0,
+            },
+            ambient: 0.0,
+            diffuse: 1.0,
+            specular: 0.0,
+            shininess: 1.0,
+        }
+    }
+}
+
+impl FromStr for LightingSettings {
+    type Err = String;
+
+    /// Parses the name of a preset
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "headlight" => Ok(Self::headlight()),
+            "sun" => Ok(Self::sun()),
+            "hillshade" => Ok(Self::hillshade()),
+            _ => Err(format!("Unknown lighting preset: {s}")),
+        }
+    }
+}
+
+/// Shades the color buffer with the normals written by the points pass.
+pub struct LightingPass {
+    color_texture: TextureHandle,
+    normal_texture: TextureHandle,
+    output_texture: TextureHandle,
+    bind_group_layout: BindGroupLayout,
+    material: Material,
+    bind_group: Option<wgpu::BindGroup>,
+    vertex_buffer: Buffer,
+    uniform_buf: Buffer,
+    settings: LightingSettings,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightingUniform {
+    light: Vector3<f32>,
+    ambient: f32,
+    view: Vector3<f32>,
+    diffuse: f32,
+    specular: f32,
+    shininess: f32,
+    pad: [f32; 2],
+}
+
+impl LightingPass {
+    pub fn new(
+        device: &Device,
+        color_buffer: TextureHandle,
+        normal_buffer: TextureHandle,
+        output_texture: TextureHandle,
+        output_format: TextureFormat,
+        settings: LightingSettings,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<LightingUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let material = create_quad_material(
+            device,
+            "lighting",
+            Cow::Borrowed(include_str!("../shaders/lighting.wgsl")),
+            output_format,
+            &bind_group_layout,
+        );
+
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("lighting vertex buffer"),
+            contents: bytemuck::cast_slice(FULLSCREEN_QUAD),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("lighting uniform buffer"),
+            size: std::mem::size_of::<LightingUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            color_texture: color_buffer,
+            normal_texture: normal_buffer,
+            output_texture,
+            material,
+            bind_group_layout,
+            vertex_buffer,
+            uniform_buf,
+            settings,
+            bind_group: None,
+        }
+    }
+}
+
+impl Pass for LightingPass {
+    fn render(
+        &mut self,
+        _: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        textures: &crate::texture_store::TextureResolver,
+        elapsed: std::time::Duration,
+    ) {
+        let camera = Camera::orbit(elapsed.as_secs_f32());
+        let uniform = LightingUniform {
+            light: self.settings.light.direction(&camera),
+            ambient: self.settings.ambient,
+            view: (camera.position - camera.target).normalize(),
+            diffuse: self.settings.diffuse,
+            specular: self.settings.specular,
+            shininess: self.settings.shininess,
+            pad: [0.0; 2],
+        };
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniform));
+
+        let color_view = textures.resolve(self.color_texture);
+        let normal_view = textures.resolve(self.normal_texture);
+        let output_view = textures.resolve(self.output_texture);
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("lighting pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("lighting bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(color_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(normal_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        self.bind_group = Some(bind_group);
+
+        rpass.set_bind_group(0, self.bind_group.as_ref().unwrap(), &[]);
+        rpass.set_pipeline(&self.material.render_pipeline);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.draw(0..6, 0..1);
+    }
+}
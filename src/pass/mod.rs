@@ -1,13 +1,19 @@
-use std::time::Duration;
+use std::{borrow::Cow, time::Duration};
 
-use wgpu::{CommandEncoder, Device, Queue};
+use wgpu::{BindGroupLayout, CommandEncoder, Device, Queue, TextureFormat};
 
-use crate::texture_store::TextureResolver;
+use crate::{material::Material, texture_store::TextureResolver};
 
 pub mod blit;
+pub mod edl;
 pub mod jumpflood;
+pub mod lighting;
+pub mod overlay;
 pub mod points_pass;
 pub mod recolor;
+pub mod splat;
+pub mod ssao;
+pub mod timeline;
 
 pub trait Pass {
     fn render(
@@ -20,3 +26,109 @@ pub trait Pass {
         elapsed: Duration,
     );
 }
+
+/// Corner of a quad drawn over the screen, with where it lies on the textures read
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct QuadVertex {
+    pub position: nalgebra::Vector2<f32>,
+    pub tex_coords: nalgebra::Vector2<f32>,
+}
+
+/// Two triangles covering the whole screen
+pub const FULLSCREEN_QUAD: &[QuadVertex] = &[
+    QuadVertex {
+        position: nalgebra::Vector2::new(-1.0, 1.0),
+        tex_coords: nalgebra::Vector2::new(0.0, 0.0),
+    },
+    QuadVertex {
+        position: nalgebra::Vector2::new(-1.0, -1.0),
+        tex_coords: nalgebra::Vector2::new(0.0, 1.0),
+    },
+    QuadVertex {
+        position: nalgebra::Vector2::new(1.0, 1.0),
+        tex_coords: nalgebra::Vector2::new(1.0, 0.0),
+    },
+    QuadVertex {
+        position: nalgebra::Vector2::new(1.0, 1.0),
+        tex_coords: nalgebra::Vector2::new(1.0, 0.0),
+    },
+    QuadVertex {
+        position: nalgebra::Vector2::new(-1.0, -1.0),
+        tex_coords: nalgebra::Vector2::new(0.0, 1.0),
+    },
+    QuadVertex {
+        position: nalgebra::Vector2::new(1.0, -1.0),
+        tex_coords: nalgebra::Vector2::new(1.0, 1.0),
+    },
+];
+
+/// Pipeline drawing [`QuadVertex`] triangles with the `vs_main` and `fs_main` of the shader
+/// into a single target
+pub fn create_quad_material(
+    device: &Device,
+    name: &str,
+    shadersource: Cow<str>,
+    format: TextureFormat,
+    bind_group_layout: &BindGroupLayout,
+) -> Material {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(&format!("{name} pipeline layout")),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(&format!("{name} shader")),
+        source: wgpu::ShaderSource::Wgsl(shadersource),
+    });
+
+    let vertex_buffer = [wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<QuadVertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2,
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2,
+                offset: 8,
+                shader_location: 1,
+            },
+        ],
+    }];
+
+    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(&format!("{name} pipeline")),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &vertex_buffer,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            ..Default::default()
+        },
+        multiview: None,
+    });
+
+    Material {
+        shader,
+        pipeline_layout,
+        render_pipeline,
+    }
+}
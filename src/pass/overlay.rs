@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use wgpu::{BindGroupLayout, Buffer, CommandEncoder, Device, Queue};
+
+use crate::{
+    object::SharedObjects,
+    texture_store::{TextureHandle, TextureResolver},
+};
+
+use super::{
+    points_pass::{PointSettings, PointsUniform},
+    Pass,
+};
+
+/// Blends see-through surfaces over the finished image. They are hidden by the points in
+/// front of them through the depth buffer of the points pass, without hiding anything.
+pub struct OverlayPass {
+    objects: SharedObjects,
+    output_texture: TextureHandle,
+    depth_buffer: TextureHandle,
+    uniform_buf: Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl OverlayPass {
+    pub fn new(
+        device: &Device,
+        bind_group_layout: &BindGroupLayout,
+        objects: SharedObjects,
+        output_texture: TextureHandle,
+        depth_buffer: TextureHandle,
+    ) -> Self {
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("overlay uniform buffer"),
+            size: std::mem::size_of::<PointsUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("overlay bind group"),
+            layout: bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            }],
+        });
+        Self {
+            objects,
+            output_texture,
+            depth_buffer,
+            uniform_buf,
+            bind_group,
+        }
+    }
+}
+
+impl Pass for OverlayPass {
+    fn render(
+        &mut self,
+        aspect_ratio: f32,
+        _: &Device,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        textures: &TextureResolver,
+        elapsed: Duration,
+    ) {
+        // Same camera as the points pass, the overlays don't read the point settings
+        let size = textures.resolve_size(self.depth_buffer);
+        let uniform = PointsUniform::new(
+            aspect_ratio,
+            size.width as f32,
+            size.height as f32,
+            PointSettings::default(),
+            elapsed,
+        );
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniform));
+
+        // Has to outlive the render pass, which borrows the objects
+        let objects = self.objects.borrow();
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("overlay pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: textures.resolve(self.output_texture),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: textures.resolve(self.depth_buffer),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        for object in objects.iter() {
+            object.draw_overlay(&mut rpass);
+        }
+    }
+}
@@ -1,36 +1,147 @@
-use std::{borrow::Cow, time::Duration};
+use std::{borrow::Cow, str::FromStr, time::Duration};
 
 use wgpu::{BindGroupLayout, Buffer, CommandEncoder, Device, Queue, TextureFormat};
 
 use crate::{
+    camera::{Camera, Z_FAR, Z_NEAR},
     material::Material,
-    object::{BasicVertex, Object},
+    object::SharedObjects,
     texture_store::{TextureHandle, TextureResolver},
 };
 
 use super::Pass;
 
+/// How the on-screen size of a point is chosen
+#[derive(Debug, Clone, Copy)]
+pub enum PointSize {
+    /// Constant width in pixels, regardless of distance
+    Pixels(f32),
+    /// Width in world units, shrinking with distance
+    World(f32),
+}
+
+impl FromStr for PointSize {
+    type Err = String;
+
+    /// Parses sizes such as `3px` or `0.05m`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |v: &str| v.parse::<f32>().map_err(|e| e.to_string());
+        if let Some(v) = s.strip_suffix("px") {
+            Ok(Self::Pixels(parse(v)?))
+        } else if let Some(v) = s.strip_suffix('m') {
+            Ok(Self::World(parse(v)?))
+        } else {
+            Ok(Self::Pixels(parse(s)?))
+        }
+    }
+}
+
+/// Shape of the sprite drawn for every point
+#[derive(Debug, Clone, Copy)]
+pub enum PointShape {
+    Square,
+    Round,
+    /// Round sprite whose depth bulges towards the camera, so overlapping points interlock
+    Paraboloid,
+}
+
+impl FromStr for PointShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(Self::Square),
+            "round" => Ok(Self::Round),
+            "paraboloid" => Ok(Self::Paraboloid),
+            _ => Err(format!("Unknown point shape: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PointSettings {
+    pub size: PointSize,
+    pub shape: PointShape,
+}
+
+impl Default for PointSettings {
+    fn default() -> Self {
+        Self {
+            size: PointSize::Pixels(1.0),
+            shape: PointShape::Square,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub(super) struct PointsUniform {
+    transform: nalgebra::Matrix4<f32>,
+    viewport: nalgebra::Vector2<f32>,
+    point_size: f32,
+    world_size: u32,
+    shape: u32,
+    projection_scale: f32,
+    z_near: f32,
+    z_far: f32,
+    camera_position: nalgebra::Vector4<f32>,
+}
+
+impl PointsUniform {
+    /// Camera at `elapsed` along its orbit, looking through a viewport of `width` by
+    /// `height` pixels
+    pub(super) fn new(
+        aspect_ratio: f32,
+        width: f32,
+        height: f32,
+        settings: PointSettings,
+        elapsed: Duration,
+    ) -> Self {
+        let camera = Camera::orbit(elapsed.as_secs_f32());
+        let (point_size, world_size) = match settings.size {
+            PointSize::Pixels(size) => (size, 0),
+            PointSize::World(size) => (size, 1),
+        };
+        Self {
+            transform: camera.view_projection(aspect_ratio),
+            viewport: nalgebra::Vector2::new(width, height),
+            point_size,
+            world_size,
+            shape: settings.shape as u32,
+            projection_scale: Camera::projection_scale(height),
+            z_near: Z_NEAR,
+            z_far: Z_FAR,
+            camera_position: camera.position.to_homogeneous(),
+        }
+    }
+}
+
 pub struct PointsPass {
-    objects: Vec<Box<dyn Object>>,
+    objects: SharedObjects,
     position_buffer: TextureHandle,
     color_buffer: TextureHandle,
+    normal_buffer: TextureHandle,
     uniform_buf: Buffer,
     bind_group: wgpu::BindGroup,
     depth_buffer: TextureHandle,
+    settings: PointSettings,
 }
 
 impl PointsPass {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         device: &Device,
         bind_group_layout: &BindGroupLayout,
-        objects: Vec<Box<dyn Object>>,
+        objects: SharedObjects,
         position_buffer: TextureHandle,
         color_buffer: TextureHandle,
+        normal_buffer: TextureHandle,
         depth_buffer: TextureHandle,
+        settings: PointSettings,
     ) -> Self {
         let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
-            size: 64,
+            size: std::mem::size_of::<PointsUniform>() as u64,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -46,45 +157,88 @@ impl PointsPass {
             objects,
             position_buffer,
             color_buffer,
+            normal_buffer,
             uniform_buf,
             bind_group,
             depth_buffer,
+            settings,
         }
     }
 
+    /// Layout of the camera uniform shared by every point material
+    pub fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Point camera layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<PointsUniform>() as u64
+                    ),
+                },
+                count: None,
+            }],
+        })
+    }
+
+    /// Layout of the per-object buffers the point sprites are read from, see `PointBuffers`
+    pub fn create_point_data_layout(device: &Device) -> BindGroupLayout {
+        let storage = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Point data layout"),
+            entries: &[
+                // Positions
+                storage(0),
+                // Surfels
+                storage(1),
+                // Colors
+                storage(2),
+                // Header
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        })
+    }
+
     pub fn create_point_material(
         device: &Device,
         position_format: TextureFormat,
         color_format: TextureFormat,
+        normal_format: TextureFormat,
         bind_group_layout: &BindGroupLayout,
+        point_data_layout: &BindGroupLayout,
     ) -> Material {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Point shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../shaders/point.wgsl"))),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("../shaders/point_data.wgsl"),
+                include_str!("../shaders/point.wgsl")
+            ))),
         });
 
-        let buffer_layout = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<BasicVertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                // Position
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                // Color
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: 12,
-                    shader_location: 1,
-                },
-            ],
-        };
-
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Point pipeline layout"),
-            bind_group_layouts: &[&bind_group_layout],
+            bind_group_layouts: &[bind_group_layout, point_data_layout],
             push_constant_ranges: &[],
         });
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -93,15 +247,20 @@ impl PointsPass {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
-                buffers: &[buffer_layout],
+                // Points are fetched from the object's storage buffer
+                buffers: &[],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: "fs_main",
-                targets: &[Some(position_format.into()), Some(color_format.into())],
+                targets: &[
+                    Some(position_format.into()),
+                    Some(color_format.into()),
+                    Some(normal_format.into()),
+                ],
             }),
             primitive: wgpu::PrimitiveState {
-                polygon_mode: wgpu::PolygonMode::Point,
+                topology: wgpu::PrimitiveTopology::TriangleList,
                 ..Default::default()
             },
             depth_stencil: Some(wgpu::DepthStencilState {
@@ -133,21 +292,23 @@ impl Pass for PointsPass {
         textures: &TextureResolver,
         elapsed: Duration,
     ) {
-        let elapsed = elapsed.as_secs_f32();
         // Write current perspective matrix to the uniform buffer
-        let perspective: nalgebra::Matrix4<f32> =
-            nalgebra::Matrix4::new_perspective(aspect_ratio, 1.0, 0.1, 100.0);
-        let camera_position = nalgebra::Point3::new(elapsed.cos() * 1.0, 0.0, elapsed.sin() * 1.0);
-        let camera_lookat_matrix = nalgebra::Matrix4::look_at_rh(
-            &camera_position,
-            &nalgebra::Point3::new(0.0, 0.0, 0.0),
-            &nalgebra::Vector3::new(0.0, 1.0, 0.0),
+        let size = textures.resolve_size(self.depth_buffer);
+        let uniform = PointsUniform::new(
+            aspect_ratio,
+            size.width as f32,
+            size.height as f32,
+            self.settings,
+            elapsed,
         );
-        let mx = perspective * camera_lookat_matrix;
-        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&mx));
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniform));
+
+        // Has to outlive the render pass, which borrows the objects
+        let objects = self.objects.borrow();
 
         let position_view = textures.resolve(self.position_buffer);
         let color_view = textures.resolve(self.color_buffer);
+        let normal_view = textures.resolve(self.normal_buffer);
         let depth_buffer = textures.resolve(self.depth_buffer);
 
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -169,6 +330,14 @@ impl Pass for PointsPass {
                         store: wgpu::StoreOp::Store,
                     },
                 }),
+                Some(wgpu::RenderPassColorAttachment {
+                    view: normal_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
             ],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                 view: depth_buffer,
@@ -182,7 +351,7 @@ impl Pass for PointsPass {
             occlusion_query_set: None,
         });
         rpass.set_bind_group(0, &self.bind_group, &[]);
-        for object in &self.objects {
+        for object in objects.iter() {
             object.draw(&mut rpass);
         }
     }
@@ -2,17 +2,17 @@ use std::borrow::Cow;
 
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    BindGroupLayout, Buffer, Device, Sampler, TextureFormat,
+    BindGroupLayout, Buffer, Device, TextureFormat,
 };
-use winit::dpi::Position;
 
 use crate::{material::Material, texture_store::TextureHandle};
 
-use super::Pass;
+use super::{create_quad_material, Pass, FULLSCREEN_QUAD};
 
 pub struct RecolorPass {
     color_texture: TextureHandle,
     position_texture: TextureHandle,
+    occlusion_texture: Option<TextureHandle>,
     output_texture: TextureHandle,
     bind_group_layout: BindGroupLayout,
     material: Material,
@@ -20,85 +20,60 @@ pub struct RecolorPass {
     vertex_buffer: Buffer,
 }
 
-#[repr(C)]
-#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
-struct RecolorVertex {
-    position: nalgebra::Vector2<f32>,
-    tex_coords: nalgebra::Vector2<f32>,
-}
-
 impl RecolorPass {
     pub fn new(
         device: &Device,
         color_buffer: TextureHandle,
         position_buffer: TextureHandle,
+        occlusion_buffer: Option<TextureHandle>,
         output_texture: TextureHandle,
         output_format: TextureFormat,
     ) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let entries = match occlusion_buffer {
+            Some(_) => vec![texture_entry(0), texture_entry(1), texture_entry(2)],
+            None => vec![texture_entry(0), texture_entry(1)],
+        };
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-            ],
+            entries: &entries,
         });
 
-        let material = Self::create_material(device, output_format, &bind_group_layout);
-
-        let vertices: &[RecolorVertex] = &[
-            RecolorVertex {
-                position: nalgebra::Vector2::new(-1.0, 1.0),
-                tex_coords: nalgebra::Vector2::new(0.0, 0.0),
-            },
-            RecolorVertex {
-                position: nalgebra::Vector2::new(-1.0, -1.0),
-                tex_coords: nalgebra::Vector2::new(0.0, 1.0),
-            },
-            RecolorVertex {
-                position: nalgebra::Vector2::new(1.0, 1.0),
-                tex_coords: nalgebra::Vector2::new(1.0, 0.0),
-            },
-            RecolorVertex {
-                position: nalgebra::Vector2::new(1.0, 1.0),
-                tex_coords: nalgebra::Vector2::new(1.0, 0.0),
-            },
-            RecolorVertex {
-                position: nalgebra::Vector2::new(-1.0, -1.0),
-                tex_coords: nalgebra::Vector2::new(0.0, 1.0),
-            },
-            RecolorVertex {
-                position: nalgebra::Vector2::new(1.0, -1.0),
-                tex_coords: nalgebra::Vector2::new(1.0, 1.0),
-            },
-        ];
+        let occlusion_source = if occlusion_buffer.is_some() {
+            "@group(0) @binding(2) var r_occlusion: texture_2d<f32>;
+fn occlusion(pos: vec2<u32>) -> f32 { return textureLoad(r_occlusion, pos, 0).r; }"
+        } else {
+            "fn occlusion(pos: vec2<u32>) -> f32 { return 1.0; }"
+        };
+        let shadersource =
+            include_str!("../shaders/recolor.wgsl").replace("{OCCLUSION}", occlusion_source);
+        let material = create_quad_material(
+            device,
+            "recolor",
+            Cow::Owned(shadersource),
+            output_format,
+            &bind_group_layout,
+        );
 
         let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("recolor vertex buffer"),
-            contents: bytemuck::cast_slice(vertices),
+            contents: bytemuck::cast_slice(FULLSCREEN_QUAD),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
         Self {
             color_texture: color_buffer,
             position_texture: position_buffer,
+            occlusion_texture: occlusion_buffer,
             output_texture,
             material,
             bind_group_layout,
@@ -106,72 +81,6 @@ impl RecolorPass {
             bind_group: None,
         }
     }
-
-    fn create_material(
-        device: &Device,
-        format: TextureFormat,
-        bind_group_layout: &BindGroupLayout,
-    ) -> Material {
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("recolor pipeline layout"),
-            bind_group_layouts: &[bind_group_layout],
-            push_constant_ranges: &[],
-        });
-
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("recolor shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../shaders/recolor.wgsl"))),
-        });
-
-        let vertex_buffer = [wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<RecolorVertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x2,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x2,
-                    offset: 8,
-                    shader_location: 1,
-                },
-            ],
-        }];
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("recolor pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &vertex_buffer,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(format.into())],
-            }),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                ..Default::default()
-            },
-            multiview: None,
-        });
-
-        Material {
-            shader,
-            pipeline_layout,
-            render_pipeline,
-        }
-    }
 }
 
 impl Pass for RecolorPass {
@@ -186,6 +95,9 @@ impl Pass for RecolorPass {
     ) {
         let color_view = textures.resolve(self.color_texture);
         let position_view = textures.resolve(self.position_texture);
+        let occlusion_view = self
+            .occlusion_texture
+            .map(|handle| textures.resolve(handle));
         let output_view = textures.resolve(self.output_texture);
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("recolor pass"),
@@ -202,24 +114,31 @@ impl Pass for RecolorPass {
             timestamp_writes: None,
         });
 
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(color_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(position_view),
+            },
+        ];
+        if let Some(occlusion_view) = occlusion_view {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(occlusion_view),
+            });
+        }
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("recolor bind group"),
             layout: &self.bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(color_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::TextureView(position_view),
-                },
-            ],
+            entries: &entries,
         });
 
         self.bind_group = Some(bind_group);
 
-        rpass.set_bind_group(0, (&self.bind_group).as_ref().unwrap(), &[]);
+        rpass.set_bind_group(0, self.bind_group.as_ref().unwrap(), &[]);
         rpass.set_pipeline(&self.material.render_pipeline);
         rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         rpass.draw(0..6, 0..1);
@@ -0,0 +1,437 @@
+use std::borrow::Cow;
+
+use nalgebra::{Matrix4, Vector4};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupLayout, Buffer, Device, TextureFormat,
+};
+
+use crate::{
+    camera::Camera,
+    material::Material,
+    object::SharedObjects,
+    texture_store::{TextureHandle, TextureResolver},
+};
+
+use super::{create_quad_material, points_pass::PointsPass, Pass, FULLSCREEN_QUAD};
+
+/// Parameters of the surface splatting render mode.
+#[derive(Debug, Clone, Copy)]
+pub struct SplatSettings {
+    /// World space radius of points which don't carry their own.
+    pub radius: f32,
+    /// How far, relative to its radius, the visibility pass pushes every splat back.
+    /// Splats closer than this to the front surface get blended into it.
+    pub depth_offset: f32,
+}
+
+impl Default for SplatSettings {
+    fn default() -> Self {
+        Self {
+            radius: 0.005,
+            depth_offset: 1.0,
+        }
+    }
+}
+
+/// The two stages which rasterize splats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplatStage {
+    /// Fills the depth buffer with splats pushed back by their depth offset
+    Visibility,
+    /// Sums the Gaussian weighted colors and normals of the splats on the front surface
+    Accumulation,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SplatUniform {
+    transform: Matrix4<f32>,
+    camera_position: Vector4<f32>,
+    camera_right: Vector4<f32>,
+    camera_up: Vector4<f32>,
+    radius: f32,
+    depth_offset: f32,
+    _padding: [f32; 2],
+}
+
+/// Draws every object as oriented, Gaussian weighted discs
+pub struct SplatPass {
+    stage: SplatStage,
+    objects: SharedObjects,
+    color_buffer: TextureHandle,
+    normal_buffer: TextureHandle,
+    depth_buffer: TextureHandle,
+    material: Material,
+    uniform_buf: Buffer,
+    bind_group: wgpu::BindGroup,
+    settings: SplatSettings,
+}
+
+impl SplatPass {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &Device,
+        stage: SplatStage,
+        objects: SharedObjects,
+        color_buffer: TextureHandle,
+        normal_buffer: TextureHandle,
+        depth_buffer: TextureHandle,
+        accumulation_format: TextureFormat,
+        settings: SplatSettings,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("splat camera layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<SplatUniform>() as u64
+                    ),
+                },
+                count: None,
+            }],
+        });
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("splat uniform buffer"),
+            size: std::mem::size_of::<SplatUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("splat bind group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            }],
+        });
+
+        let material =
+            Self::create_material(device, stage, accumulation_format, &bind_group_layout);
+
+        Self {
+            stage,
+            objects,
+            color_buffer,
+            normal_buffer,
+            depth_buffer,
+            material,
+            uniform_buf,
+            bind_group,
+            settings,
+        }
+    }
+
+    fn create_material(
+        device: &Device,
+        stage: SplatStage,
+        accumulation_format: TextureFormat,
+        bind_group_layout: &BindGroupLayout,
+    ) -> Material {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("splat shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("../shaders/point_data.wgsl"),
+                include_str!("../shaders/splat.wgsl")
+            ))),
+        });
+
+        let point_data_layout = PointsPass::create_point_data_layout(device);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("splat pipeline layout"),
+            bind_group_layouts: &[bind_group_layout, &point_data_layout],
+            push_constant_ranges: &[],
+        });
+
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let accumulation_target = Some(wgpu::ColorTargetState {
+            format: accumulation_format,
+            blend: Some(wgpu::BlendState {
+                color: additive,
+                alpha: additive,
+            }),
+            write_mask: wgpu::ColorWrites::ALL,
+        });
+        let (label, vertex_entry, fragment_entry, targets, depth_write_enabled, depth_compare) =
+            match stage {
+                SplatStage::Visibility => (
+                    "splat visibility pipeline",
+                    "vs_visibility",
+                    "fs_visibility",
+                    vec![],
+                    true,
+                    wgpu::CompareFunction::Less,
+                ),
+                SplatStage::Accumulation => (
+                    "splat accumulation pipeline",
+                    "vs_accumulation",
+                    "fs_accumulation",
+                    vec![accumulation_target.clone(), accumulation_target],
+                    false,
+                    wgpu::CompareFunction::LessEqual,
+                ),
+            };
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: vertex_entry,
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: fragment_entry,
+                targets: &targets,
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled,
+                depth_compare,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Material {
+            shader,
+            pipeline_layout,
+            render_pipeline,
+        }
+    }
+}
+
+impl Pass for SplatPass {
+    fn render(
+        &mut self,
+        aspect_ratio: f32,
+        _: &Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        textures: &TextureResolver,
+        elapsed: std::time::Duration,
+    ) {
+        let camera = Camera::orbit(elapsed.as_secs_f32());
+        let (right, up) = camera.screen_axes();
+        let uniform = SplatUniform {
+            transform: camera.view_projection(aspect_ratio),
+            camera_position: camera.position.to_homogeneous(),
+            camera_right: right.push(0.0),
+            camera_up: up.push(0.0),
+            radius: self.settings.radius,
+            depth_offset: self.settings.depth_offset,
+            _padding: [0.0; 2],
+        };
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniform));
+
+        let objects = self.objects.borrow();
+
+        let depth_view = textures.resolve(self.depth_buffer);
+        let color_attachments = match self.stage {
+            SplatStage::Visibility => vec![],
+            SplatStage::Accumulation => [self.color_buffer, self.normal_buffer]
+                .into_iter()
+                .map(|handle| {
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: textures.resolve(handle),
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })
+                })
+                .collect(),
+        };
+        let depth_load = match self.stage {
+            SplatStage::Visibility => wgpu::LoadOp::Clear(1.0),
+            SplatStage::Accumulation => wgpu::LoadOp::Load,
+        };
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("splat pass"),
+            color_attachments: &color_attachments,
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: depth_load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.material.render_pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        for object in objects.iter() {
+            object.draw_points(&mut rpass);
+        }
+    }
+}
+
+/// Divides the accumulated colors and normals by their summed weights and shades the result
+pub struct SplatNormalizePass {
+    color_texture: TextureHandle,
+    normal_texture: TextureHandle,
+    output_texture: TextureHandle,
+    bind_group_layout: BindGroupLayout,
+    material: Material,
+    bind_group: Option<wgpu::BindGroup>,
+    vertex_buffer: Buffer,
+    uniform_buf: Buffer,
+}
+
+impl SplatNormalizePass {
+    pub fn new(
+        device: &Device,
+        color_buffer: TextureHandle,
+        normal_buffer: TextureHandle,
+        output_texture: TextureHandle,
+        output_format: TextureFormat,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(16),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let material = create_quad_material(
+            device,
+            "splat normalize",
+            Cow::Borrowed(include_str!("../shaders/splat_normalize.wgsl")),
+            output_format,
+            &bind_group_layout,
+        );
+
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("splat normalize vertex buffer"),
+            contents: bytemuck::cast_slice(FULLSCREEN_QUAD),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("splat normalize uniform buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            color_texture: color_buffer,
+            normal_texture: normal_buffer,
+            output_texture,
+            bind_group_layout,
+            material,
+            bind_group: None,
+            vertex_buffer,
+            uniform_buf,
+        }
+    }
+}
+
+impl Pass for SplatNormalizePass {
+    fn render(
+        &mut self,
+        _: f32,
+        device: &Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        textures: &TextureResolver,
+        elapsed: std::time::Duration,
+    ) {
+        // The blended normals are lit by a headlight
+        let camera = Camera::orbit(elapsed.as_secs_f32());
+        let view_direction = (camera.target - camera.position).normalize().push(0.0);
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&view_direction));
+
+        let color_view = textures.resolve(self.color_texture);
+        let normal_view = textures.resolve(self.normal_texture);
+        let output_view = textures.resolve(self.output_texture);
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("splat normalize pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("splat normalize bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(color_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(normal_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        self.bind_group = Some(bind_group);
+
+        rpass.set_bind_group(0, self.bind_group.as_ref().unwrap(), &[]);
+        rpass.set_pipeline(&self.material.render_pipeline);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.draw(0..6, 0..1);
+    }
+}
@@ -0,0 +1,365 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupLayout, Buffer, Device, TextureFormat,
+};
+
+use crate::{
+    camera::{FOV_Y, Z_FAR, Z_NEAR},
+    material::Material,
+    texture_store::TextureHandle,
+};
+
+use super::{create_quad_material, Pass, FULLSCREEN_QUAD};
+
+/// Parameters of the screen-space ambient occlusion.
+#[derive(Debug, Clone, Copy)]
+pub struct SsaoSettings {
+    /// Number of depth samples taken per pixel.
+    pub quality: u32,
+    /// World space radius in which geometry occludes a pixel.
+    pub radius: f32,
+    /// How dark fully occluded pixels become.
+    pub intensity: f32,
+    /// Radius, in pixels, of the blur which removes the sampling noise.
+    pub blur: u32,
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        Self {
+            quality: 16,
+            radius: 0.05,
+            intensity: 1.0,
+            blur: 4,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsaoUniform {
+    radius: f32,
+    intensity: f32,
+    tan_half_fov: f32,
+    aspect_ratio: f32,
+    z_near: f32,
+    z_far: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsaoBlurUniform {
+    z_near: f32,
+    z_far: f32,
+}
+
+/// Estimates ambient occlusion from the hole-filled positions produced by the jump flood.
+pub struct SsaoPass {
+    position_texture: TextureHandle,
+    output_texture: TextureHandle,
+    bind_group_layout: BindGroupLayout,
+    material: Material,
+    bind_group: Option<wgpu::BindGroup>,
+    vertex_buffer: Buffer,
+    uniform_buf: Buffer,
+    settings: SsaoSettings,
+}
+
+impl SsaoPass {
+    pub fn new(
+        device: &Device,
+        position_buffer: TextureHandle,
+        output_texture: TextureHandle,
+        output_format: TextureFormat,
+        settings: SsaoSettings,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<SsaoUniform>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shadersource = include_str!("../shaders/ssao.wgsl")
+            .replace("{SAMPLES}", &settings.quality.max(1).to_string());
+        let material = create_quad_material(
+            device,
+            "ssao",
+            Cow::Owned(shadersource),
+            output_format,
+            &bind_group_layout,
+        );
+
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("ssao vertex buffer"),
+            contents: bytemuck::cast_slice(FULLSCREEN_QUAD),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ssao uniform buffer"),
+            size: std::mem::size_of::<SsaoUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            position_texture: position_buffer,
+            output_texture,
+            bind_group_layout,
+            material,
+            bind_group: None,
+            vertex_buffer,
+            uniform_buf,
+            settings,
+        }
+    }
+}
+
+impl Pass for SsaoPass {
+    fn render(
+        &mut self,
+        aspect_ratio: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        textures: &crate::texture_store::TextureResolver,
+        _: std::time::Duration,
+    ) {
+        let uniform = SsaoUniform {
+            radius: self.settings.radius,
+            intensity: self.settings.intensity,
+            tan_half_fov: (FOV_Y / 2.0).tan(),
+            aspect_ratio,
+            z_near: Z_NEAR,
+            z_far: Z_FAR,
+        };
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniform));
+
+        let position_view = textures.resolve(self.position_texture);
+        let output_view = textures.resolve(self.output_texture);
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("ssao pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ssao bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(position_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        self.bind_group = Some(bind_group);
+
+        rpass.set_bind_group(0, self.bind_group.as_ref().unwrap(), &[]);
+        rpass.set_pipeline(&self.material.render_pipeline);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.draw(0..6, 0..1);
+    }
+}
+
+/// Blurs the occlusion along one axis, without bleeding across depth discontinuities.
+pub struct SsaoBlurPass {
+    occlusion_texture: TextureHandle,
+    position_texture: TextureHandle,
+    output_texture: TextureHandle,
+    bind_group_layout: BindGroupLayout,
+    material: Material,
+    bind_group: Option<wgpu::BindGroup>,
+    vertex_buffer: Buffer,
+    uniform_buf: Buffer,
+}
+
+impl SsaoBlurPass {
+    pub fn new(
+        device: &Device,
+        occlusion_buffer: TextureHandle,
+        position_buffer: TextureHandle,
+        output_texture: TextureHandle,
+        output_format: TextureFormat,
+        radius: u32,
+        vertical: bool,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<SsaoBlurUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let direction = if vertical {
+            "vec2<i32>(0, 1)"
+        } else {
+            "vec2<i32>(1, 0)"
+        };
+        let shadersource = include_str!("../shaders/ssao_blur.wgsl")
+            .replace("{RADIUS}", &radius.to_string())
+            .replace("{DIRECTION}", direction);
+        let material = create_quad_material(
+            device,
+            "ssao blur",
+            Cow::Owned(shadersource),
+            output_format,
+            &bind_group_layout,
+        );
+
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("ssao blur vertex buffer"),
+            contents: bytemuck::cast_slice(FULLSCREEN_QUAD),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ssao blur uniform buffer"),
+            size: std::mem::size_of::<SsaoBlurUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            occlusion_texture: occlusion_buffer,
+            position_texture: position_buffer,
+            output_texture,
+            bind_group_layout,
+            material,
+            bind_group: None,
+            vertex_buffer,
+            uniform_buf,
+        }
+    }
+}
+
+impl Pass for SsaoBlurPass {
+    fn render(
+        &mut self,
+        _: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        textures: &crate::texture_store::TextureResolver,
+        _: std::time::Duration,
+    ) {
+        let uniform = SsaoBlurUniform {
+            z_near: Z_NEAR,
+            z_far: Z_FAR,
+        };
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniform));
+
+        let occlusion_view = textures.resolve(self.occlusion_texture);
+        let position_view = textures.resolve(self.position_texture);
+        let output_view = textures.resolve(self.output_texture);
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("ssao blur pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ssao blur bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(occlusion_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(position_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        self.bind_group = Some(bind_group);
+
+        rpass.set_bind_group(0, self.bind_group.as_ref().unwrap(), &[]);
+        rpass.set_pipeline(&self.material.render_pipeline);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.draw(0..6, 0..1);
+    }
+}
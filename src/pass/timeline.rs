@@ -0,0 +1,164 @@
+use std::{borrow::Cow, cell::Cell, rc::Rc};
+
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    Buffer, Device, TextureFormat,
+};
+
+use crate::{material::Material, texture_store::TextureHandle};
+
+use super::{create_quad_material, Pass, QuadVertex};
+
+/// Part of the window height taken by the bar
+pub const TIMELINE_HEIGHT: f32 = 0.04;
+
+/// Draws a bar along the bottom of the window with the position in a recording.
+pub struct TimelinePass {
+    output_texture: TextureHandle,
+    bind_group: wgpu::BindGroup,
+    material: Material,
+    vertex_buffer: Buffer,
+    uniform_buf: Buffer,
+    /// Played part of the recording, from 0 to 1
+    progress: Rc<Cell<f32>>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct TimelineUniform {
+    progress: f32,
+    pad: [f32; 3],
+}
+
+impl TimelinePass {
+    pub fn new(
+        device: &Device,
+        output_texture: TextureHandle,
+        output_format: TextureFormat,
+        progress: Rc<Cell<f32>>,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<TimelineUniform>() as u64
+                    ),
+                },
+                count: None,
+            }],
+        });
+
+        let material = create_quad_material(
+            device,
+            "timeline",
+            Cow::Borrowed(include_str!("../shaders/timeline.wgsl")),
+            output_format,
+            &bind_group_layout,
+        );
+
+        // Only the bottom of the window is covered
+        let top = -1.0 + 2.0 * TIMELINE_HEIGHT;
+        let vertices: &[QuadVertex] = &[
+            QuadVertex {
+                position: nalgebra::Vector2::new(-1.0, top),
+                tex_coords: nalgebra::Vector2::new(0.0, 0.0),
+            },
+            QuadVertex {
+                position: nalgebra::Vector2::new(-1.0, -1.0),
+                tex_coords: nalgebra::Vector2::new(0.0, 1.0),
+            },
+            QuadVertex {
+                position: nalgebra::Vector2::new(1.0, top),
+                tex_coords: nalgebra::Vector2::new(1.0, 0.0),
+            },
+            QuadVertex {
+                position: nalgebra::Vector2::new(1.0, top),
+                tex_coords: nalgebra::Vector2::new(1.0, 0.0),
+            },
+            QuadVertex {
+                position: nalgebra::Vector2::new(-1.0, -1.0),
+                tex_coords: nalgebra::Vector2::new(0.0, 1.0),
+            },
+            QuadVertex {
+                position: nalgebra::Vector2::new(1.0, -1.0),
+                tex_coords: nalgebra::Vector2::new(1.0, 1.0),
+            },
+        ];
+
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("timeline vertex buffer"),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("timeline uniform buffer"),
+            size: std::mem::size_of::<TimelineUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("timeline bind group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            output_texture,
+            bind_group,
+            material,
+            vertex_buffer,
+            uniform_buf,
+            progress,
+        }
+    }
+}
+
+impl Pass for TimelinePass {
+    fn render(
+        &mut self,
+        _: f32,
+        _: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        textures: &crate::texture_store::TextureResolver,
+        _: std::time::Duration,
+    ) {
+        let uniform = TimelineUniform {
+            progress: self.progress.get(),
+            pad: [0.0; 3],
+        };
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniform));
+
+        // Drawn over the finished frame
+        let output_view = textures.resolve(self.output_texture);
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("timeline pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_pipeline(&self.material.render_pipeline);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.draw(0..6, 0..1);
+    }
+}
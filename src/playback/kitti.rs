@@ -0,0 +1,96 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    loader::{
+        kitti::{self, KittiSettings},
+        LoadResult,
+    },
+    object::BasicVertex,
+};
+
+use super::FrameSource;
+
+/// Time between the scans of a sequence without a `times.txt`, the sensor spins at 10 Hz
+const SCAN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Whether the input is a KITTI scan, a sequence directory or its `velodyne` directory
+pub fn is_kitti(path: &Path) -> bool {
+    kitti::is_scan(path) || path.join("velodyne").is_dir() || (path.is_dir() && has_scans(path))
+}
+
+fn has_scans(directory: &Path) -> bool {
+    std::fs::read_dir(directory).is_ok_and(|mut entries| {
+        entries.any(|entry| entry.is_ok_and(|entry| kitti::is_scan(&entry.path())))
+    })
+}
+
+/// The scans of a sequence, played in the order of their file names
+pub struct KittiSequence {
+    scans: Vec<PathBuf>,
+    times: Vec<Duration>,
+    settings: KittiSettings,
+}
+
+impl KittiSequence {
+    /// Opens a sequence directory, its `velodyne` directory, or a single scan
+    pub fn open(path: &Path, settings: KittiSettings) -> LoadResult<Self> {
+        if kitti::is_scan(path) {
+            return Ok(Self {
+                scans: vec![path.to_owned()],
+                times: vec![Duration::ZERO],
+                settings,
+            });
+        }
+        let (sequence, velodyne) = if path.join("velodyne").is_dir() {
+            (path.to_owned(), path.join("velodyne"))
+        } else {
+            (path.parent().unwrap_or(path).to_owned(), path.to_owned())
+        };
+
+        let mut scans = Vec::new();
+        for entry in std::fs::read_dir(&velodyne)? {
+            let path = entry?.path();
+            if kitti::is_scan(&path) {
+                scans.push(path);
+            }
+        }
+        if scans.is_empty() {
+            return Err(format!("{} has no scans", velodyne.display()).into());
+        }
+        scans.sort();
+        log::info!("Found {} scans in {}", scans.len(), velodyne.display());
+
+        // times.txt holds the seconds of every scan, one per line
+        let times = std::fs::read_to_string(sequence.join("times.txt"))
+            .ok()
+            .and_then(|times| {
+                times
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| line.trim().parse().ok().map(Duration::from_secs_f64))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .filter(|times| times.len() == scans.len())
+            .map(|times| times.iter().map(|&t| t.saturating_sub(times[0])).collect())
+            .unwrap_or_else(|| (0..scans.len() as u32).map(|i| SCAN_INTERVAL * i).collect());
+
+        Ok(Self {
+            scans,
+            times,
+            settings,
+        })
+    }
+}
+
+impl FrameSource for KittiSequence {
+    fn frame_times(&self) -> &[Duration] {
+        &self.times
+    }
+
+    fn load(&mut self, frame: usize) -> LoadResult<Vec<BasicVertex>> {
+        kitti::load_scan(&self.scans[frame], &self.settings)
+    }
+}
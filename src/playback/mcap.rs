@@ -0,0 +1,353 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{loader::LoadResult, object::BasicVertex};
+
+use super::{pointcloud2, FrameSource};
+
+const MAGIC: &[u8; 8] = b"\x89MCAP0\r\n";
+
+const SCHEMA: u8 = 0x03;
+const CHANNEL: u8 = 0x04;
+const MESSAGE: u8 = 0x05;
+const CHUNK: u8 = 0x06;
+const DATA_END: u8 = 0x0f;
+
+/// Schema names of point clouds, as written by ROS 2 and ROS 1
+const POINT_CLOUD_SCHEMAS: &[&str] = &["sensor_msgs/msg/PointCloud2", "sensor_msgs/PointCloud2"];
+
+/// Topic a channel publishes on
+pub struct Topic {
+    pub name: String,
+    /// Name of the message type, empty without a schema
+    pub schema: String,
+    pub encoding: String,
+    pub messages: usize,
+}
+
+impl Topic {
+    pub fn is_point_cloud(&self) -> bool {
+        self.encoding == "cdr" && POINT_CLOUD_SCHEMAS.contains(&self.schema.as_str())
+    }
+}
+
+/// Where the bytes of a message are
+#[derive(Clone, Copy)]
+enum Location {
+    /// In the file itself
+    File { offset: u64, length: usize },
+    /// In the decompressed records of a chunk
+    Chunk {
+        chunk: usize,
+        offset: usize,
+        length: usize,
+    },
+}
+
+struct Message {
+    channel: u16,
+    /// Nanoseconds since the epoch
+    log_time: u64,
+    location: Location,
+}
+
+/// Records of a chunk, as stored in the file
+struct Chunk {
+    offset: u64,
+    length: usize,
+    uncompressed_size: usize,
+    compression: String,
+}
+
+/// Index of the messages in an MCAP file, built by reading it through once
+pub struct McapFile {
+    path: PathBuf,
+    topics: HashMap<u16, Topic>,
+    messages: Vec<Message>,
+    chunks: Vec<Chunk>,
+}
+
+impl McapFile {
+    pub fn open(path: &Path) -> LoadResult<Self> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(format!("{} isn't an MCAP file", path.display()).into());
+        }
+
+        let mut file = Self {
+            path: path.to_owned(),
+            topics: HashMap::new(),
+            messages: Vec::new(),
+            chunks: Vec::new(),
+        };
+        let mut schemas = HashMap::new();
+        let mut offset = magic.len() as u64;
+        loop {
+            let mut header = [0; 9];
+            if input.read_exact(&mut header).is_err() {
+                break;
+            }
+            let opcode = header[0];
+            let length = u64::from_le_bytes(header[1..].try_into().unwrap());
+            offset += header.len() as u64;
+            // The summary section after the data only repeats what was already read
+            if opcode == DATA_END {
+                break;
+            }
+            match opcode {
+                SCHEMA | CHANNEL | MESSAGE => {
+                    let mut record = vec![0; length as usize];
+                    input.read_exact(&mut record)?;
+                    let location = Location::File {
+                        offset: offset + 22,
+                        length: (length as usize).saturating_sub(22),
+                    };
+                    file.add_record(opcode, &record, location, &mut schemas)?;
+                }
+                CHUNK => {
+                    let mut record = vec![0; length as usize];
+                    input.read_exact(&mut record)?;
+                    file.add_chunk(offset, &record, &mut schemas)?;
+                }
+                _ => {
+                    input.seek_relative(length as i64)?;
+                }
+            }
+            offset += length;
+        }
+        Ok(file)
+    }
+
+    /// Remembers a chunk and indexes the records in it
+    fn add_chunk(
+        &mut self,
+        offset: u64,
+        record: &[u8],
+        schemas: &mut HashMap<u16, String>,
+    ) -> LoadResult<()> {
+        let mut fields = Fields(record);
+        fields.u64()?;
+        fields.u64()?;
+        let uncompressed_size = fields.u64()? as usize;
+        fields.u32()?;
+        let compression = fields.string()?;
+        let length = fields.u64()? as usize;
+        let chunk = Chunk {
+            offset: offset + (record.len() - fields.0.len()) as u64,
+            length,
+            uncompressed_size,
+            compression,
+        };
+        let records = decompress(&chunk, fields.bytes(length)?)?;
+        let index = self.chunks.len();
+        self.chunks.push(chunk);
+
+        let mut position = 0;
+        while position + 9 <= records.len() {
+            let opcode = records[position];
+            let length = u64::from_le_bytes(records[position + 1..position + 9].try_into()?);
+            let start = position + 9;
+            let record = records
+                .get(start..start + length as usize)
+                .ok_or("Truncated MCAP chunk")?;
+            let location = Location::Chunk {
+                chunk: index,
+                offset: start + 22,
+                length: record.len().saturating_sub(22),
+            };
+            self.add_record(opcode, record, location, schemas)?;
+            position = start + length as usize;
+        }
+        Ok(())
+    }
+
+    /// Indexes a schema, channel or message. The data of a message starts 22 bytes into
+    /// its record, at `location`.
+    fn add_record(
+        &mut self,
+        opcode: u8,
+        record: &[u8],
+        location: Location,
+        schemas: &mut HashMap<u16, String>,
+    ) -> LoadResult<()> {
+        let mut fields = Fields(record);
+        match opcode {
+            SCHEMA => {
+                let id = fields.u16()?;
+                schemas.insert(id, fields.string()?);
+            }
+            CHANNEL => {
+                let id = fields.u16()?;
+                let schema = fields.u16()?;
+                let name = fields.string()?;
+                let encoding = fields.string()?;
+                self.topics.insert(
+                    id,
+                    Topic {
+                        name,
+                        schema: schemas.get(&schema).cloned().unwrap_or_default(),
+                        encoding,
+                        messages: 0,
+                    },
+                );
+            }
+            MESSAGE => {
+                let channel = fields.u16()?;
+                fields.u32()?;
+                let log_time = fields.u64()?;
+                if let Some(topic) = self.topics.get_mut(&channel) {
+                    topic.messages += 1;
+                    self.messages.push(Message {
+                        channel,
+                        log_time,
+                        location,
+                    });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Topics sorted by name
+    pub fn topics(&self) -> Vec<&Topic> {
+        let mut topics: Vec<_> = self.topics.values().collect();
+        topics.sort_by(|a, b| a.name.cmp(&b.name));
+        topics
+    }
+
+    /// Plays back the point clouds of a topic, or of the first point cloud topic
+    pub fn into_source(self, topic: Option<&str>) -> LoadResult<McapSource> {
+        let channel = self
+            .topics
+            .iter()
+            .filter(|(_, t)| t.is_point_cloud())
+            .filter(|(_, t)| topic.is_none_or(|name| t.name == name))
+            .min_by(|a, b| a.1.name.cmp(&b.1.name))
+            .map(|(&id, _)| id)
+            .ok_or_else(|| match topic {
+                Some(name) => format!("No point cloud topic named {name}"),
+                None => "The file has no point cloud topics".to_string(),
+            })?;
+        log::info!("Playing back {}", self.topics[&channel].name);
+
+        let mut messages: Vec<_> = self
+            .messages
+            .iter()
+            .filter(|m| m.channel == channel)
+            .map(|m| (m.log_time, m.location))
+            .collect();
+        messages.sort_by_key(|&(time, _)| time);
+        let first = messages.first().map_or(0, |&(time, _)| time);
+        Ok(McapSource {
+            input: File::open(&self.path)?,
+            times: messages
+                .iter()
+                .map(|&(time, _)| Duration::from_nanos(time - first))
+                .collect(),
+            locations: messages.into_iter().map(|(_, l)| l).collect(),
+            chunks: self.chunks,
+            cached: None,
+        })
+    }
+}
+
+/// Point clouds of one topic, in the order they were logged
+pub struct McapSource {
+    input: File,
+    times: Vec<Duration>,
+    locations: Vec<Location>,
+    chunks: Vec<Chunk>,
+    /// Last decompressed chunk, consecutive frames usually share one
+    cached: Option<(usize, Vec<u8>)>,
+}
+
+impl FrameSource for McapSource {
+    fn frame_times(&self) -> &[Duration] {
+        &self.times
+    }
+
+    fn load(&mut self, frame: usize) -> LoadResult<Vec<BasicVertex>> {
+        match self.locations[frame] {
+            Location::File { offset, length } => {
+                let mut message = vec![0; length];
+                self.input.seek(SeekFrom::Start(offset))?;
+                self.input.read_exact(&mut message)?;
+                pointcloud2::decode(&message)
+            }
+            Location::Chunk {
+                chunk,
+                offset,
+                length,
+            } => {
+                if self.cached.as_ref().is_none_or(|(i, _)| *i != chunk) {
+                    let stored = &self.chunks[chunk];
+                    let mut compressed = vec![0; stored.length];
+                    self.input.seek(SeekFrom::Start(stored.offset))?;
+                    self.input.read_exact(&mut compressed)?;
+                    self.cached = Some((chunk, decompress(stored, &compressed)?));
+                }
+                let records = &self.cached.as_ref().unwrap().1;
+                let message = records
+                    .get(offset..offset + length)
+                    .ok_or("Truncated MCAP chunk")?;
+                pointcloud2::decode(message)
+            }
+        }
+    }
+}
+
+fn decompress(chunk: &Chunk, data: &[u8]) -> LoadResult<Vec<u8>> {
+    let mut records = Vec::with_capacity(chunk.uncompressed_size);
+    match chunk.compression.as_str() {
+        "" => records.extend_from_slice(data),
+        "zstd" => {
+            ruzstd::decoding::StreamingDecoder::new(data)
+                .map_err(|e| e.to_string())?
+                .read_to_end(&mut records)?;
+        }
+        "lz4" => {
+            lz4_flex::frame::FrameDecoder::new(data).read_to_end(&mut records)?;
+        }
+        other => return Err(format!("Unsupported MCAP compression {other}").into()),
+    }
+    Ok(records)
+}
+
+/// Little endian fields at the start of a record, consumed as they are read
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    fn bytes(&mut self, count: usize) -> LoadResult<&'a [u8]> {
+        if self.0.len() < count {
+            return Err("Truncated MCAP record".into());
+        }
+        let (bytes, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> LoadResult<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> LoadResult<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> LoadResult<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into()?))
+    }
+
+    fn string(&mut self) -> LoadResult<String> {
+        let length = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+}
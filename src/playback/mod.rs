@@ -0,0 +1,154 @@
+use std::{
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    loader::{convention::Convention, LoadResult},
+    object::BasicVertex,
+};
+
+pub mod kitti;
+pub mod mcap;
+pub mod pointcloud2;
+
+/// Whether the file is an MCAP recording, such as a ROS 2 bag
+pub fn is_mcap(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("mcap"))
+}
+
+/// Recording of point clouds captured one after another
+pub trait FrameSource: Send {
+    /// When every frame was captured, relative to the first one
+    fn frame_times(&self) -> &[Duration];
+    fn load(&mut self, frame: usize) -> LoadResult<Vec<BasicVertex>>;
+}
+
+/// Plays a recording back in real time. Frames are loaded on their own thread, so seeking
+/// never blocks drawing.
+pub struct Playback {
+    times: Vec<Duration>,
+    /// Position on the timeline
+    time: Duration,
+    playing: bool,
+    last_update: Instant,
+    /// Frame asked from the loading thread last
+    requested: Option<usize>,
+    requests: Sender<usize>,
+    frames: Receiver<(usize, LoadResult<Vec<BasicVertex>>)>,
+}
+
+impl Playback {
+    /// Frames are turned from the axes of the recording by `convention`
+    pub fn spawn(mut source: Box<dyn FrameSource>, convention: Convention) -> Self {
+        let times = source.frame_times().to_vec();
+        let (requests, requested) = mpsc::channel::<usize>();
+        let (sender, frames) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(mut frame) = requested.recv() {
+                // Frames skipped over while this one loaded aren't needed anymore
+                while let Ok(newer) = requested.try_recv() {
+                    frame = newer;
+                }
+                let points = source.load(frame).map(|mut points| {
+                    convention.apply_to(&mut points);
+                    points
+                });
+                if sender.send((frame, points)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            times,
+            time: Duration::ZERO,
+            playing: true,
+            last_update: Instant::now(),
+            requested: None,
+            requests,
+            frames,
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.times.last().copied().unwrap_or_default()
+    }
+
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Position on the timeline, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        let duration = self.duration().as_secs_f32();
+        if duration > 0.0 {
+            self.time.as_secs_f32() / duration
+        } else {
+            0.0
+        }
+    }
+
+    /// Frame shown at the current time
+    pub fn frame(&self) -> usize {
+        self.times
+            .partition_point(|&t| t <= self.time)
+            .saturating_sub(1)
+    }
+
+    pub fn toggle(&mut self) {
+        self.playing = !self.playing;
+    }
+
+    /// Jumps to a position on the timeline, from 0 to 1
+    pub fn seek(&mut self, progress: f32) {
+        self.time = self.duration().mul_f32(progress.clamp(0.0, 1.0));
+    }
+
+    /// Pauses and moves by whole frames
+    pub fn step(&mut self, frames: isize) {
+        self.playing = false;
+        let frame = self.frame().saturating_add_signed(frames);
+        if let Some(&time) = self
+            .times
+            .get(frame.min(self.frame_count().saturating_sub(1)))
+        {
+            self.time = time;
+        }
+    }
+
+    /// Advances the clock, looping at the end. Returns a newly loaded frame to show, with
+    /// its index.
+    pub fn update(&mut self) -> Option<(usize, Vec<BasicVertex>)> {
+        let now = Instant::now();
+        if self.playing {
+            self.time += now - self.last_update;
+            if self.time > self.duration() {
+                self.time = Duration::ZERO;
+            }
+        }
+        self.last_update = now;
+
+        let frame = self.frame();
+        if !self.times.is_empty() && self.requested != Some(frame) {
+            self.requested = Some(frame);
+            let _ = self.requests.send(frame);
+        }
+
+        let mut shown = None;
+        for (frame, points) in self.frames.try_iter() {
+            match points {
+                Ok(points) => shown = Some((frame, points)),
+                Err(e) => log::error!("Failed to load frame {frame}: {e}"),
+            }
+        }
+        shown
+    }
+}
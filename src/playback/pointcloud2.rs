@@ -0,0 +1,198 @@
+use nalgebra::{vector, Vector3};
+
+use crate::{loader::LoadResult, object::BasicVertex};
+
+/// `sensor_msgs/PointField` data types
+const INT8: u8 = 1;
+const UINT8: u8 = 2;
+const INT16: u8 = 3;
+const UINT16: u8 = 4;
+const INT32: u8 = 5;
+const UINT32: u8 = 6;
+const FLOAT32: u8 = 7;
+const FLOAT64: u8 = 8;
+
+/// Where a field sits in every point
+struct Field {
+    name: String,
+    offset: usize,
+    datatype: u8,
+}
+
+impl Field {
+    fn read(&self, point: &[u8], big_endian: bool) -> Option<f64> {
+        let bytes = point.get(self.offset..)?;
+        macro_rules! number {
+            ($t:ty) => {{
+                let raw = bytes.get(..std::mem::size_of::<$t>())?.try_into().ok()?;
+                if big_endian {
+                    <$t>::from_be_bytes(raw) as f64
+                } else {
+                    <$t>::from_le_bytes(raw) as f64
+                }
+            }};
+        }
+        Some(match self.datatype {
+            INT8 => number!(i8),
+            UINT8 => number!(u8),
+            INT16 => number!(i16),
+            UINT16 => number!(u16),
+            INT32 => number!(i32),
+            UINT32 => number!(u32),
+            FLOAT32 => number!(f32),
+            FLOAT64 => number!(f64),
+            _ => return None,
+        })
+    }
+
+    /// Colors packed into the bits of a float, the way PCL stores them
+    fn read_packed(&self, point: &[u8], big_endian: bool) -> Option<Vector3<f32>> {
+        let raw = point.get(self.offset..self.offset + 4)?.try_into().ok()?;
+        let bits = if big_endian {
+            u32::from_be_bytes(raw)
+        } else {
+            u32::from_le_bytes(raw)
+        };
+        let channel = |shift: u32| ((bits >> shift) & 0xff) as f32 / 255.0;
+        Some(vector![channel(16), channel(8), channel(0)])
+    }
+}
+
+/// Reads the CDR encapsulated payload of a message, aligning values like the encoder did
+struct Cdr<'a> {
+    data: &'a [u8],
+    position: usize,
+    big_endian: bool,
+}
+
+impl<'a> Cdr<'a> {
+    fn new(message: &'a [u8]) -> LoadResult<Self> {
+        // Representation identifier, then two bytes of options
+        let header = message.get(..4).ok_or("Truncated CDR message")?;
+        let big_endian = match header[1] {
+            0x00 | 0x06 | 0x08 | 0x0a => true,
+            0x01 | 0x07 | 0x09 | 0x0b => false,
+            other => return Err(format!("Unknown CDR representation {other:#x}").into()),
+        };
+        Ok(Self {
+            data: &message[4..],
+            position: 0,
+            big_endian,
+        })
+    }
+
+    fn bytes(&mut self, count: usize) -> LoadResult<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or("Truncated CDR message")?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> LoadResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> LoadResult<u32> {
+        self.position = self.position.next_multiple_of(4);
+        let raw = self.bytes(4)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(raw)
+        } else {
+            u32::from_le_bytes(raw)
+        })
+    }
+
+    fn string(&mut self) -> LoadResult<String> {
+        let length = self.u32()? as usize;
+        let bytes = self.bytes(length)?;
+        // The length counts the terminating null
+        let text = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+        Ok(String::from_utf8_lossy(text).into_owned())
+    }
+}
+
+/// Decodes a `sensor_msgs/msg/PointCloud2` message. Points without a finite position are
+/// skipped. Colors come from an `rgb` or `rgba` field, separate `r`, `g` and `b` fields, or
+/// an `intensity` field, in that order.
+pub fn decode(message: &[u8]) -> LoadResult<Vec<BasicVertex>> {
+    let mut cdr = Cdr::new(message)?;
+    // std_msgs/Header: the stamp and the frame id
+    cdr.u32()?;
+    cdr.u32()?;
+    cdr.string()?;
+    let height = cdr.u32()? as usize;
+    let width = cdr.u32()? as usize;
+    let mut fields = Vec::new();
+    for _ in 0..cdr.u32()? {
+        let name = cdr.string()?;
+        let offset = cdr.u32()? as usize;
+        let datatype = cdr.u8()?;
+        let _count = cdr.u32()?;
+        fields.push(Field {
+            name,
+            offset,
+            datatype,
+        });
+    }
+    let big_endian = cdr.u8()? != 0;
+    let point_step = cdr.u32()? as usize;
+    let row_step = cdr.u32()? as usize;
+    let length = cdr.u32()? as usize;
+    let data = cdr.bytes(length)?;
+
+    let field = |name: &str| fields.iter().find(|f| f.name == name);
+    let (Some(x), Some(y), Some(z)) = (field("x"), field("y"), field("z")) else {
+        return Err("The point cloud has no x, y and z fields".into());
+    };
+    let packed = field("rgb").or_else(|| field("rgba"));
+    let channels = field("r").zip(field("g")).zip(field("b"));
+    let intensity = field("intensity");
+
+    let mut vertices = Vec::with_capacity(width * height);
+    let mut intensities = Vec::new();
+    for row in 0..height {
+        for column in 0..width {
+            let start = row * row_step + column * point_step;
+            let Some(point) = data.get(start..start + point_step) else {
+                return Err("The point data is shorter than its size".into());
+            };
+            let read = |field: &Field| field.read(point, big_endian).unwrap_or(f64::NAN) as f32;
+            let position = vector![read(x), read(y), read(z)];
+            if !position.iter().all(|c| c.is_finite()) {
+                continue;
+            }
+            let color = if let Some(packed) = packed {
+                packed.read_packed(point, big_endian).unwrap_or_default()
+            } else if let Some(((r, g), b)) = channels {
+                // Integer channels are bytes, float ones already normalized
+                let scale = if r.datatype == FLOAT32 || r.datatype == FLOAT64 {
+                    1.0
+                } else {
+                    255.0
+                };
+                vector![read(r), read(g), read(b)] / scale
+            } else {
+                if let Some(intensity) = intensity {
+                    intensities.push(read(intensity));
+                }
+                Vector3::zeros()
+            };
+            vertices.push(BasicVertex { position, color });
+        }
+    }
+
+    // Intensities have no fixed range, they're scaled by the brightest point
+    if !intensities.is_empty() {
+        let max = intensities
+            .iter()
+            .copied()
+            .filter(|i| i.is_finite())
+            .fold(f32::EPSILON, f32::max);
+        for (vertex, intensity) in vertices.iter_mut().zip(intensities) {
+            vertex.color = Vector3::repeat((intensity / max).clamp(0.0, 1.0));
+        }
+    }
+    Ok(vertices)
+}
@@ -0,0 +1,330 @@
+use bytemuck::{Pod, Zeroable};
+use nalgebra::{Point3, Vector3};
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, Queue, RenderPass};
+
+use crate::object::{BasicVertex, Surfel};
+
+/// Coarsest step, in world units, 16 bit positions may be quantized to
+const MAX_NARROW_STEP: f32 = 0.001;
+/// Widest extent of points whose positions fit in 16 bits per coordinate
+pub const MAX_NARROW_EXTENT: f32 = MAX_NARROW_STEP * u16::MAX as f32;
+
+/// Every coordinate takes 16 bits
+const NARROW: u32 = 0;
+/// Every coordinate takes 32 bits
+const WIDE: u32 = 1;
+/// Coordinates are stored as floats, for buffers filled before their bounds are known
+const FLOAT: u32 = 2;
+
+/// How the positions of a buffer are decoded, laid out like `PointHeader` in point_data.wgsl
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct PointHeader {
+    origin: Vector3<f32>,
+    /// How the coordinates are stored, one of `NARROW`, `WIDE` and `FLOAT`
+    encoding: u32,
+    scale: Vector3<f32>,
+    pad: u32,
+}
+
+/// Points uploaded in the compact layout: positions quantized relative to the bounds of the
+/// buffer, the way LAS stores them, and colors as `Unorm8x4`
+pub struct PointBuffers {
+    #[allow(dead_code)]
+    positions: Buffer,
+    #[allow(dead_code)]
+    colors: Buffer,
+    #[allow(dead_code)]
+    header: Buffer,
+    #[allow(dead_code)]
+    surfels: Buffer,
+    bind_group: BindGroup,
+    point_count: u32,
+    /// Most points the buffers can hold
+    capacity: u32,
+    min: Point3<f32>,
+    max: Point3<f32>,
+    /// Bounds of the points written since the buffers started being overwritten from the
+    /// start, which become the bounds once every point has been replaced
+    fresh: Option<(Point3<f32>, Point3<f32>)>,
+    size: u64,
+}
+
+impl PointBuffers {
+    /// Most bytes a single buffer takes per point, used to size chunks under the device
+    /// limits. The surfels take more than the positions, which take at most 12.
+    pub const MAX_BYTES_PER_POINT: u64 = std::mem::size_of::<Surfel>() as u64;
+
+    /// `vertices` must not be empty, storage buffers can't be
+    pub fn new(device: &Device, layout: &BindGroupLayout, vertices: &[BasicVertex]) -> Self {
+        let (min, max) = vertices.iter().fold(
+            (
+                Vector3::repeat(f32::INFINITY),
+                Vector3::repeat(f32::NEG_INFINITY),
+            ),
+            |(min, max), v| (min.inf(&v.position), max.sup(&v.position)),
+        );
+        let extent = max - min;
+        let wide = extent.max() > MAX_NARROW_EXTENT;
+        let steps = if wide {
+            u32::MAX as f32
+        } else {
+            u16::MAX as f32
+        };
+        // Flat axes still need a non-zero scale
+        let scale = extent.map(|e| e.max(f32::EPSILON) / steps);
+        let quantize = |v: &BasicVertex| {
+            (v.position - min)
+                .component_div(&scale)
+                .map(|c| c.round().clamp(0.0, steps) as u32)
+        };
+
+        let positions: Vec<u8> = if wide {
+            let coordinates: Vec<u32> = vertices
+                .iter()
+                .flat_map(|v| quantize(v).data.0[0])
+                .collect();
+            bytemuck::cast_slice(&coordinates).to_vec()
+        } else {
+            let mut coordinates: Vec<u16> = vertices
+                .iter()
+                .flat_map(|v| quantize(v).map(|c| c as u16).data.0[0])
+                .collect();
+            // Storage buffers are read in whole words
+            if !coordinates.len().is_multiple_of(2) {
+                coordinates.push(0);
+            }
+            bytemuck::cast_slice(&coordinates).to_vec()
+        };
+        let colors: Vec<u32> = vertices.iter().map(pack_color).collect();
+        let header = PointHeader {
+            origin: min,
+            encoding: if wide { WIDE } else { NARROW },
+            scale,
+            pad: 0,
+        };
+
+        let positions = create_storage(device, "Point Position Buffer", &positions);
+        let colors = create_storage(device, "Point Color Buffer", bytemuck::cast_slice(&colors));
+        let header = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Point Header Buffer"),
+            contents: bytemuck::bytes_of(&header),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        // Shaders treat points past the end of the surfel buffer as having no surfel
+        let surfels = create_storage(
+            device,
+            "Surfel Buffer",
+            bytemuck::bytes_of(&Surfel::zeroed()),
+        );
+        let bind_group = create_bind_group(device, layout, &positions, &colors, &header, &surfels);
+
+        Self {
+            size: positions.size() + colors.size(),
+            positions,
+            colors,
+            header,
+            surfels,
+            bind_group,
+            point_count: vertices.len() as u32,
+            capacity: vertices.len() as u32,
+            min: min.into(),
+            max: max.into(),
+            fresh: None,
+        }
+    }
+
+    /// Empty buffers filled later by [`PointBuffers::write`]. Positions are kept as floats,
+    /// since the bounds aren't known up front.
+    pub fn with_capacity(device: &Device, layout: &BindGroupLayout, capacity: u32) -> Self {
+        let create = |label, size: u64| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let positions = create(
+            "Point Position Buffer",
+            capacity as u64 * std::mem::size_of::<Vector3<f32>>() as u64,
+        );
+        let colors = create("Point Color Buffer", capacity as u64 * 4);
+        let header = PointHeader {
+            origin: Vector3::zeros(),
+            encoding: FLOAT,
+            scale: Vector3::repeat(1.0),
+            pad: 0,
+        };
+        let header = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Point Header Buffer"),
+            contents: bytemuck::bytes_of(&header),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let surfels = create_storage(
+            device,
+            "Surfel Buffer",
+            bytemuck::bytes_of(&Surfel::zeroed()),
+        );
+        let bind_group = create_bind_group(device, layout, &positions, &colors, &header, &surfels);
+
+        let mut buffers = Self {
+            size: positions.size() + colors.size(),
+            positions,
+            colors,
+            header,
+            surfels,
+            bind_group,
+            point_count: 0,
+            capacity,
+            min: Point3::origin(),
+            max: Point3::origin(),
+            fresh: None,
+        };
+        buffers.clear();
+        buffers
+    }
+
+    /// Uploads points after the ones already written, only for buffers made by
+    /// [`PointBuffers::with_capacity`]. They must fit in the remaining capacity.
+    pub fn write(&mut self, queue: &Queue, vertices: &[BasicVertex]) {
+        assert!(vertices.len() as u32 <= self.capacity - self.point_count);
+        for position in self.upload(queue, self.point_count, vertices) {
+            self.min = self.min.inf(&position.into());
+            self.max = self.max.sup(&position.into());
+        }
+        self.point_count += vertices.len() as u32;
+    }
+
+    /// Replaces the points from `first` on with `vertices`, which must not go past the
+    /// points already written. Points are replaced in order, wrapping around to the start.
+    pub fn overwrite(&mut self, queue: &Queue, first: u32, vertices: &[BasicVertex]) {
+        assert!(first + vertices.len() as u32 <= self.point_count);
+        let empty = (
+            Point3::from(Vector3::repeat(f32::INFINITY)),
+            Point3::from(Vector3::repeat(f32::NEG_INFINITY)),
+        );
+        if first == 0 {
+            self.fresh = Some(empty);
+        }
+        let (mut min, mut max) = self.fresh.unwrap_or(empty);
+        for position in self.upload(queue, first, vertices) {
+            min = min.inf(&position.into());
+            max = max.sup(&position.into());
+        }
+        // The old points still left keep the bounds from shrinking
+        self.min = self.min.inf(&min);
+        self.max = self.max.sup(&max);
+        self.fresh = Some((min, max));
+        if first + vertices.len() as u32 == self.point_count {
+            (self.min, self.max) = (min, max);
+            self.fresh = None;
+        }
+    }
+
+    /// Writes points as floats from `first` on, returning their positions
+    fn upload(&self, queue: &Queue, first: u32, vertices: &[BasicVertex]) -> Vec<Vector3<f32>> {
+        let positions: Vec<Vector3<f32>> = vertices.iter().map(|v| v.position).collect();
+        let colors: Vec<u32> = vertices.iter().map(pack_color).collect();
+        let first = first as u64;
+        queue.write_buffer(
+            &self.positions,
+            first * std::mem::size_of::<Vector3<f32>>() as u64,
+            bytemuck::cast_slice(&positions),
+        );
+        queue.write_buffer(&self.colors, first * 4, bytemuck::cast_slice(&colors));
+        positions
+    }
+
+    /// Forgets every point, so the buffers can be written again
+    pub fn clear(&mut self) {
+        self.point_count = 0;
+        self.fresh = None;
+        self.min = Point3::from(Vector3::repeat(f32::INFINITY));
+        self.max = Point3::from(Vector3::repeat(f32::NEG_INFINITY));
+    }
+
+    /// Replaces the orientation of every point, in the same order as the points
+    pub fn set_surfels(&mut self, device: &Device, layout: &BindGroupLayout, surfels: &[Surfel]) {
+        self.surfels = create_storage(device, "Surfel Buffer", bytemuck::cast_slice(surfels));
+        self.bind_group = create_bind_group(
+            device,
+            layout,
+            &self.positions,
+            &self.colors,
+            &self.header,
+            &self.surfels,
+        );
+    }
+
+    pub fn point_count(&self) -> u32 {
+        self.point_count
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Corners of the box around every point
+    pub fn bounds(&self) -> (Point3<f32>, Point3<f32>) {
+        (self.min, self.max)
+    }
+
+    /// Bytes taken by the positions and colors
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Binds the points to group 1 and draws one six vertex instance per point
+    pub fn draw<'a>(&'a self, pass: &mut RenderPass<'a>) {
+        pass.set_bind_group(1, &self.bind_group, &[]);
+        pass.draw(0..6, 0..self.point_count);
+    }
+}
+
+fn pack_color(vertex: &BasicVertex) -> u32 {
+    let c = vertex
+        .color
+        .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u32);
+    c.x | c.y << 8 | c.z << 16 | 255 << 24
+}
+
+fn create_storage(device: &Device, label: &str, contents: &[u8]) -> Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents,
+        usage: wgpu::BufferUsages::STORAGE,
+    })
+}
+
+fn create_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    positions: &Buffer,
+    colors: &Buffer,
+    header: &Buffer,
+    surfels: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Point bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: positions.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: surfels.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: colors.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: header.as_entire_binding(),
+            },
+        ],
+    })
+}
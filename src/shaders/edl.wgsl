@@ -0,0 +1,74 @@
+struct VertexOutput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+
+@vertex
+fn vs_main(
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+) -> VertexOutput {
+    var result: VertexOutput;
+    result.tex_coord = tex_coords;
+    result.position = vec4<f32>(position, 0.0, 1.0);
+    return result;
+}
+
+struct EdlSettings {
+    strength: f32,
+    radius: f32,
+    z_near: f32,
+    z_far: f32,
+};
+
+@group(0)
+@binding(0)
+var r_color: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var r_depth: texture_depth_2d;
+
+@group(0)
+@binding(2)
+var<uniform> settings: EdlSettings;
+
+// Turns a depth buffer value back into a logarithmic view distance
+fn log_depth(pos: vec2<i32>) -> f32 {
+    let ndc = textureLoad(r_depth, pos, 0);
+    let m22 = (settings.z_far + settings.z_near) / (settings.z_near - settings.z_far);
+    let m23 = 2.0 * settings.z_far * settings.z_near / (settings.z_near - settings.z_far);
+    return log2(m23 / (ndc + m22));
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let pos = vec2<i32>(vertex.position.xy);
+    let color = textureLoad(r_color, pos, 0);
+    if(textureLoad(r_depth, pos, 0) >= 1.0){
+        return color;
+    }
+
+    let size = vec2<i32>(textureDimensions(r_depth));
+    let depth = log_depth(pos);
+    var response = 0.0;
+    var count = 0.0;
+    for(var i = 0; i < 8; i++){
+        let angle = f32(i) * 0.785398;
+        let offset = vec2<i32>(round(vec2<f32>(cos(angle), sin(angle)) * settings.radius));
+        let neighbour = clamp(pos + offset, vec2<i32>(0), size - 1);
+        // Empty pixels are filled in later by the jump flood, so they don't count as edges
+        if(textureLoad(r_depth, neighbour, 0) >= 1.0){
+            continue;
+        }
+        response += max(0.0, depth - log_depth(neighbour));
+        count += 1.0;
+    }
+    if(count > 0.0){
+        response /= count;
+    }
+
+    let shade = exp(-response * 300.0 * settings.strength);
+    return vec4<f32>(color.rgb * shade, color.a);
+}
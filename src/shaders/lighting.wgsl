@@ -0,0 +1,62 @@
+struct VertexOutput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+
+@vertex
+fn vs_main(
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+) -> VertexOutput {
+    var result: VertexOutput;
+    result.tex_coord = tex_coords;
+    result.position = vec4<f32>(position, 0.0, 1.0);
+    return result;
+}
+
+struct Lighting {
+    // Direction towards the light, in world space
+    light: vec3<f32>,
+    ambient: f32,
+    // Direction towards the camera, in world space
+    view: vec3<f32>,
+    diffuse: f32,
+    specular: f32,
+    shininess: f32,
+};
+
+@group(0)
+@binding(0)
+var r_color: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var r_normal: texture_2d<f32>;
+
+@group(0)
+@binding(2)
+var<uniform> lighting: Lighting;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let pos = vec2<i32>(vertex.position.xy);
+    let color = textureLoad(r_color, pos, 0);
+    let normal = textureLoad(r_normal, pos, 0);
+    // Background and points without a normal are left unlit
+    if(normal.a == 0.0){
+        return color;
+    }
+
+    // Blinn-Phong
+    let n = normalize(normal.xyz);
+    let lambert = max(dot(n, lighting.light), 0.0);
+    let half_vector = normalize(lighting.light + lighting.view);
+    var highlight = 0.0;
+    if(lambert > 0.0){
+        highlight = pow(max(dot(n, half_vector), 0.0), lighting.shininess);
+    }
+
+    let shade = lighting.ambient + lighting.diffuse * lambert;
+    return vec4<f32>(color.rgb * shade + vec3<f32>(lighting.specular * highlight), color.a);
+}
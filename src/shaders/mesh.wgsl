@@ -0,0 +1,90 @@
+struct VertexOutput {
+    @location(0) color: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coord: vec2<f32>,
+    @location(3) world_position: vec3<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+// Same uniform as the points are drawn with, see point.wgsl
+struct Camera {
+    transform: mat4x4<f32>,
+    viewport: vec2<f32>,
+    point_size: f32,
+    world_size: u32,
+    shape: u32,
+    projection_scale: f32,
+    z_near: f32,
+    z_far: f32,
+    camera_position: vec4<f32>,
+};
+
+// Laid out like `MeshUniform`
+struct Mesh {
+    opacity: f32,
+    lit: u32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> camera: Camera;
+
+@group(1)
+@binding(0)
+var<uniform> mesh: Mesh;
+
+@group(1)
+@binding(1)
+var t_color: texture_2d<f32>;
+
+@group(1)
+@binding(2)
+var s_color: sampler;
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+    @location(3) tex_coord: vec2<f32>,
+) -> VertexOutput {
+    var result: VertexOutput;
+    result.position = camera.transform * vec4<f32>(position, 1.0);
+    result.color = color;
+    result.normal = normal;
+    result.tex_coord = tex_coord;
+    result.world_position = position;
+    return result;
+}
+
+struct FragmentOutput{
+    @location(0) posbuf: vec4<f32>,
+    @location(1) colorbuf: vec4<f32>,
+    @location(2) normalbuf: vec4<f32>,
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> FragmentOutput {
+    let texel = textureSample(t_color, s_color, vertex.tex_coord);
+
+    var result: FragmentOutput;
+    result.posbuf = vec4<f32>(vertex.position.xyz, 1.0);
+    result.colorbuf = vec4<f32>(vertex.color * texel.rgb, 1.0);
+    // Both sides of a triangle face the camera, like the points do
+    result.normalbuf = vec4<f32>(0.0);
+    if(mesh.lit != 0u && dot(vertex.normal, vertex.normal) > 0.0){
+        var normal = normalize(vertex.normal);
+        if(dot(normal, camera.camera_position.xyz - vertex.world_position) < 0.0){
+            normal = -normal;
+        }
+        result.normalbuf = vec4<f32>(normal, 1.0);
+    }
+    return result;
+}
+
+// Transparent meshes blend straight onto the finished image, unlit
+@fragment
+fn fs_overlay(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(t_color, s_color, vertex.tex_coord);
+    return vec4<f32>(vertex.color * texel.rgb, mesh.opacity);
+}
@@ -1,36 +1,104 @@
 struct VertexOutput {
     @location(0) color: vec3<f32>,
-    @location(1) screenpos: vec2<f32>,
+    @location(1) corner: vec2<f32>,
+    @location(2) depth_bulge: f32,
+    @location(3) normal: vec4<f32>,
     @builtin(position) position: vec4<f32>,
-    
 };
 
+struct Camera {
+    transform: mat4x4<f32>,
+    viewport: vec2<f32>,
+    point_size: f32,
+    world_size: u32,
+    shape: u32,
+    projection_scale: f32,
+    z_near: f32,
+    z_far: f32,
+    camera_position: vec4<f32>,
+};
+
+const SHAPE_SQUARE: u32 = 0u;
+const SHAPE_PARABOLOID: u32 = 2u;
+
 @group(0)
 @binding(0)
-var<uniform> transform: mat4x4<f32>;
+var<uniform> camera: Camera;
+
+fn ndc_depth(distance: f32) -> f32 {
+    let m22 = (camera.z_far + camera.z_near) / (camera.z_near - camera.z_far);
+    let m23 = 2.0 * camera.z_far * camera.z_near / (camera.z_near - camera.z_far);
+    return -m22 + m23 / distance;
+}
 
 @vertex
 fn vs_main(
-    @location(0) position: vec3<f32>,
-    @location(1) color: vec3<f32>,
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
 ) -> VertexOutput {
+    // Two triangles covering the sprite
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+
     var result: VertexOutput;
-    result.position = transform*vec4<f32>(position, 1.0);
-    result.color = color;
-    result.screenpos = position.xy;
+    let center = camera.transform * vec4<f32>(point_position(instance_index), 1.0);
+
+    var size_px = camera.point_size;
+    var world_radius = camera.point_size / camera.projection_scale * center.w * 0.5;
+    if(camera.world_size != 0u){
+        size_px = camera.point_size * camera.projection_scale / center.w;
+        world_radius = camera.point_size * 0.5;
+    }
+    // Don't let points vanish between pixels
+    size_px = max(size_px, 1.0);
+
+    result.position = center + vec4<f32>(corner * size_px / camera.viewport * center.w, 0.0, 0.0);
+    result.color = point_color(instance_index);
+    result.corner = corner;
+    result.depth_bulge = ndc_depth(max(center.w - world_radius, camera.z_near)) - ndc_depth(center.w);
+
+    // Unknown normals stay zero, so shading can leave those points alone
+    let surfel = point_surfel(instance_index);
+    result.normal = vec4<f32>(0.0);
+    if(dot(surfel.xyz, surfel.xyz) > 0.0){
+        var normal = normalize(surfel.xyz);
+        if(dot(normal, camera.camera_position.xyz - point_position(instance_index)) < 0.0){
+            normal = -normal;
+        }
+        result.normal = vec4<f32>(normal, 1.0);
+    }
     return result;
 }
 
 struct FragmentOutput{
     @location(0) posbuf: vec4<f32>,
     @location(1) colorbuf: vec4<f32>,
-
+    @location(2) normalbuf: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
 }
 
 @fragment
 fn fs_main(vertex: VertexOutput) -> FragmentOutput {
+    let r2 = dot(vertex.corner, vertex.corner);
+    if(camera.shape != SHAPE_SQUARE && r2 > 1.0){
+        discard;
+    }
+    var depth = vertex.position.z;
+    if(camera.shape == SHAPE_PARABOLOID){
+        depth += vertex.depth_bulge * (1.0 - r2);
+    }
+
     var result: FragmentOutput;
-    result.posbuf = vec4<f32>(vertex.position.xyz, 1.0);
+    result.posbuf = vec4<f32>(vertex.position.xy, depth, 1.0);
     result.colorbuf = vec4<f32>(vertex.color, 1.0);
+    result.normalbuf = vertex.normal;
+    result.depth = depth;
     return result;
-}
\ No newline at end of file
+}
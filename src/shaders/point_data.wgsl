@@ -0,0 +1,60 @@
+// How the positions of the drawn buffer are decoded, laid out like `PointHeader`
+struct PointHeader {
+    origin: vec3<f32>,
+    encoding: u32,
+    scale: vec3<f32>,
+};
+
+// Coordinates of the drawn points, quantized to 16 or 32 bits, or floats
+@group(1)
+@binding(0)
+var<storage, read> positions: array<u32>;
+
+// Colors of the drawn points, packed like `Unorm8x4`
+@group(1)
+@binding(2)
+var<storage, read> colors: array<u32>;
+
+@group(1)
+@binding(3)
+var<uniform> header: PointHeader;
+
+fn point_coordinate(index: u32) -> f32 {
+    switch(header.encoding){
+        case 1u: {
+            return f32(positions[index]);
+        }
+        case 2u: {
+            return bitcast<f32>(positions[index]);
+        }
+        default: {
+            return f32((positions[index / 2u] >> ((index % 2u) * 16u)) & 0xffffu);
+        }
+    }
+}
+
+fn point_position(index: u32) -> vec3<f32> {
+    let base = index * 3u;
+    let stored = vec3<f32>(
+        point_coordinate(base),
+        point_coordinate(base + 1u),
+        point_coordinate(base + 2u),
+    );
+    return header.origin + stored * header.scale;
+}
+
+fn point_color(index: u32) -> vec3<f32> {
+    return unpack4x8unorm(colors[index]).rgb;
+}
+
+// Normal and radius of every point, laid out like `Surfel`. May be shorter than the points.
+@group(1)
+@binding(1)
+var<storage, read> surfels: array<vec4<f32>>;
+
+fn point_surfel(index: u32) -> vec4<f32> {
+    if(index >= arrayLength(&surfels)){
+        return vec4<f32>(0.0);
+    }
+    return surfels[index];
+}
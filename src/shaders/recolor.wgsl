@@ -23,9 +23,11 @@ var r_color: texture_2d<f32>;
 @binding(1)
 var r_pos: texture_2d<f32>;
 
+{OCCLUSION}
+
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     let p = vec2<u32>(textureLoad(r_pos, vec2<u32>(vertex.position.xy), 0).xy);
     let tex = textureLoad(r_color, p, 0);
-    return tex;
+    return vec4<f32>(tex.rgb * occlusion(vec2<u32>(vertex.position.xy)), tex.a);
 }
\ No newline at end of file
@@ -0,0 +1,110 @@
+struct VertexOutput {
+    @location(0) color: vec3<f32>,
+    @location(1) corner: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+struct Camera {
+    transform: mat4x4<f32>,
+    position: vec4<f32>,
+    right: vec4<f32>,
+    up: vec4<f32>,
+    radius: f32,
+    depth_offset: f32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> camera: Camera;
+
+// Builds one corner of the disc around a point, pushed away from the camera by `push_back` radii
+fn splat_vertex(vertex_index: u32, instance_index: u32, push_back: f32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+
+    let center = point_position(instance_index);
+    let surfel = point_surfel(instance_index);
+    var radius = surfel.w;
+    if(radius <= 0.0){
+        radius = camera.radius;
+    }
+
+    // Points without a normal face the camera
+    var normal = -cross(camera.right.xyz, camera.up.xyz);
+    var u = camera.right.xyz;
+    var v = camera.up.xyz;
+    if(dot(surfel.xyz, surfel.xyz) > 0.0){
+        normal = normalize(surfel.xyz);
+        var helper = vec3<f32>(1.0, 0.0, 0.0);
+        if(abs(normal.x) > 0.9){
+            helper = vec3<f32>(0.0, 1.0, 0.0);
+        }
+        u = normalize(cross(normal, helper));
+        v = cross(normal, u);
+    }
+    let to_camera = camera.position.xyz - center;
+    if(dot(normal, to_camera) < 0.0){
+        normal = -normal;
+    }
+
+    var world = center + (corner.x * u + corner.y * v) * radius;
+    world -= normalize(to_camera) * radius * push_back;
+
+    var result: VertexOutput;
+    result.position = camera.transform * vec4<f32>(world, 1.0);
+    result.color = point_color(instance_index);
+    result.corner = corner;
+    result.normal = normal;
+    return result;
+}
+
+@vertex
+fn vs_visibility(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    return splat_vertex(vertex_index, instance_index, camera.depth_offset);
+}
+
+@vertex
+fn vs_accumulation(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    return splat_vertex(vertex_index, instance_index, 0.0);
+}
+
+@fragment
+fn fs_visibility(vertex: VertexOutput) {
+    if(dot(vertex.corner, vertex.corner) > 1.0){
+        discard;
+    }
+}
+
+struct AccumulationOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+}
+
+@fragment
+fn fs_accumulation(vertex: VertexOutput) -> AccumulationOutput {
+    let r2 = dot(vertex.corner, vertex.corner);
+    if(r2 > 1.0){
+        discard;
+    }
+    // Gaussian kernel, truncated at the edge of the disc
+    let weight = exp(-2.0 * r2);
+
+    var result: AccumulationOutput;
+    result.color = vec4<f32>(vertex.color * weight, weight);
+    result.normal = vec4<f32>(vertex.normal * weight, weight);
+    return result;
+}
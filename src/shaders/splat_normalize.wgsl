@@ -0,0 +1,44 @@
+struct VertexOutput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+
+@vertex
+fn vs_main(
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+) -> VertexOutput {
+    var result: VertexOutput;
+    result.tex_coord = tex_coords;
+    result.position = vec4<f32>(position, 0.0, 1.0);
+    return result;
+}
+
+@group(0)
+@binding(0)
+var r_color: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var r_normal: texture_2d<f32>;
+
+@group(0)
+@binding(2)
+var<uniform> view_direction: vec4<f32>;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let pos = vec2<u32>(vertex.position.xy);
+    let color = textureLoad(r_color, pos, 0);
+    if(color.a <= 0.0){
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    let normal = textureLoad(r_normal, pos, 0).xyz;
+
+    var shade = 1.0;
+    if(dot(normal, normal) > 0.0){
+        shade = 0.4 + 0.6 * abs(dot(normalize(normal), view_direction.xyz));
+    }
+    return vec4<f32>(color.rgb / color.a * shade, 1.0);
+}
@@ -0,0 +1,117 @@
+struct VertexOutput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+
+@vertex
+fn vs_main(
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+) -> VertexOutput {
+    var result: VertexOutput;
+    result.tex_coord = tex_coords;
+    result.position = vec4<f32>(position, 0.0, 1.0);
+    return result;
+}
+
+struct SsaoSettings {
+    radius: f32,
+    intensity: f32,
+    tan_half_fov: f32,
+    aspect_ratio: f32,
+    z_near: f32,
+    z_far: f32,
+};
+
+@group(0)
+@binding(0)
+var r_pos: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var<uniform> settings: SsaoSettings;
+
+fn linear_depth(ndc: f32) -> f32 {
+    let m22 = (settings.z_far + settings.z_near) / (settings.z_near - settings.z_far);
+    let m23 = 2.0 * settings.z_far * settings.z_near / (settings.z_near - settings.z_far);
+    return m23 / (ndc + m22);
+}
+
+// View space position of a pixel. w is 0 for pixels the jump flood left empty.
+fn view_position(pixel: vec2<i32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(r_pos));
+    if(any(pixel < vec2<i32>(0)) || any(pixel >= size)){
+        return vec4<f32>(0.0);
+    }
+    let t = textureLoad(r_pos, pixel, 0);
+    if(t.a < 0.5){
+        return vec4<f32>(0.0);
+    }
+    let depth = linear_depth(t.z);
+    let ndc = (vec2<f32>(pixel) + 0.5) / vec2<f32>(size) * 2.0 - 1.0;
+    return vec4<f32>(
+        ndc.x * depth * settings.tan_half_fov * settings.aspect_ratio,
+        -ndc.y * depth * settings.tan_half_fov,
+        -depth,
+        1.0,
+    );
+}
+
+// Picks whichever neighbour lies closer to the surface, to avoid smearing normals over edges
+fn tangent(p: vec3<f32>, a: vec4<f32>, b: vec4<f32>) -> vec3<f32> {
+    if(a.w == 0.0 && b.w == 0.0){
+        return vec3<f32>(0.0);
+    }
+    if(b.w == 0.0 || (a.w != 0.0 && abs(a.z - p.z) < abs(b.z - p.z))){
+        return a.xyz - p;
+    }
+    return p - b.xyz;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(vertex.position.xy);
+    let center = view_position(pixel);
+    if(center.w == 0.0){
+        return vec4<f32>(1.0);
+    }
+    let p = center.xyz;
+
+    let dx = tangent(p, view_position(pixel + vec2<i32>(2, 0)), view_position(pixel - vec2<i32>(2, 0)));
+    let dy = tangent(p, view_position(pixel - vec2<i32>(0, 2)), view_position(pixel + vec2<i32>(0, 2)));
+    var normal = normalize(-p);
+    let c = cross(dx, dy);
+    if(dot(c, c) > 0.0){
+        normal = normalize(c);
+        if(dot(normal, -p) < 0.0){
+            normal = -normal;
+        }
+    }
+
+    let size = vec2<f32>(textureDimensions(r_pos));
+    let radius_px = clamp(settings.radius / (-p.z * settings.tan_half_fov) * size.y * 0.5, 1.0, 64.0);
+    // Interleaved gradient noise rotates the sample spiral per pixel, the blur removes the pattern
+    let noise = fract(52.9829189 * fract(dot(vertex.position.xy, vec2<f32>(0.06711056, 0.00583715))));
+
+    var occlusion = 0.0;
+    for(var i = 0; i < {SAMPLES}; i++){
+        let r = sqrt((f32(i) + 0.5) / f32({SAMPLES}));
+        let angle = f32(i) * 2.39996 + noise * 6.28318;
+        let offset = vec2<f32>(cos(angle), sin(angle)) * r * radius_px;
+        let s = view_position(pixel + vec2<i32>(round(offset)));
+        if(s.w == 0.0){
+            continue;
+        }
+        let v = s.xyz - p;
+        let distance = length(v);
+        if(distance < 0.00001){
+            continue;
+        }
+        let falloff = 1.0 - smoothstep(0.0, settings.radius, distance);
+        occlusion += max(0.0, dot(v / distance, normal) - 0.1) * falloff;
+    }
+
+    let ao = clamp(1.0 - settings.intensity * occlusion * 2.0 / f32({SAMPLES}), 0.0, 1.0);
+    return vec4<f32>(ao, ao, ao, 1.0);
+}
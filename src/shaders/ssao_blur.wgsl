@@ -0,0 +1,71 @@
+struct VertexOutput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+
+@vertex
+fn vs_main(
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+) -> VertexOutput {
+    var result: VertexOutput;
+    result.tex_coord = tex_coords;
+    result.position = vec4<f32>(position, 0.0, 1.0);
+    return result;
+}
+
+@group(0)
+@binding(0)
+var r_occlusion: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var r_pos: texture_2d<f32>;
+
+struct BlurSettings {
+    z_near: f32,
+    z_far: f32,
+};
+
+@group(0)
+@binding(2)
+var<uniform> settings: BlurSettings;
+
+fn linear_depth(ndc: f32) -> f32 {
+    let m22 = (settings.z_far + settings.z_near) / (settings.z_near - settings.z_far);
+    let m23 = 2.0 * settings.z_far * settings.z_near / (settings.z_near - settings.z_far);
+    return m23 / (ndc + m22);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(vertex.position.xy);
+    let size = vec2<i32>(textureDimensions(r_pos));
+    let center = textureLoad(r_pos, pixel, 0);
+    if(center.a < 0.5){
+        return vec4<f32>(1.0);
+    }
+    let depth = linear_depth(center.z);
+
+    let sigma = max(f32({RADIUS}), 1.0) * 0.5;
+    var sum = 0.0;
+    var weights = 0.0;
+    for(var i = -{RADIUS}; i <= {RADIUS}; i++){
+        let q = clamp(pixel + {DIRECTION} * i, vec2<i32>(0), size - 1);
+        let t = textureLoad(r_pos, q, 0);
+        if(t.a < 0.5){
+            continue;
+        }
+        // Samples from other surfaces would blur the occlusion across silhouettes
+        let similarity = max(0.0, 1.0 - abs(linear_depth(t.z) - depth) / (0.05 * depth));
+        let w = exp(-f32(i * i) / (2.0 * sigma * sigma)) * similarity;
+        sum += textureLoad(r_occlusion, q, 0).r * w;
+        weights += w;
+    }
+    if(weights <= 0.0){
+        return vec4<f32>(1.0);
+    }
+    let ao = sum / weights;
+    return vec4<f32>(ao, ao, ao, 1.0);
+}
@@ -0,0 +1,39 @@
+struct VertexOutput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+
+@vertex
+fn vs_main(
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+) -> VertexOutput {
+    var result: VertexOutput;
+    result.tex_coord = tex_coords;
+    result.position = vec4<f32>(position, 0.0, 1.0);
+    return result;
+}
+
+struct Timeline {
+    // Played part of the recording, from 0 to 1
+    progress: f32,
+    pad: vec3<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> timeline: Timeline;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    // The cursor is two pixels wide
+    let pixel = fwidth(vertex.tex_coord.x);
+    if(abs(vertex.tex_coord.x - timeline.progress) < pixel){
+        return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+    }
+    if(vertex.tex_coord.x < timeline.progress){
+        return vec4<f32>(0.35, 0.55, 0.85, 1.0);
+    }
+    return vec4<f32>(0.15, 0.15, 0.15, 1.0);
+}
@@ -1,4 +1,4 @@
-use wgpu::{Device, TextureDescriptor, TextureFormat, TextureView};
+use wgpu::{Device, Extent3d, TextureDescriptor, TextureFormat, TextureView};
 
 pub struct TextureStore {
     textures: Vec<Texture>,
@@ -10,10 +10,15 @@ impl TextureStore {
             textures: Vec::new(),
         }
     }
-    pub fn get_resolver<'a>(&'a self, surface_view: &'a TextureView) -> TextureResolver<'a> {
+    pub fn get_resolver<'a>(
+        &'a self,
+        surface_view: &'a TextureView,
+        surface_size: Extent3d,
+    ) -> TextureResolver<'a> {
         TextureResolver {
             store: self,
             surface_view,
+            surface_size,
         }
     }
 
@@ -29,6 +34,7 @@ impl TextureStore {
         TextureHandle(InnerTextureHandle::TextureID(TextureID { id }))
     }
 
+    #[allow(dead_code)]
     pub fn resolve_format(&self, handle: TextureHandle) -> Option<TextureFormat> {
         match handle.0 {
             InnerTextureHandle::Surface => None,
@@ -64,6 +70,7 @@ pub struct Texture {
 pub struct TextureResolver<'a> {
     store: &'a TextureStore,
     surface_view: &'a TextureView,
+    surface_size: Extent3d,
 }
 
 impl<'a> TextureResolver<'a> {
@@ -73,6 +80,13 @@ impl<'a> TextureResolver<'a> {
             InnerTextureHandle::TextureID(i) => &self.store.textures[i.id].view,
         }
     }
+
+    pub fn resolve_size(&self, view: TextureHandle) -> Extent3d {
+        match view.0 {
+            InnerTextureHandle::Surface => self.surface_size,
+            InnerTextureHandle::TextureID(i) => self.store.textures[i.id].texture.size(),
+        }
+    }
 }
 #[derive(Debug, Copy, Clone)]
 pub struct TextureHandle(InnerTextureHandle);